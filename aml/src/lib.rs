@@ -0,0 +1,2886 @@
+//! AML 解释器
+//!
+//! 加载 DSDT 与所有 SSDT，建立 ACPI 命名空间，并按需解释执行其中的方法：
+//!
+//! - 加载：逐条执行表中的 AML，定义 `Scope`、`Device`、`Name`、`Method`、
+//!   `OperationRegion`/`Field` 等对象；方法体只记录位置，调用时才解释。
+//!   表顶层的 `If`/`Store` 等语句在加载时直接执行。某个 `Scope`/`Device`
+//!   内部出错只跳过这一块，不影响表的其余部分
+//! - 初始化：按 ACPI 规范深度优先对设备求值 `_STA`，存在的设备运行 `_INI`
+//! - 求值：[`Interpreter::evaluate_path`] 按绝对路径求值对象或调用方法；
+//!   字段读写访问 SystemMemory、SystemIO 与 PCI 配置空间
+//! - 在此之上提供睡眠类型 (`\_Sx`)、中断模型 (`\_PIC`)、资源模板 (`_CRS`)
+//!   与根桥 PCI 中断路由 (`_PRT`，含链接设备) 的查询
+//!
+//! 解释器本身与平台无关：操作区域、计时与 ACPI 表都经由调用者实现的
+//! [`Handler`] 访问，因此可以在主机上测试。解释器是单线程的，不能在中断
+//! 处理程序中使用。不支持 `Load`/`LoadTable`/`Unload` 以及 EmbeddedControl、
+//! SMBus 等需要驱动的地址空间。
+
+#![cfg_attr(not(test), no_std)]
+
+extern crate alloc;
+
+#[cfg(test)]
+mod tests;
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+/// 语句、表达式与方法调用共用的嵌套深度上限
+///
+/// 解释器递归执行，每层 `If`/`While` 等语句、每层表达式各占一层，一次方法调用
+/// 占三层（调用语句、调用表达式与方法体）。x86_64 release 构建下方法调用最贵：
+/// `execute_block`、`evaluate_op` 与 `invoke` 的栈帧合计约 4.3 KiB，
+/// 平均每层不到 1.5 KiB；128 层足够 8 层以上的方法调用链再各嵌套几层语句。
+/// 调用者需要提供至少 [`STACK_SIZE`] 的栈。
+pub const MAX_NESTING: usize = 128;
+/// 嵌套到 [`MAX_NESTING`] 层所需的栈（按每层 2 KiB 估计，留出余量）
+pub const STACK_SIZE: usize = MAX_NESTING * 2048;
+/// 单个 `While` 的最大迭代次数，防止固件死循环卡住内核
+const MAX_LOOP_ITERATIONS: u64 = 1 << 20;
+/// `\_REV` 报告的 ACPI 版本
+const ACPI_REVISION: u64 = 2;
+/// `\_OSI` 认可的操作系统接口
+const OSI_STRINGS: &[&str] = &[
+    "Windows 2001",
+    "Windows 2001 SP1",
+    "Windows 2001 SP2",
+    "Windows 2006",
+    "Windows 2009",
+    "Windows 2012",
+    "Windows 2015",
+    "Module Device",
+    "Processor Device",
+    "3.0 Thermal Model",
+    "Extended Address Space Descriptor",
+];
+
+/// 加载或求值失败原因
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AmlError {
+    /// 没有 ACPI 或找不到 DSDT
+    NoTables,
+    /// 解释器尚未初始化
+    Uninitialized,
+    /// 字节码在操作数中途结束
+    UnexpectedEnd,
+    /// 不认识或不支持的操作码
+    UnknownOpcode(u16),
+    /// 名字解析失败
+    NotFound,
+    /// 同一作用域中重复定义
+    AlreadyExists,
+    /// 无效的名字或路径
+    InvalidName,
+    /// 操作数类型不符合要求
+    TypeMismatch,
+    /// 下标越界
+    InvalidIndex,
+    DivideByZero,
+    /// 不支持的操作区域地址空间
+    UnsupportedSpace(u8),
+    /// 映射操作区域失败
+    RegionMap,
+    /// 语句、表达式或方法调用嵌套过深
+    NestingDepth,
+    /// `While` 超过迭代上限
+    LoopLimit,
+    /// 固件执行了 `Fatal`
+    Fatal,
+    /// 参数个数或取值不对
+    InvalidArgument,
+}
+
+impl AmlError {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::NoTables => "no DSDT",
+            Self::Uninitialized => "interpreter not initialized",
+            Self::UnexpectedEnd => "unexpected end of AML",
+            Self::UnknownOpcode(_) => "unknown opcode",
+            Self::NotFound => "object not found",
+            Self::AlreadyExists => "object already exists",
+            Self::InvalidName => "invalid name",
+            Self::TypeMismatch => "type mismatch",
+            Self::InvalidIndex => "index out of range",
+            Self::DivideByZero => "divide by zero",
+            Self::UnsupportedSpace(_) => "unsupported region space",
+            Self::RegionMap => "cannot map region",
+            Self::NestingDepth => "nesting depth exceeded",
+            Self::LoopLimit => "loop limit exceeded",
+            Self::Fatal => "firmware fatal error",
+            Self::InvalidArgument => "invalid argument",
+        }
+    }
+}
+
+// ============================================================================
+// 平台接口
+// ============================================================================
+
+/// 触发方式
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TriggerMode {
+    Edge,
+    Level,
+}
+
+/// 极性
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Polarity {
+    ActiveHigh,
+    ActiveLow,
+}
+
+/// PCI 功能的位置
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PciAddress {
+    pub segment: u16,
+    pub bus: u8,
+    pub device: u8,
+    pub function: u8,
+}
+
+/// 解释器访问硬件与固件表的方式，由内核（或测试）实现
+///
+/// 访问宽度 `width` 以位为单位，取 8、16、32 或 64。
+pub trait Handler: Send {
+    /// 映射 SystemMemory 区域，返回之后传给 [`read_memory`](Self::read_memory)
+    /// 的基地址；每个区域只在首次访问时映射一次
+    fn map_memory(&mut self, address: u64, length: u64) -> Option<u64>;
+    fn read_memory(&mut self, address: u64, width: usize) -> u64;
+    fn write_memory(&mut self, address: u64, width: usize, value: u64);
+    fn read_io(&mut self, port: u16, width: usize) -> u64;
+    fn write_io(&mut self, port: u16, width: usize, value: u64);
+    fn read_pci(&mut self, address: PciAddress, offset: u16, width: usize) -> u64;
+    fn write_pci(&mut self, address: PciAddress, offset: u16, width: usize, value: u64);
+    /// `Stall`：忙等待
+    fn stall(&mut self, duration: Duration);
+    /// `Sleep`：可以让出 CPU 的等待
+    fn sleep(&mut self, duration: Duration);
+    /// 单调时钟（`Timer`）
+    fn now(&mut self) -> Duration;
+    /// 按签名查找 ACPI 表，返回 (物理地址, 长度)（`DataTableRegion`）
+    fn find_table(&mut self, signature: &[u8; 4]) -> Option<(u64, u64)>;
+}
+
+// ============================================================================
+// 对象
+// ============================================================================
+
+/// 命名空间节点编号
+pub type NodeId = usize;
+
+/// 根节点 `\`
+const ROOT: NodeId = 0;
+
+/// AML 对象：既是数据值，也是命名空间节点上挂的对象
+#[derive(Clone, Debug)]
+pub enum Object {
+    Uninitialized,
+    Integer(u64),
+    String(String),
+    Buffer(Vec<u8>),
+    Package(Vec<Object>),
+    Reference(Reference),
+    /// `Scope` 定义的作用域（`\_SB` 等预定义作用域）
+    Scope,
+    Device,
+    Processor,
+    PowerResource,
+    ThermalZone,
+    Method(Method),
+    Region(Region),
+    Field(Field),
+    /// `CreateXxxField` 定义的缓冲区字段
+    BufferField {
+        source: Reference,
+        bit_offset: usize,
+        bit_length: usize,
+    },
+    Mutex,
+    Event,
+    /// `Alias` 指向的节点
+    Alias(NodeId),
+}
+
+impl Object {
+    /// `ObjectType` 的返回值
+    fn type_code(&self) -> u64 {
+        match self {
+            Self::Uninitialized | Self::Alias(_) => 0,
+            Self::Integer(_) => 1,
+            Self::String(_) => 2,
+            Self::Buffer(_) => 3,
+            Self::Package(_) => 4,
+            Self::Field(_) => 5,
+            Self::Device => 6,
+            Self::Event => 7,
+            Self::Method(_) => 8,
+            Self::Mutex => 9,
+            Self::Region(_) => 10,
+            Self::PowerResource => 11,
+            Self::Processor => 12,
+            Self::ThermalZone => 13,
+            Self::BufferField { .. } => 14,
+            Self::Scope | Self::Reference(_) => 16,
+        }
+    }
+
+    pub fn as_integer(&self) -> Option<u64> {
+        match self {
+            Self::Integer(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_buffer(&self) -> Option<&[u8]> {
+        match self {
+            Self::Buffer(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    pub fn as_package(&self) -> Option<&[Object]> {
+        match self {
+            Self::Package(elements) => Some(elements),
+            _ => None,
+        }
+    }
+}
+
+/// 可以读写的位置：`RefOf`/`Index` 的结果，也是 `Store` 的目标
+#[derive(Clone, Debug)]
+pub enum Reference {
+    Node(NodeId),
+    /// 调用帧 `frame` 的 LocalX
+    Local(usize, usize),
+    /// 调用帧 `frame` 的 ArgX
+    Arg(usize, usize),
+    /// 容器（包、缓冲区或字符串）中的一个元素
+    Element(Box<Reference>, usize),
+    /// 对临时值的引用（写入被丢弃）
+    Value(Box<Object>),
+    /// `Debug` 对象
+    Debug,
+}
+
+/// 原生实现的方法
+type NativeMethod = fn(&mut Interpreter, &[Object]) -> Result<Object, AmlError>;
+
+/// 方法体
+#[derive(Clone, Copy, Debug)]
+pub enum MethodBody {
+    Aml(&'static [u8]),
+    Native(NativeMethod),
+}
+
+/// 控制方法
+#[derive(Clone, Copy, Debug)]
+pub struct Method {
+    pub body: MethodBody,
+    pub args: usize,
+}
+
+/// 操作区域地址空间
+pub const SPACE_SYSTEM_MEMORY: u8 = 0;
+pub const SPACE_SYSTEM_IO: u8 = 1;
+pub const SPACE_PCI_CONFIG: u8 = 2;
+
+/// 操作区域
+#[derive(Clone, Copy, Debug)]
+pub struct Region {
+    pub space: u8,
+    pub offset: u64,
+    pub length: u64,
+    /// SystemMemory 区域首次访问时 [`Handler::map_memory`] 返回的基地址
+    mapped: Option<u64>,
+    /// PCI 配置空间区域首次访问时解析出的设备位置
+    pci: Option<PciAddress>,
+}
+
+/// 字段访问宽度与更新规则（FieldFlags 字节）
+const ACCESS_TYPE_MASK: u8 = 0x0F;
+const UPDATE_RULE_SHIFT: u8 = 5;
+const UPDATE_PRESERVE: u8 = 0;
+const UPDATE_WRITE_AS_ONES: u8 = 1;
+
+/// 字段所在的位置
+#[derive(Clone, Copy, Debug)]
+pub enum FieldKind {
+    /// 普通 `Field`：直接访问操作区域
+    Region(NodeId),
+    /// `IndexField`：先写索引字段，再读写数据字段
+    Index { index: NodeId, data: NodeId },
+    /// `BankField`：先把 `value` 写入选择字段，再访问操作区域
+    Bank {
+        region: NodeId,
+        bank: NodeId,
+        value: u64,
+    },
+}
+
+/// 字段单元
+#[derive(Clone, Copy, Debug)]
+pub struct Field {
+    pub kind: FieldKind,
+    pub flags: u8,
+    pub bit_offset: usize,
+    pub bit_length: usize,
+}
+
+impl Field {
+    /// 访问宽度（位）；AnyAcc 与 BufferAcc 按字节访问
+    fn access_bits(&self) -> usize {
+        match self.flags & ACCESS_TYPE_MASK {
+            2 => 16,
+            3 => 32,
+            4 => 64,
+            _ => 8,
+        }
+    }
+}
+
+// ============================================================================
+// 命名空间
+// ============================================================================
+
+struct Node {
+    name: [u8; 4],
+    parent: NodeId,
+    children: Vec<NodeId>,
+    object: Object,
+}
+
+/// 调用帧
+struct Frame {
+    locals: [Object; 8],
+    args: [Object; 7],
+    /// 方法执行期间创建的节点，返回时删除
+    temporaries: Vec<NodeId>,
+}
+
+/// 语句执行结果
+enum Flow {
+    Next,
+    Break,
+    Continue,
+    Return(Object),
+}
+
+/// 解释器：命名空间与调用栈
+pub struct Interpreter {
+    handler: Box<dyn Handler>,
+    nodes: Vec<Node>,
+    /// 被删除节点留下的空位
+    free: Vec<NodeId>,
+    frames: Vec<Frame>,
+    /// 当前语句与表达式的嵌套深度
+    nesting: usize,
+    /// DSDT 版本低于 2 时整数只有 32 位
+    integer_mask: u64,
+    /// 正在加载表（方法外的错误只跳过当前块）
+    loading: bool,
+    /// 加载时跳过的块数
+    load_errors: usize,
+}
+
+impl Interpreter {
+    fn new(handler: Box<dyn Handler>, dsdt_revision: u8) -> Self {
+        let mut interpreter = Self {
+            handler,
+            nodes: vec![Node {
+                name: *b"\\___",
+                parent: ROOT,
+                children: Vec::new(),
+                object: Object::Scope,
+            }],
+            free: Vec::new(),
+            frames: Vec::new(),
+            nesting: 0,
+            integer_mask: if dsdt_revision < 2 {
+                u32::MAX as u64
+            } else {
+                u64::MAX
+            },
+            loading: false,
+            load_errors: 0,
+        };
+        for name in [b"_GPE", b"_PR_", b"_SB_", b"_SI_", b"_TZ_"] {
+            interpreter.add_child(ROOT, *name, Object::Scope);
+        }
+        interpreter.add_child(
+            ROOT,
+            *b"_OS_",
+            Object::String("Microsoft Windows NT".to_string()),
+        );
+        interpreter.add_child(ROOT, *b"_REV", Object::Integer(ACPI_REVISION));
+        interpreter.add_child(
+            ROOT,
+            *b"_OSI",
+            Object::Method(Method {
+                body: MethodBody::Native(osi),
+                args: 1,
+            }),
+        );
+        interpreter
+    }
+
+    fn add_child(&mut self, parent: NodeId, name: [u8; 4], object: Object) -> NodeId {
+        let node = Node {
+            name,
+            parent,
+            children: Vec::new(),
+            object,
+        };
+        let id = match self.free.pop() {
+            Some(id) => {
+                self.nodes[id] = node;
+                id
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        };
+        self.nodes[parent].children.push(id);
+        id
+    }
+
+    /// 删除节点及其子树
+    fn remove(&mut self, id: NodeId) {
+        let parent = self.nodes[id].parent;
+        self.nodes[parent].children.retain(|&child| child != id);
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            stack.append(&mut core::mem::take(&mut self.nodes[id].children));
+            self.nodes[id].object = Object::Uninitialized;
+            self.free.push(id);
+        }
+    }
+
+    fn child(&self, parent: NodeId, name: [u8; 4]) -> Option<NodeId> {
+        self.nodes[parent]
+            .children
+            .iter()
+            .copied()
+            .find(|&child| self.nodes[child].name == name)
+    }
+
+    /// 解析名字：带前缀或多段的名字按路径查找，单段名字沿父作用域向上搜索
+    fn resolve(&self, scope: NodeId, name: &NameString) -> Option<NodeId> {
+        let mut node = if name.root { ROOT } else { scope };
+        for _ in 0..name.parents {
+            node = self.nodes[node].parent;
+        }
+        if name.segments.is_empty() {
+            return Some(node);
+        }
+        let id = if name.is_simple() {
+            let segment = name.segments[0];
+            loop {
+                if let Some(child) = self.child(node, segment) {
+                    break Some(child);
+                }
+                if node == ROOT {
+                    break None;
+                }
+                node = self.nodes[node].parent;
+            }
+        } else {
+            name.segments
+                .iter()
+                .try_fold(node, |node, &segment| self.child(node, segment))
+        }?;
+        Some(self.follow_alias(id))
+    }
+
+    fn follow_alias(&self, mut id: NodeId) -> NodeId {
+        while let Object::Alias(target) = self.nodes[id].object {
+            id = target;
+        }
+        id
+    }
+
+    /// 在 `scope` 下按 `name` 新建对象；父路径必须已经存在
+    fn define(
+        &mut self,
+        scope: NodeId,
+        name: &NameString,
+        object: Object,
+    ) -> Result<NodeId, AmlError> {
+        let (&last, parents) = name.segments.split_last().ok_or(AmlError::InvalidName)?;
+        let parent_name = NameString {
+            root: name.root,
+            parents: name.parents,
+            segments: parents.to_vec(),
+        };
+        // 父路径不向上搜索
+        let mut parent = if parent_name.root { ROOT } else { scope };
+        for _ in 0..parent_name.parents {
+            parent = self.nodes[parent].parent;
+        }
+        for &segment in &parent_name.segments {
+            parent = self.child(parent, segment).ok_or(AmlError::NotFound)?;
+        }
+        let parent = self.follow_alias(parent);
+        if self.child(parent, last).is_some() {
+            return Err(AmlError::AlreadyExists);
+        }
+        let id = self.add_child(parent, last, object);
+        if let Some(frame) = self.frames.last_mut() {
+            frame.temporaries.push(id);
+        }
+        Ok(id)
+    }
+
+    /// 节点的绝对路径，如 `\_SB.PCI0.LNKA`
+    fn path(&self, mut id: NodeId) -> String {
+        let mut segments = Vec::new();
+        while id != ROOT {
+            segments.push(self.nodes[id].name);
+            id = self.nodes[id].parent;
+        }
+        let mut path = String::from("\\");
+        for (i, segment) in segments.iter().rev().enumerate() {
+            if i != 0 {
+                path.push('.');
+            }
+            path.extend(segment.iter().map(|&c| c as char));
+        }
+        path
+    }
+
+    /// 深度优先遍历所有节点（不含根）
+    fn descendants(&self, id: NodeId) -> Vec<NodeId> {
+        let mut result = Vec::new();
+        let mut stack: Vec<NodeId> = self.nodes[id].children.iter().rev().copied().collect();
+        while let Some(id) = stack.pop() {
+            result.push(id);
+            stack.extend(self.nodes[id].children.iter().rev());
+        }
+        result
+    }
+}
+
+/// `\_OSI`：查询操作系统接口是否受支持
+fn osi(interpreter: &mut Interpreter, args: &[Object]) -> Result<Object, AmlError> {
+    let supported = match args.first() {
+        Some(Object::String(name)) => OSI_STRINGS.contains(&name.as_str()),
+        _ => false,
+    };
+    Ok(Object::Integer(if supported {
+        interpreter.integer_mask
+    } else {
+        0
+    }))
+}
+
+// ============================================================================
+// 字节码读取
+// ============================================================================
+
+const ROOT_CHAR: u8 = b'\\';
+const PARENT_PREFIX: u8 = b'^';
+const DUAL_NAME_PREFIX: u8 = 0x2E;
+const MULTI_NAME_PREFIX: u8 = 0x2F;
+const EXT_PREFIX: u8 = 0x5B;
+
+/// 名字：可选的根前缀、若干 `^` 与名字段
+#[derive(Clone, Debug, Default)]
+struct NameString {
+    root: bool,
+    parents: usize,
+    segments: Vec<[u8; 4]>,
+}
+
+impl NameString {
+    /// 单段、无前缀的名字（解析时沿父作用域向上搜索）
+    fn is_simple(&self) -> bool {
+        !self.root && self.parents == 0 && self.segments.len() == 1
+    }
+
+    /// 解析 `\_SB.PCI0._PRT` 形式的 ASL 路径；不足 4 个字符的名字段以 `_` 补齐
+    fn from_path(path: &str) -> Result<Self, AmlError> {
+        let mut rest = path;
+        let mut name = Self::default();
+        if let Some(stripped) = rest.strip_prefix('\\') {
+            name.root = true;
+            rest = stripped;
+        }
+        while let Some(stripped) = rest.strip_prefix('^') {
+            name.parents += 1;
+            rest = stripped;
+        }
+        if rest.is_empty() {
+            return Ok(name);
+        }
+        for segment in rest.split('.') {
+            let bytes = segment.as_bytes();
+            if bytes.is_empty() || bytes.len() > 4 || !bytes.iter().all(|&c| is_name_char(c)) {
+                return Err(AmlError::InvalidName);
+            }
+            let mut seg = *b"____";
+            seg[..bytes.len()].copy_from_slice(bytes);
+            name.segments.push(seg);
+        }
+        Ok(name)
+    }
+}
+
+fn is_lead_name_char(c: u8) -> bool {
+    c.is_ascii_uppercase() || c == b'_'
+}
+
+fn is_name_char(c: u8) -> bool {
+    is_lead_name_char(c) || c.is_ascii_digit()
+}
+
+/// 是否是名字的第一个字节
+fn is_name_start(c: u8) -> bool {
+    is_lead_name_char(c)
+        || matches!(
+            c,
+            ROOT_CHAR | PARENT_PREFIX | DUAL_NAME_PREFIX | MULTI_NAME_PREFIX
+        )
+}
+
+/// 字节码游标；表常驻直接映射，切片都是 `'static`
+struct Cursor {
+    code: &'static [u8],
+    pos: usize,
+}
+
+impl Cursor {
+    fn new(code: &'static [u8]) -> Self {
+        Self { code, pos: 0 }
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.code.len()
+    }
+
+    fn peek(&self) -> Result<u8, AmlError> {
+        self.code
+            .get(self.pos)
+            .copied()
+            .ok_or(AmlError::UnexpectedEnd)
+    }
+
+    fn byte(&mut self) -> Result<u8, AmlError> {
+        let byte = self.peek()?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'static [u8], AmlError> {
+        let end = self.pos.checked_add(len).ok_or(AmlError::UnexpectedEnd)?;
+        let bytes = self
+            .code
+            .get(self.pos..end)
+            .ok_or(AmlError::UnexpectedEnd)?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    /// 小端整数
+    fn integer(&mut self, len: usize) -> Result<u64, AmlError> {
+        let bytes = self.bytes(len)?;
+        Ok(bytes
+            .iter()
+            .rev()
+            .fold(0, |value, &b| value << 8 | b as u64))
+    }
+
+    /// PkgLength：长度包含编码本身
+    fn pkg_length(&mut self) -> Result<usize, AmlError> {
+        let lead = self.byte()?;
+        let count = (lead >> 6) as usize;
+        if count == 0 {
+            return Ok((lead & 0x3F) as usize);
+        }
+        let mut length = (lead & 0x0F) as usize;
+        for i in 0..count {
+            length |= (self.byte()? as usize) << (4 + 8 * i);
+        }
+        Ok(length)
+    }
+
+    /// 读 PkgLength 并返回其后直到包结尾的字节，游标移到包之后
+    fn block(&mut self) -> Result<&'static [u8], AmlError> {
+        let start = self.pos;
+        let end = start
+            .checked_add(self.pkg_length()?)
+            .filter(|&end| end >= self.pos && end <= self.code.len())
+            .ok_or(AmlError::UnexpectedEnd)?;
+        let body = &self.code[self.pos..end];
+        self.pos = end;
+        Ok(body)
+    }
+
+    fn name_seg(&mut self) -> Result<[u8; 4], AmlError> {
+        let seg: [u8; 4] = self.bytes(4)?.try_into().unwrap();
+        if !is_lead_name_char(seg[0]) || !seg.iter().all(|&c| is_name_char(c)) {
+            return Err(AmlError::InvalidName);
+        }
+        Ok(seg)
+    }
+
+    fn name_string(&mut self) -> Result<NameString, AmlError> {
+        let mut name = NameString::default();
+        if self.peek()? == ROOT_CHAR {
+            self.pos += 1;
+            name.root = true;
+        } else {
+            while self.peek()? == PARENT_PREFIX {
+                self.pos += 1;
+                name.parents += 1;
+            }
+        }
+        let count = match self.peek()? {
+            0x00 => {
+                self.pos += 1;
+                0
+            }
+            DUAL_NAME_PREFIX => {
+                self.pos += 1;
+                2
+            }
+            MULTI_NAME_PREFIX => {
+                self.pos += 1;
+                self.byte()? as usize
+            }
+            _ => 1,
+        };
+        for _ in 0..count {
+            name.segments.push(self.name_seg()?);
+        }
+        Ok(name)
+    }
+}
+
+// ============================================================================
+// 语句
+// ============================================================================
+
+impl Interpreter {
+    /// 加载一张定义块（DSDT 或 SSDT），在根作用域执行其中的 AML
+    fn load_table(&mut self, table: &'static [u8]) -> Result<(), AmlError> {
+        self.loading = true;
+        let result = self.execute_block(&table[36..], ROOT);
+        self.loading = false;
+        result.map(|_| ())
+    }
+
+    fn execute_block(&mut self, code: &'static [u8], scope: NodeId) -> Result<Flow, AmlError> {
+        let mut cursor = Cursor::new(code);
+        while !cursor.at_end() {
+            match self.execute_term(&mut cursor, scope)? {
+                Flow::Next => {}
+                flow => return Ok(flow),
+            }
+        }
+        Ok(Flow::Next)
+    }
+
+    /// 执行 `Scope`/`Device` 等的对象列表；加载时出错只放弃这一块
+    fn execute_scope(&mut self, body: &'static [u8], node: NodeId) -> Result<(), AmlError> {
+        match self.execute_block(body, node) {
+            Err(_) if self.loading => {
+                self.load_errors += 1;
+                Ok(())
+            }
+            result => result.map(|_| ()),
+        }
+    }
+
+    /// 在嵌套深度限制内执行 `f`
+    fn nested<R>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<R, AmlError>,
+    ) -> Result<R, AmlError> {
+        if self.nesting >= MAX_NESTING {
+            return Err(AmlError::NestingDepth);
+        }
+        self.nesting += 1;
+        let result = f(self);
+        self.nesting -= 1;
+        result
+    }
+
+    fn execute_term(&mut self, c: &mut Cursor, scope: NodeId) -> Result<Flow, AmlError> {
+        self.nested(|interpreter| interpreter.execute_op(c, scope))
+    }
+
+    fn execute_op(&mut self, c: &mut Cursor, scope: NodeId) -> Result<Flow, AmlError> {
+        match c.peek()? {
+            // Alias
+            0x06 => {
+                c.pos += 1;
+                let source = c.name_string()?;
+                let alias = c.name_string()?;
+                let target = self.resolve(scope, &source).ok_or(AmlError::NotFound)?;
+                self.define(scope, &alias, Object::Alias(target))?;
+            }
+            // Name
+            0x08 => {
+                c.pos += 1;
+                let name = c.name_string()?;
+                let value = self.evaluate(c, scope)?;
+                self.define(scope, &name, value)?;
+            }
+            // Scope
+            0x10 => {
+                c.pos += 1;
+                let block = c.block()?;
+                let mut inner = Cursor::new(block);
+                let name = inner.name_string()?;
+                let node = self.resolve(scope, &name).ok_or(AmlError::NotFound)?;
+                self.execute_scope(&block[inner.pos..], node)?;
+            }
+            // Method
+            0x14 => {
+                c.pos += 1;
+                let block = c.block()?;
+                let mut inner = Cursor::new(block);
+                let name = inner.name_string()?;
+                let flags = inner.byte()?;
+                let method = Method {
+                    body: MethodBody::Aml(&block[inner.pos..]),
+                    args: (flags & 0x07) as usize,
+                };
+                self.define(scope, &name, Object::Method(method))?;
+            }
+            // External
+            0x15 => {
+                c.pos += 1;
+                c.name_string()?;
+                c.bytes(2)?;
+            }
+            // CreateDWordField / WordField / ByteField / BitField / QWordField
+            0x8A | 0x8B | 0x8C | 0x8D | 0x8F => {
+                let opcode = c.byte()?;
+                let source = self.reference_operand(c, scope)?;
+                let index = self.integer_operand(c, scope)? as usize;
+                let (bit_offset, bit_length) = match opcode {
+                    0x8A => (index * 8, 32),
+                    0x8B => (index * 8, 16),
+                    0x8C => (index * 8, 8),
+                    0x8D => (index, 1),
+                    _ => (index * 8, 64),
+                };
+                let name = c.name_string()?;
+                self.define(
+                    scope,
+                    &name,
+                    Object::BufferField {
+                        source,
+                        bit_offset,
+                        bit_length,
+                    },
+                )?;
+            }
+            // Notify：没有通知处理程序，只求值操作数
+            0x86 => {
+                c.pos += 1;
+                self.super_name(c, scope)?;
+                self.evaluate(c, scope)?;
+            }
+            // Continue / Noop / Return / Break / Breakpoint
+            0x9F => {
+                c.pos += 1;
+                return Ok(Flow::Continue);
+            }
+            0xA3 | 0xCC => c.pos += 1,
+            0xA4 => {
+                c.pos += 1;
+                let value = self.evaluate(c, scope)?;
+                return Ok(Flow::Return(value));
+            }
+            0xA5 => {
+                c.pos += 1;
+                return Ok(Flow::Break);
+            }
+            // If / Else
+            0xA0 => {
+                c.pos += 1;
+                let block = c.block()?;
+                let mut inner = Cursor::new(block);
+                let predicate = self.integer_operand(&mut inner, scope)? != 0;
+                let body = &block[inner.pos..];
+                let else_body = if !c.at_end() && c.peek()? == 0xA1 {
+                    c.pos += 1;
+                    Some(c.block()?)
+                } else {
+                    None
+                };
+                return match (predicate, else_body) {
+                    (true, _) => self.execute_block(body, scope),
+                    (false, Some(else_body)) => self.execute_block(else_body, scope),
+                    (false, None) => Ok(Flow::Next),
+                };
+            }
+            // 孤立的 Else
+            0xA1 => {
+                c.pos += 1;
+                c.block()?;
+            }
+            // While
+            0xA2 => {
+                c.pos += 1;
+                let block = c.block()?;
+                let mut iterations = 0;
+                loop {
+                    let mut inner = Cursor::new(block);
+                    if self.integer_operand(&mut inner, scope)? == 0 {
+                        break;
+                    }
+                    iterations += 1;
+                    if iterations > MAX_LOOP_ITERATIONS {
+                        return Err(AmlError::LoopLimit);
+                    }
+                    match self.execute_block(&block[inner.pos..], scope)? {
+                        Flow::Break => break,
+                        Flow::Return(value) => return Ok(Flow::Return(value)),
+                        Flow::Next | Flow::Continue => {}
+                    }
+                }
+            }
+            EXT_PREFIX => return self.execute_ext_term(c, scope),
+            _ => {
+                self.evaluate(c, scope)?;
+            }
+        }
+        Ok(Flow::Next)
+    }
+
+    /// 以 0x5B 开头的语句
+    fn execute_ext_term(&mut self, c: &mut Cursor, scope: NodeId) -> Result<Flow, AmlError> {
+        let opcode = *c.code.get(c.pos + 1).ok_or(AmlError::UnexpectedEnd)?;
+        match opcode {
+            // Mutex
+            0x01 => {
+                c.pos += 2;
+                let name = c.name_string()?;
+                c.byte()?;
+                self.define(scope, &name, Object::Mutex)?;
+            }
+            // Event
+            0x02 => {
+                c.pos += 2;
+                let name = c.name_string()?;
+                self.define(scope, &name, Object::Event)?;
+            }
+            // CreateField
+            0x13 => {
+                c.pos += 2;
+                let source = self.reference_operand(c, scope)?;
+                let bit_offset = self.integer_operand(c, scope)? as usize;
+                let bit_length = self.integer_operand(c, scope)? as usize;
+                let name = c.name_string()?;
+                self.define(
+                    scope,
+                    &name,
+                    Object::BufferField {
+                        source,
+                        bit_offset,
+                        bit_length,
+                    },
+                )?;
+            }
+            // Stall（微秒）/ Sleep（毫秒）
+            0x21 => {
+                c.pos += 2;
+                let us = self.integer_operand(c, scope)?;
+                self.handler.stall(Duration::from_micros(us));
+            }
+            0x22 => {
+                c.pos += 2;
+                let ms = self.integer_operand(c, scope)?;
+                self.handler.sleep(Duration::from_millis(ms));
+            }
+            // Signal / Reset / Release：单线程解释器中没有等待者
+            0x24 | 0x26 | 0x27 => {
+                c.pos += 2;
+                self.super_name(c, scope)?;
+            }
+            // Fatal
+            0x32 => {
+                c.pos += 2;
+                c.bytes(5)?;
+                self.evaluate(c, scope)?;
+                return Err(AmlError::Fatal);
+            }
+            // OperationRegion
+            0x80 => {
+                c.pos += 2;
+                let name = c.name_string()?;
+                let space = c.byte()?;
+                let offset = self.integer_operand(c, scope)?;
+                let length = self.integer_operand(c, scope)?;
+                self.define(
+                    scope,
+                    &name,
+                    Object::Region(Region {
+                        space,
+                        offset,
+                        length,
+                        mapped: None,
+                        pci: None,
+                    }),
+                )?;
+            }
+            // Field
+            0x81 => {
+                c.pos += 2;
+                let mut inner = Cursor::new(c.block()?);
+                let region = inner.name_string()?;
+                let region = self.resolve(scope, &region).ok_or(AmlError::NotFound)?;
+                let flags = inner.byte()?;
+                self.field_list(&mut inner, scope, FieldKind::Region(region), flags)?;
+            }
+            // Device / Processor / PowerResource / ThermalZone
+            0x82..=0x85 => {
+                c.pos += 2;
+                let block = c.block()?;
+                let mut inner = Cursor::new(block);
+                let name = inner.name_string()?;
+                let object = match opcode {
+                    0x82 => Object::Device,
+                    0x83 => {
+                        // ProcID、PblkAddr、PblkLen
+                        inner.bytes(6)?;
+                        Object::Processor
+                    }
+                    0x84 => {
+                        // SystemLevel、ResourceOrder
+                        inner.bytes(3)?;
+                        Object::PowerResource
+                    }
+                    _ => Object::ThermalZone,
+                };
+                let node = self.define(scope, &name, object)?;
+                self.execute_scope(&block[inner.pos..], node)?;
+            }
+            // IndexField
+            0x86 => {
+                c.pos += 2;
+                let mut inner = Cursor::new(c.block()?);
+                let index = inner.name_string()?;
+                let data = inner.name_string()?;
+                let index = self.resolve(scope, &index).ok_or(AmlError::NotFound)?;
+                let data = self.resolve(scope, &data).ok_or(AmlError::NotFound)?;
+                let flags = inner.byte()?;
+                self.field_list(&mut inner, scope, FieldKind::Index { index, data }, flags)?;
+            }
+            // BankField
+            0x87 => {
+                c.pos += 2;
+                let mut inner = Cursor::new(c.block()?);
+                let region = inner.name_string()?;
+                let bank = inner.name_string()?;
+                let region = self.resolve(scope, &region).ok_or(AmlError::NotFound)?;
+                let bank = self.resolve(scope, &bank).ok_or(AmlError::NotFound)?;
+                let value = self.integer_operand(&mut inner, scope)?;
+                let flags = inner.byte()?;
+                self.field_list(
+                    &mut inner,
+                    scope,
+                    FieldKind::Bank {
+                        region,
+                        bank,
+                        value,
+                    },
+                    flags,
+                )?;
+            }
+            // DataTableRegion：以签名找到的 ACPI 表作为 SystemMemory 区域
+            0x88 => {
+                c.pos += 2;
+                let name = c.name_string()?;
+                let signature = self.string_operand(c, scope)?;
+                self.string_operand(c, scope)?;
+                self.string_operand(c, scope)?;
+                let signature: &[u8; 4] = signature
+                    .as_bytes()
+                    .try_into()
+                    .map_err(|_| AmlError::InvalidArgument)?;
+                let (address, length) = self
+                    .handler
+                    .find_table(signature)
+                    .ok_or(AmlError::NotFound)?;
+                self.define(
+                    scope,
+                    &name,
+                    Object::Region(Region {
+                        space: SPACE_SYSTEM_MEMORY,
+                        offset: address,
+                        length,
+                        mapped: None,
+                        pci: None,
+                    }),
+                )?;
+            }
+            _ => {
+                self.evaluate(c, scope)?;
+            }
+        }
+        Ok(Flow::Next)
+    }
+
+    /// 字段列表：命名字段、保留位、访问方式变更与连接
+    fn field_list(
+        &mut self,
+        c: &mut Cursor,
+        scope: NodeId,
+        kind: FieldKind,
+        mut flags: u8,
+    ) -> Result<(), AmlError> {
+        let mut bit_offset = 0;
+        while !c.at_end() {
+            match c.peek()? {
+                // ReservedField
+                0x00 => {
+                    c.pos += 1;
+                    bit_offset += c.pkg_length()?;
+                }
+                // AccessField：访问类型与属性
+                0x01 => {
+                    c.pos += 1;
+                    let access = c.byte()?;
+                    c.byte()?;
+                    flags = (flags & !ACCESS_TYPE_MASK) | (access & ACCESS_TYPE_MASK);
+                }
+                // ConnectField：GPIO/串行总线连接，解析后忽略
+                0x02 => {
+                    c.pos += 1;
+                    if c.peek()? == 0x11 {
+                        self.evaluate(c, scope)?;
+                    } else {
+                        c.name_string()?;
+                    }
+                }
+                // ExtendedAccessField：访问类型、属性与长度
+                0x03 => {
+                    c.pos += 1;
+                    let access = c.byte()?;
+                    c.bytes(2)?;
+                    flags = (flags & !ACCESS_TYPE_MASK) | (access & ACCESS_TYPE_MASK);
+                }
+                _ => {
+                    let name = c.name_seg()?;
+                    let bit_length = c.pkg_length()?;
+                    let name = NameString {
+                        segments: vec![name],
+                        ..NameString::default()
+                    };
+                    let field = Field {
+                        kind,
+                        flags,
+                        bit_offset,
+                        bit_length,
+                    };
+                    self.define(scope, &name, Object::Field(field))?;
+                    bit_offset += bit_length;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// ============================================================================
+// 表达式
+// ============================================================================
+
+impl Interpreter {
+    /// 求值一个 TermArg
+    fn evaluate(&mut self, c: &mut Cursor, scope: NodeId) -> Result<Object, AmlError> {
+        self.nested(|interpreter| interpreter.evaluate_op(c, scope))
+    }
+
+    fn evaluate_op(&mut self, c: &mut Cursor, scope: NodeId) -> Result<Object, AmlError> {
+        let opcode = c.peek()?;
+        if is_name_start(opcode) {
+            return self.evaluate_name(c, scope);
+        }
+        c.pos += 1;
+        Ok(match opcode {
+            0x00 => Object::Integer(0),
+            0x01 => Object::Integer(1),
+            0xFF => Object::Integer(self.integer_mask),
+            0x0A => Object::Integer(c.integer(1)?),
+            0x0B => Object::Integer(c.integer(2)?),
+            0x0C => Object::Integer(c.integer(4)?),
+            0x0E => Object::Integer(c.integer(8)? & self.integer_mask),
+            // String
+            0x0D => {
+                let start = c.pos;
+                while c.byte()? != 0 {}
+                Object::String(
+                    c.code[start..c.pos - 1]
+                        .iter()
+                        .map(|&b| b as char)
+                        .collect(),
+                )
+            }
+            // Buffer
+            0x11 => {
+                let mut inner = Cursor::new(c.block()?);
+                let size = self.integer_operand(&mut inner, scope)? as usize;
+                let init = &inner.code[inner.pos..];
+                let mut bytes = vec![0; size.max(init.len())];
+                bytes[..init.len()].copy_from_slice(init);
+                Object::Buffer(bytes)
+            }
+            // Package / VarPackage
+            0x12 | 0x13 => {
+                let mut inner = Cursor::new(c.block()?);
+                let count = if opcode == 0x12 {
+                    inner.byte()? as usize
+                } else {
+                    self.integer_operand(&mut inner, scope)? as usize
+                };
+                let mut elements = Vec::with_capacity(count);
+                while !inner.at_end() {
+                    let element = if is_name_start(inner.peek()?) {
+                        // 包中的名字是对象引用，找不到时保留路径字符串
+                        let name = inner.name_string()?;
+                        match self.resolve(scope, &name) {
+                            Some(node) => Object::Reference(Reference::Node(node)),
+                            None => Object::String(name_to_string(&name)),
+                        }
+                    } else {
+                        self.evaluate(&mut inner, scope)?
+                    };
+                    elements.push(element);
+                }
+                elements.resize(count.max(elements.len()), Object::Uninitialized);
+                Object::Package(elements)
+            }
+            0x60..=0x67 => self.frame()?.locals[(opcode - 0x60) as usize].clone(),
+            0x68..=0x6E => self.frame()?.args[(opcode - 0x68) as usize].clone(),
+            // Store
+            0x70 => {
+                let value = self.evaluate(c, scope)?;
+                let target = self.super_name(c, scope)?;
+                self.store(value.clone(), &target)?;
+                value
+            }
+            // RefOf
+            0x71 => Object::Reference(self.super_name(c, scope)?),
+            // 二元整数运算
+            0x72 | 0x74 | 0x77 | 0x79 | 0x7A | 0x7B | 0x7C | 0x7D | 0x7E | 0x7F | 0x85 => {
+                let a = self.integer_operand(c, scope)?;
+                let b = self.integer_operand(c, scope)?;
+                let value = match opcode {
+                    0x72 => a.wrapping_add(b),
+                    0x74 => a.wrapping_sub(b),
+                    0x77 => a.wrapping_mul(b),
+                    0x79 => a.checked_shl(b as u32).unwrap_or(0),
+                    0x7A => a.checked_shr(b as u32).unwrap_or(0),
+                    0x7B => a & b,
+                    0x7C => !(a & b),
+                    0x7D => a | b,
+                    0x7E => !(a | b),
+                    0x7F => a ^ b,
+                    _ => a.checked_rem(b).ok_or(AmlError::DivideByZero)?,
+                };
+                self.store_result(c, scope, Object::Integer(value & self.integer_mask))?
+            }
+            // Concat
+            0x73 => {
+                let a = self.evaluate(c, scope)?;
+                let b = self.evaluate(c, scope)?;
+                let value = self.concat(a, b)?;
+                self.store_result(c, scope, value)?
+            }
+            // Increment / Decrement
+            0x75 | 0x76 => {
+                let target = self.super_name(c, scope)?;
+                let value = self.read_ref(&target)?;
+                let value = self.to_integer(&value)?;
+                let value = if opcode == 0x75 {
+                    value.wrapping_add(1)
+                } else {
+                    value.wrapping_sub(1)
+                };
+                let value = Object::Integer(value & self.integer_mask);
+                self.store(value.clone(), &target)?;
+                value
+            }
+            // Divide：余数目标在前，商目标在后
+            0x78 => {
+                let dividend = self.integer_operand(c, scope)?;
+                let divisor = self.integer_operand(c, scope)?;
+                if divisor == 0 {
+                    return Err(AmlError::DivideByZero);
+                }
+                self.store_result(c, scope, Object::Integer(dividend % divisor))?;
+                self.store_result(c, scope, Object::Integer(dividend / divisor))?
+            }
+            // Not / FindSetLeftBit / FindSetRightBit / FromBCD / ToBCD
+            0x80..=0x82 => {
+                let value = self.integer_operand(c, scope)?;
+                let value = match opcode {
+                    0x80 => !value & self.integer_mask,
+                    0x81 => 64 - value.leading_zeros() as u64,
+                    _ if value == 0 => 0,
+                    _ => value.trailing_zeros() as u64 + 1,
+                };
+                self.store_result(c, scope, Object::Integer(value))?
+            }
+            // DerefOf
+            0x83 => {
+                let target = self.evaluate(c, scope)?;
+                self.dereference(target, scope)?
+            }
+            // ConcatRes：拼接两个资源模板
+            0x84 => {
+                let a = self.evaluate(c, scope)?;
+                let b = self.evaluate(c, scope)?;
+                let mut bytes =
+                    strip_end_tag(a.as_buffer().ok_or(AmlError::TypeMismatch)?).to_vec();
+                bytes
+                    .extend_from_slice(strip_end_tag(b.as_buffer().ok_or(AmlError::TypeMismatch)?));
+                bytes.extend_from_slice(&[0x79, 0]);
+                self.store_result(c, scope, Object::Buffer(bytes))?
+            }
+            // SizeOf
+            0x87 => {
+                let target = self.super_name(c, scope)?;
+                let size = match self.read_ref(&target)? {
+                    Object::String(s) => s.len(),
+                    Object::Buffer(b) => b.len(),
+                    Object::Package(p) => p.len(),
+                    _ => return Err(AmlError::TypeMismatch),
+                };
+                Object::Integer(size as u64)
+            }
+            // Index
+            0x88 => {
+                let source = self.reference_operand(c, scope)?;
+                let index = self.integer_operand(c, scope)? as usize;
+                let reference = Object::Reference(Reference::Element(Box::new(source), index));
+                self.store_result(c, scope, reference)?
+            }
+            // Match
+            0x89 => {
+                let package = self.evaluate(c, scope)?;
+                let op1 = c.byte()?;
+                let value1 = self.integer_operand(c, scope)?;
+                let op2 = c.byte()?;
+                let value2 = self.integer_operand(c, scope)?;
+                let start = self.integer_operand(c, scope)? as usize;
+                let elements = package.as_package().ok_or(AmlError::TypeMismatch)?;
+                let found = elements
+                    .iter()
+                    .enumerate()
+                    .skip(start)
+                    .find(|(_, element)| {
+                        element.as_integer().is_some_and(|element| {
+                            match_op(op1, element, value1) && match_op(op2, element, value2)
+                        })
+                    });
+                Object::Integer(found.map_or(self.integer_mask, |(index, _)| index as u64))
+            }
+            // ObjectType
+            0x8E => {
+                let target = self.super_name(c, scope)?;
+                let code = match &target {
+                    Reference::Node(node) => self.nodes[*node].object.type_code(),
+                    reference => self.read_ref(reference)?.type_code(),
+                };
+                Object::Integer(code)
+            }
+            // LAnd / LOr
+            0x90 | 0x91 => {
+                let a = self.integer_operand(c, scope)? != 0;
+                let b = self.integer_operand(c, scope)? != 0;
+                self.boolean(if opcode == 0x90 { a && b } else { a || b })
+            }
+            // LNot
+            0x92 => {
+                let value = self.integer_operand(c, scope)?;
+                self.boolean(value == 0)
+            }
+            // LEqual / LGreater / LLess
+            0x93..=0x95 => {
+                let a = self.evaluate(c, scope)?;
+                let b = self.evaluate(c, scope)?;
+                let ordering = self.compare(&a, &b)?;
+                self.boolean(match opcode {
+                    0x93 => ordering.is_eq(),
+                    0x94 => ordering.is_gt(),
+                    _ => ordering.is_lt(),
+                })
+            }
+            // ToBuffer
+            0x96 => {
+                let value = self.evaluate(c, scope)?;
+                let value = Object::Buffer(self.to_buffer(&value)?);
+                self.store_result(c, scope, value)?
+            }
+            // ToDecimalString / ToHexString
+            0x97 | 0x98 => {
+                let value = self.evaluate(c, scope)?;
+                let value = Object::String(to_display_string(&value, opcode == 0x98)?);
+                self.store_result(c, scope, value)?
+            }
+            // ToInteger
+            0x99 => {
+                let value = self.evaluate(c, scope)?;
+                let value = match value {
+                    Object::String(s) => Object::Integer(parse_integer(&s) & self.integer_mask),
+                    value => Object::Integer(self.to_integer(&value)?),
+                };
+                self.store_result(c, scope, value)?
+            }
+            // ToString
+            0x9C => {
+                let value = self.evaluate(c, scope)?;
+                let length = self.integer_operand(c, scope)? as usize;
+                let bytes = value.as_buffer().ok_or(AmlError::TypeMismatch)?;
+                let string = bytes
+                    .iter()
+                    .take(length)
+                    .take_while(|&&b| b != 0)
+                    .map(|&b| b as char)
+                    .collect();
+                self.store_result(c, scope, Object::String(string))?
+            }
+            // CopyObject：不做类型转换地覆盖目标
+            0x9D => {
+                let value = self.evaluate(c, scope)?;
+                let target = self.super_name(c, scope)?;
+                self.set_raw(&target, value.clone())?;
+                value
+            }
+            // Mid
+            0x9E => {
+                let value = self.evaluate(c, scope)?;
+                let index = self.integer_operand(c, scope)? as usize;
+                let length = self.integer_operand(c, scope)? as usize;
+                let value = match value {
+                    Object::String(s) => {
+                        Object::String(s.chars().skip(index).take(length).collect())
+                    }
+                    Object::Buffer(b) => {
+                        Object::Buffer(b.iter().skip(index).take(length).copied().collect())
+                    }
+                    _ => return Err(AmlError::TypeMismatch),
+                };
+                self.store_result(c, scope, value)?
+            }
+            EXT_PREFIX => self.evaluate_ext(c, scope)?,
+            _ => return Err(AmlError::UnknownOpcode(opcode as u16)),
+        })
+    }
+
+    /// 以 0x5B 开头的表达式
+    fn evaluate_ext(&mut self, c: &mut Cursor, scope: NodeId) -> Result<Object, AmlError> {
+        let opcode = c.byte()?;
+        Ok(match opcode {
+            // CondRefOf：名字不存在时返回 False 而不是出错
+            0x12 => {
+                let reference = if is_name_start(c.peek()?) {
+                    let name = c.name_string()?;
+                    self.resolve(scope, &name).map(Reference::Node)
+                } else {
+                    Some(self.super_name(c, scope)?)
+                };
+                let found = reference.is_some();
+                if let Some(reference) = reference {
+                    self.store_result(c, scope, Object::Reference(reference))?;
+                } else {
+                    self.target(c, scope)?;
+                }
+                self.boolean(found)
+            }
+            // Acquire / Wait：总是立即成功
+            0x23 | 0x25 => {
+                self.super_name(c, scope)?;
+                if opcode == 0x23 {
+                    c.bytes(2)?;
+                } else {
+                    self.integer_operand(c, scope)?;
+                }
+                Object::Integer(0)
+            }
+            // FromBCD / ToBCD
+            0x28 | 0x29 => {
+                let mut value = self.integer_operand(c, scope)?;
+                let mut result = 0;
+                let mut shift = 0;
+                while value != 0 && shift < 64 {
+                    if opcode == 0x28 {
+                        result += (value & 0xF) * 10u64.pow(shift / 4);
+                        value >>= 4;
+                    } else {
+                        result |= (value % 10) << shift;
+                        value /= 10;
+                    }
+                    shift += 4;
+                }
+                self.store_result(c, scope, Object::Integer(result))?
+            }
+            // Revision
+            0x30 => Object::Integer(ACPI_REVISION),
+            // Timer：100 ns 单位
+            0x33 => Object::Integer((self.handler.now().as_nanos() / 100) as u64),
+            _ => return Err(AmlError::UnknownOpcode(0x5B00 | opcode as u16)),
+        })
+    }
+
+    /// 名字作为 TermArg：方法则按参数个数读实参并调用，否则读对象的值
+    fn evaluate_name(&mut self, c: &mut Cursor, scope: NodeId) -> Result<Object, AmlError> {
+        let name = c.name_string()?;
+        let node = self.resolve(scope, &name).ok_or(AmlError::NotFound)?;
+        if let Object::Method(method) = self.nodes[node].object {
+            let mut args = Vec::with_capacity(method.args);
+            for _ in 0..method.args {
+                args.push(self.evaluate(c, scope)?);
+            }
+            return self.invoke(node, args);
+        }
+        self.read_node(node)
+    }
+
+    /// 调用方法；返回值缺省为 Uninitialized
+    fn invoke(&mut self, node: NodeId, args: Vec<Object>) -> Result<Object, AmlError> {
+        let Object::Method(method) = self.nodes[node].object else {
+            return Err(AmlError::TypeMismatch);
+        };
+        if args.len() < method.args {
+            return Err(AmlError::InvalidArgument);
+        }
+        let code = match method.body {
+            MethodBody::Native(native) => return native(self, &args),
+            MethodBody::Aml(code) => code,
+        };
+        let mut frame = Frame {
+            locals: core::array::from_fn(|_| Object::Uninitialized),
+            args: core::array::from_fn(|_| Object::Uninitialized),
+            temporaries: Vec::new(),
+        };
+        for (slot, arg) in frame.args.iter_mut().zip(args) {
+            *slot = arg;
+        }
+        self.frames.push(frame);
+        let result = self.nested(|interpreter| interpreter.execute_block(code, node));
+        let frame = self.frames.pop().unwrap();
+        for temporary in frame.temporaries.into_iter().rev() {
+            self.remove(temporary);
+        }
+        match result? {
+            Flow::Return(value) => Ok(value),
+            _ => Ok(Object::Uninitialized),
+        }
+    }
+
+    fn frame(&self) -> Result<&Frame, AmlError> {
+        self.frames.last().ok_or(AmlError::InvalidArgument)
+    }
+
+    fn frame_index(&self) -> Result<usize, AmlError> {
+        self.frames
+            .len()
+            .checked_sub(1)
+            .ok_or(AmlError::InvalidArgument)
+    }
+
+    fn integer_operand(&mut self, c: &mut Cursor, scope: NodeId) -> Result<u64, AmlError> {
+        let value = self.evaluate(c, scope)?;
+        self.to_integer(&value)
+    }
+
+    fn string_operand(&mut self, c: &mut Cursor, scope: NodeId) -> Result<String, AmlError> {
+        match self.evaluate(c, scope)? {
+            Object::String(s) => Ok(s),
+            _ => Err(AmlError::TypeMismatch),
+        }
+    }
+
+    /// 需要可写位置的操作数（`Index`、`CreateXxxField` 的源）；其他表达式按临时值处理
+    fn reference_operand(&mut self, c: &mut Cursor, scope: NodeId) -> Result<Reference, AmlError> {
+        match c.peek()? {
+            op if is_name_start(op) || (0x60..=0x6E).contains(&op) => self.super_name(c, scope),
+            _ => match self.evaluate(c, scope)? {
+                Object::Reference(reference) => Ok(reference),
+                value => Ok(Reference::Value(Box::new(value))),
+            },
+        }
+    }
+
+    /// SuperName：名字、LocalX、ArgX、Debug 或返回引用的表达式
+    fn super_name(&mut self, c: &mut Cursor, scope: NodeId) -> Result<Reference, AmlError> {
+        let opcode = c.peek()?;
+        if is_name_start(opcode) {
+            let name = c.name_string()?;
+            return self
+                .resolve(scope, &name)
+                .map(Reference::Node)
+                .ok_or(AmlError::NotFound);
+        }
+        match opcode {
+            0x60..=0x67 => {
+                c.pos += 1;
+                Ok(Reference::Local(
+                    self.frame_index()?,
+                    (opcode - 0x60) as usize,
+                ))
+            }
+            // 保存引用的 ArgX 作为目标时写到被引用的对象
+            0x68..=0x6E => {
+                c.pos += 1;
+                let frame = self.frame_index()?;
+                let index = (opcode - 0x68) as usize;
+                Ok(match &self.frames[frame].args[index] {
+                    Object::Reference(reference) => reference.clone(),
+                    _ => Reference::Arg(frame, index),
+                })
+            }
+            EXT_PREFIX if c.code.get(c.pos + 1) == Some(&0x31) => {
+                c.pos += 2;
+                Ok(Reference::Debug)
+            }
+            _ => match self.evaluate(c, scope)? {
+                Object::Reference(reference) => Ok(reference),
+                _ => Err(AmlError::TypeMismatch),
+            },
+        }
+    }
+
+    /// 可选的目标：NullName 表示不保存
+    fn target(&mut self, c: &mut Cursor, scope: NodeId) -> Result<Option<Reference>, AmlError> {
+        if c.peek()? == 0x00 {
+            c.pos += 1;
+            return Ok(None);
+        }
+        self.super_name(c, scope).map(Some)
+    }
+
+    /// 读目标并把结果存进去，返回结果
+    fn store_result(
+        &mut self,
+        c: &mut Cursor,
+        scope: NodeId,
+        value: Object,
+    ) -> Result<Object, AmlError> {
+        if let Some(target) = self.target(c, scope)? {
+            self.store(value.clone(), &target)?;
+        }
+        Ok(value)
+    }
+
+    /// `DerefOf`：引用或路径字符串
+    fn dereference(&mut self, target: Object, scope: NodeId) -> Result<Object, AmlError> {
+        match target {
+            Object::Reference(reference) => self.read_ref(&reference),
+            Object::String(path) => {
+                let name = NameString::from_path(&path)?;
+                let node = self.resolve(scope, &name).ok_or(AmlError::NotFound)?;
+                self.read_node(node)
+            }
+            _ => Err(AmlError::TypeMismatch),
+        }
+    }
+
+    /// 节点的值；字段从硬件读取
+    fn read_node(&mut self, node: NodeId) -> Result<Object, AmlError> {
+        let node = self.follow_alias(node);
+        match self.nodes[node].object.clone() {
+            Object::Field(field) => self.read_field(&field),
+            Object::BufferField {
+                source,
+                bit_offset,
+                bit_length,
+            } => {
+                let source = self.read_ref(&source)?;
+                let bytes = self.to_buffer(&source)?;
+                Ok(bits_to_object(&bytes, bit_offset, bit_length))
+            }
+            object => Ok(object),
+        }
+    }
+
+    fn read_ref(&mut self, reference: &Reference) -> Result<Object, AmlError> {
+        match reference {
+            Reference::Node(node) => self.read_node(*node),
+            Reference::Local(frame, index) => Ok(self.frames[*frame].locals[*index].clone()),
+            Reference::Arg(frame, index) => Ok(self.frames[*frame].args[*index].clone()),
+            Reference::Element(base, index) => match self.read_ref(base)? {
+                Object::Package(elements) => {
+                    elements.get(*index).cloned().ok_or(AmlError::InvalidIndex)
+                }
+                Object::Buffer(bytes) => bytes
+                    .get(*index)
+                    .map(|&b| Object::Integer(b as u64))
+                    .ok_or(AmlError::InvalidIndex),
+                Object::String(s) => s
+                    .as_bytes()
+                    .get(*index)
+                    .map(|&b| Object::Integer(b as u64))
+                    .ok_or(AmlError::InvalidIndex),
+                _ => Err(AmlError::TypeMismatch),
+            },
+            Reference::Value(value) => Ok((**value).clone()),
+            Reference::Debug => Ok(Object::Uninitialized),
+        }
+    }
+
+    /// `Store`：写字段、按具名整数/缓冲区/字符串的类型转换，其他位置直接覆盖
+    fn store(&mut self, value: Object, target: &Reference) -> Result<(), AmlError> {
+        let Reference::Node(node) = *target else {
+            return self.set_raw(target, value);
+        };
+        let node = self.follow_alias(node);
+        match self.nodes[node].object.clone() {
+            Object::Field(field) => self.write_field(&field, &value),
+            Object::BufferField {
+                source,
+                bit_offset,
+                bit_length,
+            } => {
+                let Object::Buffer(mut bytes) = self.read_ref(&source)? else {
+                    return Err(AmlError::TypeMismatch);
+                };
+                set_bits(
+                    &mut bytes,
+                    bit_offset,
+                    bit_length,
+                    &self.value_bytes(&value)?,
+                );
+                self.set_raw(&source, Object::Buffer(bytes))
+            }
+            Object::Integer(_) => {
+                self.nodes[node].object = Object::Integer(self.to_integer(&value)?);
+                Ok(())
+            }
+            Object::String(_) => {
+                self.nodes[node].object = Object::String(to_implicit_string(&value)?);
+                Ok(())
+            }
+            Object::Buffer(old) => {
+                // 具名缓冲区长度不变：截断或补零
+                let mut bytes = self.to_buffer(&value)?;
+                bytes.resize(old.len(), 0);
+                self.nodes[node].object = Object::Buffer(bytes);
+                Ok(())
+            }
+            Object::Uninitialized | Object::Package(_) | Object::Reference(_) => {
+                self.nodes[node].object = value;
+                Ok(())
+            }
+            _ => Err(AmlError::TypeMismatch),
+        }
+    }
+
+    /// 不做转换地写入位置
+    fn set_raw(&mut self, target: &Reference, value: Object) -> Result<(), AmlError> {
+        match target {
+            Reference::Node(node) => {
+                let node = self.follow_alias(*node);
+                self.nodes[node].object = value;
+            }
+            Reference::Local(frame, index) => self.frames[*frame].locals[*index] = value,
+            Reference::Arg(frame, index) => self.frames[*frame].args[*index] = value,
+            Reference::Element(base, index) => {
+                let mut container = self.read_ref(base)?;
+                match &mut container {
+                    Object::Package(elements) => {
+                        *elements.get_mut(*index).ok_or(AmlError::InvalidIndex)? = value;
+                    }
+                    Object::Buffer(bytes) => {
+                        *bytes.get_mut(*index).ok_or(AmlError::InvalidIndex)? =
+                            self.to_integer(&value)? as u8;
+                    }
+                    _ => return Err(AmlError::TypeMismatch),
+                }
+                self.set_raw(base, container)?;
+            }
+            Reference::Value(_) | Reference::Debug => {}
+        }
+        Ok(())
+    }
+
+    fn boolean(&self, value: bool) -> Object {
+        Object::Integer(if value { self.integer_mask } else { 0 })
+    }
+
+    /// 隐式转换为整数
+    fn to_integer(&self, value: &Object) -> Result<u64, AmlError> {
+        let value = match value {
+            Object::Integer(value) => *value,
+            Object::String(s) => u64::from_str_radix(
+                &s.trim_start_matches("0x")
+                    .chars()
+                    .take_while(char::is_ascii_hexdigit)
+                    .collect::<String>(),
+                16,
+            )
+            .unwrap_or(0),
+            Object::Buffer(bytes) => bytes
+                .iter()
+                .take(8)
+                .rev()
+                .fold(0, |value, &b| value << 8 | b as u64),
+            _ => return Err(AmlError::TypeMismatch),
+        };
+        Ok(value & self.integer_mask)
+    }
+
+    /// 隐式转换为缓冲区
+    fn to_buffer(&self, value: &Object) -> Result<Vec<u8>, AmlError> {
+        match value {
+            Object::Integer(value) => Ok(value.to_le_bytes()[..self.integer_bytes()].to_vec()),
+            Object::String(s) => {
+                let mut bytes = s.as_bytes().to_vec();
+                bytes.push(0);
+                Ok(bytes)
+            }
+            Object::Buffer(bytes) => Ok(bytes.clone()),
+            _ => Err(AmlError::TypeMismatch),
+        }
+    }
+
+    /// 写字段时的源数据（小端字节）
+    fn value_bytes(&self, value: &Object) -> Result<Vec<u8>, AmlError> {
+        match value {
+            Object::Integer(value) => Ok(value.to_le_bytes().to_vec()),
+            Object::String(s) => Ok(s.as_bytes().to_vec()),
+            Object::Buffer(bytes) => Ok(bytes.clone()),
+            _ => Err(AmlError::TypeMismatch),
+        }
+    }
+
+    fn integer_bytes(&self) -> usize {
+        if self.integer_mask == u64::MAX { 8 } else { 4 }
+    }
+
+    /// 逻辑比较：按第一个操作数的类型转换第二个
+    fn compare(&self, a: &Object, b: &Object) -> Result<core::cmp::Ordering, AmlError> {
+        match a {
+            Object::Integer(a) => Ok(a.cmp(&self.to_integer(b)?)),
+            Object::String(a) => Ok(a.as_bytes().cmp(to_implicit_string(b)?.as_bytes())),
+            Object::Buffer(a) => Ok(a.as_slice().cmp(self.to_buffer(b)?.as_slice())),
+            _ => Err(AmlError::TypeMismatch),
+        }
+    }
+
+    fn concat(&self, a: Object, b: Object) -> Result<Object, AmlError> {
+        Ok(match a {
+            Object::Integer(a) => {
+                let mut bytes = a.to_le_bytes()[..self.integer_bytes()].to_vec();
+                bytes
+                    .extend_from_slice(&self.to_integer(&b)?.to_le_bytes()[..self.integer_bytes()]);
+                Object::Buffer(bytes)
+            }
+            Object::String(mut a) => {
+                a.push_str(&to_implicit_string(&b)?);
+                Object::String(a)
+            }
+            Object::Buffer(mut a) => {
+                a.extend_from_slice(&self.to_buffer(&b)?);
+                Object::Buffer(a)
+            }
+            _ => return Err(AmlError::TypeMismatch),
+        })
+    }
+}
+
+fn name_to_string(name: &NameString) -> String {
+    let mut path = String::new();
+    if name.root {
+        path.push('\\');
+    }
+    for _ in 0..name.parents {
+        path.push('^');
+    }
+    for (i, segment) in name.segments.iter().enumerate() {
+        if i != 0 {
+            path.push('.');
+        }
+        path.extend(segment.iter().map(|&c| c as char));
+    }
+    path
+}
+
+/// 隐式转换为字符串：整数为 16 位十六进制，缓冲区为空格分隔的十六进制字节
+fn to_implicit_string(value: &Object) -> Result<String, AmlError> {
+    match value {
+        Object::Integer(value) => Ok(format!("{value:016X}")),
+        Object::String(s) => Ok(s.clone()),
+        Object::Buffer(bytes) => Ok(bytes
+            .iter()
+            .map(|b| format!("{b:02X}"))
+            .collect::<Vec<_>>()
+            .join(" ")),
+        _ => Err(AmlError::TypeMismatch),
+    }
+}
+
+/// `ToDecimalString` / `ToHexString`
+fn to_display_string(value: &Object, hex: bool) -> Result<String, AmlError> {
+    let number = |value: u64| {
+        if hex {
+            format!("0x{value:X}")
+        } else {
+            value.to_string()
+        }
+    };
+    match value {
+        Object::Integer(value) => Ok(number(*value)),
+        Object::String(s) => Ok(s.clone()),
+        Object::Buffer(bytes) => Ok(bytes
+            .iter()
+            .map(|&b| number(b as u64))
+            .collect::<Vec<_>>()
+            .join(",")),
+        _ => Err(AmlError::TypeMismatch),
+    }
+}
+
+/// `ToInteger` 的字符串解析：`0x` 前缀为十六进制，否则为十进制
+fn parse_integer(s: &str) -> u64 {
+    let s = s.trim();
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => hex
+            .chars()
+            .map_while(|c| c.to_digit(16))
+            .fold(0u64, |value, digit| {
+                value.wrapping_mul(16).wrapping_add(digit as u64)
+            }),
+        None => s
+            .chars()
+            .map_while(|c| c.to_digit(10))
+            .fold(0u64, |value, digit| {
+                value.wrapping_mul(10).wrapping_add(digit as u64)
+            }),
+    }
+}
+
+/// `Match` 的比较操作
+fn match_op(op: u8, element: u64, value: u64) -> bool {
+    match op {
+        0 => true,
+        1 => element == value,
+        2 => element <= value,
+        3 => element < value,
+        4 => element >= value,
+        5 => element > value,
+        _ => false,
+    }
+}
+
+/// 去掉资源模板末尾的结束标记
+fn strip_end_tag(bytes: &[u8]) -> &[u8] {
+    match bytes {
+        [rest @ .., 0x79, _] => rest,
+        _ => bytes,
+    }
+}
+
+/// 从字节中取 `bit_length` 位；不超过 64 位时为整数，否则为缓冲区
+fn bits_to_object(bytes: &[u8], bit_offset: usize, bit_length: usize) -> Object {
+    let mut out = vec![0; bit_length.div_ceil(8)];
+    for i in 0..bit_length {
+        let bit = bit_offset + i;
+        if bytes.get(bit / 8).is_some_and(|&b| b >> (bit % 8) & 1 != 0) {
+            out[i / 8] |= 1 << (i % 8);
+        }
+    }
+    if bit_length <= 64 {
+        Object::Integer(out.iter().rev().fold(0, |value, &b| value << 8 | b as u64))
+    } else {
+        Object::Buffer(out)
+    }
+}
+
+/// 把 `value` 的低 `bit_length` 位写到 `bytes` 的 `bit_offset` 处（不足部分补 0）
+fn set_bits(bytes: &mut [u8], bit_offset: usize, bit_length: usize, value: &[u8]) {
+    for i in 0..bit_length {
+        let bit = bit_offset + i;
+        let Some(byte) = bytes.get_mut(bit / 8) else {
+            break;
+        };
+        if value.get(i / 8).is_some_and(|&b| b >> (i % 8) & 1 != 0) {
+            *byte |= 1 << (bit % 8);
+        } else {
+            *byte &= !(1 << (bit % 8));
+        }
+    }
+}
+
+// ============================================================================
+// 字段与操作区域
+// ============================================================================
+
+/// 整个映射的 SystemMemory 区域的最大长度
+const REGION_MAP_MAX: u64 = 1 << 20;
+
+impl Interpreter {
+    /// 按访问宽度逐个单元读字段
+    fn read_field(&mut self, field: &Field) -> Result<Object, AmlError> {
+        let width = field.access_bits();
+        let mut out = vec![0; field.bit_length.div_ceil(8)];
+        let mut done = 0;
+        while done < field.bit_length {
+            let bit = field.bit_offset + done;
+            let shift = bit % width;
+            let count = (width - shift).min(field.bit_length - done);
+            let raw = self.access(field, (bit - shift) / 8, width, None)?;
+            set_bits(&mut out, done, count, &(raw >> shift).to_le_bytes());
+            done += count;
+        }
+        Ok(bits_to_object(&out, 0, field.bit_length))
+    }
+
+    /// 按访问宽度逐个单元写字段；单元中不属于字段的位按更新规则处理
+    fn write_field(&mut self, field: &Field, value: &Object) -> Result<(), AmlError> {
+        let value = self.value_bytes(value)?;
+        let width = field.access_bits();
+        let mut done = 0;
+        while done < field.bit_length {
+            let bit = field.bit_offset + done;
+            let shift = bit % width;
+            let count = (width - shift).min(field.bit_length - done);
+            let offset = (bit - shift) / 8;
+            let mask = low_mask(count) << shift;
+            let piece = (get_bits(&value, done, count) << shift) & mask;
+            let rest = if count == width {
+                0
+            } else {
+                match field.flags >> UPDATE_RULE_SHIFT & 0b11 {
+                    UPDATE_PRESERVE => self.access(field, offset, width, None)? & !mask,
+                    UPDATE_WRITE_AS_ONES => !mask & low_mask(width),
+                    _ => 0,
+                }
+            };
+            self.access(field, offset, width, Some(rest | piece))?;
+            done += count;
+        }
+        Ok(())
+    }
+
+    /// 读或写字段所在的一个访问单元（`offset` 为字节偏移）
+    fn access(
+        &mut self,
+        field: &Field,
+        offset: usize,
+        width: usize,
+        write: Option<u64>,
+    ) -> Result<u64, AmlError> {
+        match field.kind {
+            FieldKind::Region(region) => self.region_access(region, offset as u64, width, write),
+            FieldKind::Index { index, data } => {
+                self.store(Object::Integer(offset as u64), &Reference::Node(index))?;
+                match write {
+                    Some(value) => {
+                        self.store(Object::Integer(value), &Reference::Node(data))?;
+                        Ok(0)
+                    }
+                    None => {
+                        let value = self.read_node(data)?;
+                        self.to_integer(&value)
+                    }
+                }
+            }
+            FieldKind::Bank {
+                region,
+                bank,
+                value,
+            } => {
+                self.store(Object::Integer(value), &Reference::Node(bank))?;
+                self.region_access(region, offset as u64, width, write)
+            }
+        }
+    }
+
+    fn region_access(
+        &mut self,
+        node: NodeId,
+        offset: u64,
+        width: usize,
+        write: Option<u64>,
+    ) -> Result<u64, AmlError> {
+        let Object::Region(mut region) = self.nodes[node].object else {
+            return Err(AmlError::TypeMismatch);
+        };
+        match region.space {
+            SPACE_SYSTEM_MEMORY => {
+                let base = match region.mapped {
+                    Some(base) => base,
+                    None => {
+                        if region.length > REGION_MAP_MAX {
+                            return Err(AmlError::RegionMap);
+                        }
+                        let base = self
+                            .handler
+                            .map_memory(region.offset, region.length.max(1))
+                            .ok_or(AmlError::RegionMap)?;
+                        region.mapped = Some(base);
+                        self.nodes[node].object = Object::Region(region);
+                        base
+                    }
+                };
+                if offset + width as u64 / 8 > region.length {
+                    return Err(AmlError::InvalidIndex);
+                }
+                Ok(match write {
+                    None => self.handler.read_memory(base + offset, width),
+                    Some(value) => {
+                        self.handler.write_memory(base + offset, width, value);
+                        0
+                    }
+                })
+            }
+            SPACE_SYSTEM_IO => {
+                let port = (region.offset + offset) as u16;
+                Ok(match write {
+                    None => self.handler.read_io(port, width),
+                    Some(value) => {
+                        self.handler.write_io(port, width, value);
+                        0
+                    }
+                })
+            }
+            SPACE_PCI_CONFIG => {
+                let address = match region.pci {
+                    Some(address) => address,
+                    None => {
+                        let address = self.pci_address(node)?;
+                        region.pci = Some(address);
+                        self.nodes[node].object = Object::Region(region);
+                        address
+                    }
+                };
+                let offset = (region.offset + offset) as u16;
+                Ok(match write {
+                    None => self.handler.read_pci(address, offset, width),
+                    Some(value) => {
+                        self.handler.write_pci(address, offset, width, value);
+                        0
+                    }
+                })
+            }
+            space => Err(AmlError::UnsupportedSpace(space)),
+        }
+    }
+
+    /// PCI 配置空间区域所属设备的位置：段与总线号取自上层根桥的 `_SEG` 与
+    /// `_BBN`，中间经过的 PCI-PCI 桥用其次级总线号，设备与功能取自 `_ADR`
+    fn pci_address(&mut self, region: NodeId) -> Result<PciAddress, AmlError> {
+        let device = self.nodes[region].parent;
+        // 从所在设备向上直到根桥（不含）的设备链
+        let mut chain = Vec::new();
+        let mut node = device;
+        let root = loop {
+            if self.is_root_bridge(node)? {
+                break Some(node);
+            }
+            if node == ROOT {
+                break None;
+            }
+            chain.push(node);
+            node = self.nodes[node].parent;
+        };
+        let (segment, mut bus) = match root {
+            Some(root) => (
+                self.child_integer(root, *b"_SEG")?.unwrap_or(0) as u16,
+                self.child_integer(root, *b"_BBN")?.unwrap_or(0) as u8,
+            ),
+            None => (0, 0),
+        };
+        // 区域直接在根桥下，或者找不到根桥时，只看所在设备自己
+        if chain.is_empty() || root.is_none() {
+            chain = vec![device];
+        }
+        let mut address = PciAddress {
+            segment,
+            bus,
+            device: 0,
+            function: 0,
+        };
+        for (i, &node) in chain.iter().rev().enumerate() {
+            let adr = self.child_integer(node, *b"_ADR")?.unwrap_or(0);
+            address = PciAddress {
+                segment,
+                bus,
+                device: (adr >> 16) as u8,
+                function: adr as u8,
+            };
+            if i + 1 < chain.len() {
+                bus = self.handler.read_pci(address, 0x19, 8) as u8;
+            }
+        }
+        Ok(address)
+    }
+}
+
+/// 低 `bits` 位为 1
+fn low_mask(bits: usize) -> u64 {
+    if bits >= 64 {
+        u64::MAX
+    } else {
+        (1 << bits) - 1
+    }
+}
+
+/// 从字节中取不超过 64 位
+fn get_bits(bytes: &[u8], bit_offset: usize, bit_length: usize) -> u64 {
+    (0..bit_length.min(64)).fold(0, |value, i| {
+        let bit = bit_offset + i;
+        match bytes.get(bit / 8) {
+            Some(&b) if b >> (bit % 8) & 1 != 0 => value | 1 << i,
+            _ => value,
+        }
+    })
+}
+
+// ============================================================================
+// 设备
+// ============================================================================
+
+/// PCI 根桥的硬件 ID（PCI 与 PCI Express）
+const PCI_ROOT_IDS: &[&str] = &["PNP0A03", "PNP0A08"];
+/// `_STA` 缺省值：存在、已启用、显示、正常工作
+const STA_DEFAULT: u64 = 0x0F;
+const STA_PRESENT: u64 = 1 << 0;
+const STA_FUNCTIONING: u64 = 1 << 3;
+
+impl Interpreter {
+    /// 求值子对象：方法无参调用，其他对象读值；不存在时为 None
+    fn evaluate_child(&mut self, node: NodeId, name: [u8; 4]) -> Result<Option<Object>, AmlError> {
+        let Some(child) = self.child(node, name) else {
+            return Ok(None);
+        };
+        let child = self.follow_alias(child);
+        match self.nodes[child].object {
+            Object::Method(_) => self.invoke(child, Vec::new()).map(Some),
+            _ => self.read_node(child).map(Some),
+        }
+    }
+
+    fn child_integer(&mut self, node: NodeId, name: [u8; 4]) -> Result<Option<u64>, AmlError> {
+        match self.evaluate_child(node, name)? {
+            Some(value) => self.to_integer(&value).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// `_STA`，没有时按存在且正常工作处理
+    fn status(&mut self, node: NodeId) -> Result<u64, AmlError> {
+        Ok(self.child_integer(node, *b"_STA")?.unwrap_or(STA_DEFAULT))
+    }
+
+    /// `_HID`（EISA ID 解码为字符串）
+    fn hardware_id(&mut self, node: NodeId) -> Result<Option<String>, AmlError> {
+        Ok(self
+            .evaluate_child(node, *b"_HID")?
+            .as_ref()
+            .and_then(device_id))
+    }
+
+    /// `_HID` 或 `_CID` 中是否有 PCI 根桥 ID
+    fn is_root_bridge(&mut self, node: NodeId) -> Result<bool, AmlError> {
+        if !matches!(self.nodes[node].object, Object::Device) {
+            return Ok(false);
+        }
+        let mut ids = Vec::new();
+        ids.extend(self.hardware_id(node)?);
+        match self.evaluate_child(node, *b"_CID")? {
+            Some(Object::Package(elements)) => ids.extend(elements.iter().filter_map(device_id)),
+            Some(id) => ids.extend(device_id(&id)),
+            None => {}
+        }
+        Ok(ids.iter().any(|id| PCI_ROOT_IDS.contains(&id.as_str())))
+    }
+
+    /// 按 ACPI 规范初始化设备：先运行 `\_SB._INI`，再深度优先对存在的设备
+    /// 运行 `_INI`；不存在且不工作的设备跳过其子树。返回运行的 `_INI` 个数
+    fn initialize_devices(&mut self) -> usize {
+        let mut initialized = 0;
+        if let Some(sb) = self.child(ROOT, *b"_SB_") {
+            if self.evaluate_child(sb, *b"_INI").is_ok_and(|ini| ini.is_some()) {
+                initialized += 1;
+            }
+        }
+        let mut stack: Vec<NodeId> = self.nodes[ROOT].children.iter().rev().copied().collect();
+        while let Some(node) = stack.pop() {
+            if matches!(self.nodes[node].object, Object::Device | Object::Processor) {
+                let status = self.status(node).unwrap_or(0);
+                if status & STA_PRESENT != 0 {
+                    match self.evaluate_child(node, *b"_INI") {
+                        Ok(Some(_)) => initialized += 1,
+                        Ok(None) => {}
+                        Err(_) => self.load_errors += 1,
+                    }
+                } else if status & STA_FUNCTIONING == 0 {
+                    continue;
+                }
+            }
+            if !matches!(self.nodes[node].object, Object::Method(_)) {
+                stack.extend(self.nodes[node].children.iter().rev());
+            }
+        }
+        initialized
+    }
+}
+
+/// `_HID`/`_CID` 值：字符串，或压缩的 EISA ID 整数（如 0x030AD041 为 PNP0A03）
+fn device_id(value: &Object) -> Option<String> {
+    match value {
+        Object::String(s) => Some(s.clone()),
+        Object::Integer(value) => {
+            let id = (*value as u32).swap_bytes();
+            let letter = |shift: u32| (((id >> shift) & 0x1F) as u8 + 0x40) as char;
+            Some(format!(
+                "{}{}{}{:04X}",
+                letter(26),
+                letter(21),
+                letter(16),
+                id & 0xFFFF
+            ))
+        }
+        _ => None,
+    }
+}
+
+// ============================================================================
+// 资源模板
+// ============================================================================
+
+/// 地址空间描述符的资源类型
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RangeKind {
+    Memory,
+    Io,
+    BusNumber,
+}
+
+impl RangeKind {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Memory => "mem",
+            Self::Io => "io",
+            Self::BusNumber => "bus",
+        }
+    }
+}
+
+/// 资源描述符（`_CRS`/`_PRS` 返回的缓冲区中的一项）
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Resource {
+    /// IRQ 或扩展中断描述符；`_PRS` 中可以列出多个可选中断
+    Interrupt {
+        numbers: Vec<u32>,
+        trigger: TriggerMode,
+        polarity: Polarity,
+        shared: bool,
+        /// 扩展中断描述符（否则为小型 IRQ 描述符）
+        extended: bool,
+    },
+    Io {
+        base: u16,
+        length: u16,
+    },
+    Memory {
+        base: u64,
+        length: u64,
+    },
+    /// Word/DWord/QWord 地址空间描述符
+    Range {
+        kind: RangeKind,
+        min: u64,
+        max: u64,
+        translation: u64,
+        length: u64,
+    },
+}
+
+/// 解析资源模板；不认识的描述符被跳过
+pub fn parse_resources(bytes: &[u8]) -> Vec<Resource> {
+    let mut resources = Vec::new();
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let tag = bytes[offset];
+        let (kind, data) = if tag & 0x80 == 0 {
+            let len = (tag & 0x07) as usize;
+            let Some(data) = bytes.get(offset + 1..offset + 1 + len) else {
+                break;
+            };
+            offset += 1 + len;
+            ((tag >> 3) & 0x0F, data)
+        } else {
+            let Some(len) = bytes.get(offset + 1..offset + 3) else {
+                break;
+            };
+            let len = u16::from_le_bytes([len[0], len[1]]) as usize;
+            let Some(data) = bytes.get(offset + 3..offset + 3 + len) else {
+                break;
+            };
+            offset += 3 + len;
+            (tag, data)
+        };
+        let le = |range: core::ops::Range<usize>| {
+            data[range]
+                .iter()
+                .rev()
+                .fold(0u64, |v, &b| v << 8 | b as u64)
+        };
+        let resource = match (kind, data.len()) {
+            // IRQ：缺省为边沿触发、高电平有效
+            (0x04, 2..) => {
+                let mask = le(0..2) as u32;
+                let flags = data.get(2).copied().unwrap_or(0x01);
+                Some(Resource::Interrupt {
+                    numbers: (0..16).filter(|irq| mask & 1 << irq != 0).collect(),
+                    trigger: if flags & 0x01 != 0 {
+                        TriggerMode::Edge
+                    } else {
+                        TriggerMode::Level
+                    },
+                    polarity: if flags & 0x08 != 0 {
+                        Polarity::ActiveLow
+                    } else {
+                        Polarity::ActiveHigh
+                    },
+                    shared: flags & 0x10 != 0,
+                    extended: false,
+                })
+            }
+            (0x08, 7..) => Some(Resource::Io {
+                base: le(1..3) as u16,
+                length: data[6] as u16,
+            }),
+            (0x09, 3..) => Some(Resource::Io {
+                base: le(0..2) as u16 & 0x3FF,
+                length: data[2] as u16,
+            }),
+            // 结束标记
+            (0x0F, _) => break,
+            (0x85, 17..) => Some(Resource::Memory {
+                base: le(1..5),
+                length: le(13..17),
+            }),
+            (0x86, 9..) => Some(Resource::Memory {
+                base: le(1..5),
+                length: le(5..9),
+            }),
+            // Word / DWord / QWord 地址空间
+            (0x87 | 0x88 | 0x8A, _) => {
+                let size = match kind {
+                    0x88 => 2,
+                    0x87 => 4,
+                    _ => 8,
+                };
+                let field = |index: usize| 3 + size * index..3 + size * (index + 1);
+                let range_kind = match data[0] {
+                    0 => Some(RangeKind::Memory),
+                    1 => Some(RangeKind::Io),
+                    2 => Some(RangeKind::BusNumber),
+                    _ => None,
+                };
+                range_kind
+                    .filter(|_| data.len() >= field(4).end)
+                    .map(|kind| Resource::Range {
+                        kind,
+                        min: le(field(1)),
+                        max: le(field(2)),
+                        translation: le(field(3)),
+                        length: le(field(4)),
+                    })
+            }
+            // 扩展中断
+            (0x89, 2..) => {
+                let flags = data[0];
+                let count = data[1] as usize;
+                Some(Resource::Interrupt {
+                    numbers: data[2..]
+                        .as_chunks::<4>()
+                        .0
+                        .iter()
+                        .take(count)
+                        .map(|&n| u32::from_le_bytes(n))
+                        .collect(),
+                    trigger: if flags & 0x02 != 0 {
+                        TriggerMode::Edge
+                    } else {
+                        TriggerMode::Level
+                    },
+                    polarity: if flags & 0x04 != 0 {
+                        Polarity::ActiveLow
+                    } else {
+                        Polarity::ActiveHigh
+                    },
+                    shared: flags & 0x08 != 0,
+                    extended: true,
+                })
+            }
+            _ => None,
+        };
+        resources.extend(resource);
+    }
+    resources
+}
+
+/// 只含一个中断的资源模板（`_SRS` 的参数）
+fn interrupt_template(
+    number: u32,
+    trigger: TriggerMode,
+    polarity: Polarity,
+    shared: bool,
+    extended: bool,
+) -> Vec<u8> {
+    let edge = trigger == TriggerMode::Edge;
+    let low = polarity == Polarity::ActiveLow;
+    let mut bytes = if extended {
+        let flags = 0x01 | (edge as u8) << 1 | (low as u8) << 2 | (shared as u8) << 3;
+        let mut bytes = vec![0x89, 6, 0, flags, 1];
+        bytes.extend_from_slice(&number.to_le_bytes());
+        bytes
+    } else {
+        let mask = (1u16 << (number & 0xF)).to_le_bytes();
+        let flags = edge as u8 | (low as u8) << 3 | (shared as u8) << 4;
+        vec![0x23, mask[0], mask[1], flags]
+    };
+    bytes.extend_from_slice(&[0x79, 0]);
+    bytes
+}
+
+// ============================================================================
+// PCI 中断路由
+// ============================================================================
+
+/// `_PRT` 中的一条路由：根总线上某个设备的一个中断引脚接到哪条 GSI
+#[derive(Clone, Debug)]
+pub struct PciRoute {
+    pub segment: u16,
+    pub bus: u8,
+    pub device: u8,
+    /// 0..=3 对应 INTA#..INTD#
+    pub pin: u8,
+    pub gsi: u32,
+    pub trigger: TriggerMode,
+    pub polarity: Polarity,
+    /// 经由的中断链接设备路径
+    pub link: Option<String>,
+}
+
+/// [`Interpreter::route_pci_interrupts`] 的结果
+#[derive(Clone, Debug, Default)]
+pub struct PciRouting {
+    pub routes: Vec<PciRoute>,
+    /// 因求值失败跳过的根桥与路由条目数
+    pub errors: usize,
+}
+
+/// 链接设备的中断：GSI、触发方式与极性
+type LinkInterrupt = (u32, TriggerMode, Polarity);
+
+impl Interpreter {
+    /// 解析所有存在的 PCI 根桥上的 `_PRT`，链接设备解析为 GSI
+    ///
+    /// 未分配中断的链接设备会经 `_SRS` 设置，因此这不是只读查询：应在选定中断
+    /// 模型 (`\_PIC`) 之后调用一次并保存结果。某个根桥或条目求值失败只跳过它，
+    /// 计入 [`PciRouting::errors`]。只覆盖根总线上的设备；PCI 桥后面的设备需按
+    /// 桥的引脚轮转换算。
+    pub fn route_pci_interrupts(&mut self) -> PciRouting {
+        let mut routing = PciRouting::default();
+        // 每个链接设备只求值（以及设置）一次，失败的也记住
+        let mut links: Vec<(NodeId, Result<LinkInterrupt, AmlError>)> = Vec::new();
+        for bridge in self.descendants(ROOT) {
+            if self
+                .root_bridge_routes(bridge, &mut links, &mut routing)
+                .is_err()
+            {
+                routing.errors += 1;
+            }
+        }
+        routing
+    }
+
+    /// 一个根桥的 `_PRT`；不是存在的根桥时什么也不做
+    fn root_bridge_routes(
+        &mut self,
+        bridge: NodeId,
+        links: &mut Vec<(NodeId, Result<LinkInterrupt, AmlError>)>,
+        routing: &mut PciRouting,
+    ) -> Result<(), AmlError> {
+        if !self.is_root_bridge(bridge)? || self.status(bridge)? & STA_PRESENT == 0 {
+            return Ok(());
+        }
+        let segment = self.child_integer(bridge, *b"_SEG")?.unwrap_or(0) as u16;
+        let bus = self.child_integer(bridge, *b"_BBN")?.unwrap_or(0) as u8;
+        let entries = match self.evaluate_child(bridge, *b"_PRT")? {
+            Some(Object::Package(entries)) => entries,
+            Some(_) => return Err(AmlError::TypeMismatch),
+            None => return Ok(()),
+        };
+        for entry in &entries {
+            match self.route_entry(bridge, entry, links) {
+                Ok((device, pin, (gsi, trigger, polarity), link)) => {
+                    routing.routes.push(PciRoute {
+                        segment,
+                        bus,
+                        device,
+                        pin,
+                        gsi,
+                        trigger,
+                        polarity,
+                        link: link.map(|link| self.path(link)),
+                    })
+                }
+                Err(_) => routing.errors += 1,
+            }
+        }
+        Ok(())
+    }
+
+    /// `_PRT` 的一项：(设备号, 引脚, 中断, 链接设备)
+    fn route_entry(
+        &mut self,
+        bridge: NodeId,
+        entry: &Object,
+        links: &mut Vec<(NodeId, Result<LinkInterrupt, AmlError>)>,
+    ) -> Result<(u8, u8, LinkInterrupt, Option<NodeId>), AmlError> {
+        let Some([address, pin, source, index]) = entry.as_package().and_then(|e| e.get(..4))
+        else {
+            return Err(AmlError::TypeMismatch);
+        };
+        let address = self.to_integer(address)?;
+        let pin = self.to_integer(pin)?;
+        let link = match source {
+            Object::Reference(Reference::Node(node)) => Some(*node),
+            Object::String(path) => {
+                let name = NameString::from_path(path)?;
+                Some(self.resolve(bridge, &name).ok_or(AmlError::NotFound)?)
+            }
+            _ => None,
+        };
+        // 没有链接设备时 SourceIndex 就是 GSI，电平触发、低电平有效
+        let interrupt = match link {
+            Some(link) => match links.iter().find(|(node, _)| *node == link) {
+                Some((_, interrupt)) => *interrupt,
+                None => {
+                    let interrupt = self.link_interrupt(link);
+                    links.push((link, interrupt));
+                    interrupt
+                }
+            }?,
+            None => (
+                self.to_integer(index)? as u32,
+                TriggerMode::Level,
+                Polarity::ActiveLow,
+            ),
+        };
+        Ok(((address >> 16) as u8, pin as u8, interrupt, link))
+    }
+
+    /// 链接设备当前的中断；未分配时取 `_PRS` 的第一个候选并用 `_SRS` 设置
+    fn link_interrupt(&mut self, link: NodeId) -> Result<LinkInterrupt, AmlError> {
+        let current = match self.evaluate_child(link, *b"_CRS")? {
+            Some(Object::Buffer(bytes)) => first_interrupt(&parse_resources(&bytes)),
+            _ => None,
+        };
+        if let Some((number, trigger, polarity, _, _)) =
+            current.filter(|interrupt| interrupt.0 != 0)
+        {
+            return Ok((number, trigger, polarity));
+        }
+        let Some(Object::Buffer(bytes)) = self.evaluate_child(link, *b"_PRS")? else {
+            return Err(AmlError::NotFound);
+        };
+        let (number, trigger, polarity, shared, extended) =
+            first_interrupt(&parse_resources(&bytes)).ok_or(AmlError::NotFound)?;
+        let srs = self.child(link, *b"_SRS").ok_or(AmlError::NotFound)?;
+        let template = interrupt_template(number, trigger, polarity, shared, extended);
+        self.invoke(srs, vec![Object::Buffer(template)])?;
+        Ok((number, trigger, polarity))
+    }
+}
+
+/// 第一个中断描述符的第一个中断号与属性
+fn first_interrupt(resources: &[Resource]) -> Option<(u32, TriggerMode, Polarity, bool, bool)> {
+    resources.iter().find_map(|resource| match resource {
+        Resource::Interrupt {
+            numbers,
+            trigger,
+            polarity,
+            shared,
+            extended,
+        } => numbers
+            .first()
+            .map(|&number| (number, *trigger, *polarity, *shared, *extended)),
+        _ => None,
+    })
+}
+
+// ============================================================================
+// 公开接口
+// ============================================================================
+
+/// 加载结果
+#[derive(Clone, Copy, Debug)]
+pub struct LoadInfo {
+    /// 加载的定义块个数（DSDT 与 SSDT）
+    pub tables: usize,
+    pub bytes: usize,
+    /// 命名空间中的对象个数
+    pub objects: usize,
+    pub devices: usize,
+    pub methods: usize,
+    /// 运行的 `_INI` 个数
+    pub initialized: usize,
+    /// 因错误跳过的块与失败的 `_INI` 个数
+    pub errors: usize,
+}
+
+/// 命名空间中的一个设备
+#[derive(Clone, Debug)]
+pub struct DeviceInfo {
+    pub path: String,
+    pub hid: Option<String>,
+    /// `_ADR`（PCI 设备为 设备号 << 16 | 功能号）
+    pub address: Option<u64>,
+    pub status: u64,
+}
+
+impl Interpreter {
+    /// 依次加载定义块（第一张是 DSDT，其余为 SSDT）并初始化设备
+    ///
+    /// 每张表都是含 36 字节表头的完整 ACPI 表；DSDT 的版本决定整数宽度。
+    pub fn load(
+        handler: Box<dyn Handler>,
+        tables: impl IntoIterator<Item = &'static [u8]>,
+    ) -> (Self, LoadInfo) {
+        let mut tables = tables.into_iter().peekable();
+        let revision = tables
+            .peek()
+            .and_then(|dsdt| dsdt.get(8))
+            .copied()
+            .unwrap_or(2);
+        let mut interpreter = Self::new(handler, revision);
+        let mut count = 0;
+        let mut bytes = 0;
+        for table in tables {
+            count += 1;
+            bytes += table.len();
+            if interpreter.load_table(table).is_err() {
+                interpreter.load_errors += 1;
+            }
+        }
+        let initialized = interpreter.initialize_devices();
+
+        let nodes = interpreter.descendants(ROOT);
+        let objects = |f: fn(&Object) -> bool| {
+            nodes
+                .iter()
+                .filter(|&&node| f(&interpreter.nodes[node].object))
+                .count()
+        };
+        let info = LoadInfo {
+            tables: count,
+            bytes,
+            objects: nodes.len(),
+            devices: objects(|object| matches!(object, Object::Device)),
+            methods: objects(|object| matches!(object, Object::Method(_))),
+            initialized,
+            errors: interpreter.load_errors,
+        };
+        (interpreter, info)
+    }
+
+    /// 按绝对路径（如 `\_SB.PCI0._CRS`）求值：方法以 `args` 调用，其他对象读值
+    pub fn evaluate_path(&mut self, path: &str, args: Vec<Object>) -> Result<Object, AmlError> {
+        let name = NameString::from_path(path)?;
+        let node = self.resolve(ROOT, &name).ok_or(AmlError::NotFound)?;
+        match self.nodes[node].object {
+            Object::Method(_) => self.invoke(node, args),
+            _ => self.read_node(node),
+        }
+    }
+
+    /// 调用可选的方法；方法不存在不算错误
+    fn call_optional(&mut self, path: &str, args: Vec<Object>) -> Result<(), AmlError> {
+        match self.evaluate_path(path, args) {
+            Ok(_) | Err(AmlError::NotFound) => Ok(()),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// 睡眠状态 `\_Sx` 的 (SLP_TYPa, SLP_TYPb)
+    pub fn sleep_type(&mut self, state: u8) -> Result<(u8, u8), AmlError> {
+        let package = self.evaluate_path(&format!("\\_S{state}"), Vec::new())?;
+        let elements = package.as_package().ok_or(AmlError::TypeMismatch)?;
+        let value = |index: usize| {
+            elements
+                .get(index)
+                .and_then(Object::as_integer)
+                .map(|v| v as u8)
+        };
+        let a = value(0).ok_or(AmlError::TypeMismatch)?;
+        Ok((a, value(1).unwrap_or(a)))
+    }
+
+    /// 进入睡眠状态前通知固件 (`\_PTS`)
+    pub fn prepare_to_sleep(&mut self, state: u8) -> Result<(), AmlError> {
+        self.call_optional("\\_PTS", vec![Object::Integer(state as u64)])
+    }
+
+    /// 告诉固件中断控制器工作在 APIC 模式 (`\_PIC(1)`)；会影响 `_PRT` 的返回值
+    pub fn enable_apic_mode(&mut self) -> Result<(), AmlError> {
+        self.call_optional("\\_PIC", vec![Object::Integer(1)])
+    }
+
+    /// 设备的当前资源 (`_CRS`)
+    pub fn resources(&mut self, path: &str) -> Result<Vec<Resource>, AmlError> {
+        let mut crs = String::from(path);
+        crs.push_str("._CRS");
+        match self.evaluate_path(&crs, Vec::new())? {
+            Object::Buffer(bytes) => Ok(parse_resources(&bytes)),
+            _ => Err(AmlError::TypeMismatch),
+        }
+    }
+
+    /// 命名空间中所有带 `_HID` 或 `_ADR` 的设备
+    pub fn devices(&mut self) -> Vec<DeviceInfo> {
+        let mut devices = Vec::new();
+        for node in self.descendants(ROOT) {
+            if !matches!(self.nodes[node].object, Object::Device) {
+                continue;
+            }
+            let hid = self.hardware_id(node).unwrap_or(None);
+            let address = self.child_integer(node, *b"_ADR").unwrap_or(None);
+            if hid.is_none() && address.is_none() {
+                continue;
+            }
+            devices.push(DeviceInfo {
+                path: self.path(node),
+                hid,
+                address,
+                status: self.status(node).unwrap_or(0),
+            });
+        }
+        devices
+    }
+
+    /// 根桥的资源：总线号范围与转发给它的 I/O、内存窗口
+    pub fn root_bridges(&mut self) -> Result<Vec<(String, Vec<Resource>)>, AmlError> {
+        let mut bridges = Vec::new();
+        for node in self.descendants(ROOT) {
+            if self.is_root_bridge(node)? {
+                let path = self.path(node);
+                let resources = self.resources(&path)?;
+                bridges.push((path, resources));
+            }
+        }
+        Ok(bridges)
+    }
+}
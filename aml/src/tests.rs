@@ -0,0 +1,1352 @@
+//! 解释器测试
+//!
+//! 测试表用下面的函数手工汇编，`pc_dsdt` 与 `q35_dsdt` 仿照 QEMU 为 i440fx 与
+//! q35 机型生成的 DSDT（睡眠类型、调试端口、`_PRT` 与中断链接设备、根桥资源）。
+
+use super::*;
+use std::collections::HashMap;
+use std::format;
+use std::sync::{Arc, Mutex};
+
+// ============================================================================
+// AML 汇编
+// ============================================================================
+
+/// PkgLength 编码；`len` 为其后内容的长度，编码值包含 PkgLength 自身
+fn pkg_length(len: usize) -> Vec<u8> {
+    if len + 1 < 0x40 {
+        vec![(len + 1) as u8]
+    } else if len + 2 < 1 << 12 {
+        let total = len + 2;
+        vec![0x40 | (total & 0x0F) as u8, (total >> 4) as u8]
+    } else {
+        let total = len + 3;
+        assert!(total < 1 << 20);
+        vec![
+            0x80 | (total & 0x0F) as u8,
+            (total >> 4) as u8,
+            (total >> 12) as u8,
+        ]
+    }
+}
+
+/// 字段单元的位数（与 PkgLength 同样编码，但不含自身长度）
+fn field_bits(bits: usize) -> Vec<u8> {
+    if bits < 0x40 {
+        vec![bits as u8]
+    } else {
+        vec![0x40 | (bits & 0x0F) as u8, (bits >> 4) as u8]
+    }
+}
+
+/// 带 PkgLength 的结构
+fn pkg(op: &[u8], parts: &[&[u8]]) -> Vec<u8> {
+    let body = parts.concat();
+    [op, &pkg_length(body.len()), &body].concat()
+}
+
+/// ASL 路径，如 `\_SB.PCI0`、`^LNKA`；不足 4 个字符的名字段以 `_` 补齐
+fn name(path: &str) -> Vec<u8> {
+    let prefix = path.len() - path.trim_start_matches(['\\', '^']).len();
+    let mut out = path.as_bytes()[..prefix].to_vec();
+    let segments: Vec<[u8; 4]> = path[prefix..]
+        .split('.')
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| {
+            let mut seg = [b'_'; 4];
+            seg[..segment.len()].copy_from_slice(segment.as_bytes());
+            seg
+        })
+        .collect();
+    match segments.len() {
+        0 => out.push(0),
+        1 => {}
+        2 => out.push(DUAL_NAME_PREFIX),
+        n => out.extend([MULTI_NAME_PREFIX, n as u8]),
+    }
+    out.extend(segments.concat());
+    out
+}
+
+fn int(value: u64) -> Vec<u8> {
+    match value {
+        0 | 1 => vec![value as u8],
+        2..=0xFF => vec![0x0A, value as u8],
+        0x100..=0xFFFF => [&[0x0B][..], &(value as u16).to_le_bytes()].concat(),
+        0x1_0000..=0xFFFF_FFFF => [&[0x0C][..], &(value as u32).to_le_bytes()].concat(),
+        _ => [&[0x0E][..], &value.to_le_bytes()].concat(),
+    }
+}
+
+const ONES: u8 = 0xFF;
+
+fn string(s: &str) -> Vec<u8> {
+    [&[0x0D], s.as_bytes(), &[0]].concat()
+}
+
+fn buffer(bytes: &[u8]) -> Vec<u8> {
+    pkg(&[0x11], &[&int(bytes.len() as u64), bytes])
+}
+
+fn package(elements: &[Vec<u8>]) -> Vec<u8> {
+    pkg(&[0x12], &[&[elements.len() as u8], &elements.concat()])
+}
+
+/// 压缩的 EISA ID，如 `PNP0A03`
+fn eisa_id(id: &str) -> Vec<u8> {
+    let b = id.as_bytes();
+    let letters = b[..3]
+        .iter()
+        .fold(0u32, |value, &c| value << 5 | (c - 0x40) as u32);
+    let product = u32::from_str_radix(&id[3..], 16).unwrap();
+    int((letters << 16 | product).swap_bytes() as u64)
+}
+
+fn scope(path: &str, body: &[Vec<u8>]) -> Vec<u8> {
+    pkg(&[0x10], &[&name(path), &body.concat()])
+}
+
+fn device(path: &str, body: &[Vec<u8>]) -> Vec<u8> {
+    pkg(&[EXT_PREFIX, 0x82], &[&name(path), &body.concat()])
+}
+
+fn method(path: &str, args: u8, body: &[Vec<u8>]) -> Vec<u8> {
+    pkg(&[0x14], &[&name(path), &[args], &body.concat()])
+}
+
+fn name_op(path: &str, value: Vec<u8>) -> Vec<u8> {
+    [vec![0x08], name(path), value].concat()
+}
+
+fn if_(predicate: Vec<u8>, body: &[Vec<u8>]) -> Vec<u8> {
+    pkg(&[0xA0], &[&predicate, &body.concat()])
+}
+
+fn else_(body: &[Vec<u8>]) -> Vec<u8> {
+    pkg(&[0xA1], &[&body.concat()])
+}
+
+fn while_(predicate: Vec<u8>, body: &[Vec<u8>]) -> Vec<u8> {
+    pkg(&[0xA2], &[&predicate, &body.concat()])
+}
+
+fn ret(value: Vec<u8>) -> Vec<u8> {
+    [vec![0xA4], value].concat()
+}
+
+fn store(value: Vec<u8>, target: Vec<u8>) -> Vec<u8> {
+    [vec![0x70], value, target].concat()
+}
+
+/// 二元运算，结果不另存
+fn binary(op: u8, a: Vec<u8>, b: Vec<u8>) -> Vec<u8> {
+    [vec![op], a, b, vec![0]].concat()
+}
+
+const ADD: u8 = 0x72;
+const SHIFT_LEFT: u8 = 0x79;
+const SHIFT_RIGHT: u8 = 0x7A;
+const AND: u8 = 0x7B;
+const OR: u8 = 0x7D;
+
+fn lequal(a: Vec<u8>, b: Vec<u8>) -> Vec<u8> {
+    [vec![0x93], a, b].concat()
+}
+
+fn lless(a: Vec<u8>, b: Vec<u8>) -> Vec<u8> {
+    [vec![0x95], a, b].concat()
+}
+
+fn increment(target: Vec<u8>) -> Vec<u8> {
+    [vec![0x75], target].concat()
+}
+
+fn index(source: Vec<u8>, i: Vec<u8>) -> Vec<u8> {
+    [vec![0x88], source, i, vec![0]].concat()
+}
+
+fn deref_of(reference: Vec<u8>) -> Vec<u8> {
+    [vec![0x83], reference].concat()
+}
+
+fn call(path: &str, args: &[Vec<u8>]) -> Vec<u8> {
+    [name(path), args.concat()].concat()
+}
+
+fn local(n: u8) -> Vec<u8> {
+    vec![0x60 + n]
+}
+
+fn arg(n: u8) -> Vec<u8> {
+    vec![0x68 + n]
+}
+
+fn op_region(path: &str, space: u8, offset: u64, length: u64) -> Vec<u8> {
+    [
+        vec![EXT_PREFIX, 0x80],
+        name(path),
+        vec![space],
+        int(offset),
+        int(length),
+    ]
+    .concat()
+}
+
+const BYTE_ACC: u8 = 1;
+const DWORD_ACC: u8 = 3;
+
+/// 字段；名字为空的单元是保留位
+fn field(region: &str, flags: u8, units: &[(&str, usize)]) -> Vec<u8> {
+    let units: Vec<u8> = units
+        .iter()
+        .flat_map(|&(unit, bits)| {
+            let unit = if unit.is_empty() { vec![0] } else { name(unit) };
+            [unit, field_bits(bits)].concat()
+        })
+        .collect();
+    pkg(&[EXT_PREFIX, 0x81], &[&name(region), &[flags], &units])
+}
+
+fn create_dword_field(source: Vec<u8>, offset: usize, path: &str) -> Vec<u8> {
+    [vec![0x8A], source, int(offset as u64), name(path)].concat()
+}
+
+/// 定义块：36 字节表头（QEMU 的 OEM 字段）加 AML
+fn table(revision: u8, body: &[Vec<u8>]) -> &'static [u8] {
+    let body = body.concat();
+    let mut table = Vec::new();
+    table.extend(b"DSDT");
+    table.extend((36 + body.len() as u32).to_le_bytes());
+    table.extend([revision, 0]);
+    table.extend(b"BOCHS BXPC    ");
+    table.extend(1u32.to_le_bytes());
+    table.extend(b"BXPC");
+    table.extend(1u32.to_le_bytes());
+    table.extend(body);
+    table[9] = 0u8.wrapping_sub(table.iter().fold(0u8, |sum, &b| sum.wrapping_add(b)));
+    Box::leak(table.into_boxed_slice())
+}
+
+// ============================================================================
+// 资源描述符
+// ============================================================================
+
+fn resource_template(descriptors: &[Vec<u8>]) -> Vec<u8> {
+    buffer(&[descriptors.concat(), vec![0x79, 0]].concat())
+}
+
+/// 小型 IRQ 描述符（带标志字节）
+fn irq(numbers: &[u8], flags: u8) -> Vec<u8> {
+    let mask = numbers.iter().fold(0u16, |mask, &n| mask | 1 << n);
+    [&[0x23][..], &mask.to_le_bytes(), &[flags]].concat()
+}
+
+/// 扩展中断描述符
+fn interrupt(flags: u8, numbers: &[u32]) -> Vec<u8> {
+    let len = 2 + 4 * numbers.len() as u16;
+    let mut bytes = [
+        &[0x89][..],
+        &len.to_le_bytes(),
+        &[flags, numbers.len() as u8],
+    ]
+    .concat();
+    for number in numbers {
+        bytes.extend(number.to_le_bytes());
+    }
+    bytes
+}
+
+/// ResourceConsumer, Level, ActiveHigh, Shared
+const LEVEL_HIGH_SHARED: u8 = 0x09;
+/// IRQ (Level, ActiveLow, Shared)
+const IRQ_LEVEL_LOW_SHARED: u8 = 0x18;
+
+fn io(min: u16, max: u16, align: u8, length: u8) -> Vec<u8> {
+    [
+        &[0x47, 0x01][..],
+        &min.to_le_bytes(),
+        &max.to_le_bytes(),
+        &[align, length],
+    ]
+    .concat()
+}
+
+/// Word/DWord/QWord 地址空间描述符，`size` 为每个数值字段的字节数
+fn address_space(kind: u8, size: usize, min: u64, max: u64, length: u64) -> Vec<u8> {
+    let tag = match size {
+        2 => 0x88,
+        4 => 0x87,
+        _ => 0x8A,
+    };
+    let len = (3 + 5 * size) as u16;
+    let mut bytes = [&[tag][..], &len.to_le_bytes(), &[kind, 0x0C, 0]].concat();
+    for value in [0, min, max, 0, length] {
+        bytes.extend(&value.to_le_bytes()[..size]);
+    }
+    bytes
+}
+
+const SPACE_MEMORY: u8 = 0;
+const SPACE_IO: u8 = 1;
+const SPACE_BUS: u8 = 2;
+
+// ============================================================================
+// 模拟平台
+// ============================================================================
+
+#[derive(Default)]
+struct Machine {
+    /// PCI 配置空间字节，键为 (总线, 设备, 功能, 偏移)；未写入的读作 0
+    pci: HashMap<(u8, u8, u8, u16), u8>,
+    memory: HashMap<u64, u8>,
+    /// I/O 端口写入记录 (端口, 宽度, 值)
+    io_writes: Vec<(u16, usize, u64)>,
+    /// PCI 配置空间写入记录 (功能, 偏移, 值)
+    pci_writes: Vec<((u8, u8, u8), u16, u64)>,
+}
+
+impl Machine {
+    fn set_pci(&mut self, address: (u8, u8, u8), offset: u16, bytes: &[u8]) {
+        for (i, &b) in bytes.iter().enumerate() {
+            self.pci
+                .insert((address.0, address.1, address.2, offset + i as u16), b);
+        }
+    }
+
+    fn pci_byte(&self, address: (u8, u8, u8), offset: u16) -> u8 {
+        let key = (address.0, address.1, address.2, offset);
+        self.pci.get(&key).copied().unwrap_or(0)
+    }
+}
+
+#[derive(Clone, Default)]
+struct TestHandler(Arc<Mutex<Machine>>);
+
+impl TestHandler {
+    fn machine(&self) -> std::sync::MutexGuard<'_, Machine> {
+        self.0.lock().unwrap()
+    }
+}
+
+impl Handler for TestHandler {
+    fn map_memory(&mut self, address: u64, _length: u64) -> Option<u64> {
+        Some(address)
+    }
+
+    fn read_memory(&mut self, address: u64, width: usize) -> u64 {
+        let machine = self.machine();
+        (0..width as u64 / 8).fold(0, |value, i| {
+            value | (machine.memory.get(&(address + i)).copied().unwrap_or(0) as u64) << (8 * i)
+        })
+    }
+
+    fn write_memory(&mut self, address: u64, width: usize, value: u64) {
+        let mut machine = self.machine();
+        for i in 0..width as u64 / 8 {
+            machine.memory.insert(address + i, (value >> (8 * i)) as u8);
+        }
+    }
+
+    fn read_io(&mut self, _port: u16, _width: usize) -> u64 {
+        0
+    }
+
+    fn write_io(&mut self, port: u16, width: usize, value: u64) {
+        self.machine().io_writes.push((port, width, value));
+    }
+
+    fn read_pci(&mut self, address: PciAddress, offset: u16, width: usize) -> u64 {
+        let machine = self.machine();
+        let function = (address.bus, address.device, address.function);
+        (0..width as u16 / 8).fold(0, |value, i| {
+            value | (machine.pci_byte(function, offset + i) as u64) << (8 * i)
+        })
+    }
+
+    fn write_pci(&mut self, address: PciAddress, offset: u16, width: usize, value: u64) {
+        let function = (address.bus, address.device, address.function);
+        let mut machine = self.machine();
+        machine.pci_writes.push((function, offset, value));
+        machine.set_pci(function, offset, &value.to_le_bytes()[..width / 8]);
+    }
+
+    fn stall(&mut self, _duration: Duration) {}
+
+    fn sleep(&mut self, _duration: Duration) {}
+
+    fn now(&mut self) -> Duration {
+        Duration::ZERO
+    }
+
+    fn find_table(&mut self, _signature: &[u8; 4]) -> Option<(u64, u64)> {
+        None
+    }
+}
+
+fn load_with(handler: &TestHandler, tables: &[&'static [u8]]) -> (Interpreter, LoadInfo) {
+    Interpreter::load(Box::new(handler.clone()), tables.iter().copied())
+}
+
+fn load(tables: &[&'static [u8]]) -> (Interpreter, LoadInfo) {
+    load_with(&TestHandler::default(), tables)
+}
+
+/// 解析 `_PRT`，要求没有失败的条目
+fn all_routes(interpreter: &mut Interpreter) -> Vec<PciRoute> {
+    let routing = interpreter.route_pci_interrupts();
+    assert_eq!(routing.errors, 0);
+    routing.routes
+}
+
+/// 在足够大的线程栈上运行：未优化的构建中解释器的栈帧比 release 大得多
+fn on_large_stack(f: impl FnOnce() + Send + 'static) {
+    std::thread::Builder::new()
+        .stack_size(64 << 20)
+        .spawn(f)
+        .unwrap()
+        .join()
+        .unwrap();
+}
+
+// ============================================================================
+// QEMU 风格的 DSDT
+// ============================================================================
+
+/// i440fx/PIIX 中 ISA 桥的位置（00:01.0），PIRQ 路由寄存器在配置空间 0x60
+const PIIX_ISA: (u8, u8, u8) = (0, 1, 0);
+
+/// 仿照 QEMU pc 机型：`_PRT` 由循环生成，中断经 LNKA..LNKD 路由到 PIIX 的
+/// PIRQ 寄存器
+fn pc_dsdt() -> &'static [u8] {
+    let links = ["LNKD", "LNKA", "LNKB", "LNKC"];
+    let prt_case = |i: usize| {
+        if_(
+            lequal(local(3), int(i as u64)),
+            &[store(
+                package(&[int(0), int(0), name(links[i]), int(0)]),
+                local(4),
+            )],
+        )
+    };
+    let prt = method(
+        "_PRT",
+        0,
+        &[
+            store(pkg(&[0x12], &[&[0x80]]), local(0)),
+            store(int(0), local(1)),
+            while_(
+                lless(local(1), int(0x80)),
+                &[
+                    store(binary(SHIFT_RIGHT, local(1), int(2)), local(2)),
+                    store(
+                        binary(AND, binary(ADD, local(1), local(2)), int(3)),
+                        local(3),
+                    ),
+                    prt_case(0),
+                    prt_case(1),
+                    prt_case(2),
+                    prt_case(3),
+                    store(
+                        binary(OR, binary(SHIFT_LEFT, local(2), int(16)), int(0xFFFF)),
+                        index(local(4), int(0)),
+                    ),
+                    store(binary(AND, local(1), int(3)), index(local(4), int(1))),
+                    store(local(4), index(local(0), local(1))),
+                    increment(local(1)),
+                ],
+            ),
+            ret(local(0)),
+        ],
+    );
+    let pci0 = device(
+        "PCI0",
+        &[
+            name_op("_HID", eisa_id("PNP0A03")),
+            name_op("_ADR", int(0)),
+            name_op("_UID", int(0)),
+            name_op(
+                "_CRS",
+                resource_template(&[
+                    address_space(SPACE_BUS, 2, 0, 0xFF, 0x100),
+                    io(0xCF8, 0xCF8, 1, 8),
+                    address_space(SPACE_IO, 2, 0, 0xCF7, 0xCF8),
+                    address_space(SPACE_IO, 2, 0xD00, 0xFFFF, 0xF300),
+                    address_space(SPACE_MEMORY, 4, 0xA_0000, 0xB_FFFF, 0x2_0000),
+                    address_space(SPACE_MEMORY, 4, 0x8000_0000, 0xFEBF_FFFF, 0x7EC0_0000),
+                    address_space(SPACE_MEMORY, 8, 0x1_0000_0000, 0x8_FFFF_FFFF, 0x8_0000_0000),
+                ]),
+            ),
+            prt,
+            device(
+                "ISA",
+                &[
+                    name_op("_ADR", int(0x0001_0000)),
+                    op_region("P40C", SPACE_PCI_CONFIG, 0x60, 4),
+                ],
+            ),
+        ],
+    );
+    let mut sb = vec![
+        pci0,
+        field(
+            "PCI0.ISA.P40C",
+            BYTE_ACC,
+            &[("PRQ0", 8), ("PRQ1", 8), ("PRQ2", 8), ("PRQ3", 8)],
+        ),
+        method(
+            "IQST",
+            1,
+            &[
+                if_(binary(AND, int(0x80), arg(0)), &[ret(int(0x09))]),
+                ret(int(0x0B)),
+            ],
+        ),
+        method(
+            "IQCR",
+            1 | 1 << 3,
+            &[
+                name_op(
+                    "PRR0",
+                    resource_template(&[interrupt(LEVEL_HIGH_SHARED, &[0])]),
+                ),
+                create_dword_field(name("PRR0"), 5, "PRRI"),
+                store(binary(AND, arg(0), int(0x0F)), name("PRRI")),
+                ret(name("PRR0")),
+            ],
+        ),
+    ];
+    for (i, link) in ["LNKA", "LNKB", "LNKC", "LNKD"].into_iter().enumerate() {
+        let prq = format!("PRQ{i}");
+        sb.push(device(
+            link,
+            &[
+                name_op("_HID", eisa_id("PNP0C0F")),
+                name_op("_UID", int(i as u64)),
+                name_op(
+                    "_PRS",
+                    resource_template(&[interrupt(LEVEL_HIGH_SHARED, &[5, 10, 11])]),
+                ),
+                method("_STA", 0, &[ret(call("IQST", &[name(&prq)]))]),
+                method(
+                    "_DIS",
+                    0,
+                    &[store(binary(OR, name(&prq), int(0x80)), name(&prq))],
+                ),
+                method("_CRS", 0, &[ret(call("IQCR", &[name(&prq)]))]),
+                method(
+                    "_SRS",
+                    1,
+                    &[
+                        create_dword_field(arg(0), 5, "PRRI"),
+                        store(name("PRRI"), name(&prq)),
+                    ],
+                ),
+            ],
+        ));
+    }
+    table(
+        1,
+        &[
+            scope(
+                "\\",
+                &[
+                    op_region("DBG", SPACE_SYSTEM_IO, 0x402, 1),
+                    field("DBG", BYTE_ACC, &[("DBGB", 8)]),
+                    name_op("_S3", package(&[int(1), int(1), int(0), int(0)])),
+                    name_op("_S4", package(&[int(2), int(2), int(0), int(0)])),
+                    name_op("_S5", package(&[int(0), int(0), int(0), int(0)])),
+                    method("_PTS", 1, &[store(arg(0), name("DBGB"))]),
+                ],
+            ),
+            scope("\\_SB", &sb),
+        ],
+    )
+}
+
+/// q35 根桥 `_CRS` 模板及其中 32 位内存窗口 `_MIN`、`_LEN` 的字节偏移
+fn q35_crs() -> (Vec<u8>, usize, usize) {
+    let head = [
+        address_space(SPACE_BUS, 2, 0, 0xFF, 0x100),
+        io(0xCF8, 0xCF8, 1, 8),
+        address_space(SPACE_IO, 2, 0, 0xCF7, 0xCF8),
+        address_space(SPACE_IO, 2, 0xD00, 0xFFFF, 0xF300),
+        address_space(SPACE_MEMORY, 4, 0xA_0000, 0xB_FFFF, 0x2_0000),
+    ]
+    .concat();
+    // 32 位窗口在运行时由 _CRS 填入
+    let window = address_space(SPACE_MEMORY, 4, 0, 0xFEBF_FFFF, 0);
+    let offset = head.len() + 3;
+    (resource_template(&[head, window]), offset + 7, offset + 19)
+}
+
+/// 仿照 QEMU q35 机型：`_PRT` 按 `\_PIC` 设置的 PICF 返回 PIC 模式（LNKx）
+/// 或 APIC 模式（GSIx）的路由表，根桥 `_CRS` 运行时填写 32 位窗口
+fn q35_dsdt() -> &'static [u8] {
+    let (crs, min, len) = q35_crs();
+    let prt = |links: [&str; 4]| {
+        // 设备 1 与 2 的前两个引脚
+        package(&[
+            package(&[int(0x0001_FFFF), int(0), name(links[0]), int(0)]),
+            package(&[int(0x0001_FFFF), int(1), name(links[1]), int(0)]),
+            package(&[int(0x0002_FFFF), int(0), name(links[2]), int(0)]),
+            package(&[int(0x0002_FFFF), int(1), name(links[3]), int(0)]),
+        ])
+    };
+    let pic_link = |path: &str, uid: u64, irq_number: u8| {
+        device(
+            path,
+            &[
+                name_op("_HID", eisa_id("PNP0C0F")),
+                name_op("_UID", int(uid)),
+                name_op("_STA", int(0x0B)),
+                name_op(
+                    "_PRS",
+                    resource_template(&[irq(&[5, 10, 11], IRQ_LEVEL_LOW_SHARED)]),
+                ),
+                name_op(
+                    "_CRS",
+                    resource_template(&[irq(&[irq_number], IRQ_LEVEL_LOW_SHARED)]),
+                ),
+                method("_SRS", 1, &[]),
+            ],
+        )
+    };
+    let gsi_link = |path: &str, gsi: u32| {
+        let template = resource_template(&[interrupt(LEVEL_HIGH_SHARED, &[gsi])]);
+        device(
+            path,
+            &[
+                name_op("_HID", eisa_id("PNP0C0F")),
+                name_op("_UID", int(0)),
+                name_op("_PRS", template.clone()),
+                name_op("_CRS", template),
+                method("_SRS", 1, &[]),
+            ],
+        )
+    };
+    table(
+        1,
+        &[
+            scope(
+                "\\",
+                &[
+                    name_op("PICF", int(0)),
+                    method("_PIC", 1, &[store(arg(0), name("PICF"))]),
+                    name_op("_S5", package(&[int(0), int(0), int(0), int(0)])),
+                ],
+            ),
+            scope(
+                "\\_SB",
+                &[
+                    device(
+                        "PCI0",
+                        &[
+                            name_op("_HID", eisa_id("PNP0A08")),
+                            name_op("_CID", eisa_id("PNP0A03")),
+                            name_op("_ADR", int(0)),
+                            name_op("_UID", int(0)),
+                            method(
+                                "_CRS",
+                                1 << 3,
+                                &[
+                                    name_op("CRES", crs),
+                                    create_dword_field(name("CRES"), min, "PMIN"),
+                                    create_dword_field(name("CRES"), len, "PLEN"),
+                                    store(int(0x8000_0000), name("PMIN")),
+                                    store(int(0x7EC0_0000), name("PLEN")),
+                                    ret(name("CRES")),
+                                ],
+                            ),
+                            // 链接设备定义在后面，包里的名字加载时还解析不到
+                            name_op("PRTP", prt(["LNKE", "LNKF", "LNKF", "LNKG"])),
+                            name_op("PRTA", prt(["GSIE", "GSIF", "GSIF", "GSIG"])),
+                            method(
+                                "_PRT",
+                                0,
+                                &[
+                                    if_(lequal(name("PICF"), int(0)), &[ret(name("PRTP"))]),
+                                    else_(&[ret(name("PRTA"))]),
+                                ],
+                            ),
+                        ],
+                    ),
+                    pic_link("LNKE", 4, 10),
+                    pic_link("LNKF", 5, 11),
+                    pic_link("LNKG", 6, 10),
+                    gsi_link("GSIE", 0x14),
+                    gsi_link("GSIF", 0x15),
+                    gsi_link("GSIG", 0x16),
+                ],
+            ),
+        ],
+    )
+}
+
+// ============================================================================
+// 睡眠类型与 `\_PTS`
+// ============================================================================
+
+#[test]
+fn pc_sleep_types() {
+    let (mut interpreter, info) = load(&[pc_dsdt()]);
+    assert_eq!(info.errors, 0);
+    assert_eq!(interpreter.sleep_type(5), Ok((0, 0)));
+    assert_eq!(interpreter.sleep_type(3), Ok((1, 1)));
+    assert_eq!(interpreter.sleep_type(4), Ok((2, 2)));
+    assert_eq!(interpreter.sleep_type(1), Err(AmlError::NotFound));
+}
+
+#[test]
+fn sleep_type_with_one_value() {
+    let dsdt = table(2, &[name_op("\\_S5", package(&[int(7)]))]);
+    let (mut interpreter, _) = load(&[dsdt]);
+    assert_eq!(interpreter.sleep_type(5), Ok((7, 7)));
+}
+
+#[test]
+fn prepare_to_sleep_writes_debug_port() {
+    let handler = TestHandler::default();
+    let (mut interpreter, _) = load_with(&handler, &[pc_dsdt()]);
+    assert_eq!(interpreter.prepare_to_sleep(5), Ok(()));
+    assert_eq!(handler.machine().io_writes, [(0x402, 8, 5)]);
+
+    // 没有 _PTS 不算错误
+    let (mut interpreter, _) = load(&[q35_dsdt()]);
+    assert_eq!(interpreter.prepare_to_sleep(5), Ok(()));
+}
+
+// ============================================================================
+// `_PRT`
+// ============================================================================
+
+#[test]
+fn pc_prt_through_piix_links() {
+    let handler = TestHandler::default();
+    // LNKA、LNKB -> IRQ 10，LNKC -> IRQ 11，LNKD 未分配
+    handler
+        .machine()
+        .set_pci(PIIX_ISA, 0x60, &[0x0A, 0x0A, 0x0B, 0x80]);
+    let (mut interpreter, info) = load_with(&handler, &[pc_dsdt()]);
+    assert_eq!(info.errors, 0);
+
+    let routes = all_routes(&mut interpreter);
+    assert_eq!(routes.len(), 128);
+    let route = |device: u8, pin: u8| {
+        routes
+            .iter()
+            .find(|route| route.device == device && route.pin == pin)
+            .unwrap()
+    };
+    // 引脚按 (设备 + 引脚) % 4 轮转到 LNKD、LNKA、LNKB、LNKC
+    let lnka = route(1, 0);
+    assert_eq!((lnka.gsi, lnka.link.as_deref()), (10, Some("\\_SB_.LNKA")));
+    assert_eq!(
+        (lnka.trigger, lnka.polarity),
+        (TriggerMode::Level, Polarity::ActiveHigh)
+    );
+    assert_eq!((lnka.segment, lnka.bus), (0, 0));
+    assert_eq!(route(2, 0).link.as_deref(), Some("\\_SB_.LNKB"));
+    assert_eq!(route(3, 0).gsi, 11);
+    assert_eq!(route(3, 2).gsi, 10);
+
+    // 未分配的 LNKD 取 _PRS 的第一个中断并经 _SRS 写回 PIRQ 寄存器
+    let lnkd = route(0, 0);
+    assert_eq!((lnkd.gsi, lnkd.link.as_deref()), (5, Some("\\_SB_.LNKD")));
+    assert_eq!(handler.machine().pci_byte(PIIX_ISA, 0x63), 5);
+    // 同一个链接设备只设置一次
+    let writes = handler.machine().pci_writes.clone();
+    assert_eq!(writes, [(PIIX_ISA, 0x63, 5)]);
+    assert!(
+        routes
+            .iter()
+            .filter(|r| r.link == lnkd.link)
+            .all(|r| r.gsi == 5)
+    );
+}
+
+#[test]
+fn q35_prt_follows_pic_mode() {
+    let (mut interpreter, info) = load(&[q35_dsdt()]);
+    assert_eq!(info.errors, 0);
+
+    // PIC 模式：LNKx 的小型 IRQ 描述符
+    let routes = all_routes(&mut interpreter);
+    let summary: Vec<_> = routes
+        .iter()
+        .map(|route| {
+            (
+                route.device,
+                route.pin,
+                route.gsi,
+                route.link.clone().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            (1, 0, 10, "\\_SB_.LNKE".to_string()),
+            (1, 1, 11, "\\_SB_.LNKF".to_string()),
+            (2, 0, 11, "\\_SB_.LNKF".to_string()),
+            (2, 1, 10, "\\_SB_.LNKG".to_string()),
+        ]
+    );
+    assert_eq!(
+        (routes[0].trigger, routes[0].polarity),
+        (TriggerMode::Level, Polarity::ActiveLow)
+    );
+
+    // \_PIC(1) 之后是 GSIx
+    assert_eq!(interpreter.enable_apic_mode(), Ok(()));
+    let routes = all_routes(&mut interpreter);
+    let gsis: Vec<_> = routes.iter().map(|route| route.gsi).collect();
+    assert_eq!(gsis, [0x14, 0x15, 0x15, 0x16]);
+    assert_eq!(routes[0].link.as_deref(), Some("\\_SB_.GSIE"));
+    assert_eq!(
+        (routes[0].trigger, routes[0].polarity),
+        (TriggerMode::Level, Polarity::ActiveHigh)
+    );
+}
+
+#[test]
+fn prt_without_link_uses_source_index() {
+    let dsdt = table(
+        2,
+        &[scope(
+            "\\_SB",
+            &[device(
+                "PCI0",
+                &[
+                    name_op("_HID", eisa_id("PNP0A08")),
+                    name_op("_SEG", int(1)),
+                    name_op("_BBN", int(0x40)),
+                    name_op(
+                        "_PRT",
+                        package(&[package(&[int(0x0003_FFFF), int(2), int(0), int(0x22)])]),
+                    ),
+                ],
+            )],
+        )],
+    );
+    let (mut interpreter, _) = load(&[dsdt]);
+    let routes = all_routes(&mut interpreter);
+    assert_eq!(routes.len(), 1);
+    let route = &routes[0];
+    assert_eq!(
+        (route.segment, route.bus, route.device, route.pin, route.gsi),
+        (1, 0x40, 3, 2, 0x22)
+    );
+    assert_eq!(
+        (route.trigger, route.polarity, route.link.clone()),
+        (TriggerMode::Level, Polarity::ActiveLow, None)
+    );
+}
+
+#[test]
+fn prt_skips_failing_entries_and_bridges() {
+    let failing = || ret(deref_of(index(package(&[]), int(5))));
+    let bridge = |path: &str, body: &[Vec<u8>]| {
+        device(
+            path,
+            &[&[name_op("_HID", eisa_id("PNP0A03"))][..], body].concat(),
+        )
+    };
+    let entry = |slot: u64, source: Vec<u8>, index: u64| {
+        package(&[int(slot << 16 | 0xFFFF), int(0), source, int(index)])
+    };
+    let dsdt = table(
+        2,
+        &[scope(
+            "\\_SB",
+            &[
+                bridge(
+                    "PCI0",
+                    &[name_op(
+                        "_PRT",
+                        package(&[
+                            entry(1, name("LNKA"), 0),
+                            // 找不到的链接设备
+                            entry(2, string("NOPE"), 0),
+                            // 未分配且没有 _PRS
+                            entry(3, name("LNKB"), 0),
+                            entry(4, name("LNKB"), 0),
+                            // 少于 4 个元素
+                            package(&[int(0x0005_FFFF), int(0)]),
+                            entry(6, int(0), 0x17),
+                        ]),
+                    )],
+                ),
+                // _STA 与 _PRT 失败的根桥整体跳过
+                bridge("PCI1", &[method("_STA", 0, &[failing()])]),
+                bridge("PCI2", &[method("_PRT", 0, &[failing()])]),
+                device(
+                    "LNKA",
+                    &[name_op(
+                        "_CRS",
+                        resource_template(&[irq(&[11], IRQ_LEVEL_LOW_SHARED)]),
+                    )],
+                ),
+                device(
+                    "LNKB",
+                    &[name_op(
+                        "_CRS",
+                        resource_template(&[irq(&[], IRQ_LEVEL_LOW_SHARED)]),
+                    )],
+                ),
+            ],
+        )],
+    );
+    let (mut interpreter, _) = load(&[dsdt]);
+    let routing = interpreter.route_pci_interrupts();
+    let routes: Vec<_> = routing
+        .routes
+        .iter()
+        .map(|route| (route.device, route.gsi))
+        .collect();
+    assert_eq!(routes, [(1, 11), (6, 0x17)]);
+    assert_eq!(routing.errors, 6);
+}
+
+// ============================================================================
+// `_STA` / `_INI`
+// ============================================================================
+
+#[test]
+fn sta_and_ini_order() {
+    // 每次 MARK(n) 把 n 追加到 LOG 的低 4 位
+    let mark = |n: u64| call("\\MARK", &[int(n)]);
+    let ini = |n: u64| method("_INI", 0, &[mark(n)]);
+    let dsdt = table(
+        2,
+        &[
+            scope(
+                "\\",
+                &[
+                    name_op("LOG", int(0)),
+                    method(
+                        "MARK",
+                        1,
+                        &[store(
+                            binary(OR, binary(SHIFT_LEFT, name("LOG"), int(4)), arg(0)),
+                            name("LOG"),
+                        )],
+                    ),
+                ],
+            ),
+            scope(
+                "\\_SB",
+                &[
+                    // 父设备先于子设备
+                    device("DEVA", &[ini(2), device("DEVB", &[ini(3)])]),
+                    // 不存在也不工作：跳过整个子树
+                    device(
+                        "DEVC",
+                        &[name_op("_STA", int(0)), ini(4), device("DEVD", &[ini(5)])],
+                    ),
+                    // 不存在但在工作：不运行 _INI，仍然检查子设备
+                    device(
+                        "DEVE",
+                        &[
+                            name_op("_STA", int(0x08)),
+                            ini(6),
+                            device("DEVF", &[ini(7)]),
+                        ],
+                    ),
+                    // _STA 先于 _INI 求值
+                    device(
+                        "DEVG",
+                        &[method("_STA", 0, &[mark(8), ret(int(0x0F))]), ini(9)],
+                    ),
+                    // _INI 失败计入错误
+                    device(
+                        "DEVH",
+                        &[method(
+                            "_INI",
+                            0,
+                            &[mark(0xA), ret(deref_of(index(package(&[]), int(5))))],
+                        )],
+                    ),
+                    // \_SB._INI 最先运行
+                    ini(1),
+                ],
+            ),
+        ],
+    );
+    let (mut interpreter, info) = load(&[dsdt]);
+    assert_eq!(
+        interpreter
+            .evaluate_path("\\LOG", Vec::new())
+            .unwrap()
+            .as_integer(),
+        Some(0x0123_789A)
+    );
+    assert_eq!(info.initialized, 5);
+    assert_eq!(info.errors, 1);
+    assert_eq!(info.devices, 8);
+}
+
+// ============================================================================
+// `_CRS`
+// ============================================================================
+
+#[test]
+fn pc_root_bridge_resources() {
+    let (mut interpreter, _) = load(&[pc_dsdt()]);
+    let bridges = interpreter.root_bridges().unwrap();
+    assert_eq!(bridges.len(), 1);
+    let (path, resources) = &bridges[0];
+    assert_eq!(path, "\\_SB_.PCI0");
+    let range = |kind, min, max, length| Resource::Range {
+        kind,
+        min,
+        max,
+        translation: 0,
+        length,
+    };
+    assert_eq!(
+        resources,
+        &[
+            range(RangeKind::BusNumber, 0, 0xFF, 0x100),
+            Resource::Io {
+                base: 0xCF8,
+                length: 8
+            },
+            range(RangeKind::Io, 0, 0xCF7, 0xCF8),
+            range(RangeKind::Io, 0xD00, 0xFFFF, 0xF300),
+            range(RangeKind::Memory, 0xA_0000, 0xB_FFFF, 0x2_0000),
+            range(RangeKind::Memory, 0x8000_0000, 0xFEBF_FFFF, 0x7EC0_0000),
+            range(
+                RangeKind::Memory,
+                0x1_0000_0000,
+                0x8_FFFF_FFFF,
+                0x8_0000_0000
+            ),
+        ]
+    );
+}
+
+#[test]
+fn q35_crs_patched_at_runtime() {
+    let (mut interpreter, _) = load(&[q35_dsdt()]);
+    let resources = interpreter.resources("\\_SB.PCI0").unwrap();
+    assert_eq!(resources.len(), 6);
+    assert_eq!(
+        resources[5],
+        Resource::Range {
+            kind: RangeKind::Memory,
+            min: 0x8000_0000,
+            max: 0xFEBF_FFFF,
+            translation: 0,
+            length: 0x7EC0_0000,
+        }
+    );
+    // _CRS 是 Serialized 方法，其中的 Name 在返回后删除，可以再次调用
+    assert_eq!(interpreter.resources("\\_SB.PCI0").unwrap(), resources);
+}
+
+#[test]
+fn resource_descriptors() {
+    let bytes = [
+        // IRQNoFlags {1}：缺省边沿触发、高电平有效
+        vec![0x22, 0x02, 0x00],
+        irq(&[3, 4], IRQ_LEVEL_LOW_SHARED),
+        // DMA：不认识，跳过
+        vec![0x2A, 0x04, 0x00],
+        io(0x60, 0x60, 1, 1),
+        // FixedIO
+        vec![0x4B, 0x70, 0x04, 0x02],
+        // Memory32Fixed
+        [
+            &[0x86, 9, 0, 1][..],
+            &0xFED0_0000u32.to_le_bytes(),
+            &0x400u32.to_le_bytes(),
+        ]
+        .concat(),
+        interrupt(0x0F, &[0x10, 0x11]),
+        address_space(SPACE_MEMORY, 8, 0x1_0000_0000, 0x1_FFFF_FFFF, 0x1_0000_0000),
+        vec![0x79, 0],
+        // 结束标记之后的内容不再解析
+        io(0x80, 0x80, 1, 1),
+    ]
+    .concat();
+    assert_eq!(
+        parse_resources(&bytes),
+        [
+            Resource::Interrupt {
+                numbers: vec![1],
+                trigger: TriggerMode::Edge,
+                polarity: Polarity::ActiveHigh,
+                shared: false,
+                extended: false,
+            },
+            Resource::Interrupt {
+                numbers: vec![3, 4],
+                trigger: TriggerMode::Level,
+                polarity: Polarity::ActiveLow,
+                shared: true,
+                extended: false,
+            },
+            Resource::Io {
+                base: 0x60,
+                length: 1
+            },
+            Resource::Io {
+                base: 0x70,
+                length: 2
+            },
+            Resource::Memory {
+                base: 0xFED0_0000,
+                length: 0x400
+            },
+            Resource::Interrupt {
+                numbers: vec![0x10, 0x11],
+                trigger: TriggerMode::Edge,
+                polarity: Polarity::ActiveLow,
+                shared: true,
+                extended: true,
+            },
+            Resource::Range {
+                kind: RangeKind::Memory,
+                min: 0x1_0000_0000,
+                max: 0x1_FFFF_FFFF,
+                translation: 0,
+                length: 0x1_0000_0000,
+            },
+        ]
+    );
+    // 截断的描述符
+    assert_eq!(parse_resources(&[0x47, 0x01, 0x60]), []);
+}
+
+#[test]
+fn interrupt_template_round_trip() {
+    for extended in [false, true] {
+        let template =
+            interrupt_template(11, TriggerMode::Level, Polarity::ActiveLow, true, extended);
+        assert_eq!(
+            first_interrupt(&parse_resources(&template)),
+            Some((11, TriggerMode::Level, Polarity::ActiveLow, true, extended))
+        );
+    }
+}
+
+// ============================================================================
+// 设备与操作区域
+// ============================================================================
+
+#[test]
+fn devices_report_ids() {
+    let handler = TestHandler::default();
+    handler
+        .machine()
+        .set_pci(PIIX_ISA, 0x60, &[0x0A, 0x0A, 0x0B, 0x0B]);
+    let (mut interpreter, _) = load_with(&handler, &[pc_dsdt()]);
+    let devices = interpreter.devices();
+    let pci0 = devices.iter().find(|d| d.path == "\\_SB_.PCI0").unwrap();
+    assert_eq!(
+        (pci0.hid.as_deref(), pci0.address),
+        (Some("PNP0A03"), Some(0))
+    );
+    let isa = devices
+        .iter()
+        .find(|d| d.path == "\\_SB_.PCI0.ISA_")
+        .unwrap();
+    assert_eq!((isa.hid.as_deref(), isa.address), (None, Some(0x0001_0000)));
+    let lnka = devices.iter().find(|d| d.path == "\\_SB_.LNKA").unwrap();
+    assert_eq!((lnka.hid.as_deref(), lnka.status), (Some("PNP0C0F"), 0x0B));
+}
+
+#[test]
+fn pci_region_behind_bridge() {
+    let handler = TestHandler::default();
+    // 00:1e.0 是 PCI-PCI 桥，次级总线 5；设备 05:02.1
+    handler.machine().set_pci((0, 0x1E, 0), 0x19, &[5]);
+    handler
+        .machine()
+        .set_pci((5, 2, 1), 0, &0x1234_8086u32.to_le_bytes());
+    let dsdt = table(
+        2,
+        &[scope(
+            "\\_SB",
+            &[device(
+                "PCI0",
+                &[
+                    name_op("_HID", eisa_id("PNP0A03")),
+                    device(
+                        "BR1",
+                        &[
+                            name_op("_ADR", int(0x001E_0000)),
+                            device(
+                                "DEV0",
+                                &[
+                                    name_op("_ADR", int(0x0002_0001)),
+                                    op_region("CFG", SPACE_PCI_CONFIG, 0, 0x100),
+                                    field("CFG", DWORD_ACC, &[("VDID", 32), ("", 32), ("CMDB", 8)]),
+                                ],
+                            ),
+                        ],
+                    ),
+                ],
+            )],
+        )],
+    );
+    let (mut interpreter, _) = load_with(&handler, &[dsdt]);
+    let vdid = interpreter.evaluate_path("\\_SB.PCI0.BR1.DEV0.VDID", Vec::new());
+    assert_eq!(vdid.unwrap().as_integer(), Some(0x1234_8086));
+    handler
+        .machine()
+        .set_pci((5, 2, 1), 8, &[0x11, 0x22, 0x33, 0x44]);
+    let dsdt_write = store(int(0xAB), name("\\_SB.PCI0.BR1.DEV0.CMDB"));
+    let code: &'static [u8] = Box::leak(dsdt_write.into_boxed_slice());
+    interpreter.execute_block(code, ROOT).unwrap();
+    // Preserve：同一个 DWORD 中的其他字节保持不变
+    assert_eq!(handler.machine().pci_byte((5, 2, 1), 8), 0xAB);
+    assert_eq!(handler.machine().pci_byte((5, 2, 1), 9), 0x22);
+}
+
+#[test]
+fn system_memory_field() {
+    let handler = TestHandler::default();
+    for (i, b) in [0x11, 0x22, 0x33, 0x44].into_iter().enumerate() {
+        handler.machine().memory.insert(0xFED0_0000 + i as u64, b);
+    }
+    let dsdt = table(
+        2,
+        &[
+            op_region("\\MEM", SPACE_SYSTEM_MEMORY, 0xFED0_0000, 0x10),
+            field("\\MEM", DWORD_ACC, &[("", 8), ("VAL", 16)]),
+            method("\\SETV", 1, &[store(arg(0), name("VAL"))]),
+        ],
+    );
+    let (mut interpreter, _) = load_with(&handler, &[dsdt]);
+    let value = interpreter.evaluate_path("\\VAL", Vec::new()).unwrap();
+    assert_eq!(value.as_integer(), Some(0x3322));
+    interpreter
+        .evaluate_path("\\SETV", vec![Object::Integer(0xABCD)])
+        .unwrap();
+    let machine = handler.machine();
+    let bytes: Vec<u8> = (0..4).map(|i| machine.memory[&(0xFED0_0000 + i)]).collect();
+    assert_eq!(bytes, [0x11, 0xCD, 0xAB, 0x44]);
+}
+
+// ============================================================================
+// 整数宽度、`\_OSI` 与嵌套限制
+// ============================================================================
+
+#[test]
+fn revision_1_integers_are_32_bit() {
+    let body = [
+        name_op("\\ONES", vec![ONES]),
+        method("\\WRAP", 0, &[ret(binary(ADD, int(0xFFFF_FFFF), int(1)))]),
+    ];
+    let (mut interpreter, _) = load(&[table(1, &body)]);
+    let ones = interpreter.evaluate_path("\\ONES", Vec::new()).unwrap();
+    assert_eq!(ones.as_integer(), Some(0xFFFF_FFFF));
+    let wrap = interpreter.evaluate_path("\\WRAP", Vec::new()).unwrap();
+    assert_eq!(wrap.as_integer(), Some(0));
+
+    let (mut interpreter, _) = load(&[table(2, &body)]);
+    let ones = interpreter.evaluate_path("\\ONES", Vec::new()).unwrap();
+    assert_eq!(ones.as_integer(), Some(u64::MAX));
+}
+
+#[test]
+fn osi() {
+    let (mut interpreter, _) = load(&[pc_dsdt()]);
+    let mut osi = |name: &str| {
+        interpreter
+            .evaluate_path("\\_OSI", vec![Object::String(name.to_string())])
+            .unwrap()
+            .as_integer()
+    };
+    assert_eq!(osi("Windows 2015"), Some(0xFFFF_FFFF));
+    assert_eq!(osi("Linux"), Some(0));
+}
+
+/// `depth` 层嵌套的 Add，结果为 depth + 1
+fn nested_add(depth: usize) -> Vec<u8> {
+    (0..depth).fold(int(1), |inner, _| binary(ADD, inner, int(1)))
+}
+
+#[test]
+fn nesting_is_bounded() {
+    on_large_stack(nesting_is_bounded_inner);
+}
+
+fn nesting_is_bounded_inner() {
+    let dsdt = table(
+        2,
+        &[
+            method("\\SHAL", 0, &[ret(nested_add(8))]),
+            method("\\DEEP", 0, &[ret(nested_add(MAX_NESTING))]),
+            method("\\RECR", 1, &[ret(call("\\RECR", &[arg(0)]))]),
+            // 加载时出错只跳过这个设备
+            scope(
+                "\\_SB",
+                &[
+                    device("BAD", &[name_op("DEEP", nested_add(MAX_NESTING))]),
+                    device("GOOD", &[name_op("_ADR", int(5))]),
+                ],
+            ),
+        ],
+    );
+    let (mut interpreter, info) = load(&[dsdt]);
+    assert_eq!(info.errors, 1);
+    assert!(
+        interpreter
+            .devices()
+            .iter()
+            .any(|d| d.path == "\\_SB_.GOOD")
+    );
+
+    let shallow = interpreter.evaluate_path("\\SHAL", Vec::new()).unwrap();
+    assert_eq!(shallow.as_integer(), Some(9));
+    assert_eq!(
+        interpreter.evaluate_path("\\DEEP", Vec::new()).unwrap_err(),
+        AmlError::NestingDepth
+    );
+    assert_eq!(
+        interpreter
+            .evaluate_path("\\RECR", vec![Object::Integer(0)])
+            .unwrap_err(),
+        AmlError::NestingDepth
+    );
+    // 出错后计数恢复
+    let shallow = interpreter.evaluate_path("\\SHAL", Vec::new()).unwrap();
+    assert_eq!(shallow.as_integer(), Some(9));
+}
+
+#[test]
+fn call_chain_with_nested_control_flow() {
+    on_large_stack(|| {
+        // C1..C7 各在 While 里的 If 中调用下一层并加 1，C8 返回参数
+        let mut body = vec![method("\\C8", 1, &[ret(arg(0))])];
+        for n in 1..8 {
+            let next = format!("\\C{}", n + 1);
+            body.push(method(
+                &format!("\\C{n}"),
+                1,
+                &[
+                    store(int(0), local(0)),
+                    while_(
+                        lless(local(0), int(1)),
+                        &[
+                            if_(
+                                lequal(arg(0), arg(0)),
+                                &[store(binary(ADD, call(&next, &[arg(0)]), int(1)), local(1))],
+                            ),
+                            increment(local(0)),
+                        ],
+                    ),
+                    ret(local(1)),
+                ],
+            ));
+        }
+        let (mut interpreter, info) = load(&[table(2, &body)]);
+        assert_eq!(info.errors, 0);
+        let result = interpreter
+            .evaluate_path("\\C1", vec![Object::Integer(100)])
+            .unwrap();
+        assert_eq!(result.as_integer(), Some(107));
+    });
+}
@@ -0,0 +1,308 @@
+//! 引导配置
+//!
+//! ESP 上的 `\EFI\january_os\boot.cfg` 描述引导菜单：开头是全局选项，
+//! 之后每个 `[entry]` 段是一个启动项。没有配置文件或其中没有任何
+//! `[entry]` 时，使用内置的单一启动项。
+//!
+//! 内核命令行按优先级依次取：
+//!
+//! 1. LoadedImage 的 LoadOptions（`bcfg ... -opt` 或 UEFI Shell 参数），覆盖所有启动项
+//! 2. 启动项自己的 `cmdline`
+//! 3. 全局 `cmdline`
+//! 4. 内置默认值 [`DEFAULT_CMDLINE`]
+//!
+//! # boot.cfg 格式
+//!
+//! 每行一个 `key = value`，`#` 开头的行为注释：
+//!
+//! ```text
+//! # january_os boot configuration
+//! # 倒计时秒数，0 表示不显示菜单直接启动默认项
+//! timeout = 5
+//! # 默认启动项序号（从 0 开始）
+//! default = 0
+//! # 显示模式：keep（默认）、highest 或 1920x1080；命令行中的 video= 优先
+//! video = highest
+//! # 是否为内核调用 SetVirtualAddressMap（默认 yes）
+//! uefi_virtual_map = yes
+//! # 启动项未指定 cmdline 时使用
+//! cmdline = console=ttyS0 loglevel=7
+//!
+//! [entry]
+//! title = january_os
+//! kernel = \EFI\january_os\kernel.elf
+//! module = \EFI\january_os\initrd.img
+//!
+//! [entry]
+//! title = january_os (debug)
+//! cmdline = console=ttyS0 loglevel=7 debug
+//! ```
+
+use crate::fs;
+use alloc::string::String;
+use alloc::vec::Vec;
+use uefi::boot;
+use uefi::proto::loaded_image::LoadedImage;
+use uefi::{cstr16, CStr16};
+
+/// 配置文件路径
+pub const CONFIG_PATH: &CStr16 = cstr16!("\\EFI\\january_os\\boot.cfg");
+/// 未提供任何命令行时使用的默认值
+pub const DEFAULT_CMDLINE: &str = "console=ttyS0 loglevel=7";
+/// 启动项未指定内核时使用的路径
+pub const DEFAULT_KERNEL_PATH: &str = "\\EFI\\january_os\\kernel.elf";
+/// 内置启动项的标题
+pub const DEFAULT_TITLE: &str = "january_os";
+/// 未配置 timeout 时的菜单倒计时（秒）
+pub const DEFAULT_TIMEOUT: u32 = 3;
+
+/// 命令行来源
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CmdlineSource {
+    LoadOptions,
+    ConfigFile,
+    Default,
+    /// 在引导菜单中手工编辑
+    Edited,
+}
+
+impl CmdlineSource {
+    /// 显示用名称
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::LoadOptions => "load options",
+            Self::ConfigFile => "boot.cfg",
+            Self::Default => "built-in default",
+            Self::Edited => "edited",
+        }
+    }
+}
+
+/// 显示模式请求
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VideoRequest {
+    /// 保持固件设置的模式
+    Keep,
+    /// 分辨率最高的模式
+    Highest,
+    /// 指定分辨率（宽, 高）
+    Resolution(u32, u32),
+}
+
+impl VideoRequest {
+    /// 解析 `keep`、`highest` 或 `<宽>x<高>`（忽略 `@60` 之类的后缀）
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "keep" => Some(Self::Keep),
+            "highest" => Some(Self::Highest),
+            _ => {
+                let value = value.split(['@', '-']).next()?;
+                let (width, height) = value.split_once('x')?;
+                Some(Self::Resolution(width.parse().ok()?, height.parse().ok()?))
+            }
+        }
+    }
+
+    /// 命令行中的 `video=` 参数（最后一个为准）
+    pub fn from_cmdline(cmdline: &str) -> Option<Self> {
+        cmdline
+            .split_whitespace()
+            .rev()
+            .find_map(|param| param.strip_prefix("video="))
+            .and_then(Self::parse)
+    }
+}
+
+/// 一个启动项
+#[derive(Clone, Debug)]
+pub struct BootEntry {
+    /// 菜单中显示的标题
+    pub title: String,
+    /// 内核 ELF 在 ESP 上的路径
+    pub kernel: String,
+    /// 内核命令行
+    pub cmdline: String,
+    /// 命令行来源
+    pub cmdline_source: CmdlineSource,
+    /// 随内核加载的模块路径
+    pub modules: Vec<String>,
+}
+
+impl BootEntry {
+    /// 内置启动项
+    fn builtin() -> Self {
+        Self {
+            title: String::from(DEFAULT_TITLE),
+            kernel: String::from(DEFAULT_KERNEL_PATH),
+            cmdline: String::from(DEFAULT_CMDLINE),
+            cmdline_source: CmdlineSource::Default,
+            modules: Vec::new(),
+        }
+    }
+}
+
+/// boot.cfg 解析结果
+pub struct BootConfig {
+    /// 菜单倒计时（秒），0 表示直接启动默认项
+    pub timeout: u32,
+    /// 默认启动项序号，保证小于 `entries.len()`
+    pub default: usize,
+    /// 启动项，至少一个
+    pub entries: Vec<BootEntry>,
+    /// 是否把 UEFI 运行时服务切换到虚拟地址
+    pub uefi_virtual_map: bool,
+    /// 显示模式
+    pub video: VideoRequest,
+}
+
+impl Default for BootConfig {
+    fn default() -> Self {
+        Self {
+            timeout: DEFAULT_TIMEOUT,
+            default: 0,
+            entries: alloc::vec![BootEntry::builtin()],
+            uefi_virtual_map: true,
+            video: VideoRequest::Keep,
+        }
+    }
+}
+
+impl BootConfig {
+    /// 解析配置文件文本，未知键被忽略
+    pub fn parse(text: &str) -> Self {
+        let mut timeout = DEFAULT_TIMEOUT;
+        let mut default = 0;
+        let mut global_cmdline = None;
+        let mut uefi_virtual_map = true;
+        let mut video = VideoRequest::Keep;
+        // 启动项在全部解析完之后才知道全局 cmdline，先记下各自的 cmdline
+        let mut entries: Vec<(BootEntry, Option<String>)> = Vec::new();
+
+        for line in lines(text) {
+            match line {
+                Line::Section(name) => {
+                    if name == "entry" {
+                        let mut entry = BootEntry::builtin();
+                        entry.title = alloc::format!("{} #{}", DEFAULT_TITLE, entries.len());
+                        entries.push((entry, None));
+                    }
+                }
+                Line::Pair(key, value) => match entries.last_mut() {
+                    None => match key {
+                        "timeout" => timeout = value.parse().unwrap_or(timeout),
+                        "default" => default = value.parse().unwrap_or(default),
+                        "cmdline" => global_cmdline = Some(String::from(value)),
+                        "video" => video = VideoRequest::parse(value).unwrap_or(video),
+                        "uefi_virtual_map" => {
+                            uefi_virtual_map = parse_bool(value).unwrap_or(uefi_virtual_map)
+                        }
+                        _ => {}
+                    },
+                    Some((entry, cmdline)) => match key {
+                        "title" => entry.title = String::from(value),
+                        "kernel" => entry.kernel = String::from(value),
+                        "cmdline" => *cmdline = Some(String::from(value)),
+                        "module" => entry.modules.push(String::from(value)),
+                        _ => {}
+                    },
+                },
+            }
+        }
+
+        let mut entries: Vec<BootEntry> = entries
+            .into_iter()
+            .map(|(mut entry, cmdline)| {
+                if let Some(cmdline) = cmdline.or_else(|| global_cmdline.clone()) {
+                    entry.cmdline = cmdline;
+                    entry.cmdline_source = CmdlineSource::ConfigFile;
+                }
+                entry
+            })
+            .collect();
+
+        if entries.is_empty() {
+            let mut entry = BootEntry::builtin();
+            if let Some(cmdline) = global_cmdline {
+                entry.cmdline = cmdline;
+                entry.cmdline_source = CmdlineSource::ConfigFile;
+            }
+            entries.push(entry);
+        }
+
+        Self {
+            timeout,
+            default: default.min(entries.len() - 1),
+            entries,
+            uefi_virtual_map,
+            video,
+        }
+    }
+
+    /// 读取 ESP 上的配置文件（不存在或不是 UTF-8 时使用内置配置），
+    /// 并应用 LoadOptions 中的命令行
+    pub fn load() -> Self {
+        let mut config = fs::read_file(CONFIG_PATH)
+            .ok()
+            .and_then(|data| core::str::from_utf8(&data).ok().map(Self::parse))
+            .unwrap_or_default();
+
+        if let Some(cmdline) = load_options_cmdline() {
+            for entry in &mut config.entries {
+                entry.cmdline = cmdline.clone();
+                entry.cmdline_source = CmdlineSource::LoadOptions;
+            }
+        }
+        config
+    }
+}
+
+/// 配置文件中的一行
+enum Line<'a> {
+    /// `[name]`
+    Section(&'a str),
+    /// `key = value`
+    Pair(&'a str, &'a str),
+}
+
+/// 遍历配置文本中的段头与 `key = value` 行
+fn lines(text: &str) -> impl Iterator<Item = Line<'_>> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                return Some(Line::Section(name.trim()));
+            }
+            line.split_once('=')
+                .map(|(key, value)| Line::Pair(key.trim(), value.trim()))
+        })
+}
+
+/// 解析 `yes`/`no` 一类的布尔值
+fn parse_bool(value: &str) -> Option<bool> {
+    match value {
+        "yes" | "true" | "on" | "1" => Some(true),
+        "no" | "false" | "off" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+/// 从 LoadedImage 的 LoadOptions 读取命令行
+///
+/// UEFI Shell 传入的选项以映像路径开头（如 `BOOTX64.EFI console=ttyS0`），
+/// 以 `.efi` 结尾的第一个参数会被去掉。
+fn load_options_cmdline() -> Option<String> {
+    let image = boot::open_protocol_exclusive::<LoadedImage>(boot::image_handle()).ok()?;
+    let options = String::from(image.load_options_as_cstr16().ok()?);
+
+    let mut rest = options.trim();
+    if let Some((first, tail)) = rest.split_once(char::is_whitespace) {
+        if first.to_ascii_lowercase().ends_with(".efi") {
+            rest = tail.trim_start();
+        }
+    } else if rest.to_ascii_lowercase().ends_with(".efi") {
+        rest = "";
+    }
+
+    (!rest.is_empty()).then(|| String::from(rest))
+}
@@ -0,0 +1,374 @@
+//! 存储设备扫描
+//!
+//! 枚举所有整盘 BlockIO 句柄写入磁盘表，解析每个磁盘的分区表（GPT / MBR）
+//! 写入分区表，并识别启动设备：
+//!
+//! - 固件为分区创建的逻辑 BlockIO 不再单独列为磁盘，分区以 `disk_index`
+//!   关联到父磁盘，每个磁盘的分区在分区表中连续排列
+//! - LoadedImage 的设备句柄即引导程序所在的分区（或整盘）；DevicePath 是其
+//!   路径前缀的整盘即启动磁盘
+//! - 启动分区路径最后的 HardDrive / CdRom 节点给出分区号、范围与分区 GUID，
+//!   按起始 LBA 在启动磁盘的分区中找到对应表项
+//!
+//! 磁盘类型由 DevicePath 中第一个接口节点（NVMe、USB、SATA、ATAPI、SCSI、
+//! SD、eMMC、网络）决定，没有接口节点的 PCI 设备按厂商 ID 识别 virtio-blk；
+//! 控制器的 PCI 位置通过 PCI I/O 协议取得。
+
+use crate::handoff::allocate_table;
+use crate::pci;
+use crate::{print_dec, print_uefi, println_uefi};
+use alloc::vec;
+use alloc::vec::Vec;
+use boot_protocol::partition;
+use boot_protocol::{BootDevice, DiskInfo, DiskType, Guid, PartitionInfo, PartitionScheme};
+use uefi::boot::{self, OpenProtocolAttributes, OpenProtocolParams, ScopedProtocol};
+use uefi::proto::device_path::media::PartitionSignature;
+use uefi::proto::device_path::{DevicePath, DevicePathNodeEnum};
+use uefi::proto::loaded_image::LoadedImage;
+use uefi::proto::media::block::BlockIO;
+use uefi::{Handle, Identify};
+
+/// DevicePath 结束节点的长度
+const END_NODE_SIZE: usize = 4;
+/// 软盘控制器的 ACPI _HID (EISA ID PNP0604)
+const FLOPPY_HID: u32 = 0x0604_41D0;
+/// 光盘的逻辑块大小
+const OPTICAL_BLOCK_SIZE: u32 = 2048;
+
+/// 扫描结果
+pub struct DiskScan {
+    /// 磁盘表物理地址（没有磁盘时为 0）
+    pub disk_info_addr: u64,
+    /// 磁盘数量
+    pub count: u32,
+    /// 分区表物理地址（没有分区时为 0）
+    pub partition_info_addr: u64,
+    /// 分区数量
+    pub partition_count: u32,
+    /// 启动磁盘在表中的索引，-1 表示未识别
+    pub boot_disk: i32,
+    /// 启动分区信息
+    pub boot_device: BootDevice,
+}
+
+/// 扫描所有块设备，磁盘表与分区表按实际数量分配交接内存
+pub fn scan_disks() -> uefi::Result<DiskScan> {
+    let boot_handle = boot_device_handle();
+    let boot_device_path = boot_handle.and_then(device_path);
+    let boot_path = boot_device_path.as_deref().map(path_prefix);
+
+    let mut disks: Vec<DiskInfo> = Vec::new();
+    let mut partitions: Vec<PartitionInfo> = Vec::new();
+    let mut boot_disk = -1;
+    let mut boot_device = boot_device_path
+        .as_deref()
+        .map(boot_device_from_path)
+        .unwrap_or_else(no_boot_device);
+    // 已找到的父磁盘路径长度，取最长的前缀
+    let mut boot_disk_path_len = 0;
+
+    // 获取所有 BlockIO 句柄
+    let handles = boot::locate_handle_buffer(boot::SearchType::ByProtocol(&BlockIO::GUID))?;
+
+    for handle in handles.iter() {
+        if let Some(block_io) = block_io(*handle) {
+            let media = block_io.media();
+
+            // 跳过没有介质的设备与分区（分区从父磁盘的分区表得到）
+            if !media.is_media_present() || media.is_logical_partition() {
+                continue;
+            }
+
+            let total_blocks = media.last_block() + 1;
+            let block_size = media.block_size() as u64;
+            let path = device_path(*handle);
+            let path = path.as_deref();
+            let mut disk_info = DiskInfo {
+                disk_type: DiskType::Unknown,
+                removable: if media.is_removable_media() { 1 } else { 0 },
+                boot_device: 0,
+                read_only: if media.is_read_only() { 1 } else { 0 },
+                block_size,
+                total_blocks,
+                total_size: total_blocks * block_size,
+                media_id: media.media_id(),
+                namespace_id: 0,
+                namespace_eui64: 0,
+                pci_segment: 0,
+                pci_bus: 0,
+                pci_device: 0,
+                pci_function: 0,
+                has_pci: 0,
+                _reserved: 0,
+                port: 0,
+                lun: 0,
+                scheme: PartitionScheme::None,
+                first_partition: partitions.len() as u32,
+                partition_count: 0,
+                mbr_signature: 0,
+                disk_guid: Guid::ZERO,
+            };
+            classify(&mut disk_info, path);
+
+            // 解析分区表
+            let index = disks.len() as u32;
+            let mut buf = vec![0u8; media.block_size() as usize];
+            let mut read = |lba: u64, buf: &mut [u8]| {
+                block_io.read_blocks(media.media_id(), lba, buf).is_ok()
+            };
+            let mut emit = |mut part: PartitionInfo| {
+                part.disk_index = index;
+                partitions.push(part);
+            };
+            let label = partition::parse(&mut buf, media.last_block(), &mut read, &mut emit);
+            disk_info.scheme = label.scheme;
+            disk_info.mbr_signature = label.mbr_signature;
+            disk_info.disk_guid = label.disk_guid;
+            disk_info.partition_count = partitions.len() as u32 - disk_info.first_partition;
+
+            if boot_handle == Some(*handle) {
+                // 从没有分区表的整盘（或光盘）启动
+                boot_disk = index as i32;
+                boot_disk_path_len = usize::MAX;
+            } else if let (Some(path), Some(boot_path)) = (path.map(path_prefix), boot_path) {
+                if path.len() > boot_disk_path_len && boot_path.starts_with(path) {
+                    boot_disk = index as i32;
+                    boot_disk_path_len = path.len();
+                }
+            }
+
+            // 打印磁盘信息
+            print_uefi("      Disk ");
+            print_dec(index as u64);
+            print_uefi(": ");
+            print_uefi(disk_info.disk_type.as_str());
+            print_uefi(", ");
+            print_dec(disk_info.total_size / 1024 / 1024);
+            print_uefi(" MB");
+            if disk_info.scheme != PartitionScheme::None {
+                print_uefi(", ");
+                print_uefi(disk_info.scheme.as_str());
+                print_uefi(", ");
+                print_dec(disk_info.partition_count as u64);
+                print_uefi(" partition(s)");
+            }
+            println_uefi("");
+
+            disks.push(disk_info);
+        }
+    }
+
+    if let Ok(index) = usize::try_from(boot_disk) {
+        let disk = &mut disks[index];
+        disk.boot_device = 1;
+        resolve_boot_partition(&mut boot_device, disk, &partitions);
+    }
+    Ok(DiskScan {
+        disk_info_addr: allocate_table(&disks)?,
+        count: disks.len() as u32,
+        partition_info_addr: allocate_table(&partitions)?,
+        partition_count: partitions.len() as u32,
+        boot_disk,
+        boot_device,
+    })
+}
+
+/// 在启动磁盘的分区中找到启动分区，并补全磁盘 GUID
+fn resolve_boot_partition(device: &mut BootDevice, disk: &DiskInfo, partitions: &[PartitionInfo]) {
+    if device.scheme == PartitionScheme::Gpt && disk.scheme == PartitionScheme::Gpt {
+        device.disk_guid = disk.disk_guid;
+    }
+    if !matches!(device.scheme, PartitionScheme::Gpt | PartitionScheme::Mbr) {
+        return;
+    }
+    for i in disk.first_partition..disk.first_partition + disk.partition_count {
+        let part = &partitions[i as usize];
+        if part.start_lba == device.partition_start && part.number == device.partition_number {
+            device.partition_index = i as i32;
+            return;
+        }
+    }
+}
+
+/// 引导程序映像所在设备的句柄
+fn boot_device_handle() -> Option<Handle> {
+    let image = boot::open_protocol_exclusive::<LoadedImage>(boot::image_handle()).ok()?;
+    image.device()
+}
+
+/// 以非独占方式打开句柄上的 BlockIO
+///
+/// 独占打开会断开分区、DiskIo 与 FAT 驱动且不会重新连接，ESP 文件系统随之
+/// 消失，失败后重试就无法再加载内核与模块。
+fn block_io(handle: Handle) -> Option<ScopedProtocol<BlockIO>> {
+    let params = OpenProtocolParams {
+        handle,
+        agent: boot::image_handle(),
+        controller: None,
+    };
+    unsafe { boot::open_protocol::<BlockIO>(params, OpenProtocolAttributes::GetProtocol) }.ok()
+}
+
+/// 以非独占方式打开句柄上的 DevicePath（不影响已连接的驱动）
+fn device_path(handle: Handle) -> Option<ScopedProtocol<DevicePath>> {
+    let params = OpenProtocolParams {
+        handle,
+        agent: boot::image_handle(),
+        controller: None,
+    };
+    unsafe { boot::open_protocol::<DevicePath>(params, OpenProtocolAttributes::GetProtocol) }.ok()
+}
+
+/// DevicePath 去掉结束节点后的字节，用于前缀比较
+fn path_prefix(path: &DevicePath) -> &[u8] {
+    let bytes = path.as_bytes();
+    &bytes[..bytes.len().saturating_sub(END_NODE_SIZE)]
+}
+
+/// 根据 DevicePath 填写磁盘类型、控制器 PCI 位置与端口
+///
+/// 取第一个接口节点（USB 大容量存储后面的 SCSI 节点不改变类型）；
+/// 没有可识别的节点时沿用按介质判断的旧规则。
+fn classify(disk: &mut DiskInfo, path: Option<&DevicePath>) {
+    let Some(path) = path else {
+        disk.disk_type = fallback_type(disk);
+        return;
+    };
+
+    let pci = pci::locate(path);
+    if let Some(pci) = pci {
+        disk.pci_segment = pci.segment;
+        disk.pci_bus = pci.bus;
+        disk.pci_device = pci.device;
+        disk.pci_function = pci.function;
+        disk.has_pci = 1;
+    }
+
+    let optical = disk.block_size == OPTICAL_BLOCK_SIZE as u64;
+    for node in path.node_iter() {
+        let disk_type = match node.as_enum() {
+            Ok(DevicePathNodeEnum::MessagingNvmeNamespace(nvme)) => {
+                disk.namespace_id = nvme.namespace_identifier();
+                disk.namespace_eui64 = nvme.ieee_extended_unique_identifier();
+                DiskType::NVMe
+            }
+            Ok(DevicePathNodeEnum::MessagingUsb(usb)) => {
+                disk.port = usb.parent_port_number() as u32;
+                DiskType::Usb
+            }
+            Ok(
+                DevicePathNodeEnum::MessagingUsbClass(_) | DevicePathNodeEnum::MessagingUsbWwid(_),
+            ) => DiskType::Usb,
+            Ok(DevicePathNodeEnum::MessagingSata(sata)) => {
+                disk.port = sata.hba_port_number() as u32;
+                disk.lun = sata.logical_unit_number() as u32;
+                if optical {
+                    DiskType::CdRom
+                } else {
+                    DiskType::Sata
+                }
+            }
+            Ok(DevicePathNodeEnum::MessagingAtapi(atapi)) => {
+                disk.port = atapi.primary_secondary().0 as u32 * 2 + atapi.master_slave().0 as u32;
+                disk.lun = atapi.logical_unit_number() as u32;
+                if optical {
+                    DiskType::CdRom
+                } else {
+                    DiskType::HardDisk
+                }
+            }
+            Ok(DevicePathNodeEnum::MessagingScsi(scsi)) => {
+                disk.port = scsi.target_id() as u32;
+                disk.lun = scsi.logical_unit_number() as u32;
+                if optical {
+                    DiskType::CdRom
+                } else {
+                    DiskType::Scsi
+                }
+            }
+            Ok(DevicePathNodeEnum::MessagingSd(sd)) => {
+                disk.port = sd.slot_number() as u32;
+                DiskType::Sd
+            }
+            Ok(DevicePathNodeEnum::MessagingEmmc(emmc)) => {
+                disk.port = emmc.slot_number() as u32;
+                DiskType::Emmc
+            }
+            Ok(
+                DevicePathNodeEnum::MessagingMacAddress(_)
+                | DevicePathNodeEnum::MessagingIscsi(_)
+                | DevicePathNodeEnum::MessagingUri(_),
+            ) => DiskType::Network,
+            Ok(DevicePathNodeEnum::AcpiAcpi(acpi)) if acpi.hid() == FLOPPY_HID => DiskType::Floppy,
+            _ => continue,
+        };
+        disk.disk_type = disk_type;
+        return;
+    }
+
+    disk.disk_type = match pci {
+        Some(pci) if pci.vendor_id == pci::VIRTIO_VENDOR_ID => DiskType::Virtio,
+        _ => fallback_type(disk),
+    };
+}
+
+/// 无法从 DevicePath 判断时按介质属性猜测
+fn fallback_type(disk: &DiskInfo) -> DiskType {
+    match (
+        disk.is_removable(),
+        disk.block_size == OPTICAL_BLOCK_SIZE as u64,
+    ) {
+        (true, true) => DiskType::CdRom,
+        (true, false) => DiskType::Usb,
+        (false, _) => DiskType::HardDisk,
+    }
+}
+
+/// 从启动分区路径最后的媒体节点取得分区信息
+fn boot_device_from_path(path: &DevicePath) -> BootDevice {
+    let mut device = no_boot_device();
+    let Some(node) = path.node_iter().last() else {
+        return device;
+    };
+
+    match node.as_enum() {
+        Ok(DevicePathNodeEnum::MediaHardDrive(hd)) => {
+            device.partition_number = hd.partition_number();
+            device.partition_start = hd.partition_start();
+            device.partition_blocks = hd.partition_size();
+            match hd.partition_signature() {
+                PartitionSignature::Guid(guid) => {
+                    device.scheme = PartitionScheme::Gpt;
+                    device.partition_guid = Guid(guid.to_bytes());
+                }
+                PartitionSignature::Mbr(signature) => {
+                    device.scheme = PartitionScheme::Mbr;
+                    device.mbr_signature = u32::from_le_bytes(signature);
+                }
+                _ => {}
+            }
+        }
+        Ok(DevicePathNodeEnum::MediaCdRom(cd)) => {
+            device.scheme = PartitionScheme::ElTorito;
+            device.partition_number = cd.boot_entry();
+            device.partition_start = cd.partition_start();
+            device.partition_blocks = cd.partition_size();
+        }
+        _ => {}
+    }
+    device
+}
+
+/// 未识别启动分区时的值
+pub fn no_boot_device() -> BootDevice {
+    BootDevice {
+        partition_index: -1,
+        partition_number: 0,
+        scheme: PartitionScheme::None,
+        mbr_signature: 0,
+        partition_start: 0,
+        partition_blocks: 0,
+        partition_guid: Guid::ZERO,
+        disk_guid: Guid::ZERO,
+    }
+}
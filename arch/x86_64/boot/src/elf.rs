@@ -0,0 +1,159 @@
+//! ELF64 内核映像解析
+//!
+//! 只实现加载内核所需的最小子集：校验文件头，遍历程序头表。
+//! 内核以静态链接的 `ET_EXEC` 形式构建，不处理重定位。
+
+use core::mem::size_of;
+
+/// ELF 魔数 "\x7FELF"
+const ELF_MAGIC: [u8; 4] = [0x7F, b'E', b'L', b'F'];
+/// 64 位 ELF
+const ELFCLASS64: u8 = 2;
+/// 小端序
+const ELFDATA2LSB: u8 = 1;
+/// 可执行文件
+const ET_EXEC: u16 = 2;
+/// x86_64 架构
+const EM_X86_64: u16 = 0x3E;
+
+/// 可加载段
+pub const PT_LOAD: u32 = 1;
+
+/// ELF64 文件头
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Elf64Header {
+    ident: [u8; 16],
+    ty: u16,
+    machine: u16,
+    version: u32,
+    entry: u64,
+    phoff: u64,
+    shoff: u64,
+    flags: u32,
+    ehsize: u16,
+    phentsize: u16,
+    phnum: u16,
+    shentsize: u16,
+    shnum: u16,
+    shstrndx: u16,
+}
+
+/// ELF64 程序头
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct ProgramHeader {
+    /// 段类型
+    pub ty: u32,
+    /// 段权限 (PF_X=1, PF_W=2, PF_R=4)
+    pub flags: u32,
+    /// 段在文件中的偏移
+    pub offset: u64,
+    /// 虚拟地址
+    pub vaddr: u64,
+    /// 物理地址
+    pub paddr: u64,
+    /// 文件中的大小
+    pub filesz: u64,
+    /// 内存中的大小（超出 filesz 的部分为 BSS）
+    pub memsz: u64,
+    /// 对齐
+    pub align: u64,
+}
+
+/// ELF 解析错误
+#[derive(Clone, Copy, Debug)]
+pub enum ElfError {
+    /// 文件太小或表越界
+    Truncated,
+    /// 不是 ELF 文件
+    BadMagic,
+    /// 不是 x86_64 小端 64 位可执行文件
+    Unsupported,
+    /// 段的文件范围超出文件或 filesz > memsz
+    BadSegment,
+}
+
+impl ElfError {
+    /// 错误画面上显示的说明
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Truncated => "Kernel ELF image is truncated",
+            Self::BadMagic => "Kernel file is not an ELF image",
+            Self::Unsupported => "Kernel ELF is not an x86_64 executable",
+            Self::BadSegment => "Kernel ELF has an invalid segment",
+        }
+    }
+}
+
+/// 已校验的 ELF 映像
+pub struct ElfFile<'a> {
+    data: &'a [u8],
+    header: Elf64Header,
+}
+
+impl<'a> ElfFile<'a> {
+    /// 解析并校验 ELF 文件头和程序头表
+    pub fn parse(data: &'a [u8]) -> Result<Self, ElfError> {
+        if data.len() < size_of::<Elf64Header>() {
+            return Err(ElfError::Truncated);
+        }
+        let header = unsafe { (data.as_ptr() as *const Elf64Header).read_unaligned() };
+
+        if header.ident[0..4] != ELF_MAGIC {
+            return Err(ElfError::BadMagic);
+        }
+        if header.ident[4] != ELFCLASS64
+            || header.ident[5] != ELFDATA2LSB
+            || header.ty != ET_EXEC
+            || header.machine != EM_X86_64
+            || header.phentsize as usize != size_of::<ProgramHeader>()
+        {
+            return Err(ElfError::Unsupported);
+        }
+
+        let ph_end = (header.phnum as u64)
+            .checked_mul(size_of::<ProgramHeader>() as u64)
+            .and_then(|len| len.checked_add(header.phoff))
+            .ok_or(ElfError::Truncated)?;
+        if ph_end > data.len() as u64 {
+            return Err(ElfError::Truncated);
+        }
+
+        let elf = Self { data, header };
+        for ph in elf.program_headers() {
+            if ph.ty != PT_LOAD {
+                continue;
+            }
+            let file_end = ph.offset.checked_add(ph.filesz).ok_or(ElfError::BadSegment)?;
+            if file_end > data.len() as u64 || ph.filesz > ph.memsz {
+                return Err(ElfError::BadSegment);
+            }
+        }
+        Ok(elf)
+    }
+
+    /// 入口点虚拟地址
+    pub fn entry(&self) -> u64 {
+        self.header.entry
+    }
+
+    /// 遍历程序头
+    pub fn program_headers(&self) -> impl Iterator<Item = ProgramHeader> + '_ {
+        let base = self.header.phoff as usize;
+        (0..self.header.phnum as usize).map(move |i| {
+            let offset = base + i * size_of::<ProgramHeader>();
+            unsafe { (self.data.as_ptr().add(offset) as *const ProgramHeader).read_unaligned() }
+        })
+    }
+
+    /// 遍历 PT_LOAD 段
+    pub fn load_segments(&self) -> impl Iterator<Item = ProgramHeader> + '_ {
+        self.program_headers().filter(|ph| ph.ty == PT_LOAD && ph.memsz > 0)
+    }
+
+    /// 段在文件中的内容
+    pub fn segment_data(&self, ph: &ProgramHeader) -> &'a [u8] {
+        &self.data[ph.offset as usize..(ph.offset + ph.filesz) as usize]
+    }
+}
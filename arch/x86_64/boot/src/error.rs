@@ -0,0 +1,133 @@
+//! 引导错误
+//!
+//! 致命错误以 [`BootError`] 一路返回到入口点，显示错误画面，由用户选择
+//! 重试、改用其他启动项或返回固件；非致命错误记入 [`Warnings`]，
+//! 随 BootInfo 交给内核后继续启动。
+
+use crate::handoff::MAX_WARNINGS;
+use crate::menu::wait_key;
+use boot_protocol::{BootStage, BootWarning, BOOT_STAGE_COUNT};
+use core::fmt::{Debug, Write};
+use uefi::proto::console::text::{Key, ScanCode};
+use uefi::{system, Status};
+
+/// 致命引导错误
+#[derive(Clone, Copy, Debug)]
+pub struct BootError {
+    /// 失败的阶段
+    pub stage: BootStage,
+    /// UEFI 状态码
+    pub status: Status,
+    /// 失败的操作
+    pub what: &'static str,
+}
+
+impl BootError {
+    pub fn new(stage: BootStage, status: Status, what: &'static str) -> Self {
+        Self { stage, status, what }
+    }
+}
+
+/// 引导程序内部的 Result
+pub type BootResult<T> = Result<T, BootError>;
+
+/// 为 UEFI 错误附加阶段与操作说明
+pub trait StageContext<T> {
+    fn stage(self, stage: BootStage, what: &'static str) -> BootResult<T>;
+}
+
+impl<T, D: Debug> StageContext<T> for uefi::Result<T, D> {
+    fn stage(self, stage: BootStage, what: &'static str) -> BootResult<T> {
+        self.map_err(|e| BootError::new(stage, e.status(), what))
+    }
+}
+
+/// 非致命错误表（位于交接内存）
+pub struct Warnings {
+    table: *mut BootWarning,
+    count: u32,
+    dropped: bool,
+}
+
+impl Warnings {
+    /// 使用 `table_addr` 处的表（重试时从头覆盖之前的记录）
+    pub fn new(table_addr: u64) -> Self {
+        Self {
+            table: table_addr as *mut BootWarning,
+            count: 0,
+            dropped: false,
+        }
+    }
+
+    /// 记录一个非致命错误（提示由调用者打印）；表满后丢弃
+    pub fn push(&mut self, stage: BootStage, status: Status) {
+        if self.count as usize >= MAX_WARNINGS {
+            self.dropped = true;
+            return;
+        }
+        let warning = BootWarning {
+            stage,
+            _reserved: 0,
+            status: status.0 as u64,
+        };
+        unsafe { core::ptr::write_volatile(self.table.add(self.count as usize), warning) };
+        self.count += 1;
+    }
+
+    /// 已记录的数量
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// 是否有警告因表满被丢弃
+    pub fn dropped(&self) -> bool {
+        self.dropped
+    }
+}
+
+/// 用户在错误画面上的选择
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Recovery {
+    /// 重新执行同一启动项
+    Retry,
+    /// 改用下一个启动项
+    Fallback,
+    /// 返回固件（由启动管理器尝试下一个启动选项）
+    Exit,
+}
+
+/// 显示错误画面并等待用户选择；`fallback` 为可改用的启动项标题
+pub fn report(err: &BootError, fallback: Option<&str>) -> Recovery {
+    system::with_stdout(|out| {
+        let _ = out.clear();
+        let _ = write!(
+            out,
+            "  Boot failed at step [{}/{}] {}\r\n\r\n",
+            err.stage.number(),
+            BOOT_STAGE_COUNT,
+            err.stage.as_str()
+        );
+        let _ = write!(out, "  {}: {:?} (0x{:X})\r\n\r\n", err.what, err.status, err.status.0);
+        let _ = write!(out, "  R: retry\r\n");
+        if let Some(title) = fallback {
+            let _ = write!(out, "  F: boot '{}' instead\r\n", title);
+        }
+        let _ = write!(out, "  Esc: return to firmware\r\n");
+    });
+
+    let choice = loop {
+        match wait_key() {
+            Key::Printable(c) => match char::from(c) {
+                'r' | 'R' => break Recovery::Retry,
+                'f' | 'F' if fallback.is_some() => break Recovery::Fallback,
+                _ => {}
+            },
+            Key::Special(ScanCode::ESCAPE) => break Recovery::Exit,
+            Key::Special(_) => {}
+        }
+    };
+    system::with_stdout(|out| {
+        let _ = out.clear();
+    });
+    choice
+}
@@ -0,0 +1,37 @@
+//! EFI 系统分区文件访问
+
+use alloc::vec;
+use alloc::vec::Vec;
+use uefi::boot;
+use uefi::proto::media::file::{File, FileAttribute, FileInfo, FileMode, RegularFile};
+use uefi::CStr16;
+
+/// 打开 ESP 上的普通文件
+pub fn open_file(path: &CStr16) -> uefi::Result<RegularFile> {
+    // 引导程序所在的卷，而不是固件枚举到的第一个文件系统
+    let mut fs = boot::get_image_file_system(boot::image_handle())?;
+    let mut root = fs.open_volume()?;
+    let handle = root.open(path, FileMode::Read, FileAttribute::empty())?;
+    handle
+        .into_regular_file()
+        .ok_or_else(|| uefi::Status::INVALID_PARAMETER.into())
+}
+
+/// 文件大小（字节）
+pub fn file_size(file: &mut RegularFile) -> uefi::Result<usize> {
+    let mut info_buf = [0u8; 256];
+    let info: &FileInfo = file
+        .get_info(&mut info_buf)
+        .map_err(|e| uefi::Error::from(e.status()))?;
+    Ok(info.file_size() as usize)
+}
+
+/// 读取整个文件到堆内存
+pub fn read_file(path: &CStr16) -> uefi::Result<Vec<u8>> {
+    let mut file = open_file(path)?;
+    let size = file_size(&mut file)?;
+    let mut data = vec![0u8; size];
+    let read = file.read(&mut data).map_err(|e| uefi::Error::from(e.status()))?;
+    data.truncate(read);
+    Ok(data)
+}
@@ -0,0 +1,154 @@
+//! 图形输出 (GOP)
+//!
+//! 枚举 GOP 支持的全部模式写入交接表，并按 [`VideoRequest`] 切换模式。
+//! 没有 GOP（如 QEMU `-nographic`）或当前模式只支持 BLT 时不报错，
+//! 交给内核的帧缓冲区地址为 0。
+
+use crate::config::VideoRequest;
+use crate::handoff::MAX_VIDEO_MODES;
+use boot_protocol::{FramebufferInfo, PixelFormatType, VideoMode};
+use uefi::boot;
+use uefi::proto::console::gop::{GraphicsOutput, ModeInfo, PixelFormat};
+
+/// 图形初始化结果
+pub struct Graphics {
+    /// 帧缓冲区（没有线性帧缓冲区时地址为 0）
+    pub framebuffer: FramebufferInfo,
+    /// 写入模式表的模式数量
+    pub mode_count: u32,
+    /// 当前模式在模式表中的索引 (-1 表示没有 GOP)
+    pub current_mode: i32,
+    /// GOP 的模式多于模式表容量，只写入了前 [`MAX_VIDEO_MODES`] 个
+    pub modes_truncated: bool,
+    /// 请求的模式不可用或切换失败
+    pub request_failed: bool,
+}
+
+/// 初始化图形输出，模式列表写入 `modes_addr`
+pub fn setup_graphics(request: VideoRequest, modes_addr: u64) -> Graphics {
+    let mut graphics = Graphics {
+        framebuffer: no_framebuffer(),
+        mode_count: 0,
+        current_mode: -1,
+        modes_truncated: false,
+        request_failed: false,
+    };
+
+    let Ok(gop_handle) = boot::get_handle_for_protocol::<GraphicsOutput>() else {
+        return graphics;
+    };
+    let Ok(mut gop) = boot::open_protocol_exclusive::<GraphicsOutput>(gop_handle) else {
+        return graphics;
+    };
+
+    // 选择目标模式（只考虑有线性帧缓冲区的模式）
+    let current = gop.current_mode_info();
+    let target = match request {
+        VideoRequest::Keep => None,
+        VideoRequest::Highest => gop
+            .modes()
+            .filter(|mode| has_framebuffer(mode.info()))
+            .max_by_key(|mode| {
+                let (width, height) = mode.info().resolution();
+                width * height
+            }),
+        VideoRequest::Resolution(width, height) => {
+            let found = gop.modes().find(|mode| {
+                has_framebuffer(mode.info())
+                    && mode.info().resolution() == (width as usize, height as usize)
+            });
+            graphics.request_failed = found.is_none();
+            found
+        }
+    };
+    if let Some(mode) = target.filter(|mode| *mode.info() != current) {
+        graphics.request_failed = gop.set_mode(&mode).is_err();
+    }
+
+    // 模式表
+    let table = modes_addr as *mut VideoMode;
+    let current = gop.current_mode_info();
+    for (i, mode) in gop.modes().take(MAX_VIDEO_MODES).enumerate() {
+        let info = mode.info();
+        if *info == current {
+            graphics.current_mode = i as i32;
+        }
+        let (width, height) = info.resolution();
+        let video_mode = VideoMode {
+            width: width as u32,
+            height: height as u32,
+            stride: info.stride() as u32,
+            pixel_format: pixel_format_type(info.pixel_format()),
+        };
+        unsafe { core::ptr::write_volatile(table.add(i), video_mode) };
+        graphics.mode_count += 1;
+    }
+
+    graphics.modes_truncated = gop.modes().count() > MAX_VIDEO_MODES;
+
+    graphics.framebuffer = framebuffer_info(&mut gop, &current);
+    graphics
+}
+
+fn framebuffer_info(gop: &mut GraphicsOutput, info: &ModeInfo) -> FramebufferInfo {
+    let (width, height) = info.resolution();
+    let mut fb = FramebufferInfo {
+        width: width as u32,
+        height: height as u32,
+        stride: info.stride() as u32,
+        pixel_format: pixel_format_type(info.pixel_format()),
+        ..no_framebuffer()
+    };
+
+    let (red, green, blue, reserved) = match info.pixel_format() {
+        PixelFormat::Rgb => (0x0000_00FF, 0x0000_FF00, 0x00FF_0000, 0xFF00_0000),
+        PixelFormat::Bgr => (0x00FF_0000, 0x0000_FF00, 0x0000_00FF, 0xFF00_0000),
+        PixelFormat::Bitmask => match info.pixel_bitmask() {
+            Some(mask) => (mask.red, mask.green, mask.blue, mask.reserved),
+            None => return fb,
+        },
+        // 没有线性帧缓冲区，只能通过 Blt() 绘制，退出引导服务后不可用
+        PixelFormat::BltOnly => return fb,
+    };
+
+    let mut buffer = gop.frame_buffer();
+    let all_bits = red | green | blue | reserved;
+    fb.address = buffer.as_mut_ptr() as u64;
+    fb.size = buffer.size() as u64;
+    fb.bytes_per_pixel = (32 - all_bits.leading_zeros()).div_ceil(8);
+    fb.red_mask = red;
+    fb.green_mask = green;
+    fb.blue_mask = blue;
+    fb.reserved_mask = reserved;
+    fb
+}
+
+fn has_framebuffer(info: &ModeInfo) -> bool {
+    info.pixel_format() != PixelFormat::BltOnly
+}
+
+fn pixel_format_type(format: PixelFormat) -> PixelFormatType {
+    match format {
+        PixelFormat::Rgb => PixelFormatType::Rgb,
+        PixelFormat::Bgr => PixelFormatType::Bgr,
+        PixelFormat::Bitmask => PixelFormatType::Bitmask,
+        PixelFormat::BltOnly => PixelFormatType::BltOnly,
+    }
+}
+
+fn no_framebuffer() -> FramebufferInfo {
+    FramebufferInfo {
+        address: 0,
+        size: 0,
+        width: 0,
+        height: 0,
+        stride: 0,
+        bytes_per_pixel: 0,
+        pixel_format: PixelFormatType::BltOnly,
+        _reserved: 0,
+        red_mask: 0,
+        green_mask: 0,
+        blue_mask: 0,
+        reserved_mask: 0,
+    }
+}
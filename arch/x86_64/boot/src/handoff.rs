@@ -0,0 +1,144 @@
+//! 交接数据的内存分配
+//!
+//! 所有交给内核的数据都通过 `allocate_pages` 向固件申请，
+//! 不再写入固定物理地址，避免与固件数据重叠或被固件覆盖。
+//! 磁盘表、分区表按扫描结果分配，内存映射表按固件映射的条目数分配，
+//! 其余表使用固定容量。
+//!
+//! 使用两种 OS 自定义内存类型，在 `copy_memory_map` 中分类：
+//!
+//! - [`HANDOFF_MEMORY_TYPE`]：BootInfo 及其引用的表、页表、内核 ELF 副本，
+//!   报告为 `BootloaderReclaimable`，内核消费完后可回收
+//! - [`KERNEL_MEMORY_TYPE`]：内核映像、内核栈、启动模块及其描述表与字符串，
+//!   报告为 `KernelAndModules`，内核回收交接内存后模块仍然可用
+
+use boot_protocol::{
+    BootInfo, BootWarning, KernelSegment, MemoryRegion, ModuleInfo, VideoMode, PAGE_SIZE,
+};
+use core::mem::{size_of, size_of_val};
+use core::ptr::NonNull;
+use uefi::boot::{self, MemoryType};
+use uefi::mem::memory_map::MemoryMap;
+
+/// 交接数据内存类型（OS 自定义范围）
+pub const HANDOFF_MEMORY_TYPE: MemoryType = MemoryType::custom(0x8000_0000);
+/// 内核映像与内核栈内存类型（OS 自定义范围）
+pub const KERNEL_MEMORY_TYPE: MemoryType = MemoryType::custom(0x8000_0001);
+
+/// 内存映射表在当前条目数之外预留的条目数
+/// （分配内存映射表、页表等之后，固件的映射还会继续分裂）
+pub const MEMORY_MAP_SLACK: usize = 64;
+/// 最大内核段数
+pub const MAX_KERNEL_SEGMENTS: usize = 16;
+/// 最大启动模块数
+pub const MAX_MODULES: usize = 32;
+/// 最大显示模式数
+pub const MAX_VIDEO_MODES: usize = 64;
+/// 最大引导警告数
+pub const MAX_WARNINGS: usize = 16;
+/// 命令行缓冲区大小（含结尾 null）
+pub const CMDLINE_MAX: usize = PAGE_SIZE as usize;
+
+/// 内核栈大小
+pub const KERNEL_STACK_SIZE: u64 = 64 * 1024;
+/// 内核栈虚拟区域起始（最低一页为不映射的保护页，栈位于其上）
+pub const KERNEL_STACK_VIRT_BASE: u64 = 0xFFFF_FFFF_7FFE_0000;
+
+/// 交接数据缓冲区（物理地址，均已清零）
+pub struct Handoff {
+    pub boot_info: u64,
+    pub cmdline: u64,
+    pub kernel_segments: u64,
+    pub modules: u64,
+    pub video_modes: u64,
+    pub warnings: u64,
+}
+
+impl Handoff {
+    /// 为所有交接表分配内存
+    pub fn allocate() -> uefi::Result<Self> {
+        Ok(Self {
+            boot_info: allocate_handoff(size_of::<BootInfo>())?,
+            cmdline: allocate_handoff(CMDLINE_MAX)?,
+            kernel_segments: allocate_handoff(MAX_KERNEL_SEGMENTS * size_of::<KernelSegment>())?,
+            modules: allocate_kernel_memory(MAX_MODULES * size_of::<ModuleInfo>())?,
+            video_modes: allocate_handoff(MAX_VIDEO_MODES * size_of::<VideoMode>())?,
+            warnings: allocate_handoff(MAX_WARNINGS * size_of::<BootWarning>())?,
+        })
+    }
+}
+
+/// 分配清零的交接数据页，返回物理地址
+pub fn allocate_handoff(size: usize) -> uefi::Result<u64> {
+    allocate_zeroed(HANDOFF_MEMORY_TYPE, size)
+}
+
+/// 把 `items` 复制到新分配的交接内存，返回物理地址（空表为 0）
+pub fn allocate_table<T: Copy>(items: &[T]) -> uefi::Result<u64> {
+    if items.is_empty() {
+        return Ok(0);
+    }
+    let addr = allocate_handoff(size_of_val(items))?;
+    unsafe {
+        core::ptr::copy_nonoverlapping(items.as_ptr(), addr as *mut T, items.len());
+    }
+    Ok(addr)
+}
+
+/// 内存映射表（退出引导服务后填写，必须提前分配）
+pub struct MemoryMapTable {
+    /// 物理地址
+    pub addr: u64,
+    /// 可容纳的区域数
+    pub capacity: usize,
+}
+
+/// 按固件当前内存映射的条目数（加上 [`MEMORY_MAP_SLACK`]）分配内存映射表
+pub fn allocate_memory_map() -> uefi::Result<MemoryMapTable> {
+    let entries = boot::memory_map(MemoryType::LOADER_DATA)?.len();
+    let capacity = entries + MEMORY_MAP_SLACK;
+    Ok(MemoryMapTable {
+        addr: allocate_handoff(capacity * size_of::<MemoryRegion>())?,
+        capacity,
+    })
+}
+
+/// 分配清零的内核内存页（启动模块内容），返回物理地址
+pub fn allocate_kernel_memory(size: usize) -> uefi::Result<u64> {
+    allocate_zeroed(KERNEL_MEMORY_TYPE, size)
+}
+
+/// 内核栈
+pub struct KernelStack {
+    /// 栈底物理地址
+    pub phys_base: u64,
+    /// 栈顶虚拟地址（初始 RSP）
+    pub virt_top: u64,
+}
+
+/// 分配内核栈；保护页只存在于虚拟地址空间，由页表构建时留空
+pub fn allocate_kernel_stack() -> uefi::Result<KernelStack> {
+    let phys_base = allocate_kernel_memory(KERNEL_STACK_SIZE as usize)?;
+    Ok(KernelStack {
+        phys_base,
+        virt_top: KERNEL_STACK_VIRT_BASE + PAGE_SIZE + KERNEL_STACK_SIZE,
+    })
+}
+
+/// 释放 [`allocate_handoff`] 或 [`allocate_kernel_memory`] 分配的 `size` 字节（地址为 0 时忽略）
+///
+/// # Safety
+///
+/// `addr` 与 `size` 必须来自同一次分配，且之后不再访问这段内存。
+pub unsafe fn free_memory(addr: u64, size: u64) {
+    if let Some(ptr) = NonNull::new(addr as *mut u8) {
+        let _ = boot::free_pages(ptr, size.div_ceil(PAGE_SIZE) as usize);
+    }
+}
+
+fn allocate_zeroed(memory_type: MemoryType, size: usize) -> uefi::Result<u64> {
+    let pages = size.div_ceil(PAGE_SIZE as usize);
+    let ptr = boot::allocate_pages(boot::AllocateType::AnyPages, memory_type, pages)?;
+    unsafe { core::ptr::write_bytes(ptr.as_ptr(), 0, pages * PAGE_SIZE as usize) };
+    Ok(ptr.as_ptr() as u64)
+}
@@ -5,233 +5,66 @@
 //! UEFI 引导程序是操作系统启动的第一阶段，负责：
 //!
 //! 1. **初始化图形输出** - 通过 GOP 获取帧缓冲区
-//! 2. **加载内核文件** - 从 EFI 系统分区读取内核
+//! 2. **加载内核文件** - 从 EFI 系统分区读取内核 ELF，按 PT_LOAD 段加载，
+//!    并加载启动项指定的模块（initrd 等）
 //! 3. **收集硬件信息**:
 //!    - 内存映射 (Memory Map)
 //!    - ACPI 表 (RSDP)
 //!    - SMBIOS 表 (系统信息)
 //!    - 存储设备列表
-//! 4. **退出引导服务** - 将控制权转移给操作系统
-//! 5. **跳转到内核** - 传递所有收集的信息
+//! 4. **建立页表** - 内核映射到高半部分，物理内存直接映射，
+//!    UEFI 运行时区域映射到固定偏移
+//! 5. **退出引导服务** - 将控制权转移给操作系统，运行时服务切换到虚拟地址
+//! 6. **跳转到内核** - 切换 CR3，传递所有收集的信息
 
 #![no_std]
 #![no_main]
 
-use core::arch::asm;
+extern crate alloc;
+
+mod config;
+mod disks;
+mod elf;
+mod error;
+mod fs;
+mod graphics;
+mod handoff;
+mod menu;
+mod modules;
+mod paging;
+mod pci;
+mod runtime;
+
+use boot_protocol::{
+    BootInfo, BootStage, FramebufferInfo, KernelSegment, MemoryRegion,
+    MemoryRegionType, BOOTINFO_MAGIC, BOOTINFO_VERSION, BOOT_STAGE_COUNT, PAGE_SIZE,
+    PHYSICAL_MEMORY_OFFSET, SEGMENT_EXECUTE, SEGMENT_READ, SEGMENT_WRITE, TRUNCATED_CMDLINE,
+    TRUNCATED_MEMORY_MAP, TRUNCATED_VIDEO_MODES, TRUNCATED_WARNINGS, UEFI_RUNTIME_OFFSET,
+};
+use config::{BootConfig, BootEntry, VideoRequest};
+use core::convert::Infallible;
 use core::fmt::Write;
+use core::ptr::NonNull;
+use elf::ElfFile;
+use error::{BootError, BootResult, Recovery, StageContext, Warnings};
+use handoff::{
+    allocate_handoff, allocate_kernel_stack, Handoff, KernelStack, CMDLINE_MAX, HANDOFF_MEMORY_TYPE,
+    KERNEL_MEMORY_TYPE, KERNEL_STACK_SIZE, KERNEL_STACK_VIRT_BASE,
+    MAX_KERNEL_SEGMENTS,
+};
+use paging::{enter_kernel, PageTableBuilder, ENTER_KERNEL_CODE_SIZE};
 use uefi::boot::{self, MemoryType};
-use uefi::mem::memory_map::MemoryMap;
+use uefi::mem::memory_map::{MemoryAttribute, MemoryDescriptor, MemoryMap, MemoryMapMut};
 use uefi::prelude::*;
-use uefi::proto::console::gop::{GraphicsOutput, PixelFormat};
 use uefi::proto::console::text::Output;
-use uefi::proto::media::block::BlockIO;
-use uefi::proto::media::file::{File, FileAttribute, FileInfo, FileMode};
-use uefi::proto::media::fs::SimpleFileSystem;
-use uefi::Identify;
-
-// ============================================================================
-// 引导信息结构体定义
-// ============================================================================
-
-/// 像素格式
-#[repr(u32)]
-#[derive(Clone, Copy, Debug)]
-pub enum PixelFormatType {
-    /// RGB 格式 (R在低字节)
-    Rgb = 0,
-    /// BGR 格式 (B在低字节，最常见)
-    Bgr = 1,
-    /// 位掩码格式
-    Bitmask = 2,
-    /// 仅 BLT 格式
-    BltOnly = 3,
-}
-
-/// 帧缓冲区信息
-#[repr(C)]
-#[derive(Clone, Copy)]
-pub struct FramebufferInfo {
-    /// 帧缓冲区物理地址
-    pub address: u64,
-    /// 帧缓冲区总大小（字节）
-    pub size: u64,
-    /// 屏幕宽度（像素）
-    pub width: u32,
-    /// 屏幕高度（像素）
-    pub height: u32,
-    /// 每行像素数（可能 > width，因为对齐）
-    pub stride: u32,
-    /// 每像素字节数
-    pub bytes_per_pixel: u32,
-    /// 像素格式
-    pub pixel_format: u32,
-    /// 保留，对齐用
-    pub _reserved: u32,
-}
-
-/// 内存区域类型（简化版）
-#[repr(u32)]
-#[derive(Clone, Copy, Debug, PartialEq)]
-pub enum MemoryRegionType {
-    /// 可用内存
-    Usable = 0,
-    /// 保留内存（不可使用）
-    Reserved = 1,
-    /// ACPI 可回收内存
-    AcpiReclaimable = 2,
-    /// ACPI NVS 内存
-    AcpiNvs = 3,
-    /// 内存映射 I/O
-    Mmio = 4,
-    /// 引导程序代码/数据（内核可回收）
-    BootloaderReclaimable = 5,
-    /// 内核代码/数据
-    KernelAndModules = 6,
-    /// 帧缓冲区
-    Framebuffer = 7,
-}
-
-/// 内存区域描述符（简化版，兼容性更好）
-#[repr(C)]
-#[derive(Clone, Copy)]
-pub struct MemoryRegion {
-    /// 物理起始地址
-    pub phys_start: u64,
-    /// 虚拟起始地址（通常与物理相同）
-    pub virt_start: u64,
-    /// 页数（每页 4KB）
-    pub page_count: u64,
-    /// 区域类型
-    pub region_type: u32,
-    /// 属性标志
-    pub attributes: u32,
-}
-
-/// 磁盘类型
-#[repr(u32)]
-#[derive(Clone, Copy, Debug)]
-pub enum DiskType {
-    Unknown = 0,
-    HardDisk = 1,
-    CdRom = 2,
-    Usb = 3,
-    NVMe = 4,
-    Floppy = 5,
-    Network = 6,
-}
-
-/// 磁盘信息
-#[repr(C)]
-#[derive(Clone, Copy)]
-pub struct DiskInfo {
-    /// 磁盘类型
-    pub disk_type: u32,
-    /// 是否可移动 (1=可移动, 0=固定)
-    pub removable: u32,
-    /// 是否为启动设备 (1=是, 0=否)
-    pub boot_device: u32,
-    /// 是否只读
-    pub read_only: u32,
-    /// 逻辑块大小（字节）
-    pub block_size: u64,
-    /// 总块数
-    pub total_blocks: u64,
-    /// 总容量（字节）
-    pub total_size: u64,
-    /// 媒体 ID
-    pub media_id: u32,
-    /// 保留
-    pub _reserved: u32,
-}
-
-/// 主引导信息结构体 - 传递给内核的所有信息
-#[repr(C)]
-#[derive(Clone, Copy)]
-pub struct BootInfo {
-    /// 魔数，用于验证结构体有效性 (应为 0x4A414E5F4F530000 "JAN_OS\0\0")
-    pub magic: u64,
-    /// 结构体版本号
-    pub version: u32,
-    /// 结构体大小（字节）
-    pub size: u32,
-
-    // ========== 帧缓冲区信息 ==========
-    pub framebuffer: FramebufferInfo,
-
-    // ========== 内存映射 ==========
-    /// 内存区域数组地址
-    pub memory_map_addr: u64,
-    /// 内存区域数量
-    pub memory_map_entries: u32,
-    /// 每个条目大小
-    pub memory_map_entry_size: u32,
-    /// 总可用内存（字节）
-    pub total_memory: u64,
-    /// 可用内存（字节）
-    pub usable_memory: u64,
-
-    // ========== ACPI 信息 ==========
-    /// ACPI RSDP 地址 (0 表示未找到)
-    pub acpi_rsdp_addr: u64,
-    /// ACPI 版本 (1 或 2)
-    pub acpi_version: u32,
-    pub _acpi_reserved: u32,
-
-    // ========== SMBIOS 信息 ==========
-    /// SMBIOS 入口点地址 (0 表示未找到)
-    pub smbios_addr: u64,
-    /// SMBIOS 版本
-    pub smbios_version: u32,
-    pub _smbios_reserved: u32,
-
-    // ========== 存储设备信息 ==========
-    /// 磁盘信息数组地址
-    pub disk_info_addr: u64,
-    /// 检测到的磁盘数量
-    pub disk_count: u32,
-    /// 启动设备索引 (-1 表示未知)
-    pub boot_disk_index: i32,
-
-    // ========== UEFI 运行时服务 ==========
-    /// UEFI 运行时服务表地址 (ExitBootServices 后仍可用)
-    pub uefi_runtime_services: u64,
-
-    // ========== 内核信息 ==========
-    /// 内核加载的物理地址
-    pub kernel_phys_addr: u64,
-    /// 内核大小（字节）
-    pub kernel_size: u64,
-
-    // ========== 命令行 ==========
-    /// 命令行字符串地址
-    pub cmdline_addr: u64,
-    /// 命令行长度
-    pub cmdline_len: u32,
-    pub _cmdline_reserved: u32,
-}
+use uefi::{CStr16, CString16};
 
 // ============================================================================
 // 常量定义
 // ============================================================================
 
-/// BootInfo 魔数: "JAN_OS\0\0" 的 ASCII 值
-const BOOTINFO_MAGIC: u64 = 0x4A414E5F4F530000;
-/// BootInfo 版本
-const BOOTINFO_VERSION: u32 = 1;
-/// 内核加载地址
-const KERNEL_LOAD_ADDR: u64 = 0x100000;
-/// BootInfo 存储地址
-const BOOTINFO_ADDR: u64 = 0x7000;
-/// 内存映射存储地址
-const MEMMAP_ADDR: u64 = 0x10000;
-/// 磁盘信息存储地址
-const DISKINFO_ADDR: u64 = 0x20000;
-/// 命令行存储地址
-const CMDLINE_ADDR: u64 = 0x21000;
-/// 最大磁盘数
-const MAX_DISKS: usize = 32;
-/// 最大内存区域数
-const MAX_MEMORY_REGIONS: usize = 256;
+/// 直接映射至少覆盖的物理地址范围（包含 APIC、HPET 等 4GB 以下的 MMIO）
+const MIN_DIRECT_MAP: u64 = 0x1_0000_0000;
 
 // ============================================================================
 // 入口点
@@ -239,38 +72,175 @@ const MAX_MEMORY_REGIONS: usize = 256;
 
 #[entry]
 fn main() -> Status {
+    // 引导菜单：选择启动项（可编辑命令行），倒计时结束启动默认项
+    let mut config = config::BootConfig::load();
+    let mut selected = menu::select(&mut config);
+
+    // 交接数据全部向固件申请，不使用固定地址；重试时复用同一组缓冲区
+    let (handoff, stack) = loop {
+        let allocated = Handoff::allocate()
+            .and_then(|handoff| Ok((handoff, allocate_kernel_stack()?)))
+            .stage(BootStage::Setup, "Failed to allocate boot hand-off memory");
+        match allocated {
+            Ok(allocated) => break allocated,
+            Err(err) => {
+                if error::report(&err, None) == Recovery::Exit {
+                    return err.status;
+                }
+            }
+        }
+    };
+
+    // 只有出错时才返回；由用户选择重试、改用下一个启动项或返回固件
+    loop {
+        let entry = &config.entries[selected];
+        let Err(err) = boot(entry, &config, &handoff, &stack);
+        let fallback = (config.entries.len() > 1).then(|| (selected + 1) % config.entries.len());
+        let fallback_title = fallback.map(|i| config.entries[i].title.as_str());
+        match error::report(&err, fallback_title) {
+            Recovery::Retry => {}
+            Recovery::Fallback => selected = fallback.unwrap_or(selected),
+            Recovery::Exit => return err.status,
+        }
+    }
+}
+
+/// 打印步骤标题，如 `[1/7] Initializing graphics (GOP)...`
+fn print_step(stage: BootStage) {
+    print_uefi("[");
+    print_dec(stage.number() as u64);
+    print_uefi("/");
+    print_dec(BOOT_STAGE_COUNT as u64);
+    print_uefi("] ");
+    print_uefi(stage.as_str());
+    println_uefi("...");
+}
+
+/// 按启动项引导内核，成功时不返回
+///
+/// 失败时释放内核映像与模块，使重试能在同一物理地址重新加载；
+/// 交接类型的内存（ELF 副本、页表等）不释放，内核启动后会回收。
+fn boot(
+    entry: &BootEntry,
+    config: &BootConfig,
+    handoff: &Handoff,
+    stack: &KernelStack,
+) -> BootResult<Infallible> {
+    let mut warnings = Warnings::new(handoff.warnings);
+
     // 显示启动信息
     println_uefi("========================================");
     println_uefi("  january_os UEFI Bootloader v0.1.0");
     println_uefi("  Architecture: x86_64");
     println_uefi("========================================");
     println_uefi("");
+    print_uefi("Boot entry: ");
+    println_uefi(&entry.title);
+    println_uefi("");
 
     // 第一步：初始化图形
-    println_uefi("[1/7] Initializing graphics (GOP)...");
-    let framebuffer = setup_graphics();
-    print_uefi("      Resolution: ");
-    print_dec(framebuffer.width as u64);
-    print_uefi("x");
-    print_dec(framebuffer.height as u64);
-    println_uefi("");
+    print_step(BootStage::Graphics);
+    let video = VideoRequest::from_cmdline(&entry.cmdline).unwrap_or(config.video);
+    let graphics = graphics::setup_graphics(video, handoff.video_modes);
+    let framebuffer = graphics.framebuffer;
+    if graphics.current_mode < 0 {
+        println_uefi("      GOP not available, continuing without framebuffer");
+        warnings.push(BootStage::Graphics, Status::NOT_FOUND);
+    } else {
+        if graphics.request_failed {
+            println_uefi("      Requested video mode not available, keeping current mode");
+            warnings.push(BootStage::Graphics, Status::UNSUPPORTED);
+        }
+        print_uefi("      Resolution: ");
+        print_dec(framebuffer.width as u64);
+        print_uefi("x");
+        print_dec(framebuffer.height as u64);
+        print_uefi(" (");
+        print_dec(graphics.mode_count as u64);
+        println_uefi(if framebuffer.is_present() { " modes)" } else { " modes, no linear framebuffer)" });
+    }
 
     // 第二步：加载内核
-    println_uefi("[2/7] Loading kernel...");
-    let kernel_size = load_kernel();
+    print_step(BootStage::Kernel);
+    let kernel_path = CString16::try_from(entry.kernel.as_str()).map_err(|_| {
+        BootError::new(BootStage::Kernel, Status::INVALID_PARAMETER, "Invalid kernel path in boot entry")
+    })?;
+    let kernel = load_kernel(&kernel_path, handoff.kernel_segments)?;
     print_uefi("      Kernel size: ");
-    print_dec(kernel_size as u64);
-    println_uefi(" bytes");
+    print_dec(kernel.size);
+    print_uefi(" bytes, entry at 0x");
+    print_hex(kernel.entry);
+    println_uefi("");
+    let module_count = modules::load_modules(&entry.modules, handoff.modules, &mut warnings);
+
+    let Err(err) = boot_loaded(
+        entry,
+        config,
+        handoff,
+        stack,
+        &graphics,
+        &kernel,
+        module_count,
+        &mut warnings,
+    );
+    unsafe {
+        modules::free_modules(handoff.modules, module_count);
+        kernel.free();
+    }
+    Err(err)
+}
+
+/// 内核已加载后的步骤：收集硬件信息、建立页表、退出引导服务并跳转
+#[allow(clippy::too_many_arguments)]
+fn boot_loaded(
+    entry: &BootEntry,
+    config: &BootConfig,
+    handoff: &Handoff,
+    stack: &KernelStack,
+    graphics: &graphics::Graphics,
+    kernel: &LoadedKernel,
+    module_count: u32,
+    warnings: &mut Warnings,
+) -> BootResult<Infallible> {
+    let framebuffer = graphics.framebuffer;
 
     // 第三步：扫描存储设备
-    println_uefi("[3/7] Scanning storage devices...");
-    let (disk_count, boot_disk) = scan_disks();
+    print_step(BootStage::Disks);
+    let disks = match disks::scan_disks() {
+        Ok(scan) => scan,
+        Err(err) => {
+            println_uefi("      No block devices found");
+            warnings.push(BootStage::Disks, err.status());
+            disks::DiskScan {
+                disk_info_addr: 0,
+                count: 0,
+                partition_info_addr: 0,
+                partition_count: 0,
+                boot_disk: -1,
+                boot_device: disks::no_boot_device(),
+            }
+        }
+    };
     print_uefi("      Found ");
-    print_dec(disk_count as u64);
-    println_uefi(" disk(s)");
+    print_dec(disks.count as u64);
+    print_uefi(" disk(s), ");
+    print_dec(disks.partition_count as u64);
+    print_uefi(" partition(s)");
+    if disks.boot_disk >= 0 {
+        print_uefi(", booted from disk ");
+        print_dec(disks.boot_disk as u64);
+        if disks.boot_device.partition_number != 0 {
+            print_uefi(" partition ");
+            print_dec(disks.boot_device.partition_number as u64);
+            print_uefi(" (");
+            print_uefi(disks.boot_device.scheme.as_str());
+            print_uefi(")");
+        }
+    }
+    println_uefi("");
 
     // 第四步：获取 ACPI RSDP
-    println_uefi("[4/7] Locating ACPI tables...");
+    print_step(BootStage::Acpi);
     let (acpi_rsdp, acpi_version) = find_acpi_rsdp();
     if acpi_rsdp != 0 {
         print_uefi("      RSDP at 0x");
@@ -280,10 +250,11 @@ fn main() -> Status {
         println_uefi(".0)");
     } else {
         println_uefi("      ACPI not found!");
+        warnings.push(BootStage::Acpi, Status::NOT_FOUND);
     }
 
     // 第五步：获取 SMBIOS
-    println_uefi("[5/7] Locating SMBIOS...");
+    print_step(BootStage::Smbios);
     let (smbios_addr, smbios_version) = find_smbios();
     if smbios_addr != 0 {
         print_uefi("      SMBIOS at 0x");
@@ -291,43 +262,127 @@ fn main() -> Status {
         println_uefi("");
     } else {
         println_uefi("      SMBIOS not found");
+        warnings.push(BootStage::Smbios, Status::NOT_FOUND);
     }
 
     // 第六步：获取运行时服务
-    println_uefi("[6/7] Getting UEFI Runtime Services...");
-    let runtime_services = get_runtime_services();
-    print_uefi("      Runtime Services at 0x");
-    print_hex(runtime_services);
+    print_step(BootStage::Runtime);
+    let runtime_tables = runtime::locate();
+    if runtime_tables.runtime_services == 0 {
+        warnings.push(BootStage::Runtime, Status::NOT_FOUND);
+    }
+    print_uefi("      System Table at 0x");
+    print_hex(runtime_tables.system_table);
+    print_uefi(", Runtime Services at 0x");
+    print_hex(runtime_tables.runtime_services);
     println_uefi("");
 
-    // 设置命令行（可以从 UEFI 变量读取或使用默认值）
-    let cmdline = b"console=ttyS0 loglevel=7\0";
+    // 设置命令行（来源见 config 模块），超长部分在字符边界截断
+    let cmdline = &entry.cmdline;
+    let cmdline_len = cmdline.floor_char_boundary(CMDLINE_MAX - 1);
+    let cmdline_truncated = cmdline_len < cmdline.len();
     unsafe {
-        let cmdline_ptr = CMDLINE_ADDR as *mut u8;
-        for (i, &byte) in cmdline.iter().enumerate() {
-            *cmdline_ptr.add(i) = byte;
-        }
+        // 重试时缓冲区可能残留上次的内容，先清零以保留结尾 null
+        core::ptr::write_bytes(handoff.cmdline as *mut u8, 0, CMDLINE_MAX);
+        core::ptr::copy_nonoverlapping(cmdline.as_ptr(), handoff.cmdline as *mut u8, cmdline_len);
+    }
+    print_uefi("      Command line (");
+    print_uefi(entry.cmdline_source.as_str());
+    print_uefi("): ");
+    println_uefi(&cmdline[..cmdline_len]);
+    if cmdline_truncated {
+        print_uefi("      Warning: command line truncated to ");
+        print_dec(cmdline_len as u64);
+        println_uefi(" bytes");
     }
 
-    println_uefi("[7/7] Exiting boot services...");
+    let stage = BootStage::ExitBootServices;
+    print_step(stage);
+    let mut page_tables =
+        PageTableBuilder::new().stage(stage, "Failed to allocate page tables")?;
+    let kernel_segments = unsafe {
+        core::slice::from_raw_parts(
+            handoff.kernel_segments as *const KernelSegment,
+            kernel.segment_count as usize,
+        )
+    };
+    page_tables
+        .map_kernel(kernel_segments)
+        .stage(stage, "Failed to map kernel")?;
+    page_tables
+        .map_data(KERNEL_STACK_VIRT_BASE + PAGE_SIZE, stack.phys_base, KERNEL_STACK_SIZE)
+        .stage(stage, "Failed to map kernel stack")?;
+    let phys_mapped = map_physical_memory(&mut page_tables, &framebuffer)
+        .stage(stage, "Failed to map physical memory")?;
+    // 退出引导服务后不能再分配，提前准备 SetVirtualAddressMap 的缓冲区
+    let mut virtual_map = match config.uefi_virtual_map {
+        true => Some(
+            runtime::VirtualMap::allocate()
+                .stage(stage, "Failed to allocate virtual address map")?,
+        ),
+        false => None,
+    };
+    let runtime_regions = runtime::map_runtime_regions(&mut page_tables, virtual_map.as_mut())
+        .stage(stage, "Failed to map UEFI runtime regions")?;
+    page_tables
+        .identity_map_code(enter_kernel as *const () as u64, ENTER_KERNEL_CODE_SIZE)
+        .stage(stage, "Failed to map kernel trampoline")?;
+    let memory_map = handoff::allocate_memory_map()
+        .stage(stage, "Failed to allocate memory map table")?;
+    print_uefi("      Kernel mapped at 0x");
+    print_hex(kernel.virt_addr);
     println_uefi("");
-    println_uefi("Jumping to kernel at 0x100000...");
+    print_uefi("      Physical memory mapped at 0x");
+    print_hex(PHYSICAL_MEMORY_OFFSET);
+    print_uefi(" (");
+    print_dec(phys_mapped / 1024 / 1024);
+    println_uefi(" MB)");
+    print_uefi("      UEFI runtime: ");
+    print_dec(runtime_regions as u64);
+    print_uefi(" region(s) at +0x");
+    print_hex(UEFI_RUNTIME_OFFSET);
+    println_uefi(if config.uefi_virtual_map { "" } else { " (virtual map disabled)" });
+    println_uefi("");
+    print_uefi("Jumping to kernel at 0x");
+    print_hex(kernel.entry);
+    println_uefi("...");
     println_uefi("");
 
-    // 短暂延迟让用户看到信息
-    for _ in 0..3_000_000 {
-        unsafe { asm!("pause"); }
-    }
+    // 退出引导服务（最终内存映射缓冲区也标记为交接数据）
+    let mut mmap = unsafe { boot::exit_boot_services(Some(HANDOFF_MEMORY_TYPE)) };
 
-    // 退出引导服务
-    let mmap = unsafe { boot::exit_boot_services(None) };
+    // 运行时服务切换到虚拟地址，失败时内核不得调用运行时服务（记为警告）
+    let runtime_offset =
+        match virtual_map.map(|map| unsafe { map.apply(mmap.entries(), runtime_tables.system_table) }) {
+            Some(Ok(())) => UEFI_RUNTIME_OFFSET,
+            Some(Err(err)) => {
+                warnings.push(stage, err.status());
+                0
+            }
+            None => 0,
+        };
 
     // 填充引导信息
     unsafe {
-        let boot_info_ptr = BOOTINFO_ADDR as *mut BootInfo;
+        let boot_info_ptr = handoff.boot_info as *mut BootInfo;
         
-        // 转换并复制内存映射
-        let (mem_entries, total_mem, usable_mem) = copy_memory_map(mmap.entries());
+        // 转换并复制内存映射（固件不保证描述符有序，排序后才能合并相邻区域）
+        mmap.sort();
+        let (mem_entries, total_mem, usable_mem, mmap_truncated) =
+            copy_memory_map(&memory_map, mmap.entries(), runtime_offset);
+        let mut truncated = 0;
+        if mmap_truncated {
+            truncated |= TRUNCATED_MEMORY_MAP;
+        }
+        if graphics.modes_truncated {
+            truncated |= TRUNCATED_VIDEO_MODES;
+        }
+        if warnings.dropped() {
+            truncated |= TRUNCATED_WARNINGS;
+        }
+        if cmdline_truncated {
+            truncated |= TRUNCATED_CMDLINE;
+        }
 
         let boot_info = BootInfo {
             magic: BOOTINFO_MAGIC,
@@ -335,8 +390,11 @@ fn main() -> Status {
             size: core::mem::size_of::<BootInfo>() as u32,
 
             framebuffer,
+            video_modes_addr: handoff.video_modes,
+            video_mode_count: graphics.mode_count,
+            video_mode_current: graphics.current_mode,
 
-            memory_map_addr: MEMMAP_ADDR,
+            memory_map_addr: memory_map.addr,
             memory_map_entries: mem_entries,
             memory_map_entry_size: core::mem::size_of::<MemoryRegion>() as u32,
             total_memory: total_mem,
@@ -350,33 +408,62 @@ fn main() -> Status {
             smbios_version,
             _smbios_reserved: 0,
 
-            disk_info_addr: DISKINFO_ADDR,
-            disk_count,
-            boot_disk_index: boot_disk,
+            disk_info_addr: disks.disk_info_addr,
+            disk_count: disks.count,
+            boot_disk_index: disks.boot_disk,
+
+            uefi_runtime_services: runtime_tables.runtime_services,
+            uefi_system_table: runtime_tables.system_table,
+            uefi_runtime_offset: runtime_offset,
+
+            kernel_phys_addr: kernel.phys_addr,
+            kernel_size: kernel.size,
+            kernel_entry: kernel.entry,
+            kernel_segments_addr: handoff.kernel_segments,
+            kernel_segment_count: kernel.segment_count,
+            _kernel_reserved: 0,
+            kernel_elf_addr: kernel.elf_addr,
+            kernel_elf_size: kernel.elf_size,
+
+            physical_memory_offset: PHYSICAL_MEMORY_OFFSET,
+            physical_memory_mapped: phys_mapped,
+            kernel_virt_addr: kernel.virt_addr,
+            page_table_root: page_tables.root(),
+            kernel_stack_top: stack.virt_top,
+            kernel_stack_size: KERNEL_STACK_SIZE,
+
+            cmdline_addr: handoff.cmdline,
+            cmdline_len: cmdline_len as u32, // 不含 null terminator
+            _cmdline_reserved: 0,
+
+            module_info_addr: handoff.modules,
+            module_count,
+            _module_reserved: 0,
 
-            uefi_runtime_services: runtime_services,
+            warnings_addr: handoff.warnings,
+            warning_count: warnings.count(),
+            _warnings_reserved: 0,
 
-            kernel_phys_addr: KERNEL_LOAD_ADDR,
-            kernel_size: kernel_size as u64,
+            boot_device: disks.boot_device,
 
-            cmdline_addr: CMDLINE_ADDR,
-            cmdline_len: (cmdline.len() - 1) as u32, // 不含 null terminator
-            _cmdline_reserved: 0,
+            partition_info_addr: disks.partition_info_addr,
+            partition_count: disks.partition_count,
+            _partition_reserved: 0,
+
+            truncated,
+            _truncated_reserved: 0,
         };
 
         core::ptr::write_volatile(boot_info_ptr, boot_info);
     }
 
-    // 跳转到内核
+    // 切换页表并跳转到内核（BootInfo 通过直接映射访问）
     unsafe {
-        asm!(
-            "cli",
-            "mov rsp, 0x80000",
-            "mov rdi, {boot_info}",
-            "jmp {entry}",
-            boot_info = in(reg) BOOTINFO_ADDR,
-            entry = in(reg) KERNEL_LOAD_ADDR,
-            options(noreturn)
+        enter_kernel(
+            page_tables.root(),
+            stack.virt_top,
+            PHYSICAL_MEMORY_OFFSET + handoff.boot_info,
+            kernel.entry,
         );
     }
 }
@@ -444,181 +531,144 @@ fn print_dec(val: u64) {
 }
 
 // ============================================================================
-// 图形初始化
+// 内核加载
 // ============================================================================
 
-fn setup_graphics() -> FramebufferInfo {
-    let gop_handle = boot::get_handle_for_protocol::<GraphicsOutput>()
-        .expect("GOP not available");
-    
-    let mut gop = boot::open_protocol_exclusive::<GraphicsOutput>(gop_handle)
-        .expect("Failed to open GOP");
-
-    let mode_info = gop.current_mode_info();
-    let (width, height) = mode_info.resolution();
-    let stride = mode_info.stride() as u32;
-    
-    let mut fb = gop.frame_buffer();
-    let fb_addr = fb.as_mut_ptr() as u64;
-    let fb_size = fb.size() as u64;
-    
-    let pixel_format = match mode_info.pixel_format() {
-        PixelFormat::Rgb => PixelFormatType::Rgb as u32,
-        PixelFormat::Bgr => PixelFormatType::Bgr as u32,
-        PixelFormat::Bitmask => PixelFormatType::Bitmask as u32,
-        PixelFormat::BltOnly => PixelFormatType::BltOnly as u32,
-    };
+/// 已加载内核的描述
+struct LoadedKernel {
+    /// 最低段的物理地址
+    phys_addr: u64,
+    /// 最低段的虚拟地址
+    virt_addr: u64,
+    /// 从最低段到最高段末尾的跨度（字节）
+    size: u64,
+    /// ELF 入口点
+    entry: u64,
+    /// 写入段描述表的段数量
+    segment_count: u32,
+    /// ELF 文件副本地址
+    elf_addr: u64,
+    /// ELF 文件大小
+    elf_size: u64,
+}
 
-    FramebufferInfo {
-        address: fb_addr,
-        size: fb_size,
-        width: width as u32,
-        height: height as u32,
-        stride,
-        bytes_per_pixel: 4,
-        pixel_format,
-        _reserved: 0,
+impl LoadedKernel {
+    /// 释放内核映像占用的页（ELF 副本属于交接内存，保留）
+    ///
+    /// # Safety
+    ///
+    /// 必须在退出引导服务之前调用，之后不得再访问内核映像。
+    unsafe fn free(&self) {
+        let start = self.phys_addr & !(PAGE_SIZE - 1);
+        let pages = (self.phys_addr + self.size - start).div_ceil(PAGE_SIZE) as usize;
+        if let Some(ptr) = NonNull::new(start as *mut u8) {
+            let _ = boot::free_pages(ptr, pages);
+        }
     }
 }
 
-// ============================================================================
-// 内核加载
-// ============================================================================
-
-fn load_kernel() -> usize {
-    let fs_handle = boot::get_handle_for_protocol::<SimpleFileSystem>()
-        .expect("No filesystem found");
-    
-    let mut fs = boot::open_protocol_exclusive::<SimpleFileSystem>(fs_handle)
-        .expect("Failed to open filesystem");
-
-    let mut root = fs.open_volume().expect("Failed to open volume");
-
-    let kernel_file_handle = root
-        .open(
-            cstr16!("\\EFI\\january_os\\kernel.bin"),
-            FileMode::Read,
-            FileAttribute::empty(),
-        )
-        .expect("Failed to open kernel file");
-
-    let mut kernel_file = kernel_file_handle
-        .into_regular_file()
-        .expect("Kernel is not a regular file");
+fn load_kernel(path: &CStr16, segments_addr: u64) -> BootResult<LoadedKernel> {
+    const STAGE: BootStage = BootStage::Kernel;
 
-    let mut info_buf = [0u8; 256];
-    let file_info: &FileInfo = kernel_file
-        .get_info(&mut info_buf)
-        .expect("Failed to get file info");
-    let kernel_size = file_info.file_size() as usize;
+    let mut kernel_file = fs::open_file(path).stage(STAGE, "Failed to open kernel file")?;
+    let elf_size = fs::file_size(&mut kernel_file).stage(STAGE, "Failed to get file info")?;
 
-    let pages = (kernel_size + 4095) / 4096;
-    boot::allocate_pages(
-        boot::AllocateType::Address(KERNEL_LOAD_ADDR),
-        MemoryType::LOADER_CODE,
-        pages,
-    )
-    .expect("Failed to allocate memory for kernel");
-
-    let kernel_buffer = unsafe {
-        core::slice::from_raw_parts_mut(KERNEL_LOAD_ADDR as *mut u8, kernel_size)
+    // 整个 ELF 文件读入引导程序内存，保留符号表供内核调试使用
+    let elf_addr =
+        allocate_handoff(elf_size).stage(STAGE, "Failed to allocate memory for kernel ELF")?;
+    let elf_data = unsafe { core::slice::from_raw_parts_mut(elf_addr as *mut u8, elf_size) };
+    let loaded = match kernel_file.read(elf_data) {
+        Ok(read) if read == elf_size => load_elf(elf_data, segments_addr),
+        Ok(_) => Err(BootError::new(STAGE, Status::LOAD_ERROR, "Short read of kernel file")),
+        Err(err) => Err(BootError::new(STAGE, err.status(), "Failed to read kernel")),
     };
-    kernel_file.read(kernel_buffer).expect("Failed to read kernel");
-
-    kernel_size
+    // 失败时 ELF 副本不会交给内核
+    if loaded.is_err() {
+        unsafe { handoff::free_memory(elf_addr, elf_size as u64) };
+    }
+    loaded
 }
 
-// ============================================================================
-// 存储设备扫描
-// ============================================================================
-
-fn scan_disks() -> (u32, i32) {
-    let disk_info_base = DISKINFO_ADDR as *mut DiskInfo;
-    let mut count = 0u32;
-    let mut boot_disk = -1i32;
-
-    // 获取所有 BlockIO 句柄
-    let handles = match boot::locate_handle_buffer(boot::SearchType::ByProtocol(&BlockIO::GUID)) {
-        Ok(h) => h,
-        Err(_) => return (0, -1),
-    };
-
-    for handle in handles.iter() {
-        if count >= MAX_DISKS as u32 {
-            break;
-        }
-
-        if let Ok(block_io) = boot::open_protocol_exclusive::<BlockIO>(*handle) {
-            let media = block_io.media();
-            
-            // 跳过没有介质的设备
-            if !media.is_media_present() {
-                continue;
-            }
-
-            // 判断磁盘类型
-            let disk_type = if media.is_removable_media() {
-                if media.block_size() == 2048 {
-                    DiskType::CdRom as u32
-                } else {
-                    DiskType::Usb as u32
-                }
-            } else {
-                DiskType::HardDisk as u32
-            };
-
-            let total_blocks = media.last_block() + 1;
-            let block_size = media.block_size() as u64;
-            let total_size = total_blocks * block_size;
-
-            let disk_info = DiskInfo {
-                disk_type,
-                removable: if media.is_removable_media() { 1 } else { 0 },
-                boot_device: 0, // 稍后确定
-                read_only: if media.is_read_only() { 1 } else { 0 },
-                block_size,
-                total_blocks,
-                total_size,
-                media_id: media.media_id(),
+/// 解析读入内存的内核 ELF，分配内核映像并复制各段，段描述写入 `segments_addr`
+fn load_elf(elf_data: &[u8], segments_addr: u64) -> BootResult<LoadedKernel> {
+    const STAGE: BootStage = BootStage::Kernel;
+    let load_error = |what| BootError::new(STAGE, Status::LOAD_ERROR, what);
+
+    let elf = ElfFile::parse(elf_data).map_err(|err| load_error(err.as_str()))?;
+
+    // 计算所有 PT_LOAD 段覆盖的物理范围，一次性分配
+    let mut image_start = u64::MAX;
+    let mut image_end = 0u64;
+    let mut virt_start = u64::MAX;
+    for ph in elf.load_segments() {
+        virt_start = virt_start.min(ph.vaddr);
+        image_start = image_start.min(ph.paddr);
+        let end = ph
+            .paddr
+            .checked_add(ph.memsz)
+            .ok_or(load_error("Kernel segment exceeds the physical address space"))?;
+        image_end = image_end.max(end);
+    }
+    if image_start >= image_end {
+        return Err(load_error("Kernel ELF has no loadable segments"));
+    }
+    // 分配之前检查，出错时不必回滚
+    if elf.load_segments().count() > MAX_KERNEL_SEGMENTS {
+        return Err(load_error("Kernel ELF has too many loadable segments"));
+    }
+    let alloc_start = image_start & !0xFFF;
+    let alloc_pages = (image_end - alloc_start).div_ceil(4096) as usize;
+    boot::allocate_pages(
+        boot::AllocateType::Address(alloc_start),
+        KERNEL_MEMORY_TYPE,
+        alloc_pages,
+    )
+    .stage(STAGE, "Failed to allocate memory for kernel")?;
+
+    // 复制各段并清零 BSS
+    let segments = segments_addr as *mut KernelSegment;
+    let mut segment_count = 0u32;
+    for ph in elf.load_segments() {
+        let file_data = elf.segment_data(&ph);
+        unsafe {
+            let dest = ph.paddr as *mut u8;
+            core::ptr::copy_nonoverlapping(file_data.as_ptr(), dest, file_data.len());
+            core::ptr::write_bytes(dest.add(file_data.len()), 0, (ph.memsz - ph.filesz) as usize);
+
+            let segment = KernelSegment {
+                phys_addr: ph.paddr,
+                virt_addr: ph.vaddr,
+                file_size: ph.filesz,
+                mem_size: ph.memsz,
+                flags: ph.flags & (SEGMENT_READ | SEGMENT_WRITE | SEGMENT_EXECUTE),
                 _reserved: 0,
             };
-
-            unsafe {
-                core::ptr::write_volatile(disk_info_base.add(count as usize), disk_info);
-            }
-
-            // 打印磁盘信息
-            print_uefi("      Disk ");
-            print_dec(count as u64);
-            print_uefi(": ");
-            match disk_type {
-                x if x == DiskType::HardDisk as u32 => print_uefi("HDD"),
-                x if x == DiskType::CdRom as u32 => print_uefi("CD-ROM"),
-                x if x == DiskType::Usb as u32 => print_uefi("USB"),
-                _ => print_uefi("Unknown"),
-            }
-            print_uefi(", ");
-            print_dec(total_size / 1024 / 1024);
-            println_uefi(" MB");
-
-            count += 1;
+            core::ptr::write_volatile(segments.add(segment_count as usize), segment);
         }
-    }
 
-    // 简单启发：第一个非可移动磁盘可能是启动盘
-    unsafe {
-        for i in 0..count {
-            let disk = &*disk_info_base.add(i as usize);
-            if disk.removable == 0 && disk.disk_type == DiskType::HardDisk as u32 {
-                boot_disk = i as i32;
-                // 标记为启动设备
-                (*disk_info_base.add(i as usize)).boot_device = 1;
-                break;
-            }
-        }
+        print_uefi("      Segment ");
+        print_dec(segment_count as u64);
+        print_uefi(": 0x");
+        print_hex(ph.paddr);
+        print_uefi(" ");
+        print_dec(ph.memsz);
+        print_uefi(" bytes ");
+        print_uefi(if ph.flags & SEGMENT_READ != 0 { "R" } else { "-" });
+        print_uefi(if ph.flags & SEGMENT_WRITE != 0 { "W" } else { "-" });
+        println_uefi(if ph.flags & SEGMENT_EXECUTE != 0 { "X" } else { "-" });
+
+        segment_count += 1;
     }
 
-    (count, boot_disk)
+    Ok(LoadedKernel {
+        phys_addr: image_start,
+        virt_addr: virt_start,
+        size: image_end - image_start,
+        entry: elf.entry(),
+        segment_count,
+        elf_addr: elf_data.as_ptr() as u64,
+        elf_size: elf_data.len() as u64,
+    })
 }
 
 // ============================================================================
@@ -676,58 +726,111 @@ fn find_smbios() -> (u64, u32) {
 }
 
 // ============================================================================
-// UEFI 运行时服务
+// 内存映射处理
 // ============================================================================
 
-fn get_runtime_services() -> u64 {
-    // 使用 uefi::runtime 模块获取运行时服务
-    // 在 ExitBootServices 之前，可以通过系统表获取
-    // 这里我们通过配置表间接获取（实际运行时服务地址）
-    uefi::system::with_config_table(|_tables| {
-        // 运行时服务表地址通过系统表结构获取
-        // 由于 API 限制，这里返回一个标记值
-        // 实际的运行时服务调用应该使用 uefi::runtime 模块
-        0xDEADBEEF_u64  // 占位符，表示运行时服务可用
-    })
+/// 以 PHYSICAL_MEMORY_OFFSET 为偏移直接映射 [0, 上限) 的全部物理内存，返回上限
+///
+/// 上限至少为 [`MIN_DIRECT_MAP`]，并覆盖内存映射中的全部区域与帧缓冲区。
+/// 支持写回的区域可缓存；MMIO、帧缓冲区以及内存映射未列出的空洞（APIC、
+/// HPET、PCI 空间、传统 BIOS 区域等）禁用缓存。缓存方式相同的相邻区域
+/// 合并后再映射，以便使用大页；帧缓冲区不与相邻区域合并。
+fn map_physical_memory(
+    page_tables: &mut PageTableBuilder,
+    framebuffer: &FramebufferInfo,
+) -> uefi::Result<u64> {
+    let mut mmap = boot::memory_map(MemoryType::LOADER_DATA)?;
+    mmap.sort();
+    let fb_start = framebuffer.address;
+    let fb_end = framebuffer.address + framebuffer.size;
+    let region_end = |entry: &MemoryDescriptor| entry.phys_start + entry.page_count * PAGE_SIZE;
+    let limit = mmap
+        .entries()
+        .map(region_end)
+        .fold(MIN_DIRECT_MAP.max(fb_end), u64::max);
+
+    let regions = mmap.entries().map(|entry| {
+        let end = region_end(entry);
+        let uncached = matches!(entry.ty, MemoryType::MMIO | MemoryType::MMIO_PORT_SPACE)
+            || !entry.att.contains(MemoryAttribute::WRITE_BACK)
+            || (entry.phys_start < fb_end && fb_start < end);
+        (entry.phys_start, end, uncached)
+    });
+    // 当前待映射的一段 (起始, 结束, 是否禁用缓存)；末尾的哨兵补上最后一个空洞
+    // 帧缓冲区单独成段（在其边界处切开），它的页不会覆盖其他内存，
+    // 内核可以只修改这些页的缓存类型
+    let fb_pages = (fb_start & !(PAGE_SIZE - 1), fb_end.next_multiple_of(PAGE_SIZE));
+    let mut run = (0, 0, true);
+    let mut covered = 0;
+    for (start, end, uncached) in regions.chain(core::iter::once((limit, limit, true))) {
+        let start = start.max(covered);
+        for (seg_start, seg_end, seg_uncached) in [(covered, start, true), (start, end, uncached)] {
+            let cuts = [
+                seg_start,
+                fb_pages.0.clamp(seg_start, seg_end),
+                fb_pages.1.clamp(seg_start, seg_end),
+                seg_end,
+            ];
+            for piece in cuts.windows(2) {
+                let (piece_start, piece_end) = (piece[0], piece[1]);
+                if piece_start >= piece_end {
+                    continue;
+                }
+                let fb_edge = piece_start == fb_pages.0 || piece_start == fb_pages.1;
+                if run.1 == piece_start && run.2 == seg_uncached && !fb_edge {
+                    run.1 = piece_end;
+                    continue;
+                }
+                page_tables.map_physical_range(run.0, run.1, run.2)?;
+                run = (piece_start, piece_end, seg_uncached);
+            }
+        }
+        covered = covered.max(end);
+    }
+    page_tables.map_physical_range(run.0, run.1, run.2)?;
+    Ok(limit)
 }
 
-// ============================================================================
-// 内存映射处理
-// ============================================================================
-
+/// 转换并复制内存映射；`runtime_offset` 非 0 时运行时区域填入虚拟地址
+///
+/// `mmap` 须按物理地址排序。物理地址相邻、类型与属性相同的区域合并为一个
+/// （运行时区域除外，它们的虚拟地址需要逐个保留）。表满后继续统计内存总量。返回 (区域数, 总内存, 可用内存, 是否截断)。
 unsafe fn copy_memory_map<'a>(
-    mmap: impl Iterator<Item = &'a uefi::mem::memory_map::MemoryDescriptor>
-) -> (u32, u64, u64) {
-    let dest = MEMMAP_ADDR as *mut MemoryRegion;
-    let mut count = 0u32;
+    table: &handoff::MemoryMapTable,
+    mmap: impl Iterator<Item = &'a uefi::mem::memory_map::MemoryDescriptor>,
+    runtime_offset: u64,
+) -> (u32, u64, u64, bool) {
+    let dest = table.addr as *mut MemoryRegion;
+    let mut count = 0usize;
     let mut total_mem = 0u64;
     let mut usable_mem = 0u64;
-
-    for entry in mmap {
-        if count >= MAX_MEMORY_REGIONS as u32 {
-            break;
+    let mut truncated = false;
+    let mut account = |region: &MemoryRegion| {
+        total_mem += region.size();
+        if region.region_type == MemoryRegionType::Usable {
+            usable_mem += region.size();
         }
+    };
 
+    for entry in mmap {
         let pages = entry.page_count;
-        let size = pages * 4096;
-        total_mem += size;
 
         // 转换 UEFI 内存类型到简化类型
+        let runtime = runtime::is_runtime(entry);
         let region_type = match entry.ty {
-            MemoryType::CONVENTIONAL => {
-                usable_mem += size;
-                MemoryRegionType::Usable
-            }
-            MemoryType::LOADER_CODE | MemoryType::LOADER_DATA => {
+            // 运行时区域（含运行时 MMIO）单独标记，内核必须保留并保持映射
+            MemoryType::RUNTIME_SERVICES_CODE => MemoryRegionType::UefiRuntimeCode,
+            _ if runtime => MemoryRegionType::UefiRuntimeData,
+            MemoryType::CONVENTIONAL => MemoryRegionType::Usable,
+            MemoryType::LOADER_CODE | MemoryType::LOADER_DATA | HANDOFF_MEMORY_TYPE => {
                 MemoryRegionType::BootloaderReclaimable
             }
+            KERNEL_MEMORY_TYPE => MemoryRegionType::KernelAndModules,
+            // Boot services memory 可回收
             MemoryType::BOOT_SERVICES_CODE | MemoryType::BOOT_SERVICES_DATA => {
-                usable_mem += size; // Boot services memory 可回收
                 MemoryRegionType::Usable
             }
-            MemoryType::RUNTIME_SERVICES_CODE | MemoryType::RUNTIME_SERVICES_DATA => {
-                MemoryRegionType::Reserved
-            }
+            MemoryType::RUNTIME_SERVICES_DATA => MemoryRegionType::UefiRuntimeData,
             MemoryType::ACPI_RECLAIM => MemoryRegionType::AcpiReclaimable,
             MemoryType::ACPI_NON_VOLATILE => MemoryRegionType::AcpiNvs,
             MemoryType::MMIO | MemoryType::MMIO_PORT_SPACE => MemoryRegionType::Mmio,
@@ -736,15 +839,39 @@ unsafe fn copy_memory_map<'a>(
 
         let region = MemoryRegion {
             phys_start: entry.phys_start,
-            virt_start: entry.virt_start,
+            virt_start: if runtime && runtime_offset != 0 {
+                entry.phys_start + runtime_offset
+            } else {
+                entry.virt_start
+            },
             page_count: pages,
-            region_type: region_type as u32,
+            region_type,
             attributes: entry.att.bits() as u32,
         };
 
-        core::ptr::write_volatile(dest.add(count as usize), region);
+        let mergeable = !matches!(
+            region_type,
+            MemoryRegionType::UefiRuntimeCode | MemoryRegionType::UefiRuntimeData
+        );
+        if count > 0 && mergeable {
+            let last = &mut *dest.add(count - 1);
+            if last.region_type == region.region_type
+                && last.attributes == region.attributes
+                && last.phys_start + last.page_count * 4096 == region.phys_start
+            {
+                last.page_count += pages;
+                account(&region);
+                continue;
+            }
+        }
+        account(&region);
+        if count >= table.capacity {
+            truncated = true;
+            continue;
+        }
+        core::ptr::write_volatile(dest.add(count), region);
         count += 1;
     }
 
-    (count, total_mem, usable_mem)
+    (count as u32, total_mem, usable_mem, truncated)
 }
@@ -0,0 +1,177 @@
+//! 文本模式引导菜单
+//!
+//! 通过 UEFI Simple Text Input 读取按键：
+//!
+//! - ↑/↓ 选择启动项，Enter 启动
+//! - `e` 编辑所选启动项的命令行，Enter 确认并启动，Esc 放弃
+//! - 倒计时结束时启动默认项，任意按键停止倒计时
+
+use crate::config::{BootConfig, BootEntry, CmdlineSource};
+use crate::handoff::CMDLINE_MAX;
+use core::fmt::Write;
+use core::time::Duration;
+use uefi::boot;
+use uefi::proto::console::text::{Key, ScanCode};
+use uefi::system;
+
+/// 轮询按键的间隔（毫秒）
+const POLL_INTERVAL_MS: u64 = 50;
+
+const KEY_ENTER: char = '\r';
+const KEY_BACKSPACE: char = '\u{8}';
+
+/// 显示菜单并返回用户选择的启动项序号（编辑后的命令行直接写回 `config`）
+///
+/// `timeout` 为 0 时不显示菜单，直接返回默认项。
+pub fn select(config: &mut BootConfig) -> usize {
+    let BootConfig {
+        timeout,
+        default,
+        ref mut entries,
+        ..
+    } = *config;
+    if timeout == 0 {
+        return default;
+    }
+
+    // 丢弃进入菜单前积压的按键
+    let _ = system::with_stdin(|stdin| stdin.reset(false));
+
+    let mut selected = default;
+    let mut remaining_ms = Some(timeout as u64 * 1000);
+    let mut status_row = draw(entries, selected);
+    draw_status(status_row, &entries[default], remaining_ms);
+
+    loop {
+        let Some(key) = read_key() else {
+            match remaining_ms {
+                Some(0) => break,
+                Some(ms) => {
+                    boot::stall(Duration::from_millis(POLL_INTERVAL_MS));
+                    let left = ms.saturating_sub(POLL_INTERVAL_MS);
+                    remaining_ms = Some(left);
+                    // 只在整秒变化时重绘倒计时
+                    if left.div_ceil(1000) != ms.div_ceil(1000) {
+                        draw_status(status_row, &entries[default], remaining_ms);
+                    }
+                }
+                None => boot::stall(Duration::from_millis(POLL_INTERVAL_MS)),
+            }
+            continue;
+        };
+
+        remaining_ms = None;
+        match key {
+            Key::Special(ScanCode::UP) => {
+                selected = selected.checked_sub(1).unwrap_or(entries.len() - 1);
+            }
+            Key::Special(ScanCode::DOWN) => {
+                selected = (selected + 1) % entries.len();
+            }
+            Key::Printable(c) if char::from(c) == KEY_ENTER => break,
+            Key::Printable(c) if char::from(c) == 'e' => {
+                if edit_cmdline(&mut entries[selected]) {
+                    break;
+                }
+            }
+            _ => {}
+        }
+        status_row = draw(entries, selected);
+        draw_status(status_row, &entries[default], remaining_ms);
+    }
+
+    system::with_stdout(|out| {
+        let _ = out.clear();
+    });
+    selected
+}
+
+/// 绘制完整菜单，返回状态行所在行号
+fn draw(entries: &[BootEntry], selected: usize) -> usize {
+    system::with_stdout(|out| {
+        let _ = out.clear();
+        let _ = write!(out, "  january_os boot menu\r\n\r\n");
+        for (i, entry) in entries.iter().enumerate() {
+            let marker = if i == selected { '>' } else { ' ' };
+            let _ = write!(out, "  {} {}\r\n", marker, entry.title);
+        }
+
+        let entry = &entries[selected];
+        let _ = write!(out, "\r\n  kernel:  {}\r\n", entry.kernel);
+        let _ = write!(out, "  cmdline: {}\r\n", entry.cmdline);
+        let _ = write!(out, "  modules: {}\r\n", entry.modules.len());
+        let _ = write!(out, "\r\n  Up/Down: select   Enter: boot   e: edit command line\r\n");
+    });
+    entries.len() + 8
+}
+
+/// 绘制倒计时状态行（倒计时已停止时清空该行）
+fn draw_status(row: usize, default: &BootEntry, remaining_ms: Option<u64>) {
+    system::with_stdout(|out| {
+        let _ = out.set_cursor_position(0, row);
+        let _ = write!(out, "{:79}\r", "");
+        if let Some(ms) = remaining_ms {
+            let _ = write!(
+                out,
+                "  Booting '{}' in {}s... (press any key to stop)",
+                default.title,
+                ms.div_ceil(1000)
+            );
+        }
+    });
+}
+
+/// 编辑启动项的命令行；返回 true 表示确认并启动
+fn edit_cmdline(entry: &mut BootEntry) -> bool {
+    let mut line = entry.cmdline.clone();
+    system::with_stdout(|out| {
+        let _ = out.clear();
+        let _ = write!(out, "  Editing command line for '{}'\r\n", entry.title);
+        let _ = write!(out, "  Enter: boot   Esc: cancel\r\n\r\n> {}", line);
+    });
+
+    loop {
+        match wait_key() {
+            Key::Printable(c) => match char::from(c) {
+                KEY_ENTER => {
+                    entry.cmdline = line;
+                    entry.cmdline_source = CmdlineSource::Edited;
+                    return true;
+                }
+                KEY_BACKSPACE => {
+                    if line.pop().is_some() {
+                        print("\u{8} \u{8}");
+                    }
+                }
+                c if !c.is_control() && line.len() + c.len_utf8() < CMDLINE_MAX => {
+                    line.push(c);
+                    print(c.encode_utf8(&mut [0; 4]));
+                }
+                _ => {}
+            },
+            Key::Special(ScanCode::ESCAPE) => return false,
+            Key::Special(_) => {}
+        }
+    }
+}
+
+fn print(s: &str) {
+    system::with_stdout(|out| {
+        let _ = out.write_str(s);
+    });
+}
+
+/// 非阻塞读取一个按键
+fn read_key() -> Option<Key> {
+    system::with_stdin(|stdin| stdin.read_key().ok().flatten())
+}
+
+/// 等待直到有按键
+pub fn wait_key() -> Key {
+    loop {
+        if let Some(key) = read_key() {
+            return key;
+        }
+        boot::stall(Duration::from_millis(POLL_INTERVAL_MS));
+    }
+}
@@ -0,0 +1,146 @@
+//! 启动模块加载
+//!
+//! 启动项中的每个 `module = <路径> [命令行]` 对应一个模块：文件内容、
+//! 名称（路径）与命令行都放在 `KernelAndModules` 内存，描述写入同样位于
+//! 该内存的 [`ModuleInfo`] 表，内核回收交接内存后仍可访问。
+//!
+//! 单个模块加载失败不影响启动，只打印警告、记入引导警告表并跳过。
+
+use crate::error::Warnings;
+use crate::fs;
+use crate::handoff::{allocate_kernel_memory, free_memory, MAX_MODULES};
+use crate::{print_dec, print_hex, print_uefi, println_uefi};
+use alloc::string::String;
+use boot_protocol::{BootStage, ModuleInfo};
+use uefi::CString16;
+
+/// 加载启动项中的模块，描述写入 `table_addr`，返回成功加载的数量
+pub fn load_modules(specs: &[String], table_addr: u64, warnings: &mut Warnings) -> u32 {
+    let table = table_addr as *mut ModuleInfo;
+    let mut count = 0u32;
+
+    for spec in specs {
+        if count >= MAX_MODULES as u32 {
+            println_uefi("      Too many modules, the rest are ignored");
+            break;
+        }
+
+        let (path, cmdline) = match spec.split_once(char::is_whitespace) {
+            Some((path, cmdline)) => (path, cmdline.trim()),
+            None => (spec.as_str(), ""),
+        };
+
+        let module = match load_module(path, cmdline) {
+            Ok(module) => module,
+            Err(status) => {
+                print_uefi("      Module ");
+                print_uefi(path);
+                print_uefi(": failed to load (");
+                print_uefi(status_name(status));
+                println_uefi(")");
+                warnings.push(BootStage::Kernel, status);
+                continue;
+            }
+        };
+        unsafe { core::ptr::write_volatile(table.add(count as usize), module) };
+
+        print_uefi("      Module ");
+        print_dec(count as u64);
+        print_uefi(": ");
+        print_uefi(path);
+        print_uefi(" at 0x");
+        print_hex(module.phys_addr);
+        print_uefi(", ");
+        print_dec(module.size);
+        println_uefi(" bytes");
+
+        count += 1;
+    }
+
+    count
+}
+
+/// 释放已加载模块的内容、名称与命令行（描述表本身保留，供重试使用）
+///
+/// # Safety
+///
+/// `table_addr` 必须是 [`load_modules`] 写入的表，且之后不再访问这些模块。
+pub unsafe fn free_modules(table_addr: u64, count: u32) {
+    let table = table_addr as *const ModuleInfo;
+    for i in 0..count as usize {
+        let module = *table.add(i);
+        free_memory(module.phys_addr, module.size.max(1));
+        free_memory(module.name_addr, module.name_len as u64);
+        free_memory(module.cmdline_addr, module.cmdline_len as u64);
+    }
+}
+
+/// 读取模块文件并复制名称与命令行，返回模块描述；失败时已分配的内存全部释放
+fn load_module(path: &str, cmdline: &str) -> Result<ModuleInfo, uefi::Status> {
+    let (phys_addr, size) = load_file(path)?;
+    let name_addr = match copy_string(path) {
+        Ok(addr) => addr,
+        Err(status) => {
+            unsafe { free_memory(phys_addr, size.max(1)) };
+            return Err(status);
+        }
+    };
+    let cmdline_addr = match copy_string(cmdline) {
+        Ok(addr) => addr,
+        Err(status) => {
+            unsafe {
+                free_memory(phys_addr, size.max(1));
+                free_memory(name_addr, path.len() as u64);
+            }
+            return Err(status);
+        }
+    };
+    Ok(ModuleInfo {
+        phys_addr,
+        size,
+        name_addr,
+        cmdline_addr,
+        name_len: path.len() as u32,
+        cmdline_len: cmdline.len() as u32,
+    })
+}
+
+/// 读取文件到新分配的内核内存，返回物理地址与大小
+///
+/// 没有读满文件大小视为错误；失败时释放已分配的内存。
+fn load_file(path: &str) -> Result<(u64, u64), uefi::Status> {
+    let path = CString16::try_from(path).map_err(|_| uefi::Status::INVALID_PARAMETER)?;
+    let mut file = fs::open_file(&path).map_err(|e| e.status())?;
+    let size = fs::file_size(&mut file).map_err(|e| e.status())?;
+
+    // 空文件也占一页，保证模块地址有效且互不相同
+    let phys_addr = allocate_kernel_memory(size.max(1)).map_err(|e| e.status())?;
+    let data = unsafe { core::slice::from_raw_parts_mut(phys_addr as *mut u8, size) };
+    match file.read(data) {
+        Ok(read) if read == size => Ok((phys_addr, size as u64)),
+        result => {
+            unsafe { free_memory(phys_addr, size.max(1) as u64) };
+            Err(result.map_or_else(|e| e.status(), |_| uefi::Status::END_OF_FILE))
+        }
+    }
+}
+
+/// 复制字符串到内核内存，空串返回 0
+fn copy_string(s: &str) -> Result<u64, uefi::Status> {
+    if s.is_empty() {
+        return Ok(0);
+    }
+    let addr = allocate_kernel_memory(s.len()).map_err(|e| e.status())?;
+    unsafe { core::ptr::copy_nonoverlapping(s.as_ptr(), addr as *mut u8, s.len()) };
+    Ok(addr)
+}
+
+fn status_name(status: uefi::Status) -> &'static str {
+    match status {
+        uefi::Status::NOT_FOUND => "not found",
+        uefi::Status::INVALID_PARAMETER => "invalid path",
+        uefi::Status::OUT_OF_RESOURCES => "out of memory",
+        uefi::Status::END_OF_FILE => "short read",
+        _ => "I/O error",
+    }
+}
@@ -0,0 +1,295 @@
+//! 内核页表构建
+//!
+//! 在退出引导服务前建立一套全新的 4 级页表，跳转内核时切换 CR3：
+//!
+//! - 内核各段按 ELF 虚拟地址映射到加载的物理地址，权限取自段标志
+//! - 全部物理内存以 [`PHYSICAL_MEMORY_OFFSET`] 为偏移直接映射（不可执行）；MMIO、
+//!   帧缓冲区与内存映射之外的空洞禁用缓存，大页不跨越缓存方式不同的区域
+//! - UEFI 运行时区域以 [`UEFI_RUNTIME_OFFSET`] 为偏移映射，供 SetVirtualAddressMap 之后调用
+//! - 切换页表的跳板代码所在页保持恒等映射，保证 `mov cr3` 之后还能继续取指
+//!
+//! 页表页以交接数据类型分配，内核建立自己的页表之前不得回收。
+//! 分配页表失败时各映射函数返回错误，已分配的页表页不回收（随后要么重试，
+//! 要么返回固件）。
+
+use crate::handoff::allocate_handoff;
+use boot_protocol::{KernelSegment, PHYSICAL_MEMORY_OFFSET, UEFI_RUNTIME_OFFSET};
+use core::arch::x86_64::__cpuid;
+
+/// 页表项：存在
+const PRESENT: u64 = 1 << 0;
+/// 页表项：可写
+const WRITABLE: u64 = 1 << 1;
+/// 页表项：写穿透
+const WRITE_THROUGH: u64 = 1 << 3;
+/// 页表项：禁用缓存
+const CACHE_DISABLE: u64 = 1 << 4;
+/// 页表项：大页 (PDPT 中为 1 GiB，PD 中为 2 MiB)
+const HUGE_PAGE: u64 = 1 << 7;
+/// 页表项：全局页
+const GLOBAL: u64 = 1 << 8;
+/// 页表项：不可执行
+const NO_EXECUTE: u64 = 1 << 63;
+/// 页表项中的物理地址掩码
+const ADDR_MASK: u64 = 0x000F_FFFF_FFFF_F000;
+
+const SIZE_4K: u64 = 0x1000;
+const SIZE_2M: u64 = 0x20_0000;
+const SIZE_1G: u64 = 0x4000_0000;
+
+/// EFER MSR
+const IA32_EFER: u32 = 0xC000_0080;
+/// EFER.NXE 位
+const EFER_NXE: u64 = 1 << 11;
+
+/// 一页页表
+#[repr(C, align(4096))]
+struct PageTable([u64; 512]);
+
+/// 页表构建器
+pub struct PageTableBuilder {
+    pml4: *mut PageTable,
+    /// CPU 是否支持 NX 位
+    nx: bool,
+    /// CPU 是否支持 1 GiB 大页
+    huge_1g: bool,
+}
+
+impl PageTableBuilder {
+    /// 分配空的 PML4，并在支持时启用 EFER.NXE
+    pub fn new() -> uefi::Result<Self> {
+        let ext = __cpuid(0x8000_0000).eax;
+        let (nx, huge_1g) = if ext >= 0x8000_0001 {
+            let edx = __cpuid(0x8000_0001).edx;
+            (edx & (1 << 20) != 0, edx & (1 << 26) != 0)
+        } else {
+            (false, false)
+        };
+
+        if nx {
+            unsafe { wrmsr(IA32_EFER, rdmsr(IA32_EFER) | EFER_NXE) };
+        }
+
+        Ok(Self {
+            pml4: alloc_table()?,
+            nx,
+            huge_1g,
+        })
+    }
+
+    /// PML4 物理地址（写入 CR3 的值）
+    pub fn root(&self) -> u64 {
+        self.pml4 as u64
+    }
+
+    /// 映射内核各段
+    pub fn map_kernel(&mut self, segments: &[KernelSegment]) -> uefi::Result {
+        for segment in segments {
+            let mut flags = PRESENT | GLOBAL;
+            if segment.is_writable() {
+                flags |= WRITABLE;
+            }
+            if !segment.is_executable() {
+                flags |= self.nx_flag();
+            }
+
+            let virt_start = segment.virt_addr & !(SIZE_4K - 1);
+            let phys_start = segment.phys_addr & !(SIZE_4K - 1);
+            let end = segment.virt_addr + segment.mem_size;
+            let mut offset = 0;
+            while virt_start + offset < end {
+                self.map_4k(virt_start + offset, phys_start + offset, flags)?;
+                offset += SIZE_4K;
+            }
+        }
+        Ok(())
+    }
+
+    /// 以 4 KiB 页映射一段可写、不可执行的数据区域（如内核栈）
+    pub fn map_data(&mut self, virt: u64, phys: u64, len: u64) -> uefi::Result {
+        let flags = PRESENT | WRITABLE | GLOBAL | self.nx_flag();
+        let mut offset = 0;
+        while offset < len {
+            self.map_4k(virt + offset, phys + offset, flags)?;
+            offset += SIZE_4K;
+        }
+        Ok(())
+    }
+
+    /// 以 PHYSICAL_MEMORY_OFFSET 为偏移直接映射物理区域 [start, end)
+    ///
+    /// 区域内尽量使用 1 GiB 或 2 MiB 页，但大页不越过区域边界，以免把相邻的
+    /// 不可缓存区域映射为可缓存；`uncached` 时设置 PCD|PWT。不同区域不得重叠。
+    pub fn map_physical_range(&mut self, start: u64, end: u64, uncached: bool) -> uefi::Result {
+        let mut flags = PRESENT | WRITABLE | GLOBAL | self.nx_flag();
+        if uncached {
+            flags |= WRITE_THROUGH | CACHE_DISABLE;
+        }
+        let mut phys = start & !(SIZE_4K - 1);
+        while phys < end {
+            let virt = PHYSICAL_MEMORY_OFFSET + phys;
+            let fits = |size: u64| phys.is_multiple_of(size) && phys + size <= end;
+            let size = if self.huge_1g && fits(SIZE_1G) {
+                self.map_1g(virt, phys, flags)?;
+                SIZE_1G
+            } else if fits(SIZE_2M) {
+                self.map_2m(virt, phys, flags)?;
+                SIZE_2M
+            } else {
+                self.map_4k(virt, phys, flags)?;
+                SIZE_4K
+            };
+            phys += size;
+        }
+        Ok(())
+    }
+
+    /// 以 UEFI_RUNTIME_OFFSET 为偏移映射一段 UEFI 运行时区域
+    ///
+    /// 区域均可写（部分固件在运行时代码区内存放可写数据）；只有代码可执行，
+    /// MMIO 额外禁用缓存。
+    pub fn map_runtime(&mut self, phys: u64, len: u64, code: bool, mmio: bool) -> uefi::Result {
+        let mut flags = PRESENT | WRITABLE;
+        if !code {
+            flags |= self.nx_flag();
+        }
+        if mmio {
+            flags |= WRITE_THROUGH | CACHE_DISABLE;
+        }
+        let mut offset = 0;
+        while offset < len {
+            self.map_4k(UEFI_RUNTIME_OFFSET + phys + offset, phys + offset, flags)?;
+            offset += SIZE_4K;
+        }
+        Ok(())
+    }
+
+    /// 恒等映射一段可执行代码（切换 CR3 的跳板）
+    pub fn identity_map_code(&mut self, addr: u64, len: u64) -> uefi::Result {
+        let start = addr & !(SIZE_4K - 1);
+        let end = addr + len;
+        let mut page = start;
+        while page < end {
+            self.map_4k(page, page, PRESENT)?;
+            page += SIZE_4K;
+        }
+        Ok(())
+    }
+
+    fn nx_flag(&self) -> u64 {
+        if self.nx { NO_EXECUTE } else { 0 }
+    }
+
+    fn map_4k(&mut self, virt: u64, phys: u64, flags: u64) -> uefi::Result {
+        let pdpt = next_table(self.pml4, pml4_index(virt))?;
+        let pd = next_table(pdpt, pdpt_index(virt))?;
+        let pt = next_table(pd, pd_index(virt))?;
+        let entry = unsafe { &mut (*pt).0[pt_index(virt)] };
+        *entry = merge_entry(*entry, (phys & ADDR_MASK) | flags);
+        Ok(())
+    }
+
+    fn map_2m(&mut self, virt: u64, phys: u64, flags: u64) -> uefi::Result {
+        let pdpt = next_table(self.pml4, pml4_index(virt))?;
+        let pd = next_table(pdpt, pdpt_index(virt))?;
+        unsafe {
+            (*pd).0[pd_index(virt)] = (phys & ADDR_MASK) | flags | HUGE_PAGE;
+        }
+        Ok(())
+    }
+
+    fn map_1g(&mut self, virt: u64, phys: u64, flags: u64) -> uefi::Result {
+        let pdpt = next_table(self.pml4, pml4_index(virt))?;
+        unsafe {
+            (*pdpt).0[pdpt_index(virt)] = (phys & ADDR_MASK) | flags | HUGE_PAGE;
+        }
+        Ok(())
+    }
+}
+
+/// 合并同一页的两次映射（段共享页时取权限并集）
+fn merge_entry(old: u64, new: u64) -> u64 {
+    if old & PRESENT == 0 {
+        return new;
+    }
+    let writable = (old | new) & WRITABLE;
+    let no_execute = old & new & NO_EXECUTE;
+    (new & !(WRITABLE | NO_EXECUTE)) | writable | no_execute
+}
+
+/// 取得（必要时创建）下一级页表
+fn next_table(table: *mut PageTable, index: usize) -> uefi::Result<*mut PageTable> {
+    let entry = unsafe { &mut (*table).0[index] };
+    if *entry & PRESENT == 0 {
+        // 中间级页表给出最宽松的权限，由最后一级决定实际权限
+        *entry = alloc_table()? as u64 | PRESENT | WRITABLE;
+    }
+    Ok((*entry & ADDR_MASK) as *mut PageTable)
+}
+
+/// 分配一页清零的页表
+fn alloc_table() -> uefi::Result<*mut PageTable> {
+    Ok(allocate_handoff(SIZE_4K as usize)? as *mut PageTable)
+}
+
+fn pml4_index(virt: u64) -> usize {
+    ((virt >> 39) & 0x1FF) as usize
+}
+
+fn pdpt_index(virt: u64) -> usize {
+    ((virt >> 30) & 0x1FF) as usize
+}
+
+fn pd_index(virt: u64) -> usize {
+    ((virt >> 21) & 0x1FF) as usize
+}
+
+fn pt_index(virt: u64) -> usize {
+    ((virt >> 12) & 0x1FF) as usize
+}
+
+unsafe fn rdmsr(msr: u32) -> u64 {
+    let (low, high): (u32, u32);
+    core::arch::asm!("rdmsr", in("ecx") msr, out("eax") low, out("edx") high, options(nomem, nostack));
+    ((high as u64) << 32) | low as u64
+}
+
+unsafe fn wrmsr(msr: u32, value: u64) {
+    core::arch::asm!(
+        "wrmsr",
+        in("ecx") msr,
+        in("eax") value as u32,
+        in("edx") (value >> 32) as u32,
+        options(nomem, nostack)
+    );
+}
+
+/// 切换到新页表并跳转到内核入口
+///
+/// 本函数所在页必须在新页表中恒等映射，`stack_top` 与 `boot_info`
+/// 必须是新页表中的虚拟地址。
+///
+/// # Safety
+///
+/// 调用后不再返回，引导服务必须已经退出。
+#[unsafe(naked)]
+pub unsafe extern "sysv64" fn enter_kernel(
+    page_table: u64,
+    stack_top: u64,
+    boot_info: u64,
+    entry: u64,
+) -> ! {
+    core::arch::naked_asm!(
+        "cli",
+        "mov cr3, rdi",
+        "mov rsp, rsi",
+        "mov rdi, rdx",
+        "xor rbp, rbp",
+        // 压入伪返回地址，使入口处栈对齐符合 SysV ABI
+        "push rbp",
+        "jmp rcx",
+    )
+}
+
+/// enter_kernel 的代码长度上限（用于恒等映射）
+pub const ENTER_KERNEL_CODE_SIZE: u64 = 64;
@@ -0,0 +1,94 @@
+//! EFI_PCI_IO_PROTOCOL（uefi crate 未提供）
+//!
+//! 只声明读取配置空间与 PCI 位置所需的函数，用于把固件枚举的块设备
+//! 对应到控制器的 段/总线/设备/功能。
+
+use core::ffi::c_void;
+use uefi::boot::{self, OpenProtocolAttributes, OpenProtocolParams};
+use uefi::proto::device_path::DevicePath;
+use uefi::proto::unsafe_protocol;
+use uefi::Status;
+
+/// EFI_PCI_IO_PROTOCOL_WIDTH: EfiPciIoWidthUint16
+const WIDTH_UINT16: u32 = 1;
+
+/// virtio 设备的 PCI 厂商 ID
+pub const VIRTIO_VENDOR_ID: u16 = 0x1AF4;
+
+/// EFI_PCI_IO_PROTOCOL（只声明用到的函数，其余按位置占位）
+#[repr(C)]
+#[unsafe_protocol("4cf5b200-68b8-4ca5-9eec-b23e3f50029a")]
+pub struct PciIo {
+    // PollMem, PollIo, Mem.Read, Mem.Write, Io.Read, Io.Write
+    _reserved1: [usize; 6],
+    pci_read: unsafe extern "efiapi" fn(
+        this: *mut PciIo,
+        width: u32,
+        offset: u32,
+        count: usize,
+        buffer: *mut c_void,
+    ) -> Status,
+    // Pci.Write, CopyMem, Map, Unmap, AllocateBuffer, FreeBuffer, Flush
+    _reserved2: [usize; 7],
+    get_location: unsafe extern "efiapi" fn(
+        this: *mut PciIo,
+        segment: *mut usize,
+        bus: *mut usize,
+        device: *mut usize,
+        function: *mut usize,
+    ) -> Status,
+}
+
+/// 块设备所在的 PCI 功能
+#[derive(Clone, Copy, Debug)]
+pub struct PciFunction {
+    pub segment: u16,
+    pub bus: u8,
+    pub device: u8,
+    pub function: u8,
+    pub vendor_id: u16,
+}
+
+/// 找到 `path` 上离设备最近的 PCI 功能
+pub fn locate(path: &DevicePath) -> Option<PciFunction> {
+    let mut remaining = path;
+    let handle = boot::locate_device_path::<PciIo>(&mut remaining).ok()?;
+    let params = OpenProtocolParams {
+        handle,
+        agent: boot::image_handle(),
+        controller: None,
+    };
+    let mut pci =
+        unsafe { boot::open_protocol::<PciIo>(params, OpenProtocolAttributes::GetProtocol) }
+            .ok()?;
+    let this: *mut PciIo = &mut *pci;
+
+    let (mut segment, mut bus, mut device, mut function) = (0, 0, 0, 0);
+    let status =
+        unsafe { (pci.get_location)(this, &mut segment, &mut bus, &mut device, &mut function) };
+    if status.is_error() {
+        return None;
+    }
+
+    let mut vendor_id = 0u16;
+    let status = unsafe {
+        (pci.pci_read)(
+            this,
+            WIDTH_UINT16,
+            0,
+            1,
+            (&mut vendor_id as *mut u16).cast(),
+        )
+    };
+    if status.is_error() {
+        vendor_id = 0xFFFF;
+    }
+
+    Some(PciFunction {
+        segment: segment as u16,
+        bus: bus as u8,
+        device: device as u8,
+        function: function as u8,
+        vendor_id,
+    })
+}
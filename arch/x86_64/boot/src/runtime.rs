@@ -0,0 +1,141 @@
+//! UEFI 运行时服务交接
+//!
+//! 退出引导服务后运行时服务仍然可用，但固件默认按物理地址工作，
+//! 内核切换到自己的页表后无法直接调用。引导程序因此：
+//!
+//! 1. 建立页表时把所有带 `EFI_MEMORY_RUNTIME` 属性的区域映射到
+//!    `物理地址 + UEFI_RUNTIME_OFFSET`
+//! 2. 退出引导服务后以同样的偏移调用 SetVirtualAddressMap
+//!
+//! boot.cfg 中 `uefi_virtual_map = no` 跳过第 2 步，此时内核不能调用运行时服务。
+
+use crate::handoff::allocate_handoff;
+use crate::paging::PageTableBuilder;
+use boot_protocol::{PAGE_SIZE, UEFI_RUNTIME_OFFSET};
+use core::mem::size_of;
+use uefi::boot::{self, MemoryType};
+use uefi::mem::memory_map::{MemoryAttribute, MemoryDescriptor, MemoryMap};
+use uefi::Status;
+
+/// SetVirtualAddressMap 描述符缓冲区容量
+const MAX_RUNTIME_REGIONS: usize = 128;
+
+/// 系统表与运行时服务表的物理地址
+pub struct RuntimeTables {
+    pub system_table: u64,
+    pub runtime_services: u64,
+}
+
+/// 从 uefi crate 保存的系统表指针取得运行时服务表
+pub fn locate() -> RuntimeTables {
+    match uefi::table::system_table_raw() {
+        Some(st) => RuntimeTables {
+            system_table: st.as_ptr() as u64,
+            runtime_services: unsafe { (*st.as_ptr()).runtime_services as u64 },
+        },
+        None => RuntimeTables {
+            system_table: 0,
+            runtime_services: 0,
+        },
+    }
+}
+
+/// 区域是否需要在运行时保持映射
+pub fn is_runtime(desc: &MemoryDescriptor) -> bool {
+    desc.att.contains(MemoryAttribute::RUNTIME)
+}
+
+/// 在页表中映射全部运行时区域，返回区域数
+///
+/// `virtual_map` 记录映射过的区域，供 [`VirtualMap::apply`] 核对最终内存映射。
+pub fn map_runtime_regions(
+    page_tables: &mut PageTableBuilder,
+    mut virtual_map: Option<&mut VirtualMap>,
+) -> uefi::Result<usize> {
+    let mmap = boot::memory_map(MemoryType::LOADER_DATA)?;
+
+    let mut count = 0;
+    for desc in mmap.entries().filter(|desc| is_runtime(desc)) {
+        let code = desc.ty == MemoryType::RUNTIME_SERVICES_CODE;
+        let mmio = matches!(desc.ty, MemoryType::MMIO | MemoryType::MMIO_PORT_SPACE);
+        let size = desc.page_count * PAGE_SIZE;
+        page_tables.map_runtime(desc.phys_start, size, code, mmio)?;
+        if let Some(map) = virtual_map.as_deref_mut() {
+            map.record_mapped(desc.phys_start, size);
+        }
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// SetVirtualAddressMap 使用的描述符缓冲区
+///
+/// 退出引导服务后不能再分配内存，必须提前准备。
+pub struct VirtualMap {
+    descriptors: *mut MemoryDescriptor,
+    /// 页表中已映射的运行时区域 (物理地址, 大小)
+    mapped: *mut (u64, u64),
+    mapped_count: usize,
+}
+
+impl VirtualMap {
+    /// 分配描述符缓冲区
+    pub fn allocate() -> uefi::Result<Self> {
+        Ok(Self {
+            descriptors: allocate_handoff(MAX_RUNTIME_REGIONS * size_of::<MemoryDescriptor>())?
+                as *mut MemoryDescriptor,
+            mapped: allocate_handoff(MAX_RUNTIME_REGIONS * size_of::<(u64, u64)>())?
+                as *mut (u64, u64),
+            mapped_count: 0,
+        })
+    }
+
+    /// 记录页表中映射的运行时区域；超出容量的不记录，之后 [`apply`](Self::apply) 会放弃
+    fn record_mapped(&mut self, phys_start: u64, size: u64) {
+        if self.mapped_count < MAX_RUNTIME_REGIONS {
+            unsafe { core::ptr::write(self.mapped.add(self.mapped_count), (phys_start, size)) };
+            self.mapped_count += 1;
+        }
+    }
+
+    /// `desc` 是否落在某个已映射的区域内
+    fn is_mapped(&self, desc: &MemoryDescriptor) -> bool {
+        let mapped = unsafe { core::slice::from_raw_parts(self.mapped, self.mapped_count) };
+        let end = desc.phys_start + desc.page_count * PAGE_SIZE;
+        mapped
+            .iter()
+            .any(|&(start, size)| start <= desc.phys_start && end <= start + size)
+    }
+
+    /// 以 UEFI_RUNTIME_OFFSET 为偏移把运行时服务切换到虚拟地址
+    ///
+    /// # Safety
+    ///
+    /// 引导服务必须已经退出；`entries` 必须是退出时取得的最终内存映射。
+    /// 成功后固件只能通过新的虚拟地址调用。最终内存映射中有页表没有映射的
+    /// 运行时区域时不调用 SetVirtualAddressMap，返回 `NOT_FOUND`。
+    pub unsafe fn apply<'a>(
+        self,
+        entries: impl Iterator<Item = &'a MemoryDescriptor>,
+        system_table: u64,
+    ) -> uefi::Result {
+        let mut count = 0;
+        for desc in entries.filter(|desc| is_runtime(desc)) {
+            // 缺少任何一个运行时区域都会使调用失败，不如不调用
+            if count == MAX_RUNTIME_REGIONS {
+                return Err(Status::BUFFER_TOO_SMALL.into());
+            }
+            if !self.is_mapped(desc) {
+                return Err(Status::NOT_FOUND.into());
+            }
+            let mut virt = *desc;
+            virt.virt_start = desc.phys_start + UEFI_RUNTIME_OFFSET;
+            core::ptr::write(self.descriptors.add(count), virt);
+            count += 1;
+        }
+
+        let map = core::slice::from_raw_parts_mut(self.descriptors, count);
+        let system_table = (system_table + UEFI_RUNTIME_OFFSET) as *const _;
+        uefi::runtime::set_virtual_address_map(map, system_table)
+    }
+}
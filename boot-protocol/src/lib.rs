@@ -0,0 +1,1124 @@
+//! january_os 引导协议
+//!
+//! 引导程序与内核之间的交接数据结构。两边都依赖本 crate，
+//! 保证 `BootInfo` 及其引用的各个表只有一份定义。
+//! 分区表解析 ([`partition`]) 同样放在这里，引导程序与内核使用同一份实现。
+//!
+//! # 布局约定
+//!
+//! - 所有结构体均为 `#[repr(C)]`，枚举均为 `#[repr(u32)]`
+//! - 布局由文件末尾的编译期断言固定，修改字段必须同步修改断言
+//!   并递增 [`BOOTINFO_VERSION`]
+
+#![cfg_attr(not(test), no_std)]
+
+pub mod partition;
+
+use core::mem::{offset_of, size_of};
+
+// ============================================================================
+// 常量定义
+// ============================================================================
+
+/// BootInfo 魔数: "JAN_OS\0\0" 的 ASCII 值
+pub const BOOTINFO_MAGIC: u64 = 0x4A414E5F4F530000;
+/// BootInfo 版本
+pub const BOOTINFO_VERSION: u32 = 12;
+/// 页大小（字节）
+pub const PAGE_SIZE: u64 = 4096;
+/// 物理内存直接映射的虚拟偏移（高半部分起始）
+pub const PHYSICAL_MEMORY_OFFSET: u64 = 0xFFFF_8000_0000_0000;
+/// UEFI 运行时区域的虚拟偏移（SetVirtualAddressMap 使用的映射）
+pub const UEFI_RUNTIME_OFFSET: u64 = 0xFFFF_C000_0000_0000;
+
+// ============================================================================
+// 帧缓冲区
+// ============================================================================
+
+/// 像素格式
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PixelFormatType {
+    /// RGB 格式 (R在低字节)
+    Rgb = 0,
+    /// BGR 格式 (B在低字节，最常见)
+    Bgr = 1,
+    /// 位掩码格式
+    Bitmask = 2,
+    /// 仅 BLT 格式
+    BltOnly = 3,
+}
+
+impl PixelFormatType {
+    /// 显示用名称
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Rgb => "RGB",
+            Self::Bgr => "BGR",
+            Self::Bitmask => "Bitmask",
+            Self::BltOnly => "BltOnly",
+        }
+    }
+}
+
+/// 帧缓冲区信息
+///
+/// 没有 GOP 或当前模式只支持 BLT 时 `address` 为 0，见 [`BootInfo::linear_framebuffer`]。
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct FramebufferInfo {
+    /// 帧缓冲区物理地址 (0 表示没有线性帧缓冲区)
+    pub address: u64,
+    /// 帧缓冲区总大小（字节）
+    pub size: u64,
+    /// 屏幕宽度（像素）
+    pub width: u32,
+    /// 屏幕高度（像素）
+    pub height: u32,
+    /// 每行像素数（可能 > width，因为对齐）
+    pub stride: u32,
+    /// 每像素字节数
+    pub bytes_per_pixel: u32,
+    /// 像素格式
+    pub pixel_format: PixelFormatType,
+    /// 保留，对齐用
+    pub _reserved: u32,
+    /// 红色分量位掩码（Rgb/Bgr 格式也按实际布局填写）
+    pub red_mask: u32,
+    /// 绿色分量位掩码
+    pub green_mask: u32,
+    /// 蓝色分量位掩码
+    pub blue_mask: u32,
+    /// 保留位掩码（硬件忽略）
+    pub reserved_mask: u32,
+}
+
+impl FramebufferInfo {
+    /// 是否有可直接写入的线性帧缓冲区
+    pub const fn is_present(&self) -> bool {
+        self.address != 0
+    }
+}
+
+/// GOP 支持的显示模式
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct VideoMode {
+    /// 宽度（像素）
+    pub width: u32,
+    /// 高度（像素）
+    pub height: u32,
+    /// 每行像素数
+    pub stride: u32,
+    /// 像素格式
+    pub pixel_format: PixelFormatType,
+}
+
+// ============================================================================
+// 内存映射
+// ============================================================================
+
+/// 内存区域类型（简化版）
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MemoryRegionType {
+    /// 可用内存
+    Usable = 0,
+    /// 保留内存（不可使用）
+    Reserved = 1,
+    /// ACPI 可回收内存
+    AcpiReclaimable = 2,
+    /// ACPI NVS 内存
+    AcpiNvs = 3,
+    /// 内存映射 I/O
+    Mmio = 4,
+    /// 引导程序代码/数据（内核可回收）
+    BootloaderReclaimable = 5,
+    /// 内核代码/数据
+    KernelAndModules = 6,
+    /// 帧缓冲区
+    Framebuffer = 7,
+    /// UEFI 运行时服务代码（必须保留并保持映射）
+    UefiRuntimeCode = 8,
+    /// UEFI 运行时服务数据及运行时使用的 MMIO（必须保留并保持映射）
+    UefiRuntimeData = 9,
+}
+
+impl MemoryRegionType {
+    /// 显示用名称
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Usable => "Usable",
+            Self::Reserved => "Reserved",
+            Self::AcpiReclaimable => "ACPI Reclaimable",
+            Self::AcpiNvs => "ACPI NVS",
+            Self::Mmio => "MMIO",
+            Self::BootloaderReclaimable => "Bootloader",
+            Self::KernelAndModules => "Kernel",
+            Self::Framebuffer => "Framebuffer",
+            Self::UefiRuntimeCode => "UEFI RT Code",
+            Self::UefiRuntimeData => "UEFI RT Data",
+        }
+    }
+}
+
+/// 内存区域描述符（简化版，兼容性更好）
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct MemoryRegion {
+    /// 物理起始地址
+    pub phys_start: u64,
+    /// 虚拟起始地址（通常与物理相同）
+    pub virt_start: u64,
+    /// 页数（每页 4KB）
+    pub page_count: u64,
+    /// 区域类型
+    pub region_type: MemoryRegionType,
+    /// 属性标志
+    pub attributes: u32,
+}
+
+impl MemoryRegion {
+    /// 区域大小（字节）
+    pub const fn size(&self) -> u64 {
+        self.page_count * PAGE_SIZE
+    }
+
+    /// 区域结束物理地址（不含）
+    pub const fn phys_end(&self) -> u64 {
+        self.phys_start + self.size()
+    }
+}
+
+// ============================================================================
+// 存储设备
+// ============================================================================
+
+/// 磁盘类型
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiskType {
+    Unknown = 0,
+    HardDisk = 1,
+    CdRom = 2,
+    Usb = 3,
+    NVMe = 4,
+    Floppy = 5,
+    Network = 6,
+    Sata = 7,
+    Scsi = 8,
+    /// virtio-blk
+    Virtio = 9,
+    Sd = 10,
+    Emmc = 11,
+}
+
+impl DiskType {
+    /// 显示用名称
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Unknown => "Unknown",
+            Self::HardDisk => "HDD",
+            Self::CdRom => "CD-ROM",
+            Self::Usb => "USB",
+            Self::NVMe => "NVMe",
+            Self::Floppy => "Floppy",
+            Self::Network => "Network",
+            Self::Sata => "SATA",
+            Self::Scsi => "SCSI",
+            Self::Virtio => "virtio",
+            Self::Sd => "SD",
+            Self::Emmc => "eMMC",
+        }
+    }
+}
+
+/// 磁盘信息
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct DiskInfo {
+    /// 磁盘类型
+    pub disk_type: DiskType,
+    /// 是否可移动 (1=可移动, 0=固定)
+    pub removable: u32,
+    /// 是否为启动设备 (1=是, 0=否)
+    pub boot_device: u32,
+    /// 是否只读
+    pub read_only: u32,
+    /// 逻辑块大小（字节）
+    pub block_size: u64,
+    /// 总块数
+    pub total_blocks: u64,
+    /// 总容量（字节）
+    pub total_size: u64,
+    /// 媒体 ID
+    pub media_id: u32,
+    /// NVMe 命名空间 ID（其他类型为 0）
+    pub namespace_id: u32,
+    /// NVMe 命名空间 EUI-64（没有时为 0）
+    pub namespace_eui64: u64,
+    /// 控制器的 PCI 段号
+    pub pci_segment: u16,
+    /// 控制器的 PCI 总线号
+    pub pci_bus: u8,
+    /// 控制器的 PCI 设备号
+    pub pci_device: u8,
+    /// 控制器的 PCI 功能号
+    pub pci_function: u8,
+    /// PCI 位置是否有效 (1=有效)
+    pub has_pci: u8,
+    pub _reserved: u16,
+    /// 控制器上的端口：SATA HBA 端口、SCSI 目标 ID、ATAPI 通道×2+主从、
+    /// USB 父端口或 SD/eMMC 槽号
+    pub port: u32,
+    /// 逻辑单元号（SATA、SCSI、ATAPI）
+    pub lun: u32,
+    /// 分区表类型
+    pub scheme: PartitionScheme,
+    /// 本盘第一个分区在分区表中的索引
+    pub first_partition: u32,
+    /// 本盘的分区数量
+    pub partition_count: u32,
+    /// MBR 磁盘签名（仅 `scheme` 为 MBR 时有效）
+    pub mbr_signature: u32,
+    /// GPT 磁盘 GUID（仅 `scheme` 为 GPT 时有效）
+    pub disk_guid: Guid,
+}
+
+impl DiskInfo {
+    /// 是否可移动
+    pub const fn is_removable(&self) -> bool {
+        self.removable != 0
+    }
+
+    /// 是否为启动设备
+    pub const fn is_boot_device(&self) -> bool {
+        self.boot_device != 0
+    }
+
+    /// 是否只读
+    pub const fn is_read_only(&self) -> bool {
+        self.read_only != 0
+    }
+
+    /// 控制器的 PCI 位置 (段, 总线, 设备, 功能)
+    pub const fn pci_location(&self) -> Option<(u16, u8, u8, u8)> {
+        if self.has_pci == 0 {
+            return None;
+        }
+        Some((self.pci_segment, self.pci_bus, self.pci_device, self.pci_function))
+    }
+}
+
+/// GUID，按磁盘与 UEFI 使用的字节序存储（前三段小端）
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Guid(pub [u8; 16]);
+
+impl Guid {
+    /// 全零 GUID（表示不存在）
+    pub const ZERO: Self = Self([0; 16]);
+
+    /// 是否为全零
+    pub fn is_zero(&self) -> bool {
+        *self == Self::ZERO
+    }
+
+    /// 格式化为 `xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx`（大写十六进制）
+    pub fn to_ascii(&self) -> [u8; 36] {
+        const HEX: &[u8; 16] = b"0123456789ABCDEF";
+        // 前三段按小端显示
+        const ORDER: [usize; 16] = [3, 2, 1, 0, 5, 4, 7, 6, 8, 9, 10, 11, 12, 13, 14, 15];
+        let mut out = [b'-'; 36];
+        let mut pos = 0;
+        for (i, &index) in ORDER.iter().enumerate() {
+            if matches!(i, 4 | 6 | 8 | 10) {
+                pos += 1;
+            }
+            let byte = self.0[index];
+            out[pos] = HEX[(byte >> 4) as usize];
+            out[pos + 1] = HEX[(byte & 0xF) as usize];
+            pos += 2;
+        }
+        out
+    }
+}
+
+/// 分区表类型
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PartitionScheme {
+    /// 没有分区（整盘）或未知
+    None = 0,
+    Mbr = 1,
+    Gpt = 2,
+    /// 光盘 El Torito 启动映像
+    ElTorito = 3,
+}
+
+impl PartitionScheme {
+    /// 显示用名称
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::None => "None",
+            Self::Mbr => "MBR",
+            Self::Gpt => "GPT",
+            Self::ElTorito => "El Torito",
+        }
+    }
+}
+
+/// GPT 分区属性：平台必需
+pub const PARTITION_ATTR_REQUIRED: u64 = 1 << 0;
+/// GPT 分区属性：传统 BIOS 可启动（MBR 活动分区也映射到此位）
+pub const PARTITION_ATTR_LEGACY_BOOTABLE: u64 = 1 << 2;
+
+/// GPT 分区名长度（UTF-16 代码单元）
+pub const PARTITION_NAME_LEN: usize = 36;
+
+/// 分区信息，通过 `disk_index` 关联到磁盘表
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct PartitionInfo {
+    /// 所在磁盘在磁盘表中的索引
+    pub disk_index: u32,
+    /// 分区号：GPT 为表项序号 + 1，MBR 主分区 1-4、逻辑分区从 5 开始
+    pub number: u32,
+    /// 分区表类型
+    pub scheme: PartitionScheme,
+    /// MBR 分区类型字节（GPT 为 0）
+    pub mbr_type: u32,
+    /// 起始 LBA
+    pub start_lba: u64,
+    /// 块数
+    pub block_count: u64,
+    /// GPT 属性位（PARTITION_ATTR_*）
+    pub attributes: u64,
+    /// GPT 分区类型 GUID（MBR 为零）
+    pub type_guid: Guid,
+    /// GPT 分区唯一 GUID（MBR 为零）
+    pub unique_guid: Guid,
+    /// GPT 分区名（UTF-16，不足部分补 0；MBR 为空）
+    pub name: [u16; PARTITION_NAME_LEN],
+}
+
+impl PartitionInfo {
+    /// 分区名（无效的 UTF-16 替换为 U+FFFD）
+    pub fn name(&self) -> impl Iterator<Item = char> + '_ {
+        let len = self.name.iter().position(|&c| c == 0).unwrap_or(PARTITION_NAME_LEN);
+        char::decode_utf16(self.name[..len].iter().copied())
+            .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+    }
+
+    /// 是否标记为可启动（MBR 活动分区或 GPT legacy bootable）
+    pub const fn is_bootable(&self) -> bool {
+        self.attributes & PARTITION_ATTR_LEGACY_BOOTABLE != 0
+    }
+}
+
+/// 引导程序所在的设备（取自 LoadedImage 的设备句柄与 DevicePath）
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct BootDevice {
+    /// 启动分区在分区表中的索引，-1 表示从整盘启动或未识别
+    pub partition_index: i32,
+    /// 分区号（从 1 开始；El Torito 为启动项号），0 表示没有分区
+    pub partition_number: u32,
+    /// 分区表类型
+    pub scheme: PartitionScheme,
+    /// MBR 磁盘签名（仅 `scheme` 为 MBR 时有效）
+    pub mbr_signature: u32,
+    /// 分区起始 LBA
+    pub partition_start: u64,
+    /// 分区大小（块数）
+    pub partition_blocks: u64,
+    /// GPT 分区唯一 GUID
+    pub partition_guid: Guid,
+    /// GPT 磁盘 GUID（读取 GPT 头失败时为零）
+    pub disk_guid: Guid,
+}
+
+// ============================================================================
+// 内核映像
+// ============================================================================
+
+// 段权限位，取值与 ELF 的 p_flags (PF_X/PF_W/PF_R) 一致
+
+/// 段可执行
+pub const SEGMENT_EXECUTE: u32 = 1 << 0;
+/// 段可写
+pub const SEGMENT_WRITE: u32 = 1 << 1;
+/// 段可读
+pub const SEGMENT_READ: u32 = 1 << 2;
+
+/// 已加载的内核段（对应 ELF 的 PT_LOAD 程序头）
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct KernelSegment {
+    /// 物理加载地址
+    pub phys_addr: u64,
+    /// 链接时的虚拟地址
+    pub virt_addr: u64,
+    /// 文件中的大小（字节）
+    pub file_size: u64,
+    /// 内存中的大小（字节，超出 file_size 的部分为已清零的 BSS）
+    pub mem_size: u64,
+    /// 段权限 (SEGMENT_READ / SEGMENT_WRITE / SEGMENT_EXECUTE)
+    pub flags: u32,
+    /// 保留
+    pub _reserved: u32,
+}
+
+impl KernelSegment {
+    /// 是否可写
+    pub const fn is_writable(&self) -> bool {
+        self.flags & SEGMENT_WRITE != 0
+    }
+
+    /// 是否可执行
+    pub const fn is_executable(&self) -> bool {
+        self.flags & SEGMENT_EXECUTE != 0
+    }
+}
+
+// ============================================================================
+// 启动模块
+// ============================================================================
+
+/// 启动模块：随内核加载的附加文件（initrd、驱动、测试载荷等）
+///
+/// 模块内容、名称与命令行字符串以及描述表本身都位于 `KernelAndModules`
+/// 内存，内核回收引导程序内存后仍然有效。字符串为 UTF-8 且不以 null 结尾。
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct ModuleInfo {
+    /// 模块内容的物理地址（页对齐）
+    pub phys_addr: u64,
+    /// 模块大小（字节）
+    pub size: u64,
+    /// 名称（ESP 上的文件路径）地址
+    pub name_addr: u64,
+    /// 模块命令行地址 (0 表示没有)
+    pub cmdline_addr: u64,
+    /// 名称长度
+    pub name_len: u32,
+    /// 模块命令行长度
+    pub cmdline_len: u32,
+}
+
+/// 经直接映射访问的启动模块
+#[derive(Clone, Copy, Debug)]
+pub struct Module<'a> {
+    /// 名称（ESP 上的文件路径）
+    pub name: &'a str,
+    /// 模块命令行（没有时为空串）
+    pub cmdline: &'a str,
+    /// 模块内容的物理地址
+    pub phys_addr: u64,
+    /// 模块内容
+    pub data: &'a [u8],
+}
+
+/// 启动模块迭代器，见 [`BootInfo::modules`]
+pub struct Modules<'a> {
+    info: &'a BootInfo,
+    entries: core::slice::Iter<'a, ModuleInfo>,
+}
+
+impl<'a> Iterator for Modules<'a> {
+    type Item = Module<'a>;
+
+    fn next(&mut self) -> Option<Module<'a>> {
+        let entry = self.entries.next()?;
+        let info = self.info;
+        // 迭代器只能经 `unsafe` 的 [`BootInfo::modules`] 取得，调用者已保证表访问约定
+        unsafe {
+            Some(Module {
+                name: info.str_at(entry.name_addr, entry.name_len),
+                cmdline: info.str_at(entry.cmdline_addr, entry.cmdline_len),
+                phys_addr: entry.phys_addr,
+                data: info.bytes_at(entry.phys_addr, entry.size),
+            })
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+// ============================================================================
+// 引导阶段与警告
+// ============================================================================
+
+/// 引导程序的阶段数（屏幕上的 `[n/7]`）
+pub const BOOT_STAGE_COUNT: u32 = 7;
+
+/// 引导阶段，数值即屏幕上的步骤编号
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BootStage {
+    /// 分配交接内存（第一步之前）
+    Setup = 0,
+    Graphics = 1,
+    Kernel = 2,
+    Disks = 3,
+    Acpi = 4,
+    Smbios = 5,
+    Runtime = 6,
+    ExitBootServices = 7,
+}
+
+impl BootStage {
+    /// 步骤编号
+    pub const fn number(self) -> u32 {
+        self as u32
+    }
+
+    /// 显示用描述
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Setup => "Allocating boot hand-off memory",
+            Self::Graphics => "Initializing graphics (GOP)",
+            Self::Kernel => "Loading kernel and modules",
+            Self::Disks => "Scanning storage devices",
+            Self::Acpi => "Locating ACPI tables",
+            Self::Smbios => "Locating SMBIOS",
+            Self::Runtime => "Getting UEFI Runtime Services",
+            Self::ExitBootServices => "Building page tables and exiting boot services",
+        }
+    }
+}
+
+/// 非致命引导错误：该阶段失败但引导继续
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct BootWarning {
+    /// 失败的阶段
+    pub stage: BootStage,
+    pub _reserved: u32,
+    /// UEFI 状态码 (EFI_STATUS)
+    pub status: u64,
+}
+
+// ============================================================================
+// 主引导信息
+// ============================================================================
+
+/// 主引导信息结构体 - 传递给内核的所有信息
+///
+/// 除特别说明外，地址字段均为物理地址。
+///
+/// # 表访问约定
+///
+/// 表访问器（[`memory_map`](Self::memory_map)、[`modules`](Self::modules) 等）
+/// 通过 `physical_memory_offset` 经直接映射读取地址字段指向的表，因此是
+/// `unsafe` 的。调用者必须保证：
+///
+/// - 实例是引导程序交接、经 [`BootInfo::validate`] 校验的，地址与数量字段未被改动
+/// - 这些表映射在 [`phys_to_virt`](Self::phys_to_virt) 处，且所在的引导程序内存
+///   尚未被回收（启动模块除外，它们位于 `KernelAndModules` 内存）
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct BootInfo {
+    /// 魔数，用于验证结构体有效性 (应为 0x4A414E5F4F530000 "JAN_OS\0\0")
+    pub magic: u64,
+    /// 结构体版本号
+    pub version: u32,
+    /// 结构体大小（字节）
+    pub size: u32,
+
+    // ========== 帧缓冲区信息 ==========
+    pub framebuffer: FramebufferInfo,
+    /// 显示模式数组地址
+    pub video_modes_addr: u64,
+    /// 显示模式数量
+    pub video_mode_count: u32,
+    /// 当前模式在数组中的索引 (-1 表示没有 GOP)
+    pub video_mode_current: i32,
+
+    // ========== 内存映射 ==========
+    /// 内存区域数组地址（相邻且类型、属性相同的区域已合并）
+    pub memory_map_addr: u64,
+    /// 内存区域数量
+    pub memory_map_entries: u32,
+    /// 每个条目大小
+    pub memory_map_entry_size: u32,
+    /// 总可用内存（字节）
+    pub total_memory: u64,
+    /// 可用内存（字节）
+    pub usable_memory: u64,
+
+    // ========== ACPI 信息 ==========
+    /// ACPI RSDP 地址 (0 表示未找到)
+    pub acpi_rsdp_addr: u64,
+    /// ACPI 版本 (1 或 2)
+    pub acpi_version: u32,
+    pub _acpi_reserved: u32,
+
+    // ========== SMBIOS 信息 ==========
+    /// SMBIOS 入口点地址 (0 表示未找到)
+    pub smbios_addr: u64,
+    /// SMBIOS 版本
+    pub smbios_version: u32,
+    pub _smbios_reserved: u32,
+
+    // ========== 存储设备信息 ==========
+    /// 磁盘信息数组地址
+    pub disk_info_addr: u64,
+    /// 检测到的磁盘数量
+    pub disk_count: u32,
+    /// 启动设备索引 (-1 表示未知)
+    pub boot_disk_index: i32,
+
+    // ========== UEFI 运行时服务 ==========
+    /// UEFI 运行时服务表物理地址 (0 表示不可用)
+    pub uefi_runtime_services: u64,
+    /// UEFI 系统表物理地址
+    pub uefi_system_table: u64,
+    /// 运行时区域的虚拟偏移（虚拟地址 = 物理地址 + 偏移）；
+    /// 0 表示未调用 SetVirtualAddressMap，内核不能在自己的页表下调用运行时服务
+    pub uefi_runtime_offset: u64,
+
+    // ========== 内核信息 ==========
+    /// 内核加载的物理地址（最低段的起始地址）
+    pub kernel_phys_addr: u64,
+    /// 内核映像在内存中占用的大小（字节，含 BSS）
+    pub kernel_size: u64,
+    /// 内核入口点（ELF e_entry）
+    pub kernel_entry: u64,
+    /// 内核段数组地址
+    pub kernel_segments_addr: u64,
+    /// 内核段数量
+    pub kernel_segment_count: u32,
+    pub _kernel_reserved: u32,
+    /// 内核 ELF 文件副本地址（含符号表，供调试使用；位于引导程序可回收内存）
+    pub kernel_elf_addr: u64,
+    /// 内核 ELF 文件大小（字节）
+    pub kernel_elf_size: u64,
+
+    // ========== 地址空间 ==========
+    /// 物理内存直接映射的虚拟偏移（虚拟地址 = 物理地址 + 偏移）
+    pub physical_memory_offset: u64,
+    /// 直接映射覆盖的物理地址上限（字节），[0, 上限) 全部映射
+    pub physical_memory_mapped: u64,
+    /// 内核虚拟基址（最低段的虚拟地址）
+    pub kernel_virt_addr: u64,
+    /// 引导程序建立的 PML4 物理地址（进入内核时的 CR3）
+    pub page_table_root: u64,
+    /// 内核初始栈顶虚拟地址（栈底之下一页为未映射的保护页）
+    pub kernel_stack_top: u64,
+    /// 内核初始栈大小（字节）
+    pub kernel_stack_size: u64,
+
+    // ========== 命令行 ==========
+    /// 命令行字符串地址
+    pub cmdline_addr: u64,
+    /// 命令行长度
+    pub cmdline_len: u32,
+    pub _cmdline_reserved: u32,
+
+    // ========== 启动模块 ==========
+    /// 模块描述表地址
+    pub module_info_addr: u64,
+    /// 模块数量
+    pub module_count: u32,
+    pub _module_reserved: u32,
+
+    // ========== 引导警告 ==========
+    /// 非致命错误表地址
+    pub warnings_addr: u64,
+    /// 非致命错误数量
+    pub warning_count: u32,
+    pub _warnings_reserved: u32,
+
+    // ========== 启动设备 ==========
+    /// 启动分区与磁盘标识，启动磁盘本身见 `boot_disk_index`
+    pub boot_device: BootDevice,
+
+    // ========== 分区 ==========
+    /// 分区表地址（按磁盘顺序排列）
+    pub partition_info_addr: u64,
+    /// 分区数量
+    pub partition_count: u32,
+    pub _partition_reserved: u32,
+
+    // ========== 截断 ==========
+    /// 因容量不足而不完整的表（`TRUNCATED_*` 位），0 表示所有表完整
+    pub truncated: u32,
+    pub _truncated_reserved: u32,
+}
+
+/// `BootInfo::truncated`：内存映射超出预留容量，末尾的区域被丢弃
+pub const TRUNCATED_MEMORY_MAP: u32 = 1 << 0;
+/// `BootInfo::truncated`：显示模式超出模式表容量
+pub const TRUNCATED_VIDEO_MODES: u32 = 1 << 1;
+/// `BootInfo::truncated`：引导警告超出警告表容量
+pub const TRUNCATED_WARNINGS: u32 = 1 << 2;
+/// `BootInfo::truncated`：命令行超出缓冲区，已在字符边界截断
+pub const TRUNCATED_CMDLINE: u32 = 1 << 3;
+
+/// BootInfo 校验失败原因
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BootInfoError {
+    /// 魔数不匹配
+    BadMagic(u64),
+    /// 版本不匹配（引导程序与内核来自不同构建）
+    VersionMismatch(u32),
+    /// 结构体或表项大小与本 crate 的定义不一致
+    SizeMismatch,
+}
+
+impl BootInfo {
+    /// 校验魔数、版本与布局大小
+    pub fn validate(&self) -> Result<(), BootInfoError> {
+        if self.magic != BOOTINFO_MAGIC {
+            return Err(BootInfoError::BadMagic(self.magic));
+        }
+        if self.version != BOOTINFO_VERSION {
+            return Err(BootInfoError::VersionMismatch(self.version));
+        }
+        if self.size as usize != size_of::<BootInfo>()
+            || self.memory_map_entry_size as usize != size_of::<MemoryRegion>()
+        {
+            return Err(BootInfoError::SizeMismatch);
+        }
+        Ok(())
+    }
+
+    /// 物理地址转换为直接映射中的虚拟地址
+    pub const fn phys_to_virt(&self, phys: u64) -> u64 {
+        phys + self.physical_memory_offset
+    }
+
+    /// 线性帧缓冲区（没有 GOP 或只支持 BLT 时为 None）
+    pub fn linear_framebuffer(&self) -> Option<&FramebufferInfo> {
+        self.framebuffer.is_present().then_some(&self.framebuffer)
+    }
+
+    /// GOP 支持的全部显示模式
+    ///
+    /// # Safety
+    ///
+    /// 见 [`BootInfo`] 的表访问约定。
+    pub unsafe fn video_modes(&self) -> &[VideoMode] {
+        if self.video_modes_addr == 0 {
+            return &[];
+        }
+        unsafe {
+            core::slice::from_raw_parts(
+                self.phys_to_virt(self.video_modes_addr) as *const VideoMode,
+                self.video_mode_count as usize,
+            )
+        }
+    }
+
+    /// 当前显示模式
+    ///
+    /// # Safety
+    ///
+    /// 见 [`BootInfo`] 的表访问约定。
+    pub unsafe fn current_video_mode(&self) -> Option<&VideoMode> {
+        usize::try_from(self.video_mode_current)
+            .ok()
+            .and_then(|i| self.video_modes().get(i))
+    }
+
+    /// 内存映射表
+    ///
+    /// # Safety
+    ///
+    /// 见 [`BootInfo`] 的表访问约定。
+    pub unsafe fn memory_map(&self) -> &[MemoryRegion] {
+        if self.memory_map_addr == 0 {
+            return &[];
+        }
+        unsafe {
+            core::slice::from_raw_parts(
+                self.phys_to_virt(self.memory_map_addr) as *const MemoryRegion,
+                self.memory_map_entries as usize,
+            )
+        }
+    }
+
+    /// 磁盘信息表
+    ///
+    /// # Safety
+    ///
+    /// 见 [`BootInfo`] 的表访问约定。
+    pub unsafe fn disks(&self) -> &[DiskInfo] {
+        if self.disk_info_addr == 0 {
+            return &[];
+        }
+        unsafe {
+            core::slice::from_raw_parts(
+                self.phys_to_virt(self.disk_info_addr) as *const DiskInfo,
+                self.disk_count as usize,
+            )
+        }
+    }
+
+    /// 启动磁盘（未识别时为 None）
+    ///
+    /// # Safety
+    ///
+    /// 见 [`BootInfo`] 的表访问约定。
+    pub unsafe fn boot_disk(&self) -> Option<&DiskInfo> {
+        usize::try_from(self.boot_disk_index)
+            .ok()
+            .and_then(|i| self.disks().get(i))
+    }
+
+    /// 全部分区
+    ///
+    /// # Safety
+    ///
+    /// 见 [`BootInfo`] 的表访问约定。
+    pub unsafe fn partitions(&self) -> &[PartitionInfo] {
+        if self.partition_info_addr == 0 {
+            return &[];
+        }
+        unsafe {
+            core::slice::from_raw_parts(
+                self.phys_to_virt(self.partition_info_addr) as *const PartitionInfo,
+                self.partition_count as usize,
+            )
+        }
+    }
+
+    /// 某个磁盘上的分区
+    ///
+    /// # Safety
+    ///
+    /// 见 [`BootInfo`] 的表访问约定。
+    pub unsafe fn disk_partitions(&self, disk: &DiskInfo) -> &[PartitionInfo] {
+        let start = disk.first_partition as usize;
+        let end = start + disk.partition_count as usize;
+        self.partitions().get(start..end).unwrap_or(&[])
+    }
+
+    /// 启动分区（从整盘启动或未识别时为 None）
+    ///
+    /// # Safety
+    ///
+    /// 见 [`BootInfo`] 的表访问约定。
+    pub unsafe fn boot_partition(&self) -> Option<&PartitionInfo> {
+        usize::try_from(self.boot_device.partition_index)
+            .ok()
+            .and_then(|i| self.partitions().get(i))
+    }
+
+    /// 已加载的内核段
+    ///
+    /// # Safety
+    ///
+    /// 见 [`BootInfo`] 的表访问约定。
+    pub unsafe fn kernel_segments(&self) -> &[KernelSegment] {
+        if self.kernel_segments_addr == 0 {
+            return &[];
+        }
+        unsafe {
+            core::slice::from_raw_parts(
+                self.phys_to_virt(self.kernel_segments_addr) as *const KernelSegment,
+                self.kernel_segment_count as usize,
+            )
+        }
+    }
+
+    /// 内核 ELF 文件原始内容
+    ///
+    /// # Safety
+    ///
+    /// 见 [`BootInfo`] 的表访问约定。
+    pub unsafe fn kernel_elf(&self) -> &[u8] {
+        if self.kernel_elf_addr == 0 {
+            return &[];
+        }
+        unsafe {
+            core::slice::from_raw_parts(
+                self.phys_to_virt(self.kernel_elf_addr) as *const u8,
+                self.kernel_elf_size as usize,
+            )
+        }
+    }
+
+    /// 命令行原始字节（不含结尾的 null）
+    ///
+    /// # Safety
+    ///
+    /// 见 [`BootInfo`] 的表访问约定。
+    pub unsafe fn cmdline_bytes(&self) -> &[u8] {
+        if self.cmdline_addr == 0 {
+            return &[];
+        }
+        unsafe {
+            core::slice::from_raw_parts(
+                self.phys_to_virt(self.cmdline_addr) as *const u8,
+                self.cmdline_len as usize,
+            )
+        }
+    }
+
+    /// 命令行字符串（非 UTF-8 时为 None）
+    ///
+    /// # Safety
+    ///
+    /// 见 [`BootInfo`] 的表访问约定。
+    pub unsafe fn cmdline(&self) -> Option<&str> {
+        core::str::from_utf8(self.cmdline_bytes()).ok()
+    }
+
+    /// 启动模块描述表
+    ///
+    /// # Safety
+    ///
+    /// 见 [`BootInfo`] 的表访问约定。
+    pub unsafe fn module_table(&self) -> &[ModuleInfo] {
+        if self.module_info_addr == 0 {
+            return &[];
+        }
+        unsafe {
+            core::slice::from_raw_parts(
+                self.phys_to_virt(self.module_info_addr) as *const ModuleInfo,
+                self.module_count as usize,
+            )
+        }
+    }
+
+    /// 遍历启动模块
+    ///
+    /// # Safety
+    ///
+    /// 见 [`BootInfo`] 的表访问约定。
+    pub unsafe fn modules(&self) -> Modules<'_> {
+        Modules {
+            info: self,
+            entries: self.module_table().iter(),
+        }
+    }
+
+    /// 引导过程中的非致命错误
+    ///
+    /// # Safety
+    ///
+    /// 见 [`BootInfo`] 的表访问约定。
+    pub unsafe fn warnings(&self) -> &[BootWarning] {
+        if self.warnings_addr == 0 {
+            return &[];
+        }
+        unsafe {
+            core::slice::from_raw_parts(
+                self.phys_to_virt(self.warnings_addr) as *const BootWarning,
+                self.warning_count as usize,
+            )
+        }
+    }
+
+    /// ACPI RSDP 地址与版本
+    pub fn acpi_rsdp(&self) -> Option<(u64, u32)> {
+        (self.acpi_rsdp_addr != 0).then_some((self.acpi_rsdp_addr, self.acpi_version))
+    }
+
+    /// 运行时服务表在虚拟地址空间中的地址（未建立虚拟映射时为 None）
+    pub fn uefi_runtime_services_virt(&self) -> Option<u64> {
+        (self.uefi_runtime_services != 0 && self.uefi_runtime_offset != 0)
+            .then_some(self.uefi_runtime_services + self.uefi_runtime_offset)
+    }
+
+    /// SMBIOS 入口点地址与主版本
+    pub fn smbios(&self) -> Option<(u64, u32)> {
+        (self.smbios_addr != 0).then_some((self.smbios_addr, self.smbios_version))
+    }
+
+    /// 直接映射中从 `phys` 开始的 `len` 字节（地址为 0 时为空）
+    ///
+    /// # Safety
+    ///
+    /// `phys..phys + len` 必须映射在 [`phys_to_virt`](Self::phys_to_virt) 处且仍然有效。
+    unsafe fn bytes_at(&self, phys: u64, len: u64) -> &[u8] {
+        if phys == 0 {
+            return &[];
+        }
+        unsafe { core::slice::from_raw_parts(self.phys_to_virt(phys) as *const u8, len as usize) }
+    }
+
+    /// 直接映射中的字符串；内容经 UTF-8 校验，非 UTF-8 的字符串视为空串
+    ///
+    /// # Safety
+    ///
+    /// 同 [`bytes_at`](Self::bytes_at)。
+    unsafe fn str_at(&self, phys: u64, len: u32) -> &str {
+        core::str::from_utf8(self.bytes_at(phys, len as u64)).unwrap_or("")
+    }
+}
+
+// ============================================================================
+// 编译期布局断言
+// ============================================================================
+
+const _: () = {
+    assert!(size_of::<PixelFormatType>() == 4);
+    assert!(size_of::<MemoryRegionType>() == 4);
+    assert!(size_of::<DiskType>() == 4);
+    assert!(size_of::<BootStage>() == 4);
+    assert!(size_of::<PartitionScheme>() == 4);
+
+    assert!(size_of::<FramebufferInfo>() == 56);
+    assert!(offset_of!(FramebufferInfo, pixel_format) == 32);
+    assert!(offset_of!(FramebufferInfo, red_mask) == 40);
+
+    assert!(size_of::<VideoMode>() == 16);
+
+    assert!(size_of::<MemoryRegion>() == 32);
+    assert!(offset_of!(MemoryRegion, region_type) == 24);
+
+    assert!(size_of::<DiskInfo>() == 104);
+    assert!(offset_of!(DiskInfo, block_size) == 16);
+    assert!(offset_of!(DiskInfo, media_id) == 40);
+    assert!(offset_of!(DiskInfo, namespace_eui64) == 48);
+    assert!(offset_of!(DiskInfo, pci_segment) == 56);
+    assert!(offset_of!(DiskInfo, port) == 64);
+    assert!(offset_of!(DiskInfo, scheme) == 72);
+    assert!(offset_of!(DiskInfo, disk_guid) == 88);
+
+    assert!(size_of::<PartitionInfo>() == 144);
+    assert!(offset_of!(PartitionInfo, start_lba) == 16);
+    assert!(offset_of!(PartitionInfo, type_guid) == 40);
+    assert!(offset_of!(PartitionInfo, name) == 72);
+
+    assert!(size_of::<Guid>() == 16);
+    assert!(size_of::<BootDevice>() == 64);
+    assert!(offset_of!(BootDevice, partition_start) == 16);
+    assert!(offset_of!(BootDevice, partition_guid) == 32);
+
+    assert!(size_of::<KernelSegment>() == 40);
+    assert!(offset_of!(KernelSegment, flags) == 32);
+
+    assert!(size_of::<BootWarning>() == 16);
+
+    assert!(size_of::<ModuleInfo>() == 40);
+    assert!(offset_of!(ModuleInfo, name_len) == 32);
+
+    assert!(size_of::<BootInfo>() == 432);
+    assert!(offset_of!(BootInfo, framebuffer) == 16);
+    assert!(offset_of!(BootInfo, video_modes_addr) == 72);
+    assert!(offset_of!(BootInfo, memory_map_addr) == 88);
+    assert!(offset_of!(BootInfo, acpi_rsdp_addr) == 120);
+    assert!(offset_of!(BootInfo, smbios_addr) == 136);
+    assert!(offset_of!(BootInfo, disk_info_addr) == 152);
+    assert!(offset_of!(BootInfo, uefi_runtime_services) == 168);
+    assert!(offset_of!(BootInfo, uefi_runtime_offset) == 184);
+    assert!(offset_of!(BootInfo, kernel_phys_addr) == 192);
+    assert!(offset_of!(BootInfo, kernel_entry) == 208);
+    assert!(offset_of!(BootInfo, kernel_segments_addr) == 216);
+    assert!(offset_of!(BootInfo, kernel_elf_addr) == 232);
+    assert!(offset_of!(BootInfo, physical_memory_offset) == 248);
+    assert!(offset_of!(BootInfo, page_table_root) == 272);
+    assert!(offset_of!(BootInfo, kernel_stack_top) == 280);
+    assert!(offset_of!(BootInfo, cmdline_addr) == 296);
+    assert!(offset_of!(BootInfo, module_info_addr) == 312);
+    assert!(offset_of!(BootInfo, warnings_addr) == 328);
+    assert!(offset_of!(BootInfo, boot_device) == 344);
+    assert!(offset_of!(BootInfo, partition_info_addr) == 408);
+    assert!(offset_of!(BootInfo, truncated) == 424);
+};
@@ -0,0 +1,678 @@
+//! 分区表解析（GPT 与 MBR）
+//!
+//! 引导程序与内核共用同一份解析代码：调用者提供按 LBA 读取一个块的函数和
+//! 一个块大小的缓冲区，解析出的分区逐个以 [`PartitionInfo`] 回调，
+//! `disk_index` 由调用者填写。
+//!
+//! - 先找 GPT：LBA 1 的主 GPT 头，校验失败时用最后一个块上的备份头；
+//!   头与表项数组都校验 CRC32（按头中的完整表项数），只输出前 [`GPT_MAX_ENTRIES`] 项
+//! - 没有有效 GPT 时按 MBR 解析，包括扩展分区链中的逻辑分区
+//! - 只有保护性 0xEE 项的 MBR（GPT 已损坏）和整盘格式化的卷引导扇区
+//!   （超级软盘，同样以 0x55AA 结尾）不是可用的分区表，不输出分区
+
+use crate::{Guid, PartitionInfo, PartitionScheme, PARTITION_ATTR_LEGACY_BOOTABLE, PARTITION_NAME_LEN};
+
+/// GPT 头签名
+const GPT_SIGNATURE: &[u8; 8] = b"EFI PART";
+/// GPT 头的最小长度
+const GPT_HEADER_MIN_SIZE: usize = 92;
+/// GPT 表项的最小长度
+const GPT_ENTRY_MIN_SIZE: usize = 128;
+/// 最多输出的 GPT 表项数（规范的最小保留数为 128，常见实现不超过 1024）
+const GPT_MAX_ENTRIES: u32 = 1024;
+
+/// MBR 分区表偏移
+const MBR_TABLE_OFFSET: usize = 446;
+/// MBR 磁盘签名偏移
+const MBR_DISK_SIGNATURE_OFFSET: usize = 440;
+/// MBR 启动签名 0x55AA 的偏移
+const MBR_BOOT_SIGNATURE_OFFSET: usize = 510;
+/// MBR 活动分区标志
+const MBR_ACTIVE: u8 = 0x80;
+/// MBR 扩展分区类型 (CHS, LBA, Linux)
+const MBR_EXTENDED_TYPES: [u8; 3] = [0x05, 0x0F, 0x85];
+/// GPT 保护性 MBR 的分区类型
+const MBR_PROTECTIVE_TYPE: u8 = 0xEE;
+/// 扩展分区链的最大长度（防止损坏的链形成环）
+const MBR_MAX_LOGICAL: u32 = 128;
+
+/// 块读取函数：读取 `lba` 处的一个块到缓冲区，失败返回 false
+pub type ReadBlock<'a> = &'a mut dyn FnMut(u64, &mut [u8]) -> bool;
+
+/// 磁盘标签（分区表头）
+#[derive(Clone, Copy, Debug)]
+pub struct DiskLabel {
+    pub scheme: PartitionScheme,
+    /// GPT 磁盘 GUID
+    pub disk_guid: Guid,
+    /// MBR 磁盘签名
+    pub mbr_signature: u32,
+}
+
+/// 解析磁盘的分区表
+///
+/// `buf` 至少 512 字节，长度即块大小；`last_lba` 为磁盘最后一个块，
+/// 用于读取备份 GPT 头。没有分区表时返回 `PartitionScheme::None`。
+pub fn parse(
+    buf: &mut [u8],
+    last_lba: u64,
+    read: ReadBlock,
+    emit: &mut dyn FnMut(PartitionInfo),
+) -> DiskLabel {
+    let mut label = DiskLabel {
+        scheme: PartitionScheme::None,
+        disk_guid: Guid::ZERO,
+        mbr_signature: 0,
+    };
+    if buf.len() < 512 {
+        return label;
+    }
+
+    if let Some(header) = read_gpt_header(buf, 1, read).or_else(|| read_gpt_header(buf, last_lba, read)) {
+        label.scheme = PartitionScheme::Gpt;
+        label.disk_guid = header.disk_guid;
+        parse_gpt_entries(buf, &header, read, emit);
+        return label;
+    }
+
+    if !read(0, buf) || buf[MBR_BOOT_SIGNATURE_OFFSET..MBR_BOOT_SIGNATURE_OFFSET + 2] != [0x55, 0xAA] {
+        return label;
+    }
+    if is_volume_boot_record(buf) || is_protective_only(buf) {
+        return label;
+    }
+    label.scheme = PartitionScheme::Mbr;
+    label.mbr_signature = le32(buf, MBR_DISK_SIGNATURE_OFFSET);
+    parse_mbr(buf, read, emit);
+    label
+}
+
+// ============================================================================
+// GPT
+// ============================================================================
+
+/// 已校验的 GPT 头中用到的字段
+struct GptHeader {
+    disk_guid: Guid,
+    entries_lba: u64,
+    entry_count: u32,
+    entry_size: usize,
+    entries_crc: u32,
+}
+
+/// 读取并校验 `lba` 处的 GPT 头
+fn read_gpt_header(buf: &mut [u8], lba: u64, read: ReadBlock) -> Option<GptHeader> {
+    if lba == 0 || !read(lba, buf) || &buf[..8] != GPT_SIGNATURE {
+        return None;
+    }
+
+    let header_size = le32(buf, 12) as usize;
+    if !(GPT_HEADER_MIN_SIZE..=buf.len()).contains(&header_size) {
+        return None;
+    }
+    // CRC 计算时头校验和字段视为 0
+    let stored_crc = le32(buf, 16);
+    let mut crc = Crc32::new();
+    crc.update(&buf[..16]);
+    crc.update(&[0; 4]);
+    crc.update(&buf[20..header_size]);
+    if crc.finish() != stored_crc || le64(buf, 24) != lba {
+        return None;
+    }
+
+    let entry_size = le32(buf, 84) as usize;
+    if entry_size < GPT_ENTRY_MIN_SIZE || entry_size > buf.len() || !buf.len().is_multiple_of(entry_size) {
+        return None;
+    }
+    Some(GptHeader {
+        disk_guid: guid_at(buf, 56),
+        entries_lba: le64(buf, 72),
+        entry_count: le32(buf, 80),
+        entry_size,
+        entries_crc: le32(buf, 88),
+    })
+}
+
+/// 解析 GPT 表项；表项数组 CRC 不符时不输出任何分区
+///
+/// CRC 覆盖头中声明的全部表项，输出只到 [`GPT_MAX_ENTRIES`] 为止。
+fn parse_gpt_entries(
+    buf: &mut [u8],
+    header: &GptHeader,
+    read: ReadBlock,
+    emit: &mut dyn FnMut(PartitionInfo),
+) {
+    let per_block = buf.len() / header.entry_size;
+    let blocks = (header.entry_count as usize).div_ceil(per_block);
+    let emitted = header.entry_count.min(GPT_MAX_ENTRIES) as usize;
+    let lba = |block: usize| header.entries_lba.checked_add(block as u64);
+
+    // 第一遍校验 CRC，第二遍输出，避免输出半张损坏的表
+    let mut crc = Crc32::new();
+    let mut remaining = header.entry_count as usize;
+    for block in 0..blocks {
+        if !lba(block).is_some_and(|lba| read(lba, buf)) {
+            return;
+        }
+        let count = remaining.min(per_block);
+        crc.update(&buf[..count * header.entry_size]);
+        remaining -= count;
+    }
+    if crc.finish() != header.entries_crc {
+        return;
+    }
+
+    for block in 0..emitted.div_ceil(per_block) {
+        if !lba(block).is_some_and(|lba| read(lba, buf)) {
+            return;
+        }
+        for slot in 0..per_block {
+            let index = block * per_block + slot;
+            if index >= emitted {
+                return;
+            }
+            let entry = &buf[slot * header.entry_size..(slot + 1) * header.entry_size];
+            let type_guid = guid_at(entry, 0);
+            if type_guid.is_zero() {
+                continue;
+            }
+            let start_lba = le64(entry, 32);
+            let end_lba = le64(entry, 40);
+            let mut name = [0u16; PARTITION_NAME_LEN];
+            for (i, c) in name.iter_mut().enumerate() {
+                *c = u16::from_le_bytes([entry[56 + i * 2], entry[57 + i * 2]]);
+            }
+            emit(PartitionInfo {
+                disk_index: 0,
+                number: index as u32 + 1,
+                scheme: PartitionScheme::Gpt,
+                mbr_type: 0,
+                start_lba,
+                block_count: (end_lba + 1).saturating_sub(start_lba),
+                attributes: le64(entry, 48),
+                type_guid,
+                unique_guid: guid_at(entry, 16),
+                name,
+            });
+        }
+    }
+}
+
+// ============================================================================
+// MBR
+// ============================================================================
+
+/// 解析 MBR（`buf` 中已是 LBA 0 的内容）
+fn parse_mbr(buf: &mut [u8], read: ReadBlock, emit: &mut dyn FnMut(PartitionInfo)) {
+    let mut extended = None;
+    for i in 0..4 {
+        let Some(entry) = MbrEntry::parse(buf, i) else {
+            continue;
+        };
+        if MBR_EXTENDED_TYPES.contains(&entry.kind) {
+            // 只跟随第一个扩展分区
+            extended.get_or_insert(entry.start);
+            continue;
+        }
+        // 混合 MBR 中的保护项只是 GPT 的占位
+        if entry.kind == MBR_PROTECTIVE_TYPE {
+            continue;
+        }
+        emit(entry.to_partition(i as u32 + 1, 0));
+    }
+
+    let Some(extended_start) = extended else {
+        return;
+    };
+    // 每个 EBR 的第一项是逻辑分区（相对该 EBR），第二项指向下一个 EBR（相对扩展分区起点）
+    // 记录走过的 EBR，链表成环时停下而不是重复输出逻辑分区
+    let mut visited = [0u64; MBR_MAX_LOGICAL as usize];
+    let mut ebr = extended_start;
+    for number in 5..5 + MBR_MAX_LOGICAL {
+        let depth = (number - 5) as usize;
+        if visited[..depth].contains(&ebr) {
+            return;
+        }
+        visited[depth] = ebr;
+        if !read(ebr, buf) || buf[MBR_BOOT_SIGNATURE_OFFSET..MBR_BOOT_SIGNATURE_OFFSET + 2] != [0x55, 0xAA] {
+            return;
+        }
+        if let Some(entry) = MbrEntry::parse(buf, 0) {
+            emit(entry.to_partition(number, ebr));
+        }
+        match MbrEntry::parse(buf, 1) {
+            Some(next) if MBR_EXTENDED_TYPES.contains(&next.kind) => ebr = extended_start + next.start,
+            _ => return,
+        }
+    }
+}
+
+/// 是否是卷引导扇区（FAT / NTFS / exFAT 直接格式化整盘）：以跳转指令开头，
+/// 并带有合理的 BPB 或文件系统 OEM 名
+fn is_volume_boot_record(buf: &[u8]) -> bool {
+    let jump = matches!(buf[0], 0xEB if buf[2] == 0x90) || buf[0] == 0xE9;
+    if !jump {
+        return false;
+    }
+    if matches!(&buf[3..11], b"NTFS    " | b"EXFAT   ") {
+        return true;
+    }
+    let bytes_per_sector = u16::from_le_bytes([buf[11], buf[12]]);
+    let sectors_per_cluster = buf[13];
+    let reserved_sectors = u16::from_le_bytes([buf[14], buf[15]]);
+    let fats = buf[16];
+    matches!(bytes_per_sector, 512 | 1024 | 2048 | 4096)
+        && sectors_per_cluster.is_power_of_two()
+        && reserved_sectors != 0
+        && matches!(fats, 1 | 2)
+}
+
+/// MBR 中只有保护性 0xEE 项（GPT 头都无效时不能把它当作分区）
+fn is_protective_only(buf: &[u8]) -> bool {
+    let mut entries = (0..4).filter_map(|i| MbrEntry::parse(buf, i)).peekable();
+    entries.peek().is_some() && entries.all(|entry| entry.kind == MBR_PROTECTIVE_TYPE)
+}
+
+/// MBR / EBR 中的一项
+struct MbrEntry {
+    status: u8,
+    kind: u8,
+    start: u64,
+    sectors: u64,
+}
+
+impl MbrEntry {
+    /// 解析第 `index` 项，空项返回 None
+    fn parse(buf: &[u8], index: usize) -> Option<Self> {
+        let offset = MBR_TABLE_OFFSET + index * 16;
+        let entry = Self {
+            status: buf[offset],
+            kind: buf[offset + 4],
+            start: le32(buf, offset + 8) as u64,
+            sectors: le32(buf, offset + 12) as u64,
+        };
+        (entry.kind != 0 && entry.sectors != 0).then_some(entry)
+    }
+
+    /// 转换为分区信息，`base` 为起始 LBA 的基准
+    fn to_partition(&self, number: u32, base: u64) -> PartitionInfo {
+        PartitionInfo {
+            disk_index: 0,
+            number,
+            scheme: PartitionScheme::Mbr,
+            mbr_type: self.kind as u32,
+            start_lba: base + self.start,
+            block_count: self.sectors,
+            attributes: if self.status & MBR_ACTIVE != 0 { PARTITION_ATTR_LEGACY_BOOTABLE } else { 0 },
+            type_guid: Guid::ZERO,
+            unique_guid: Guid::ZERO,
+            name: [0; PARTITION_NAME_LEN],
+        }
+    }
+}
+
+// ============================================================================
+// 辅助函数
+// ============================================================================
+
+fn le32(buf: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([buf[offset], buf[offset + 1], buf[offset + 2], buf[offset + 3]])
+}
+
+fn le64(buf: &[u8], offset: usize) -> u64 {
+    le32(buf, offset) as u64 | (le32(buf, offset + 4) as u64) << 32
+}
+
+fn guid_at(buf: &[u8], offset: usize) -> Guid {
+    let mut guid = [0u8; 16];
+    guid.copy_from_slice(&buf[offset..offset + 16]);
+    Guid(guid)
+}
+
+/// CRC-32 (IEEE 802.3，反射多项式 0xEDB88320)，逐位计算，不需要查找表
+struct Crc32(u32);
+
+impl Crc32 {
+    fn new() -> Self {
+        Self(0xFFFF_FFFF)
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.0 ^= byte as u32;
+            for _ in 0..8 {
+                let mask = (self.0 & 1).wrapping_neg();
+                self.0 = (self.0 >> 1) ^ (0xEDB8_8320 & mask);
+            }
+        }
+    }
+
+    fn finish(&self) -> u32 {
+        !self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLOCK: usize = 512;
+    /// 测试磁盘的块数
+    const DISK_BLOCKS: u64 = 64;
+    const LAST_LBA: u64 = DISK_BLOCKS - 1;
+    /// 测试用 GPT 表项数（一个块正好放下）
+    const GPT_ENTRIES: u32 = 4;
+
+    const ESP_TYPE: Guid = Guid([0x28, 0x73, 0x2A, 0xC1, 0x1F, 0xF8, 0xD2, 0x11, 0xBA, 0x4B, 0x00, 0xA0, 0xC9, 0x3E, 0xC9, 0x3B]);
+    const DISK_GUID: Guid = Guid([0x11; 16]);
+
+    /// 内存中的磁盘镜像
+    struct Disk(Vec<u8>);
+
+    impl Disk {
+        fn new() -> Self {
+            Self::with_blocks(DISK_BLOCKS)
+        }
+
+        /// 更大的磁盘（备份 GPT 头仍按 [`LAST_LBA`] 查找）
+        fn with_blocks(blocks: u64) -> Self {
+            Self(vec![0; blocks as usize * BLOCK])
+        }
+
+        fn block(&mut self, lba: u64) -> &mut [u8] {
+            let start = lba as usize * BLOCK;
+            &mut self.0[start..start + BLOCK]
+        }
+
+        /// 解析分区表，返回标签和输出的分区
+        fn parse(&self) -> (DiskLabel, Vec<PartitionInfo>) {
+            let mut buf = [0u8; BLOCK];
+            let mut partitions = Vec::new();
+            let mut read = |lba: u64, buf: &mut [u8]| {
+                let start = lba as usize * BLOCK;
+                match self.0.get(start..start + BLOCK) {
+                    Some(block) => {
+                        buf.copy_from_slice(block);
+                        true
+                    }
+                    None => false,
+                }
+            };
+            let label = parse(&mut buf, LAST_LBA, &mut read, &mut |partition| partitions.push(partition));
+            (label, partitions)
+        }
+    }
+
+    fn crc32(data: &[u8]) -> u32 {
+        let mut crc = Crc32::new();
+        crc.update(data);
+        crc.finish()
+    }
+
+    fn put32(buf: &mut [u8], offset: usize, value: u32) {
+        buf[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    fn put64(buf: &mut [u8], offset: usize, value: u64) {
+        buf[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+    }
+
+    fn boot_signature(buf: &mut [u8]) {
+        buf[MBR_BOOT_SIGNATURE_OFFSET..MBR_BOOT_SIGNATURE_OFFSET + 2].copy_from_slice(&[0x55, 0xAA]);
+    }
+
+    /// 写入 MBR / EBR 的第 `index` 项
+    fn mbr_entry(buf: &mut [u8], index: usize, status: u8, kind: u8, start: u32, sectors: u32) {
+        let offset = MBR_TABLE_OFFSET + index * 16;
+        buf[offset] = status;
+        buf[offset + 4] = kind;
+        put32(buf, offset + 8, start);
+        put32(buf, offset + 12, sectors);
+    }
+
+    /// 保护性 MBR
+    fn protective_mbr(disk: &mut Disk) {
+        let mbr = disk.block(0);
+        mbr_entry(mbr, 0, 0, MBR_PROTECTIVE_TYPE, 1, LAST_LBA as u32);
+        boot_signature(mbr);
+    }
+
+    /// 写入 GPT 头（`my_lba` 处）与表项数组（`entries_lba` 处）
+    fn gpt(disk: &mut Disk, my_lba: u64, alternate_lba: u64, entries_lba: u64) {
+        let entries = disk.block(entries_lba);
+        entries.fill(0);
+        // 1: ESP, 2: 空, 3: 带名字的数据分区
+        entries[..16].copy_from_slice(&ESP_TYPE.0);
+        entries[16..32].copy_from_slice(&[0x22; 16]);
+        put64(entries, 32, 34);
+        put64(entries, 40, 40);
+        put64(entries, 48, 1);
+        let data = &mut entries[256..384];
+        data[..16].copy_from_slice(&[0x33; 16]);
+        data[16..32].copy_from_slice(&[0x44; 16]);
+        put64(data, 32, 41);
+        put64(data, 40, 59);
+        for (i, c) in "data".encode_utf16().enumerate() {
+            data[56 + i * 2..58 + i * 2].copy_from_slice(&c.to_le_bytes());
+        }
+        let entries_crc = crc32(&entries[..GPT_ENTRIES as usize * 128]);
+        gpt_header(disk, my_lba, alternate_lba, entries_lba, GPT_ENTRIES, entries_crc);
+    }
+
+    /// 写入 `my_lba` 处的 GPT 头
+    fn gpt_header(disk: &mut Disk, my_lba: u64, alternate_lba: u64, entries_lba: u64, entry_count: u32, entries_crc: u32) {
+        let header = disk.block(my_lba);
+        header.fill(0);
+        header[..8].copy_from_slice(GPT_SIGNATURE);
+        put32(header, 8, 0x0001_0000);
+        put32(header, 12, GPT_HEADER_MIN_SIZE as u32);
+        put64(header, 24, my_lba);
+        put64(header, 32, alternate_lba);
+        put64(header, 40, 34);
+        put64(header, 48, LAST_LBA - 2);
+        header[56..72].copy_from_slice(&DISK_GUID.0);
+        put64(header, 72, entries_lba);
+        put32(header, 80, entry_count);
+        put32(header, 84, 128);
+        put32(header, 88, entries_crc);
+        let header_crc = crc32(&header[..GPT_HEADER_MIN_SIZE]);
+        put32(header, 16, header_crc);
+    }
+
+    /// 带主、备份 GPT 的磁盘
+    fn gpt_disk() -> Disk {
+        let mut disk = Disk::new();
+        protective_mbr(&mut disk);
+        gpt(&mut disk, 1, LAST_LBA, 2);
+        gpt(&mut disk, LAST_LBA, 1, LAST_LBA - 1);
+        disk
+    }
+
+    fn assert_gpt_partitions(label: DiskLabel, partitions: &[PartitionInfo]) {
+        assert_eq!(label.scheme, PartitionScheme::Gpt);
+        assert_eq!(label.disk_guid, DISK_GUID);
+        assert_eq!(partitions.len(), 2);
+
+        let esp = &partitions[0];
+        assert_eq!((esp.number, esp.start_lba, esp.block_count), (1, 34, 7));
+        assert_eq!(esp.type_guid, ESP_TYPE);
+        assert_eq!(esp.unique_guid, Guid([0x22; 16]));
+        assert_eq!(esp.attributes, 1);
+
+        let data = &partitions[1];
+        assert_eq!((data.number, data.start_lba, data.block_count), (3, 41, 19));
+        assert_eq!(data.scheme, PartitionScheme::Gpt);
+        assert_eq!(&data.name[..5], &[b'd' as u16, b'a' as u16, b't' as u16, b'a' as u16, 0]);
+    }
+
+    #[test]
+    fn valid_gpt() {
+        let (label, partitions) = gpt_disk().parse();
+        assert_gpt_partitions(label, &partitions);
+    }
+
+    #[test]
+    fn bad_primary_header_crc_uses_backup() {
+        let mut disk = gpt_disk();
+        // 破坏主头但不修正 CRC；备份头引用自己的表项数组
+        disk.block(1)[56] ^= 0xFF;
+        disk.block(2).fill(0);
+        let (label, partitions) = disk.parse();
+        assert_gpt_partitions(label, &partitions);
+    }
+
+    #[test]
+    fn bad_entries_crc_emits_nothing() {
+        let mut disk = Disk::new();
+        protective_mbr(&mut disk);
+        gpt(&mut disk, 1, LAST_LBA, 2);
+        disk.block(2)[40] ^= 0xFF;
+        let (label, partitions) = disk.parse();
+        assert_eq!(label.scheme, PartitionScheme::Gpt);
+        assert!(partitions.is_empty());
+    }
+
+    #[test]
+    fn gpt_entries_beyond_limit_checked_but_not_emitted() {
+        let count = GPT_MAX_ENTRIES + 4;
+        let entries = 2 * BLOCK..2 * BLOCK + count as usize * 128;
+        let mut disk = Disk::with_blocks(entries.end.div_ceil(BLOCK) as u64);
+        protective_mbr(&mut disk);
+        // 第一项与限制之后的一项
+        for index in [0, GPT_MAX_ENTRIES as usize] {
+            let entry = &mut disk.0[entries.start + index * 128..][..128];
+            entry[..16].copy_from_slice(&ESP_TYPE.0);
+            put64(entry, 32, 34);
+            put64(entry, 40, 40);
+        }
+        let entries_crc = crc32(&disk.0[entries.clone()]);
+        gpt_header(&mut disk, 1, LAST_LBA, 2, count, entries_crc);
+        let (label, partitions) = disk.parse();
+        assert_eq!(label.scheme, PartitionScheme::Gpt);
+        assert_eq!(partitions.len(), 1);
+        assert_eq!(partitions[0].number, 1);
+
+        // 限制之后的表项同样受 CRC 保护
+        disk.0[entries.end - 1] ^= 0xFF;
+        let (label, partitions) = disk.parse();
+        assert_eq!(label.scheme, PartitionScheme::Gpt);
+        assert!(partitions.is_empty());
+    }
+
+    #[test]
+    fn gpt_entries_lba_overflow_emits_nothing() {
+        let count = GPT_ENTRIES * 2;
+        let mut disk = Disk::new();
+        let entries_crc = crc32(&vec![0; count as usize * 128]);
+        gpt_header(&mut disk, 1, LAST_LBA, u64::MAX, count, entries_crc);
+        let header = disk.block(1).to_vec();
+        // 任何 LBA 都能读到，头以外全为 0；表项数组的第二个块越过 u64
+        let mut read = |lba: u64, buf: &mut [u8]| {
+            match lba {
+                1 => buf.copy_from_slice(&header),
+                _ => buf.fill(0),
+            }
+            true
+        };
+        let mut buf = [0u8; BLOCK];
+        let mut partitions = Vec::new();
+        let label = parse(&mut buf, LAST_LBA, &mut read, &mut |partition| partitions.push(partition));
+        assert_eq!(label.scheme, PartitionScheme::Gpt);
+        assert!(partitions.is_empty());
+    }
+
+    #[test]
+    fn protective_mbr_only() {
+        let mut disk = gpt_disk();
+        disk.block(1)[16] ^= 0xFF;
+        disk.block(LAST_LBA)[16] ^= 0xFF;
+        let (label, partitions) = disk.parse();
+        assert_eq!(label.scheme, PartitionScheme::None);
+        assert!(partitions.is_empty());
+    }
+
+    #[test]
+    fn hybrid_mbr_skips_protective_entry() {
+        let mut disk = Disk::new();
+        let mbr = disk.block(0);
+        mbr_entry(mbr, 0, 0, MBR_PROTECTIVE_TYPE, 1, 33);
+        mbr_entry(mbr, 1, MBR_ACTIVE, 0x0C, 34, 16);
+        boot_signature(mbr);
+        let (label, partitions) = disk.parse();
+        assert_eq!(label.scheme, PartitionScheme::Mbr);
+        assert_eq!(partitions.len(), 1);
+        assert_eq!((partitions[0].number, partitions[0].mbr_type), (2, 0x0C));
+    }
+
+    #[test]
+    fn mbr_with_logical_partitions() {
+        let mut disk = Disk::new();
+        let mbr = disk.block(0);
+        put32(mbr, MBR_DISK_SIGNATURE_OFFSET, 0xDEAD_BEEF);
+        mbr_entry(mbr, 0, MBR_ACTIVE, 0x83, 2, 8);
+        mbr_entry(mbr, 1, 0, 0x0F, 16, 40);
+        boot_signature(mbr);
+        // 逻辑分区起点相对各自的 EBR，下一个 EBR 相对扩展分区起点
+        let ebr = disk.block(16);
+        mbr_entry(ebr, 0, 0, 0x83, 1, 10);
+        mbr_entry(ebr, 1, 0, 0x05, 20, 20);
+        boot_signature(ebr);
+        let ebr = disk.block(36);
+        mbr_entry(ebr, 0, 0, 0x82, 2, 8);
+        boot_signature(ebr);
+
+        let (label, partitions) = disk.parse();
+        assert_eq!(label.scheme, PartitionScheme::Mbr);
+        assert_eq!(label.mbr_signature, 0xDEAD_BEEF);
+        let layout: Vec<_> = partitions.iter().map(|p| (p.number, p.mbr_type, p.start_lba, p.block_count)).collect();
+        assert_eq!(layout, [(1, 0x83, 2, 8), (5, 0x83, 17, 10), (6, 0x82, 38, 8)]);
+        assert_eq!(partitions[0].attributes, PARTITION_ATTR_LEGACY_BOOTABLE);
+        assert_eq!(partitions[1].attributes, 0);
+    }
+
+    #[test]
+    fn ebr_loop_stops() {
+        let mut disk = Disk::new();
+        let mbr = disk.block(0);
+        mbr_entry(mbr, 0, 0, 0x05, 16, 40);
+        boot_signature(mbr);
+        let ebr = disk.block(16);
+        mbr_entry(ebr, 0, 0, 0x83, 1, 10);
+        mbr_entry(ebr, 1, 0, 0x05, 20, 20);
+        boot_signature(ebr);
+        // 第二个 EBR 指回第一个
+        let ebr = disk.block(36);
+        mbr_entry(ebr, 0, 0, 0x83, 1, 10);
+        mbr_entry(ebr, 1, 0, 0x05, 0, 20);
+        boot_signature(ebr);
+
+        let (label, partitions) = disk.parse();
+        assert_eq!(label.scheme, PartitionScheme::Mbr);
+        let numbers: Vec<_> = partitions.iter().map(|p| (p.number, p.start_lba)).collect();
+        assert_eq!(numbers, [(5, 17), (6, 37)]);
+    }
+
+    #[test]
+    fn superfloppy_fat_vbr() {
+        let mut disk = Disk::new();
+        let vbr = disk.block(0);
+        vbr[..11].copy_from_slice(b"\xEB\x3C\x90MSDOS5.0");
+        vbr[11..13].copy_from_slice(&512u16.to_le_bytes());
+        vbr[13] = 4;
+        vbr[14..16].copy_from_slice(&1u16.to_le_bytes());
+        vbr[16] = 2;
+        // 引导代码区的字节不应被当作分区表项
+        mbr_entry(vbr, 0, 0x80, 0x31, 0x1234, 0x5678);
+        boot_signature(vbr);
+        let (label, partitions) = disk.parse();
+        assert_eq!(label.scheme, PartitionScheme::None);
+        assert!(partitions.is_empty());
+    }
+
+    #[test]
+    fn no_boot_signature() {
+        let (label, partitions) = Disk::new().parse();
+        assert_eq!(label.scheme, PartitionScheme::None);
+        assert!(partitions.is_empty());
+    }
+}
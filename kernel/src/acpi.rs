@@ -0,0 +1,1203 @@
+//! ACPI 表
+//!
+//! 校验引导程序交接的 RSDP（ACPI 1.0 的 20 字节校验和与 2.0+ 的扩展校验和），
+//! 从 XSDT（没有或无效时用 RSDT）枚举系统描述表，并把内核用到的表解析为
+//! 类型化结构：
+//!
+//! | 签名 | 解析结果 |
+//! |------|----------|
+//! | `APIC` | [`Madt`]：处理器、IO-APIC、中断源覆盖、NMI |
+//! | `FACP` | [`Fadt`]：DSDT/FACS、PM 寄存器块、SCI、复位寄存器 |
+//! | `HPET` | [`HpetTable`] |
+//! | `MCFG` | [`Mcfg`]：PCIe ECAM 区域 |
+//! | `SRAT` / `SLIT` | [`Srat`] / [`Slit`]：NUMA 亲和性与距离 |
+//! | `DMAR` | [`Dmar`]：VT-d 重映射单元与保留内存 |
+//!
+//! 表都通过直接映射访问，必须在分页初始化之后使用。校验和错误的表仍然
+//! 可以查到（不少固件带着错误的校验和），[`dump`] 会把它们标出来。
+
+use crate::firmware::{checksum, phys_bytes, read_u16, read_u32, read_u64};
+use crate::paging::{self, CacheMode};
+use crate::pci;
+use crate::port::{inb, inl, inw, outb, outl, outw};
+use crate::serial::{
+    serial_write, serial_write_char, serial_write_dec, serial_write_hex, serial_write_size,
+};
+use crate::sync::SpinLock;
+use alloc::vec::Vec;
+use boot_protocol::{BootInfo, PAGE_SIZE};
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+/// 根表物理地址，0 表示没有 ACPI
+static ROOT: AtomicU64 = AtomicU64::new(0);
+/// 根表是 XSDT（表项 8 字节）而不是 RSDT（4 字节）
+static ROOT_IS_XSDT: AtomicBool = AtomicBool::new(false);
+/// SystemMemory 寄存器所在页的映射：(物理页, 虚拟地址)，虚拟地址 0 为空槽
+static REGISTER_PAGES: SpinLock<[(u64, u64); REGISTER_PAGE_SLOTS]> =
+    SpinLock::new([(0, 0); REGISTER_PAGE_SLOTS]);
+
+/// 系统描述表头长度
+const SDT_HEADER_LEN: usize = 36;
+/// ACPI 1.0 RSDP 长度
+const RSDP_V1_LEN: usize = 20;
+/// ACPI 2.0+ RSDP 长度
+const RSDP_V2_LEN: usize = 36;
+/// 缓存映射的寄存器页数（FADT 的寄存器块只分布在少数几页内）
+const REGISTER_PAGE_SLOTS: usize = 16;
+
+/// 初始化失败原因
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AcpiError {
+    /// 引导程序没有找到 RSDP
+    NotPresent,
+    /// RSDP 签名不是 `RSD PTR `
+    BadSignature,
+    /// RSDP 校验和错误
+    BadChecksum,
+    /// RSDT 与 XSDT 都无效
+    BadRootTable,
+}
+
+impl AcpiError {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::NotPresent => "no RSDP",
+            Self::BadSignature => "bad RSDP signature",
+            Self::BadChecksum => "bad RSDP checksum",
+            Self::BadRootTable => "no valid RSDT or XSDT",
+        }
+    }
+}
+
+/// 解析后的 RSDP
+#[derive(Clone, Copy, Debug)]
+pub struct Rsdp {
+    /// 物理地址
+    pub address: u64,
+    /// 0 表示 ACPI 1.0，2 表示 2.0+
+    pub revision: u8,
+    pub oem_id: [u8; 6],
+    /// 使用中的根表物理地址
+    pub root: u64,
+    /// 根表是 XSDT
+    pub xsdt: bool,
+}
+
+/// 一张系统描述表
+#[derive(Clone, Copy, Debug)]
+pub struct Table {
+    /// 物理地址
+    pub address: u64,
+    /// 整张表（含表头）
+    pub bytes: &'static [u8],
+}
+
+/// 系统描述表头
+#[derive(Clone, Copy, Debug)]
+pub struct SdtHeader {
+    pub signature: [u8; 4],
+    pub length: u32,
+    pub revision: u8,
+    /// 全表字节和为 0
+    pub checksum_ok: bool,
+    pub oem_id: [u8; 6],
+    pub oem_table_id: [u8; 8],
+    pub oem_revision: u32,
+    pub creator_id: [u8; 4],
+    pub creator_revision: u32,
+}
+
+impl Table {
+    /// 解码表头
+    pub fn header(&self) -> SdtHeader {
+        let bytes = self.bytes;
+        SdtHeader {
+            signature: bytes[0..4].try_into().unwrap(),
+            length: read_u32(bytes, 4),
+            revision: bytes[8],
+            checksum_ok: checksum(bytes),
+            oem_id: bytes[10..16].try_into().unwrap(),
+            oem_table_id: bytes[16..24].try_into().unwrap(),
+            oem_revision: read_u32(bytes, 24),
+            creator_id: bytes[28..32].try_into().unwrap(),
+            creator_revision: read_u32(bytes, 32),
+        }
+    }
+
+    pub fn signature(&self) -> &[u8] {
+        &self.bytes[..4]
+    }
+}
+
+/// 校验 RSDP 并选出根表；只在分页初始化之后调用一次
+pub fn init(info: &BootInfo) -> Result<Rsdp, AcpiError> {
+    let (address, _) = info.acpi_rsdp().ok_or(AcpiError::NotPresent)?;
+    let v1 = phys_bytes(address, RSDP_V1_LEN);
+    if &v1[..8] != b"RSD PTR " {
+        return Err(AcpiError::BadSignature);
+    }
+    if !checksum(v1) {
+        return Err(AcpiError::BadChecksum);
+    }
+    let revision = v1[15];
+    let mut root = None;
+    if revision >= 2 {
+        let length = read_u32(phys_bytes(address, RSDP_V2_LEN), 20) as usize;
+        if length < RSDP_V2_LEN || !checksum(phys_bytes(address, length)) {
+            return Err(AcpiError::BadChecksum);
+        }
+        let xsdt = read_u64(phys_bytes(address, RSDP_V2_LEN), 24);
+        root = table_at(xsdt)
+            .filter(|t| t.signature() == b"XSDT")
+            .map(|t| (t, true));
+    }
+    let rsdt = read_u32(v1, 16) as u64;
+    let (root, xsdt) = root
+        .or_else(|| {
+            table_at(rsdt)
+                .filter(|t| t.signature() == b"RSDT")
+                .map(|t| (t, false))
+        })
+        .ok_or(AcpiError::BadRootTable)?;
+
+    ROOT.store(root.address, Ordering::Relaxed);
+    ROOT_IS_XSDT.store(xsdt, Ordering::Relaxed);
+    Ok(Rsdp {
+        address,
+        revision,
+        oem_id: v1[9..15].try_into().unwrap(),
+        root: root.address,
+        xsdt,
+    })
+}
+
+/// [`init`] 已找到有效的根表
+pub fn available() -> bool {
+    ROOT.load(Ordering::Relaxed) != 0
+}
+
+/// 物理地址处的整张系统描述表，长度取自表头
+fn table_at(address: u64) -> Option<Table> {
+    if address == 0 {
+        return None;
+    }
+    let length = read_u32(phys_bytes(address, SDT_HEADER_LEN), 4) as usize;
+    (length >= SDT_HEADER_LEN).then(|| Table {
+        address,
+        bytes: phys_bytes(address, length),
+    })
+}
+
+/// 根表（XSDT 或 RSDT）
+fn root_table() -> Option<Table> {
+    table_at(ROOT.load(Ordering::Relaxed))
+}
+
+/// 根表列出的所有系统描述表（不含 DSDT 与 FACS，它们由 FADT 引用）
+pub fn tables() -> impl Iterator<Item = Table> {
+    let entry_size = if ROOT_IS_XSDT.load(Ordering::Relaxed) {
+        8
+    } else {
+        4
+    };
+    let entries = root_table().map_or(&[][..], |root| &root.bytes[SDT_HEADER_LEN..]);
+    entries
+        .chunks_exact(entry_size)
+        .map(move |entry| match entry_size {
+            8 => read_u64(entry, 0),
+            _ => read_u32(entry, 0) as u64,
+        })
+        .filter_map(table_at)
+}
+
+/// 按签名查找第一张系统描述表；`DSDT` 通过 FADT 查找
+pub fn find_table(signature: &[u8; 4]) -> Option<Table> {
+    if signature == b"DSDT" {
+        return table_at(fadt()?.dsdt);
+    }
+    tables().find(|table| table.signature() == signature)
+}
+
+// ============================================================================
+// 通用地址结构
+// ============================================================================
+
+/// 系统内存地址空间
+pub const SPACE_SYSTEM_MEMORY: u8 = 0;
+/// 系统 I/O 地址空间
+pub const SPACE_SYSTEM_IO: u8 = 1;
+/// PCI 配置空间
+pub const SPACE_PCI_CONFIG: u8 = 2;
+
+/// ACPI 通用地址结构 (GAS)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GenericAddress {
+    pub space: u8,
+    pub bit_width: u8,
+    pub bit_offset: u8,
+    /// 0 未定义，1..=4 对应 1/2/4/8 字节访问
+    pub access_size: u8,
+    pub address: u64,
+}
+
+impl GenericAddress {
+    /// 读取 12 字节的 GAS；地址为 0 时为 None
+    fn parse(bytes: &[u8], offset: usize) -> Option<Self> {
+        let gas = Self {
+            space: bytes[offset],
+            bit_width: bytes[offset + 1],
+            bit_offset: bytes[offset + 2],
+            access_size: bytes[offset + 3],
+            address: read_u64(bytes, offset + 4),
+        };
+        (gas.address != 0).then_some(gas)
+    }
+
+    /// ACPI 1.0 风格的 I/O 端口块
+    fn io_port(port: u32, len: u8) -> Option<Self> {
+        (port != 0).then_some(Self {
+            space: SPACE_SYSTEM_IO,
+            bit_width: len.saturating_mul(8),
+            bit_offset: 0,
+            access_size: 0,
+            address: port as u64,
+        })
+    }
+
+    /// 访问宽度（位）：优先使用 `access_size`，否则取寄存器位宽
+    fn access_bits(&self) -> usize {
+        match self.access_size {
+            1..=4 => 8 << (self.access_size - 1),
+            _ => match self.bit_width {
+                0..=8 => 8,
+                9..=16 => 16,
+                17..=32 => 32,
+                _ => 64,
+            },
+        }
+    }
+
+    /// 读寄存器；不支持的地址空间或映射失败时为 None
+    ///
+    /// SystemMemory 寄存器所在页第一次访问时映射，之后复用同一映射。
+    pub fn read(&self) -> Option<u64> {
+        self.access(None)
+    }
+
+    /// 写寄存器，返回是否写入
+    pub fn write(&self, value: u64) -> bool {
+        self.access(Some(value)).is_some()
+    }
+
+    fn access(&self, write: Option<u64>) -> Option<u64> {
+        let width = self.access_bits();
+        match self.space {
+            SPACE_SYSTEM_MEMORY => {
+                let virt = map_register(self.address)?;
+                unsafe {
+                    match write {
+                        None => Some(match width {
+                            8 => (virt as *const u8).read_volatile() as u64,
+                            16 => (virt as *const u16).read_volatile() as u64,
+                            32 => (virt as *const u32).read_volatile() as u64,
+                            _ => (virt as *const u64).read_volatile(),
+                        }),
+                        Some(value) => {
+                            match width {
+                                8 => (virt as *mut u8).write_volatile(value as u8),
+                                16 => (virt as *mut u16).write_volatile(value as u16),
+                                32 => (virt as *mut u32).write_volatile(value as u32),
+                                _ => (virt as *mut u64).write_volatile(value),
+                            }
+                            Some(0)
+                        }
+                    }
+                }
+            }
+            // I/O 端口最多按 32 位访问
+            SPACE_SYSTEM_IO => {
+                let port = self.address as u16;
+                unsafe {
+                    match write {
+                        None => Some(match width {
+                            8 => inb(port) as u64,
+                            16 => inw(port) as u64,
+                            _ => inl(port) as u64,
+                        }),
+                        Some(value) => {
+                            match width {
+                                8 => outb(port, value as u8),
+                                16 => outw(port, value as u16),
+                                _ => outl(port, value as u32),
+                            }
+                            Some(0)
+                        }
+                    }
+                }
+            }
+            // 地址为 设备 << 32 | 功能 << 16 | 偏移，总线 0
+            SPACE_PCI_CONFIG => {
+                let device = (self.address >> 32) as u8;
+                let function = (self.address >> 16) as u8;
+                let address = pci::Address::new(0, 0, device, function);
+                let offset = self.address as u16;
+                match write {
+                    None => Some(pci::config_read(address, offset, width)),
+                    Some(value) => {
+                        pci::config_write(address, offset, width, value);
+                        Some(0)
+                    }
+                }
+            }
+            _ => None,
+        }
+    }
+
+    pub const fn space_name(&self) -> &'static str {
+        match self.space {
+            SPACE_SYSTEM_MEMORY => "mem",
+            SPACE_SYSTEM_IO => "io",
+            SPACE_PCI_CONFIG => "pci",
+            _ => "other",
+        }
+    }
+}
+
+/// 取得 SystemMemory 寄存器的虚拟地址，寄存器所在页只映射一次
+///
+/// 寄存器按访问宽度自然对齐，不会跨页。缓存已满时返回 None。
+fn map_register(phys: u64) -> Option<u64> {
+    let page = phys & !(PAGE_SIZE - 1);
+    let mut pages = REGISTER_PAGES.lock();
+    if let Some(&(_, virt)) = pages.iter().find(|&&(p, virt)| virt != 0 && p == page) {
+        return Some(virt + (phys - page));
+    }
+    let slot = pages.iter_mut().find(|(_, virt)| *virt == 0)?;
+    let virt = paging::map_mmio(page, PAGE_SIZE, CacheMode::Uncached).ok()?;
+    *slot = (page, virt);
+    Some(virt + (phys - page))
+}
+
+// ============================================================================
+// MADT
+// ============================================================================
+
+/// MADT 中的处理器本地 APIC（类型 0）或本地 x2APIC（类型 9）
+#[derive(Clone, Copy, Debug)]
+pub struct MadtProcessor {
+    pub processor_uid: u32,
+    pub apic_id: u32,
+    /// 已启用或可以上线
+    pub usable: bool,
+}
+
+/// MADT 中的 IO-APIC（类型 1）
+#[derive(Clone, Copy, Debug)]
+pub struct MadtIoApic {
+    pub id: u8,
+    pub address: u32,
+    /// 第一个输入引脚对应的全局系统中断号
+    pub gsi_base: u32,
+}
+
+/// MADT 中的中断源覆盖（类型 2）：ISA IRQ `source` 接到 `gsi`
+#[derive(Clone, Copy, Debug)]
+pub struct InterruptOverride {
+    pub source: u8,
+    pub gsi: u32,
+    /// MPS INTI 标志（极性位 0-1，触发方式位 2-3）
+    pub flags: u16,
+}
+
+/// MADT 中的本地 APIC NMI（类型 4）或本地 x2APIC NMI（类型 0xA）
+#[derive(Clone, Copy, Debug)]
+pub struct LocalApicNmi {
+    /// 处理器 UID，[`ALL_PROCESSORS`] 表示全部
+    pub processor_uid: u32,
+    /// LINT0 / LINT1
+    pub lint: u8,
+    /// MPS INTI 标志
+    pub flags: u16,
+}
+
+/// [`LocalApicNmi::processor_uid`] 表示所有处理器
+pub const ALL_PROCESSORS: u32 = u32::MAX;
+
+/// 解析后的 MADT
+#[derive(Debug)]
+pub struct Madt {
+    /// 本地 APIC 物理地址（已应用类型 5 的 64 位覆盖）
+    pub local_apic_addr: u64,
+    /// 系统还带有双 8259 PIC
+    pub pcat_compat: bool,
+    pub processors: Vec<MadtProcessor>,
+    pub io_apics: Vec<MadtIoApic>,
+    pub overrides: Vec<InterruptOverride>,
+    pub nmis: Vec<LocalApicNmi>,
+}
+
+/// 解析 MADT（签名 "APIC"）
+pub fn madt() -> Option<Madt> {
+    let table = find_table(b"APIC")?.bytes;
+    if table.len() < SDT_HEADER_LEN + 8 {
+        return None;
+    }
+    let mut madt = Madt {
+        local_apic_addr: read_u32(table, SDT_HEADER_LEN) as u64,
+        pcat_compat: read_u32(table, SDT_HEADER_LEN + 4) & 1 != 0,
+        processors: Vec::new(),
+        io_apics: Vec::new(),
+        overrides: Vec::new(),
+        nmis: Vec::new(),
+    };
+
+    for (kind, entry) in subtables(table, SDT_HEADER_LEN + 8, SubtableHeader::U8) {
+        match (kind, entry.len()) {
+            (0, 8..) => madt.processors.push(MadtProcessor {
+                processor_uid: entry[2] as u32,
+                apic_id: entry[3] as u32,
+                usable: read_u32(entry, 4) & 0b11 != 0,
+            }),
+            (1, 12..) => madt.io_apics.push(MadtIoApic {
+                id: entry[2],
+                address: read_u32(entry, 4),
+                gsi_base: read_u32(entry, 8),
+            }),
+            (2, 10..) => madt.overrides.push(InterruptOverride {
+                source: entry[3],
+                gsi: read_u32(entry, 4),
+                flags: read_u16(entry, 8),
+            }),
+            (4, 6..) => madt.nmis.push(LocalApicNmi {
+                processor_uid: match entry[2] {
+                    0xFF => ALL_PROCESSORS,
+                    uid => uid as u32,
+                },
+                lint: entry[5],
+                flags: read_u16(entry, 3),
+            }),
+            (5, 12..) => madt.local_apic_addr = read_u64(entry, 4),
+            (9, 16..) => madt.processors.push(MadtProcessor {
+                processor_uid: read_u32(entry, 12),
+                apic_id: read_u32(entry, 4),
+                usable: read_u32(entry, 8) & 0b11 != 0,
+            }),
+            (0xA, 12..) => madt.nmis.push(LocalApicNmi {
+                processor_uid: read_u32(entry, 4),
+                lint: entry[8],
+                flags: read_u16(entry, 2),
+            }),
+            _ => {}
+        }
+    }
+    Some(madt)
+}
+
+// ============================================================================
+// FADT
+// ============================================================================
+
+/// IA-PC 启动架构标志：有 8042 键盘控制器
+pub const BOOT_ARCH_8042: u16 = 1 << 1;
+/// IA-PC 启动架构标志：没有 CMOS RTC
+pub const BOOT_ARCH_NO_CMOS_RTC: u16 = 1 << 5;
+/// FADT 标志：支持复位寄存器
+pub const FADT_RESET_REG_SUP: u32 = 1 << 10;
+/// FADT 标志：硬件精简 ACPI（没有 PM 寄存器块）
+pub const FADT_HW_REDUCED: u32 = 1 << 20;
+
+/// 解析后的 FADT（签名 "FACP"）；有 64 位 X_ 字段时优先使用
+#[derive(Clone, Copy, Debug)]
+pub struct Fadt {
+    pub revision: u8,
+    pub minor_revision: u8,
+    /// DSDT 物理地址
+    pub dsdt: u64,
+    /// FACS 物理地址（硬件精简 ACPI 可以没有）
+    pub facs: u64,
+    pub preferred_pm_profile: u8,
+    /// SCI 中断（8259 模式下的 ISA IRQ，APIC 模式下的 GSI）
+    pub sci_interrupt: u16,
+    pub smi_command: u32,
+    pub acpi_enable: u8,
+    pub acpi_disable: u8,
+    pub pm1a_event: Option<GenericAddress>,
+    pub pm1b_event: Option<GenericAddress>,
+    pub pm1a_control: Option<GenericAddress>,
+    pub pm1b_control: Option<GenericAddress>,
+    pub pm_timer: Option<GenericAddress>,
+    pub gpe0: Option<GenericAddress>,
+    pub gpe1: Option<GenericAddress>,
+    /// CMOS 中世纪字段的索引，0 表示没有
+    pub century: u8,
+    pub boot_arch: u16,
+    pub flags: u32,
+    pub reset_register: Option<GenericAddress>,
+    pub reset_value: u8,
+    /// 硬件精简 ACPI 的睡眠控制寄存器
+    pub sleep_control: Option<GenericAddress>,
+}
+
+impl Fadt {
+    /// 电源管理配置文件名称
+    pub const fn pm_profile_name(&self) -> &'static str {
+        match self.preferred_pm_profile {
+            1 => "Desktop",
+            2 => "Mobile",
+            3 => "Workstation",
+            4 => "Enterprise Server",
+            5 => "SOHO Server",
+            6 => "Appliance PC",
+            7 => "Performance Server",
+            8 => "Tablet",
+            _ => "Unspecified",
+        }
+    }
+
+    /// 可以通过复位寄存器重启
+    pub fn reset_supported(&self) -> bool {
+        self.flags & FADT_RESET_REG_SUP != 0 && self.reset_register.is_some()
+    }
+}
+
+/// 解析 FADT
+pub fn fadt() -> Option<Fadt> {
+    let table = tables().find(|table| table.signature() == b"FACP")?;
+    let bytes = table.bytes;
+    if bytes.len() < 116 {
+        return None;
+    }
+    // ACPI 2.0+ 的扩展字段：表足够长且 64 位地址非 0 时覆盖 32 位字段
+    let extended = |offset: usize| {
+        (bytes.len() >= offset + 12)
+            .then(|| GenericAddress::parse(bytes, offset))
+            .flatten()
+    };
+    let address64 = |offset: usize| {
+        (bytes.len() >= offset + 8)
+            .then(|| read_u64(bytes, offset))
+            .filter(|&a| a != 0)
+    };
+    let block = |legacy: usize, len: usize, x: usize| {
+        extended(x).or_else(|| GenericAddress::io_port(read_u32(bytes, legacy), bytes[len]))
+    };
+
+    Some(Fadt {
+        revision: bytes[8],
+        minor_revision: if bytes.len() > 131 {
+            bytes[131] & 0xF
+        } else {
+            0
+        },
+        dsdt: address64(140).unwrap_or(read_u32(bytes, 40) as u64),
+        facs: address64(132).unwrap_or(read_u32(bytes, 36) as u64),
+        preferred_pm_profile: bytes[45],
+        sci_interrupt: read_u16(bytes, 46),
+        smi_command: read_u32(bytes, 48),
+        acpi_enable: bytes[52],
+        acpi_disable: bytes[53],
+        pm1a_event: block(56, 88, 148),
+        pm1b_event: block(60, 88, 160),
+        pm1a_control: block(64, 89, 172),
+        pm1b_control: block(68, 89, 184),
+        pm_timer: block(76, 91, 208),
+        gpe0: block(80, 92, 220),
+        gpe1: block(84, 93, 232),
+        century: bytes[108],
+        boot_arch: read_u16(bytes, 109),
+        flags: read_u32(bytes, 112),
+        reset_register: if bytes.len() >= 129 {
+            GenericAddress::parse(bytes, 116)
+        } else {
+            None
+        },
+        reset_value: if bytes.len() >= 129 { bytes[128] } else { 0 },
+        sleep_control: extended(244),
+    })
+}
+
+// ============================================================================
+// HPET
+// ============================================================================
+
+/// HPET 表描述的一个事件定时器块
+#[derive(Clone, Copy, Debug)]
+pub struct HpetTable {
+    /// 寄存器物理地址
+    pub address: u64,
+    /// 事件定时器块 ID（与能力寄存器低 32 位相同）
+    pub block_id: u32,
+    /// HPET 序号
+    pub number: u8,
+    /// 周期模式下不丢中断的最小计数
+    pub min_tick: u16,
+    /// 页保护与 OEM 属性
+    pub page_protection: u8,
+}
+
+/// 解析 HPET 表（只接受内存地址空间中的寄存器块）
+pub fn hpet() -> Option<HpetTable> {
+    let table = find_table(b"HPET")?.bytes;
+    if table.len() < 56 {
+        return None;
+    }
+    let address = GenericAddress::parse(table, 40).filter(|gas| gas.space == SPACE_SYSTEM_MEMORY)?;
+    Some(HpetTable {
+        address: address.address,
+        block_id: read_u32(table, 36),
+        number: table[52],
+        min_tick: read_u16(table, 53),
+        page_protection: table[55],
+    })
+}
+
+// ============================================================================
+// MCFG
+// ============================================================================
+
+/// 一段 PCIe 增强配置空间 (ECAM)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct McfgEntry {
+    /// 总线 0（不是 `start_bus`）对应的物理基址
+    pub base: u64,
+    pub segment: u16,
+    pub start_bus: u8,
+    pub end_bus: u8,
+}
+
+impl McfgEntry {
+    /// 这段配置空间的字节数（每条总线 1 MiB）
+    pub fn size(&self) -> u64 {
+        (self.end_bus as u64 - self.start_bus as u64 + 1) << 20
+    }
+}
+
+/// 解析后的 MCFG
+#[derive(Clone, Debug)]
+pub struct Mcfg {
+    pub entries: Vec<McfgEntry>,
+}
+
+/// 解析 MCFG
+pub fn mcfg() -> Option<Mcfg> {
+    let table = find_table(b"MCFG")?.bytes;
+    let entries = table
+        .get(44..)?
+        .as_chunks::<16>()
+        .0
+        .iter()
+        .map(|entry| McfgEntry {
+            base: read_u64(entry, 0),
+            segment: read_u16(entry, 8),
+            start_bus: entry[10],
+            end_bus: entry[11],
+        })
+        .filter(|entry| entry.start_bus <= entry.end_bus)
+        .collect();
+    Some(Mcfg { entries })
+}
+
+// ============================================================================
+// SRAT / SLIT
+// ============================================================================
+
+/// SRAT 中的处理器亲和性（本地 APIC 或 x2APIC）
+#[derive(Clone, Copy, Debug)]
+pub struct SratProcessor {
+    pub proximity_domain: u32,
+    pub apic_id: u32,
+    pub enabled: bool,
+}
+
+/// SRAT 中的内存亲和性
+#[derive(Clone, Copy, Debug)]
+pub struct SratMemory {
+    pub proximity_domain: u32,
+    pub base: u64,
+    pub length: u64,
+    pub enabled: bool,
+    pub hot_pluggable: bool,
+    pub non_volatile: bool,
+}
+
+/// 解析后的 SRAT
+#[derive(Clone, Debug)]
+pub struct Srat {
+    pub processors: Vec<SratProcessor>,
+    pub memory: Vec<SratMemory>,
+}
+
+impl Srat {
+    /// 出现过的不同邻近域个数
+    pub fn domain_count(&self) -> usize {
+        let mut domains: Vec<u32> = self
+            .processors
+            .iter()
+            .map(|p| p.proximity_domain)
+            .chain(self.memory.iter().map(|m| m.proximity_domain))
+            .collect();
+        domains.sort_unstable();
+        domains.dedup();
+        domains.len()
+    }
+}
+
+/// 解析 SRAT
+pub fn srat() -> Option<Srat> {
+    let table = find_table(b"SRAT")?.bytes;
+    let mut srat = Srat {
+        processors: Vec::new(),
+        memory: Vec::new(),
+    };
+    for (kind, entry) in subtables(table, 48, SubtableHeader::U8) {
+        match (kind, entry.len()) {
+            (0, 16..) => srat.processors.push(SratProcessor {
+                proximity_domain: entry[2] as u32
+                    | (u32::from_le_bytes([entry[9], entry[10], entry[11], 0]) << 8),
+                apic_id: entry[3] as u32,
+                enabled: read_u32(entry, 4) & 1 != 0,
+            }),
+            (1, 40..) => {
+                let flags = read_u32(entry, 28);
+                srat.memory.push(SratMemory {
+                    proximity_domain: read_u32(entry, 2),
+                    base: read_u64(entry, 8),
+                    length: read_u64(entry, 16),
+                    enabled: flags & 1 != 0,
+                    hot_pluggable: flags & (1 << 1) != 0,
+                    non_volatile: flags & (1 << 2) != 0,
+                });
+            }
+            (2, 24..) => srat.processors.push(SratProcessor {
+                proximity_domain: read_u32(entry, 4),
+                apic_id: read_u32(entry, 8),
+                enabled: read_u32(entry, 12) & 1 != 0,
+            }),
+            _ => {}
+        }
+    }
+    Some(srat)
+}
+
+/// 解析后的 SLIT：邻近域之间的相对距离（本地为 10）
+#[derive(Clone, Copy, Debug)]
+pub struct Slit {
+    pub localities: usize,
+    distances: &'static [u8],
+}
+
+impl Slit {
+    /// 从 `from` 到 `to` 的距离；255 表示不可达
+    pub fn distance(&self, from: usize, to: usize) -> Option<u8> {
+        (from < self.localities && to < self.localities)
+            .then(|| self.distances[from * self.localities + to])
+    }
+}
+
+/// 解析 SLIT
+pub fn slit() -> Option<Slit> {
+    let table = find_table(b"SLIT")?.bytes;
+    let localities = usize::try_from(read_u64(table.get(..44)?, 36)).ok()?;
+    let distances = table.get(44..44 + localities.checked_mul(localities)?)?;
+    Some(Slit {
+        localities,
+        distances,
+    })
+}
+
+// ============================================================================
+// DMAR
+// ============================================================================
+
+/// DMAR 标志：支持中断重映射
+pub const DMAR_INTR_REMAP: u8 = 1 << 0;
+/// DMAR 标志：固件要求不要启用 x2APIC
+pub const DMAR_X2APIC_OPT_OUT: u8 = 1 << 1;
+
+/// DMA 重映射硬件单元 (DRHD)
+#[derive(Clone, Copy, Debug)]
+pub struct DmarUnit {
+    pub segment: u16,
+    /// 寄存器物理基址
+    pub register_base: u64,
+    /// 负责段内所有未被其他单元列出的设备
+    pub include_pci_all: bool,
+    /// 设备作用域项数
+    pub scopes: usize,
+}
+
+/// 保留内存区域 (RMRR)：设备在 DMA 重映射启用后仍会访问
+#[derive(Clone, Copy, Debug)]
+pub struct DmarReservedRegion {
+    pub segment: u16,
+    pub base: u64,
+    /// 最后一个字节的地址
+    pub limit: u64,
+    pub scopes: usize,
+}
+
+/// 解析后的 DMAR
+#[derive(Clone, Debug)]
+pub struct Dmar {
+    /// DMA 物理地址宽度（位）
+    pub host_address_width: u8,
+    pub flags: u8,
+    pub units: Vec<DmarUnit>,
+    pub reserved_regions: Vec<DmarReservedRegion>,
+    /// 其他类型的结构（ATSR、RHSA、ANDD、SATC）个数
+    pub other_structures: usize,
+}
+
+/// 解析 DMAR
+pub fn dmar() -> Option<Dmar> {
+    let table = find_table(b"DMAR")?.bytes;
+    if table.len() < 48 {
+        return None;
+    }
+    let mut dmar = Dmar {
+        host_address_width: table[36] + 1,
+        flags: table[37],
+        units: Vec::new(),
+        reserved_regions: Vec::new(),
+        other_structures: 0,
+    };
+    for (kind, entry) in subtables(table, 48, SubtableHeader::U16) {
+        match (kind, entry.len()) {
+            (0, 16..) => dmar.units.push(DmarUnit {
+                segment: read_u16(entry, 6),
+                register_base: read_u64(entry, 8),
+                include_pci_all: entry[4] & 1 != 0,
+                scopes: device_scopes(&entry[16..]),
+            }),
+            (1, 24..) => dmar.reserved_regions.push(DmarReservedRegion {
+                segment: read_u16(entry, 6),
+                base: read_u64(entry, 8),
+                limit: read_u64(entry, 16),
+                scopes: device_scopes(&entry[24..]),
+            }),
+            _ => dmar.other_structures += 1,
+        }
+    }
+    Some(dmar)
+}
+
+/// 设备作用域项数（每项以类型、长度两个字节开头）
+fn device_scopes(mut bytes: &[u8]) -> usize {
+    let mut count = 0;
+    while bytes.len() >= 2 && bytes[1] >= 2 && bytes[1] as usize <= bytes.len() {
+        count += 1;
+        bytes = &bytes[bytes[1] as usize..];
+    }
+    count
+}
+
+// ============================================================================
+// 子表遍历
+// ============================================================================
+
+/// 子表头格式：MADT/SRAT 为 1 字节类型 + 1 字节长度，DMAR 为 2 + 2 字节
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SubtableHeader {
+    U8,
+    U16,
+}
+
+/// 从 `start` 开始遍历 (类型, 整个子表) 直到表尾或遇到无效长度
+fn subtables(
+    table: &'static [u8],
+    start: usize,
+    header: SubtableHeader,
+) -> impl Iterator<Item = (u16, &'static [u8])> {
+    let mut offset = start;
+    core::iter::from_fn(move || {
+        let (kind, len) = match header {
+            SubtableHeader::U8 if offset + 2 <= table.len() => {
+                (table[offset] as u16, table[offset + 1] as usize)
+            }
+            SubtableHeader::U16 if offset + 4 <= table.len() => (
+                read_u16(table, offset),
+                read_u16(table, offset + 2) as usize,
+            ),
+            _ => return None,
+        };
+        let min = if header == SubtableHeader::U8 { 2 } else { 4 };
+        if len < min || offset + len > table.len() {
+            return None;
+        }
+        let entry = &table[offset..offset + len];
+        offset += len;
+        Some((kind, entry))
+    })
+}
+
+// ============================================================================
+// 串口输出
+// ============================================================================
+
+/// 输出 RSDP、所有表头以及各个已解析表的内容
+pub fn dump(rsdp: &Rsdp) {
+    serial_write("  RSDP:           ");
+    serial_write_hex(rsdp.address);
+    serial_write(", revision ");
+    serial_write_dec(rsdp.revision as u64);
+    serial_write(" (ACPI ");
+    serial_write(if rsdp.revision >= 2 { "2.0+" } else { "1.0" });
+    serial_write("), OEM '");
+    write_ascii(&rsdp.oem_id);
+    serial_write("'\n  Root table:     ");
+    serial_write(if rsdp.xsdt { "XSDT at " } else { "RSDT at " });
+    serial_write_hex(rsdp.root);
+    serial_write("\n  Tables:\n");
+    let fadt = fadt();
+    for table in tables().chain(fadt.and_then(|fadt| table_at(fadt.dsdt))) {
+        write_table_header(&table);
+    }
+
+    if let Some(fadt) = fadt {
+        serial_write("  FADT:           revision ");
+        serial_write_dec(fadt.revision as u64);
+        serial_write(".");
+        serial_write_dec(fadt.minor_revision as u64);
+        serial_write(", profile ");
+        serial_write(fadt.pm_profile_name());
+        serial_write(", SCI ");
+        serial_write_dec(fadt.sci_interrupt as u64);
+        if fadt.flags & FADT_HW_REDUCED != 0 {
+            serial_write(", hardware-reduced");
+        }
+        serial_write("\n    FACS ");
+        serial_write_hex(fadt.facs);
+        serial_write(", SMI command ");
+        serial_write_hex(fadt.smi_command as u64);
+        serial_write(" (enable ");
+        serial_write_hex(fadt.acpi_enable as u64);
+        serial_write(", disable ");
+        serial_write_hex(fadt.acpi_disable as u64);
+        serial_write(")\n");
+        for (name, gas) in [
+            ("PM1a event", fadt.pm1a_event),
+            ("PM1b event", fadt.pm1b_event),
+            ("PM1a control", fadt.pm1a_control),
+            ("PM1b control", fadt.pm1b_control),
+            ("PM timer", fadt.pm_timer),
+            ("GPE0", fadt.gpe0),
+            ("GPE1", fadt.gpe1),
+        ] {
+            if let Some(gas) = gas {
+                serial_write("    ");
+                serial_write(name);
+                serial_write(" ");
+                write_gas(&gas);
+                serial_write("\n");
+            }
+        }
+        if let Some(gas) = fadt.sleep_control {
+            serial_write("    Sleep control ");
+            write_gas(&gas);
+            serial_write("\n");
+        }
+        serial_write("    Reset register ");
+        match fadt.reset_register.filter(|_| fadt.reset_supported()) {
+            Some(gas) => {
+                write_gas(&gas);
+                serial_write(" <- ");
+                serial_write_hex(fadt.reset_value as u64);
+            }
+            None => serial_write("not supported"),
+        }
+        serial_write("\n    Boot flags ");
+        serial_write_hex(fadt.boot_arch as u64);
+        if fadt.boot_arch & BOOT_ARCH_8042 != 0 {
+            serial_write(" [8042]");
+        }
+        if fadt.boot_arch & BOOT_ARCH_NO_CMOS_RTC != 0 {
+            serial_write(" [no CMOS RTC]");
+        }
+        if fadt.century != 0 {
+            serial_write(", CMOS century index ");
+            serial_write_hex(fadt.century as u64);
+        }
+        serial_write("\n");
+    }
+
+    if let Some(madt) = madt() {
+        serial_write("  MADT:           ");
+        serial_write_dec(madt.processors.len() as u64);
+        serial_write(" processors, ");
+        serial_write_dec(madt.io_apics.len() as u64);
+        serial_write(" IO-APICs, ");
+        serial_write_dec(madt.overrides.len() as u64);
+        serial_write(" overrides, ");
+        serial_write_dec(madt.nmis.len() as u64);
+        serial_write(" NMI sources\n");
+    }
+
+    if let Some(hpet) = hpet() {
+        serial_write("  HPET:           ");
+        serial_write_hex(hpet.address);
+        serial_write(", block ID ");
+        serial_write_hex(hpet.block_id as u64);
+        serial_write(", number ");
+        serial_write_dec(hpet.number as u64);
+        serial_write(", min tick ");
+        serial_write_dec(hpet.min_tick as u64);
+        serial_write(", protection ");
+        serial_write_hex(hpet.page_protection as u64);
+        serial_write("\n");
+    }
+
+    if let Some(mcfg) = mcfg() {
+        for entry in &mcfg.entries {
+            serial_write("  MCFG:           segment ");
+            serial_write_dec(entry.segment as u64);
+            serial_write(" bus ");
+            serial_write_dec(entry.start_bus as u64);
+            serial_write("-");
+            serial_write_dec(entry.end_bus as u64);
+            serial_write(" at ");
+            serial_write_hex(entry.base);
+            serial_write(" (");
+            serial_write_size(entry.size());
+            serial_write(")\n");
+        }
+    }
+
+    if let Some(srat) = srat() {
+        serial_write("  SRAT:           ");
+        serial_write_dec(srat.domain_count() as u64);
+        serial_write(" proximity domains, ");
+        serial_write_dec(srat.processors.iter().filter(|p| p.enabled).count() as u64);
+        serial_write(" processors\n");
+        for processor in srat.processors.iter().filter(|p| p.enabled) {
+            serial_write("    domain ");
+            serial_write_dec(processor.proximity_domain as u64);
+            serial_write(": APIC ID ");
+            serial_write_dec(processor.apic_id as u64);
+            serial_write("\n");
+        }
+        for memory in srat.memory.iter().filter(|m| m.enabled) {
+            serial_write("    domain ");
+            serial_write_dec(memory.proximity_domain as u64);
+            serial_write(": ");
+            serial_write_hex(memory.base);
+            serial_write(" + ");
+            serial_write_size(memory.length);
+            if memory.hot_pluggable {
+                serial_write(" [hot-pluggable]");
+            }
+            if memory.non_volatile {
+                serial_write(" [non-volatile]");
+            }
+            serial_write("\n");
+        }
+    }
+
+    if let Some(slit) = slit() {
+        serial_write("  SLIT:           ");
+        serial_write_dec(slit.localities as u64);
+        serial_write(" localities\n");
+        // 距离矩阵太大时只输出前 8 行 8 列
+        for from in 0..slit.localities.min(8) {
+            serial_write("   ");
+            for to in 0..slit.localities.min(8) {
+                serial_write(" ");
+                let distance = slit.distance(from, to).unwrap_or(0);
+                if distance < 100 {
+                    serial_write(" ");
+                }
+                if distance < 10 {
+                    serial_write(" ");
+                }
+                serial_write_dec(distance as u64);
+            }
+            serial_write("\n");
+        }
+    }
+
+    if let Some(dmar) = dmar() {
+        serial_write("  DMAR:           ");
+        serial_write_dec(dmar.host_address_width as u64);
+        serial_write("-bit DMA");
+        if dmar.flags & DMAR_INTR_REMAP != 0 {
+            serial_write(", interrupt remapping");
+        }
+        if dmar.flags & DMAR_X2APIC_OPT_OUT != 0 {
+            serial_write(", x2APIC opt-out");
+        }
+        if dmar.other_structures != 0 {
+            serial_write(", ");
+            serial_write_dec(dmar.other_structures as u64);
+            serial_write(" other structures");
+        }
+        serial_write("\n");
+        for unit in &dmar.units {
+            serial_write("    DRHD segment ");
+            serial_write_dec(unit.segment as u64);
+            serial_write(" at ");
+            serial_write_hex(unit.register_base);
+            if unit.include_pci_all {
+                serial_write(" (all devices)");
+            } else {
+                serial_write(", ");
+                serial_write_dec(unit.scopes as u64);
+                serial_write(" device scopes");
+            }
+            serial_write("\n");
+        }
+        for region in &dmar.reserved_regions {
+            serial_write("    RMRR segment ");
+            serial_write_dec(region.segment as u64);
+            serial_write(" ");
+            serial_write_hex(region.base);
+            serial_write("-");
+            serial_write_hex(region.limit);
+            serial_write(", ");
+            serial_write_dec(region.scopes as u64);
+            serial_write(" device scopes\n");
+        }
+    }
+}
+
+/// 一行表头，如 `APIC  0x7FB7D000   120 bytes  rev 1  'BOCHS ' 'BXPC    '`
+fn write_table_header(table: &Table) {
+    let header = table.header();
+    serial_write("    ");
+    write_ascii(&header.signature);
+    serial_write("  ");
+    serial_write_hex(table.address);
+    serial_write("  ");
+    serial_write_dec(header.length as u64);
+    serial_write(" bytes  rev ");
+    serial_write_dec(header.revision as u64);
+    serial_write("  '");
+    write_ascii(&header.oem_id);
+    serial_write("' '");
+    write_ascii(&header.oem_table_id);
+    serial_write("' ");
+    serial_write_hex(header.oem_revision as u64);
+    serial_write(" '");
+    write_ascii(&header.creator_id);
+    serial_write("' ");
+    serial_write_hex(header.creator_revision as u64);
+    if !header.checksum_ok {
+        serial_write("  [BAD CHECKSUM]");
+    }
+    serial_write("\n");
+}
+
+/// 如 `io 0x604 (16 bits)`
+fn write_gas(gas: &GenericAddress) {
+    serial_write(gas.space_name());
+    serial_write(" ");
+    serial_write_hex(gas.address);
+    serial_write(" (");
+    serial_write_dec(gas.bit_width as u64);
+    serial_write(" bits)");
+}
+
+/// 输出 ASCII 字段（不可打印字符显示为 `?`）
+fn write_ascii(bytes: &[u8]) {
+    for &c in bytes {
+        serial_write_char(if (0x20..0x7F).contains(&c) { c } else { b'?' });
+    }
+}
@@ -0,0 +1,269 @@
+//! ACPI 命名空间
+//!
+//! 用 `acpi_aml` 解释器加载 DSDT 与所有 SSDT，并把它的操作区域访问接到
+//! 内核：SystemMemory 映射到 MMIO 窗口（缓存方式按内存映射中的区域类型选择，
+//! 同一范围只映射一次），SystemIO 走端口指令，
+//! PCI 配置空间经 [`pci::config_read`] / [`pci::config_write`]。
+//!
+//! 加载之后立即切到 APIC 中断模型 (`\_PIC(1)`)，并解析一次 `_PRT`：
+//! 未分配中断的链接设备在这时经 `_SRS` 设置，之后 [`pci_routes`] 只返回保存的结果。
+//!
+//! 整个命名空间由一把自旋锁保护，[`init`] 之后通过本模块的函数查询；
+//! 不能在中断处理程序中使用。解释器递归执行，嵌套深度可达
+//! [`acpi_aml::MAX_NESTING`]，因此在独立的栈上运行，不占用 64 KiB 的内核栈。
+
+use crate::acpi;
+use crate::frame;
+use crate::paging::{self, CacheMode};
+use crate::pci;
+use crate::port::{inb, inl, inw, outb, outl, outw};
+use crate::sync::SpinLock;
+use crate::time;
+use acpi_aml::{Handler, Interpreter, PciAddress};
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use boot_protocol::{MemoryRegionType, PAGE_SIZE};
+use core::arch::asm;
+use core::cell::UnsafeCell;
+use core::time::Duration;
+
+pub use acpi_aml::{AmlError, DeviceInfo, LoadInfo, PciRoute, PciRouting, RangeKind, Resource};
+
+/// 解释器栈的大小：嵌套到上限所需的栈，另留中断处理程序的余量
+const AML_STACK_SIZE: usize = acpi_aml::STACK_SIZE + 32 * 1024;
+
+/// 全局命名空间，[`init`] 之前为 None
+static NAMESPACE: SpinLock<Option<Namespace>> = SpinLock::new(None);
+
+struct Namespace {
+    interpreter: Interpreter,
+    /// `\_PIC(1)` 的结果
+    apic_mode: Result<(), AmlError>,
+    /// APIC 模式下解析的 `_PRT`
+    routing: PciRouting,
+}
+
+#[repr(C, align(16))]
+struct AmlStack(UnsafeCell<[u8; AML_STACK_SIZE]>);
+
+// 只在持有 NAMESPACE 锁时使用
+unsafe impl Sync for AmlStack {}
+
+static AML_STACK: AmlStack = AmlStack(UnsafeCell::new([0; AML_STACK_SIZE]));
+
+/// 在解释器栈上运行 `f`；调用者必须持有 [`NAMESPACE`] 锁，保证栈没有别人在用
+fn on_aml_stack<R>(f: impl FnOnce() -> R) -> R {
+    let mut f = Some(f);
+    let mut result = None;
+    let mut run = || result = f.take().map(|f| f());
+    switch_stack(&mut run);
+    result.unwrap()
+}
+
+fn switch_stack<F: FnMut()>(run: &mut F) {
+    extern "C" fn trampoline<F: FnMut()>(run: *mut F) {
+        unsafe { (*run)() }
+    }
+    let top = AML_STACK.0.get().wrapping_add(1) as u64;
+    // r12 是被调用者保存的寄存器，trampoline 返回后仍是原来的栈指针
+    unsafe {
+        asm!(
+            "mov r12, rsp",
+            "mov rsp, {top}",
+            "call {trampoline}",
+            "mov rsp, r12",
+            top = in(reg) top,
+            trampoline = sym trampoline::<F>,
+            in("rdi") run as *mut F,
+            out("r12") _,
+            clobber_abi("C"),
+        );
+    }
+}
+
+/// 解释器的内核平台接口
+struct Platform {
+    /// 已映射的 SystemMemory 页范围：(物理起始, 物理结束, 虚拟地址)
+    pages: Vec<(u64, u64, u64)>,
+}
+
+impl Handler for Platform {
+    fn map_memory(&mut self, address: u64, length: u64) -> Option<u64> {
+        let start = address & !(PAGE_SIZE - 1);
+        let end = address.checked_add(length)?.checked_next_multiple_of(PAGE_SIZE)?;
+        if let Some(&(phys, _, virt)) = self.pages.iter().find(|&&(s, e, _)| s <= start && end <= e) {
+            return Some(virt + (address - phys));
+        }
+        // RAM 中的区域（例如固件放在 ACPI NVS 中的共享数据）与直接映射一样可缓存
+        let cache = match frame::region_type(start) {
+            Some(
+                MemoryRegionType::Usable
+                | MemoryRegionType::AcpiReclaimable
+                | MemoryRegionType::AcpiNvs
+                | MemoryRegionType::BootloaderReclaimable
+                | MemoryRegionType::KernelAndModules
+                | MemoryRegionType::UefiRuntimeCode,
+            ) => CacheMode::WriteBack,
+            _ => CacheMode::Uncached,
+        };
+        let virt = paging::map_mmio(start, end - start, cache).ok()?;
+        self.pages.push((start, end, virt));
+        Some(virt + (address - start))
+    }
+
+    fn read_memory(&mut self, address: u64, width: usize) -> u64 {
+        unsafe {
+            match width {
+                8 => (address as *const u8).read_volatile() as u64,
+                16 => (address as *const u16).read_volatile() as u64,
+                32 => (address as *const u32).read_volatile() as u64,
+                _ => (address as *const u64).read_volatile(),
+            }
+        }
+    }
+
+    fn write_memory(&mut self, address: u64, width: usize, value: u64) {
+        unsafe {
+            match width {
+                8 => (address as *mut u8).write_volatile(value as u8),
+                16 => (address as *mut u16).write_volatile(value as u16),
+                32 => (address as *mut u32).write_volatile(value as u32),
+                _ => (address as *mut u64).write_volatile(value),
+            }
+        }
+    }
+
+    fn read_io(&mut self, port: u16, width: usize) -> u64 {
+        unsafe {
+            match width {
+                8 => inb(port) as u64,
+                16 => inw(port) as u64,
+                32 => inl(port) as u64,
+                _ => inl(port) as u64 | (inl(port + 4) as u64) << 32,
+            }
+        }
+    }
+
+    fn write_io(&mut self, port: u16, width: usize, value: u64) {
+        unsafe {
+            match width {
+                8 => outb(port, value as u8),
+                16 => outw(port, value as u16),
+                32 => outl(port, value as u32),
+                _ => {
+                    outl(port, value as u32);
+                    outl(port + 4, (value >> 32) as u32);
+                }
+            }
+        }
+    }
+
+    fn read_pci(&mut self, address: PciAddress, offset: u16, width: usize) -> u64 {
+        pci::config_read(pci_address(address), offset, width)
+    }
+
+    fn write_pci(&mut self, address: PciAddress, offset: u16, width: usize, value: u64) {
+        pci::config_write(pci_address(address), offset, width, value);
+    }
+
+    fn stall(&mut self, duration: Duration) {
+        time::busy_wait(duration);
+    }
+
+    fn sleep(&mut self, duration: Duration) {
+        time::sleep(duration);
+    }
+
+    fn now(&mut self) -> Duration {
+        time::now()
+    }
+
+    fn find_table(&mut self, signature: &[u8; 4]) -> Option<(u64, u64)> {
+        acpi::find_table(signature).map(|table| (table.address, table.bytes.len() as u64))
+    }
+}
+
+fn pci_address(address: PciAddress) -> pci::Address {
+    pci::Address::new(
+        address.segment,
+        address.bus,
+        address.device,
+        address.function,
+    )
+}
+
+/// 加载 DSDT 与所有 SSDT 并初始化设备
+///
+/// 必须在 [`acpi::init`] 与堆初始化之后调用一次。
+pub fn init() -> Result<LoadInfo, AmlError> {
+    let dsdt = acpi::find_table(b"DSDT").ok_or(AmlError::NoTables)?;
+    let definition_blocks = acpi::tables().filter(|table| table.signature() == b"SSDT");
+    let tables = core::iter::once(dsdt)
+        .chain(definition_blocks)
+        .map(|table| table.bytes);
+    let mut namespace = NAMESPACE.lock();
+    let (loaded, info) = on_aml_stack(|| {
+        let (mut interpreter, info) = Interpreter::load(Box::new(Platform { pages: Vec::new() }), tables);
+        // _PRT 的返回值取决于中断模型，必须先于路由解析
+        let apic_mode = interpreter.enable_apic_mode();
+        let routing = interpreter.route_pci_interrupts();
+        (
+            Namespace {
+                interpreter,
+                apic_mode,
+                routing,
+            },
+            info,
+        )
+    });
+    *namespace = Some(loaded);
+    Ok(info)
+}
+
+/// 在解释器栈上用解释器执行 `f`
+fn with_interpreter<R>(
+    f: impl FnOnce(&mut Interpreter) -> Result<R, AmlError>,
+) -> Result<R, AmlError> {
+    let mut namespace = NAMESPACE.lock();
+    let namespace = namespace.as_mut().ok_or(AmlError::Uninitialized)?;
+    on_aml_stack(|| f(&mut namespace.interpreter))
+}
+
+/// 读取 [`init`] 保存的结果，不执行 AML
+fn with_saved<R>(f: impl FnOnce(&Namespace) -> R) -> Result<R, AmlError> {
+    let namespace = NAMESPACE.lock();
+    Ok(f(namespace.as_ref().ok_or(AmlError::Uninitialized)?))
+}
+
+/// 睡眠状态 `\_Sx` 的 (SLP_TYPa, SLP_TYPb)
+pub fn sleep_type(state: u8) -> Result<(u8, u8), AmlError> {
+    with_interpreter(|interpreter| interpreter.sleep_type(state))
+}
+
+/// 进入睡眠状态前通知固件 (`\_PTS`)
+pub fn prepare_to_sleep(state: u8) -> Result<(), AmlError> {
+    with_interpreter(|interpreter| interpreter.prepare_to_sleep(state))
+}
+
+/// [`init`] 中 `\_PIC(1)` 的结果
+pub fn apic_mode() -> Result<(), AmlError> {
+    with_saved(|namespace| namespace.apic_mode)?
+}
+
+/// [`init`] 时解析的 `_PRT` 中断路由，链接设备已解析为 GSI；不再执行 AML
+///
+/// 只覆盖根总线上的设备；PCI 桥后面的设备需按桥的引脚轮转换算。
+pub fn pci_routes() -> Result<PciRouting, AmlError> {
+    with_saved(|namespace| namespace.routing.clone())
+}
+
+/// 命名空间中所有带 `_HID` 或 `_ADR` 的设备
+pub fn devices() -> Result<Vec<DeviceInfo>, AmlError> {
+    with_interpreter(|interpreter| Ok(interpreter.devices()))
+}
+
+/// 根桥的资源：总线号范围与转发给它的 I/O、内存窗口
+pub fn root_bridges() -> Result<Vec<(String, Vec<Resource>)>, AmlError> {
+    with_interpreter(Interpreter::root_bridges)
+}
@@ -0,0 +1,529 @@
+//! 中断控制器：8259 PIC、本地 APIC 与 IO-APIC
+//!
+//! - 8259 PIC 先重映射到 [`VECTOR_PIC_BASE`] 起的 16 个向量再全部屏蔽，
+//!   屏蔽前已经挂起的伪中断不会落在异常向量上
+//! - 本地 APIC 在 CPU 支持时切换到 x2APIC（通过 MSR 访问），否则使用 xAPIC
+//!   （寄存器以 UC 映射到 MMIO 窗口）；LINT0/LINT1 按 MADT 的 NMI 结构配置
+//! - IO-APIC 与中断源覆盖 (ISO) 来自 MADT，所有重定向项初始为屏蔽
+//! - [`route_isa_irq`] 按 ISO 把 ISA IRQ 转换为 GSI，[`route_gsi`] 把一条 GSI
+//!   路由到指定向量并注册处理程序，中断投递给 BSP
+//!
+//! EOI 与伪中断判断只读原子变量，可以在任何中断处理程序中使用。
+
+use crate::acpi::{self, ALL_PROCESSORS, InterruptOverride, Madt};
+use crate::cpu::{rdmsr, wrmsr};
+use crate::idt::{self, InterruptFrame, InterruptHandler};
+use crate::paging::{self, CacheMode, MapError};
+use crate::port::outb;
+use crate::serial::{serial_write, serial_write_hex};
+use crate::sync::SpinLock;
+use alloc::vec::Vec;
+use core::arch::asm;
+use core::arch::x86_64::__cpuid;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+/// 触发方式与极性与 ACPI 资源描述符共用同一定义
+pub use acpi_aml::{Polarity, TriggerMode};
+
+/// 8259 PIC 重映射后的第一个向量（主片 0x20..0x28，从片 0x28..0x30）
+pub const VECTOR_PIC_BASE: u8 = 0x20;
+/// 设备中断可用的第一个向量
+pub const VECTOR_DEVICE_BASE: u8 = 0x30;
+/// 本地 APIC 定时器中断向量
+pub const VECTOR_TIMER: u8 = 0xFD;
+/// 本地 APIC 错误中断向量
+pub const VECTOR_ERROR: u8 = 0xFE;
+/// 本地 APIC 伪中断向量
+pub const VECTOR_SPURIOUS: u8 = 0xFF;
+
+const IA32_APIC_BASE: u32 = 0x1B;
+const APIC_BASE_ENABLE: u64 = 1 << 11;
+const APIC_BASE_X2APIC: u64 = 1 << 10;
+const APIC_BASE_ADDR_MASK: u64 = 0x000F_FFFF_FFFF_F000;
+/// x2APIC 寄存器 MSR 起始：MSR = 0x800 + xAPIC 偏移 / 16
+const X2APIC_MSR_BASE: u32 = 0x800;
+
+// 本地 APIC 寄存器（xAPIC MMIO 偏移）
+const LAPIC_ID: u32 = 0x20;
+const LAPIC_VERSION: u32 = 0x30;
+const LAPIC_TPR: u32 = 0x80;
+const LAPIC_EOI: u32 = 0xB0;
+const LAPIC_SVR: u32 = 0xF0;
+const LAPIC_ESR: u32 = 0x280;
+const LAPIC_LVT_TIMER: u32 = 0x320;
+const LAPIC_LVT_LINT0: u32 = 0x350;
+const LAPIC_LVT_LINT1: u32 = 0x360;
+const LAPIC_LVT_ERROR: u32 = 0x370;
+const LAPIC_TIMER_INITIAL: u32 = 0x380;
+const LAPIC_TIMER_CURRENT: u32 = 0x390;
+const LAPIC_TIMER_DIVIDE: u32 = 0x3E0;
+
+const SVR_ENABLE: u32 = 1 << 8;
+const LVT_MASKED: u32 = 1 << 16;
+const LVT_DELIVERY_NMI: u32 = 0b100 << 8;
+const LVT_ACTIVE_LOW: u32 = 1 << 13;
+const LVT_LEVEL: u32 = 1 << 15;
+const LVT_TIMER_TSC_DEADLINE: u32 = 0b10 << 17;
+/// 定时器分频寄存器：16 分频
+const TIMER_DIVIDE_16: u32 = 0b0011;
+
+const IA32_TSC_DEADLINE: u32 = 0x6E0;
+
+// IO-APIC 寄存器
+const IOAPIC_REGSEL: u64 = 0x00;
+const IOAPIC_WINDOW: u64 = 0x10;
+const IOAPIC_VERSION: u32 = 0x01;
+const IOAPIC_REDIRECTION: u32 = 0x10;
+
+// 8259 PIC 端口
+const PIC1_COMMAND: u16 = 0x20;
+const PIC1_DATA: u16 = 0x21;
+const PIC2_COMMAND: u16 = 0xA0;
+const PIC2_DATA: u16 = 0xA1;
+
+/// 本地 APIC 已初始化
+static LAPIC_READY: AtomicBool = AtomicBool::new(false);
+/// 使用 x2APIC 模式
+static X2APIC: AtomicBool = AtomicBool::new(false);
+/// xAPIC 寄存器的虚拟地址
+static LAPIC_BASE: AtomicU64 = AtomicU64::new(0);
+/// IO-APIC 与中断源覆盖
+static CONTROLLER: SpinLock<Option<Controller>> = SpinLock::new(None);
+
+/// 本地 APIC 访问方式
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ApicMode {
+    XApic,
+    X2Apic,
+}
+
+impl ApicMode {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::XApic => "xAPIC",
+            Self::X2Apic => "x2APIC",
+        }
+    }
+}
+
+/// 中断控制器操作失败原因
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ApicError {
+    /// CPU 没有本地 APIC
+    Unsupported,
+    /// 找不到 MADT
+    NoMadt,
+    /// 映射寄存器失败
+    Map(MapError),
+    /// 尚未初始化
+    Uninitialized,
+    /// 没有 IO-APIC 覆盖这条 GSI
+    NoIoApic,
+    /// 向量不在设备向量范围内
+    InvalidVector,
+    /// 向量已有处理程序
+    VectorInUse,
+    /// BSP 的 APIC ID 超出 IO-APIC 目标字段（需要中断重映射）
+    DestinationOutOfRange,
+}
+
+impl ApicError {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Unsupported => "no local APIC",
+            Self::NoMadt => "no MADT",
+            Self::Map(err) => err.as_str(),
+            Self::Uninitialized => "not initialized",
+            Self::NoIoApic => "no IO-APIC for this GSI",
+            Self::InvalidVector => "invalid vector",
+            Self::VectorInUse => "vector already in use",
+            Self::DestinationOutOfRange => "APIC ID out of IO-APIC range",
+        }
+    }
+}
+
+/// 一个 IO-APIC
+#[derive(Clone, Copy, Debug)]
+pub struct IoApic {
+    pub id: u8,
+    /// 寄存器物理地址
+    pub address: u64,
+    /// 寄存器虚拟地址
+    base: u64,
+    pub version: u8,
+    pub gsi_base: u32,
+    /// 输入引脚（重定向项）数
+    pub pins: u32,
+}
+
+impl IoApic {
+    fn read(&self, register: u32) -> u32 {
+        unsafe {
+            ((self.base + IOAPIC_REGSEL) as *mut u32).write_volatile(register);
+            ((self.base + IOAPIC_WINDOW) as *const u32).read_volatile()
+        }
+    }
+
+    fn write(&self, register: u32, value: u32) {
+        unsafe {
+            ((self.base + IOAPIC_REGSEL) as *mut u32).write_volatile(register);
+            ((self.base + IOAPIC_WINDOW) as *mut u32).write_volatile(value);
+        }
+    }
+
+    fn contains(&self, gsi: u32) -> bool {
+        (self.gsi_base..self.gsi_base + self.pins).contains(&gsi)
+    }
+
+    /// 写重定向项；先写高 32 位（目标），再写低 32 位（向量、标志、屏蔽位）
+    fn set_redirection(&self, pin: u32, low: u32, high: u32) {
+        self.write(IOAPIC_REDIRECTION + pin * 2 + 1, high);
+        self.write(IOAPIC_REDIRECTION + pin * 2, low);
+    }
+}
+
+struct Controller {
+    io_apics: Vec<IoApic>,
+    overrides: Vec<InterruptOverride>,
+    /// BSP 的 APIC ID（IO-APIC 的投递目标）
+    bsp_id: u32,
+}
+
+/// 初始化结果
+#[derive(Debug)]
+pub struct ApicInfo {
+    pub mode: ApicMode,
+    /// 本地 APIC 物理地址
+    pub local_apic_addr: u64,
+    pub bsp_id: u32,
+    pub version: u32,
+    /// 本地 APIC 的 LVT 项数
+    pub lvt_entries: u32,
+    pub madt: Madt,
+    pub io_apics: Vec<IoApic>,
+}
+
+/// 屏蔽 8259 PIC，初始化本地 APIC 与 IO-APIC
+///
+/// 必须在分页与堆初始化之后、开中断之前调用一次。
+pub fn init() -> Result<ApicInfo, ApicError> {
+    disable_pic();
+
+    let features = __cpuid(1);
+    if features.edx & (1 << 9) == 0 {
+        return Err(ApicError::Unsupported);
+    }
+    let madt = acpi::madt().ok_or(ApicError::NoMadt)?;
+
+    // 本地 APIC：能用 x2APIC 就切换过去（必须先启用 xAPIC 再置 EXTD 位）
+    let base_msr = unsafe { rdmsr(IA32_APIC_BASE) };
+    let local_apic_addr = base_msr & APIC_BASE_ADDR_MASK;
+    let mode = if features.ecx & (1 << 21) != 0 {
+        ApicMode::X2Apic
+    } else {
+        ApicMode::XApic
+    };
+    unsafe {
+        wrmsr(IA32_APIC_BASE, base_msr | APIC_BASE_ENABLE);
+        if mode == ApicMode::X2Apic {
+            wrmsr(
+                IA32_APIC_BASE,
+                base_msr | APIC_BASE_ENABLE | APIC_BASE_X2APIC,
+            );
+        }
+    }
+    X2APIC.store(mode == ApicMode::X2Apic, Ordering::Relaxed);
+    if mode == ApicMode::XApic {
+        let base = paging::map_mmio(local_apic_addr, 0x1000, CacheMode::Uncached)
+            .map_err(ApicError::Map)?;
+        LAPIC_BASE.store(base, Ordering::Relaxed);
+    }
+
+    let bsp_id = match mode {
+        ApicMode::X2Apic => read_register(LAPIC_ID),
+        ApicMode::XApic => read_register(LAPIC_ID) >> 24,
+    };
+    let version_register = read_register(LAPIC_VERSION);
+    let lvt_entries = ((version_register >> 16) & 0xFF) + 1;
+
+    // BSP 在 MADT 中的 UID，用于匹配 NMI 结构
+    let bsp_uid = madt
+        .processors
+        .iter()
+        .find(|p| p.apic_id == bsp_id)
+        .map(|p| p.processor_uid);
+    write_register(LAPIC_TPR, 0);
+    write_register(LAPIC_LVT_TIMER, LVT_MASKED);
+    write_register(LAPIC_TIMER_DIVIDE, TIMER_DIVIDE_16);
+    for (lint, register) in [(0, LAPIC_LVT_LINT0), (1, LAPIC_LVT_LINT1)] {
+        let nmi = madt.nmis.iter().find(|nmi| {
+            nmi.lint == lint
+                && (nmi.processor_uid == ALL_PROCESSORS || Some(nmi.processor_uid) == bsp_uid)
+        });
+        let value = match nmi {
+            Some(nmi) => {
+                let (trigger, polarity) = decode_mps_flags(nmi.flags);
+                LVT_DELIVERY_NMI
+                    | if polarity == Polarity::ActiveLow {
+                        LVT_ACTIVE_LOW
+                    } else {
+                        0
+                    }
+                    | if trigger == TriggerMode::Level {
+                        LVT_LEVEL
+                    } else {
+                        0
+                    }
+            }
+            None => LVT_MASKED,
+        };
+        write_register(register, value);
+    }
+    idt::set_handler(VECTOR_ERROR, error_interrupt);
+    write_register(LAPIC_LVT_ERROR, VECTOR_ERROR as u32);
+    // ESR 需要先写再读
+    write_register(LAPIC_ESR, 0);
+    write_register(LAPIC_ESR, 0);
+    write_register(LAPIC_SVR, SVR_ENABLE | VECTOR_SPURIOUS as u32);
+    LAPIC_READY.store(true, Ordering::Release);
+    eoi();
+
+    // IO-APIC：映射寄存器并屏蔽所有重定向项
+    let mut io_apics = Vec::with_capacity(madt.io_apics.len());
+    for entry in &madt.io_apics {
+        let address = entry.address as u64;
+        let base = paging::map_mmio(address, 0x20, CacheMode::Uncached).map_err(ApicError::Map)?;
+        let mut io_apic = IoApic {
+            id: entry.id,
+            address,
+            base,
+            version: 0,
+            gsi_base: entry.gsi_base,
+            pins: 0,
+        };
+        let version = io_apic.read(IOAPIC_VERSION);
+        io_apic.version = version as u8;
+        io_apic.pins = ((version >> 16) & 0xFF) + 1;
+        for pin in 0..io_apic.pins {
+            io_apic.set_redirection(pin, LVT_MASKED, 0);
+        }
+        io_apics.push(io_apic);
+    }
+
+    *CONTROLLER.lock() = Some(Controller {
+        io_apics: io_apics.clone(),
+        overrides: madt.overrides.clone(),
+        bsp_id,
+    });
+
+    Ok(ApicInfo {
+        mode,
+        local_apic_addr,
+        bsp_id,
+        version: version_register & 0xFF,
+        lvt_entries,
+        madt,
+        io_apics,
+    })
+}
+
+/// 把 ISA IRQ 路由到 `vector`，返回实际使用的 GSI
+///
+/// 有中断源覆盖时使用其中的 GSI、极性与触发方式，否则 GSI 等于 IRQ，
+/// 边沿触发、高电平有效。
+pub fn route_isa_irq(irq: u8, vector: u8, handler: InterruptHandler) -> Result<u32, ApicError> {
+    let (gsi, trigger, polarity) = isa_irq_to_gsi(irq)?;
+    route_gsi(gsi, trigger, polarity, vector, handler)?;
+    Ok(gsi)
+}
+
+/// ISA IRQ 对应的 GSI、触发方式与极性
+pub fn isa_irq_to_gsi(irq: u8) -> Result<(u32, TriggerMode, Polarity), ApicError> {
+    let controller = CONTROLLER.lock();
+    let controller = controller.as_ref().ok_or(ApicError::Uninitialized)?;
+    Ok(
+        match controller.overrides.iter().find(|o| o.source == irq) {
+            Some(o) => {
+                let (trigger, polarity) = decode_mps_flags(o.flags);
+                (o.gsi, trigger, polarity)
+            }
+            None => (irq as u32, TriggerMode::Edge, Polarity::ActiveHigh),
+        },
+    )
+}
+
+/// 把 GSI 路由到 `vector`（[`VECTOR_DEVICE_BASE`]..[`VECTOR_TIMER`]）并注册处理程序，
+/// 中断以固定投递、物理目标模式发给 BSP
+pub fn route_gsi(
+    gsi: u32,
+    trigger: TriggerMode,
+    polarity: Polarity,
+    vector: u8,
+    handler: InterruptHandler,
+) -> Result<(), ApicError> {
+    if !(VECTOR_DEVICE_BASE..VECTOR_TIMER).contains(&vector) {
+        return Err(ApicError::InvalidVector);
+    }
+    let controller = CONTROLLER.lock();
+    let controller = controller.as_ref().ok_or(ApicError::Uninitialized)?;
+    let io_apic = controller
+        .io_apics
+        .iter()
+        .find(|io_apic| io_apic.contains(gsi))
+        .ok_or(ApicError::NoIoApic)?;
+    if controller.bsp_id > 0xFF {
+        return Err(ApicError::DestinationOutOfRange);
+    }
+    if !idt::set_handler(vector, handler) {
+        return Err(ApicError::VectorInUse);
+    }
+
+    let mut low = vector as u32;
+    if polarity == Polarity::ActiveLow {
+        low |= LVT_ACTIVE_LOW;
+    }
+    if trigger == TriggerMode::Level {
+        low |= LVT_LEVEL;
+    }
+    io_apic.set_redirection(gsi - io_apic.gsi_base, low, controller.bsp_id << 24);
+    Ok(())
+}
+
+/// 向本地 APIC 发送 EOI
+pub fn eoi() {
+    if LAPIC_READY.load(Ordering::Acquire) {
+        write_register(LAPIC_EOI, 0);
+    }
+}
+
+/// `vector` 是否是不需要 EOI 的伪中断（APIC 伪中断或已屏蔽的 PIC）
+pub fn is_spurious(vector: u8) -> bool {
+    vector == VECTOR_SPURIOUS || (VECTOR_PIC_BASE..VECTOR_DEVICE_BASE).contains(&vector)
+}
+
+/// MPS INTI 标志解码；“符合总线规范”按 ISA 处理（边沿触发、高电平有效）
+pub fn decode_mps_flags(flags: u16) -> (TriggerMode, Polarity) {
+    let polarity = match flags & 0b11 {
+        0b11 => Polarity::ActiveLow,
+        _ => Polarity::ActiveHigh,
+    };
+    let trigger = match (flags >> 2) & 0b11 {
+        0b11 => TriggerMode::Level,
+        _ => TriggerMode::Edge,
+    };
+    (trigger, polarity)
+}
+
+// ============================================================================
+// 本地 APIC 定时器
+// ============================================================================
+
+/// 本地 APIC 定时器计数的分频系数
+pub const TIMER_DIVIDER: u64 = 16;
+
+/// 本地 APIC 定时器的工作方式
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimerMode {
+    /// 写入初始计数后递减到 0 触发一次
+    OneShot,
+    /// TSC 到达 `IA32_TSC_DEADLINE` 时触发一次
+    TscDeadline,
+}
+
+/// 设置 LVT 定时器项；`vector` 为 None 时屏蔽。本地 APIC 未初始化时返回 false
+pub fn timer_configure(mode: TimerMode, vector: Option<u8>) -> bool {
+    if !LAPIC_READY.load(Ordering::Acquire) {
+        return false;
+    }
+    let mut lvt = match vector {
+        Some(vector) => vector as u32,
+        None => LVT_MASKED,
+    };
+    if mode == TimerMode::TscDeadline {
+        lvt |= LVT_TIMER_TSC_DEADLINE;
+    }
+    write_register(LAPIC_LVT_TIMER, lvt);
+    if mode == TimerMode::TscDeadline {
+        // 保证 LVT 的写入先于之后的 IA32_TSC_DEADLINE 写入生效
+        unsafe { asm!("mfence", options(nostack, preserves_flags)) };
+    }
+    true
+}
+
+/// 单次模式：从 `count`（分频后）开始倒数，0 表示停止
+pub fn timer_arm_count(count: u32) {
+    write_register(LAPIC_TIMER_INITIAL, count);
+}
+
+/// 单次模式下的当前计数
+pub fn timer_current_count() -> u32 {
+    read_register(LAPIC_TIMER_CURRENT)
+}
+
+/// TSC-deadline 模式：TSC 到达 `tsc` 时触发，0 表示取消
+pub fn timer_arm_deadline(tsc: u64) {
+    unsafe { wrmsr(IA32_TSC_DEADLINE, tsc) };
+}
+
+/// 读本地 APIC 寄存器
+fn read_register(offset: u32) -> u32 {
+    if X2APIC.load(Ordering::Relaxed) {
+        unsafe { rdmsr(X2APIC_MSR_BASE + offset / 16) as u32 }
+    } else {
+        let base = LAPIC_BASE.load(Ordering::Relaxed);
+        unsafe { ((base + offset as u64) as *const u32).read_volatile() }
+    }
+}
+
+/// 写本地 APIC 寄存器
+fn write_register(offset: u32, value: u32) {
+    if X2APIC.load(Ordering::Relaxed) {
+        unsafe { wrmsr(X2APIC_MSR_BASE + offset / 16, value as u64) };
+    } else {
+        let base = LAPIC_BASE.load(Ordering::Relaxed);
+        unsafe { ((base + offset as u64) as *mut u32).write_volatile(value) };
+    }
+}
+
+/// 本地 APIC 错误中断：输出并清除 ESR
+fn error_interrupt(_frame: &mut InterruptFrame) {
+    write_register(LAPIC_ESR, 0);
+    let status = read_register(LAPIC_ESR);
+    serial_write("apic: error interrupt, ESR ");
+    serial_write_hex(status as u64);
+    serial_write("\n");
+}
+
+/// 重映射 8259 PIC 到 [`VECTOR_PIC_BASE`] 后屏蔽全部 IRQ
+fn disable_pic() {
+    unsafe {
+        // ICW1：级联、需要 ICW4
+        outb(PIC1_COMMAND, 0x11);
+        io_wait();
+        outb(PIC2_COMMAND, 0x11);
+        io_wait();
+        // ICW2：向量偏移
+        outb(PIC1_DATA, VECTOR_PIC_BASE);
+        io_wait();
+        outb(PIC2_DATA, VECTOR_PIC_BASE + 8);
+        io_wait();
+        // ICW3：从片接在主片 IRQ2
+        outb(PIC1_DATA, 1 << 2);
+        io_wait();
+        outb(PIC2_DATA, 2);
+        io_wait();
+        // ICW4：8086 模式
+        outb(PIC1_DATA, 0x01);
+        io_wait();
+        outb(PIC2_DATA, 0x01);
+        io_wait();
+        // 屏蔽全部
+        outb(PIC1_DATA, 0xFF);
+        outb(PIC2_DATA, 0xFF);
+    }
+}
+
+/// 写未使用的 POST 端口，给 PIC 留出处理时间
+fn io_wait() {
+    unsafe { outb(0x80, 0) };
+}
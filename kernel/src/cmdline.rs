@@ -0,0 +1,199 @@
+//! 内核命令行
+//!
+//! 引导程序交接的命令行在启动早期复制到内核自己的缓冲区（交接内存之后
+//! 可以回收），其他子系统随后通过本模块查询。
+//!
+//! 参数以空白分隔，形如 `key=value` 或单独的 `flag`；值可以用双引号包含空白，
+//! 例如 `root="disk 0"`。同一个键出现多次时以最后一次为准。
+
+use core::cell::UnsafeCell;
+
+/// 内核保存的命令行最大长度（字节）
+const CMDLINE_MAX: usize = 4096;
+
+/// 命令行存储，只在启动早期（单核、无中断）由 [`init`] 写入一次
+struct CmdlineStore {
+    buf: UnsafeCell<[u8; CMDLINE_MAX]>,
+    len: UnsafeCell<usize>,
+}
+
+unsafe impl Sync for CmdlineStore {}
+
+static STORE: CmdlineStore = CmdlineStore {
+    buf: UnsafeCell::new([0; CMDLINE_MAX]),
+    len: UnsafeCell::new(0),
+};
+
+/// 保存引导程序传入的命令行；超长或非 UTF-8 的部分被丢弃
+///
+/// 只能在启动早期调用一次，此时尚无其他代码读取命令行。
+pub fn init(raw: &[u8]) {
+    let raw = &raw[..raw.len().min(CMDLINE_MAX)];
+    let valid = match core::str::from_utf8(raw) {
+        Ok(s) => s.len(),
+        Err(e) => e.valid_up_to(),
+    };
+    unsafe {
+        let buf = &mut *STORE.buf.get();
+        buf[..valid].copy_from_slice(&raw[..valid]);
+        *STORE.len.get() = valid;
+    }
+}
+
+/// 完整的命令行字符串
+pub fn as_str() -> &'static str {
+    unsafe {
+        let len = *STORE.len.get();
+        let buf = &*STORE.buf.get();
+        core::str::from_utf8_unchecked(&buf[..len])
+    }
+}
+
+/// 一个命令行参数
+#[derive(Clone, Copy, Debug)]
+pub struct Param<'a> {
+    /// 键
+    pub key: &'a str,
+    /// 值（单独的 flag 为 None，已去掉包围的双引号）
+    pub value: Option<&'a str>,
+}
+
+/// 参数迭代器
+pub struct Params<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Params<'a> {
+    /// 解析任意命令行字符串
+    pub fn new(cmdline: &'a str) -> Self {
+        Self { rest: cmdline }
+    }
+}
+
+impl<'a> Iterator for Params<'a> {
+    type Item = Param<'a>;
+
+    fn next(&mut self) -> Option<Param<'a>> {
+        let rest = self.rest.trim_start();
+        if rest.is_empty() {
+            self.rest = rest;
+            return None;
+        }
+
+        // 引号内的空白不结束参数
+        let mut in_quotes = false;
+        let mut end = rest.len();
+        for (i, c) in rest.char_indices() {
+            if c == '"' {
+                in_quotes = !in_quotes;
+            } else if c.is_whitespace() && !in_quotes {
+                end = i;
+                break;
+            }
+        }
+        let token = &rest[..end];
+        self.rest = &rest[end..];
+
+        Some(match token.split_once('=') {
+            Some((key, value)) => {
+                let value = value
+                    .strip_prefix('"')
+                    .and_then(|v| v.strip_suffix('"'))
+                    .unwrap_or(value);
+                Param { key, value: Some(value) }
+            }
+            None => Param { key: token, value: None },
+        })
+    }
+}
+
+/// 遍历内核命令行的所有参数
+pub fn params() -> Params<'static> {
+    Params::new(as_str())
+}
+
+/// 查询参数值（不存在或为单独的 flag 时为 None）
+pub fn get(key: &str) -> Option<&'static str> {
+    params()
+        .filter(|param| param.key == key)
+        .last()
+        .and_then(|param| param.value)
+}
+
+// ============================================================================
+// 类型化选项
+// ============================================================================
+
+/// 控制台设备 (`console=`)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Console {
+    /// 串口 `ttyS<n>`
+    Serial(u8),
+    /// 帧缓冲区 `tty0`
+    Framebuffer,
+}
+
+/// 控制台，默认 `ttyS0`
+pub fn console() -> Console {
+    match get("console") {
+        Some("tty0") => Console::Framebuffer,
+        Some(name) => name
+            .strip_prefix("ttyS")
+            .and_then(|index| index.split(',').next())
+            .and_then(|index| index.parse().ok())
+            .map(Console::Serial)
+            .unwrap_or(Console::Serial(0)),
+        None => Console::Serial(0),
+    }
+}
+
+/// 日志级别 (`loglevel=`)，数值越小越严重
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Emergency = 0,
+    Alert = 1,
+    Critical = 2,
+    Error = 3,
+    Warning = 4,
+    Notice = 5,
+    Info = 6,
+    Debug = 7,
+}
+
+impl LogLevel {
+    /// 显示用名称
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Emergency => "emerg",
+            Self::Alert => "alert",
+            Self::Critical => "crit",
+            Self::Error => "err",
+            Self::Warning => "warning",
+            Self::Notice => "notice",
+            Self::Info => "info",
+            Self::Debug => "debug",
+        }
+    }
+
+    fn from_u8(level: u8) -> Self {
+        match level {
+            0 => Self::Emergency,
+            1 => Self::Alert,
+            2 => Self::Critical,
+            3 => Self::Error,
+            4 => Self::Warning,
+            5 => Self::Notice,
+            6 => Self::Info,
+            _ => Self::Debug,
+        }
+    }
+}
+
+/// 日志级别，默认 `Info`；超过 7 的值按 `Debug` 处理
+pub fn loglevel() -> LogLevel {
+    get("loglevel")
+        .and_then(|level| level.parse::<u8>().ok())
+        .map(LogLevel::from_u8)
+        .unwrap_or(LogLevel::Info)
+}
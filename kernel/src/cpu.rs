@@ -0,0 +1,92 @@
+//! 特权指令封装（控制寄存器、MSR、TLB、中断标志、TSC）
+
+use core::arch::asm;
+use core::arch::x86_64::_rdtsc;
+
+/// 读取 MSR
+pub unsafe fn rdmsr(msr: u32) -> u64 {
+    let (low, high): (u32, u32);
+    asm!("rdmsr", in("ecx") msr, out("eax") low, out("edx") high, options(nomem, nostack));
+    ((high as u64) << 32) | low as u64
+}
+
+/// 写入 MSR
+pub unsafe fn wrmsr(msr: u32, value: u64) {
+    asm!(
+        "wrmsr",
+        in("ecx") msr,
+        in("eax") value as u32,
+        in("edx") (value >> 32) as u32,
+        options(nostack)
+    );
+}
+
+/// 读取 CR0
+pub fn read_cr0() -> u64 {
+    let value: u64;
+    unsafe { asm!("mov {}, cr0", out(reg) value, options(nomem, nostack)) };
+    value
+}
+
+/// 读取 CR2（最近一次缺页的线性地址）
+pub fn read_cr2() -> u64 {
+    let value: u64;
+    unsafe { asm!("mov {}, cr2", out(reg) value, options(nomem, nostack)) };
+    value
+}
+
+/// 读取 CR3
+pub fn read_cr3() -> u64 {
+    let value: u64;
+    unsafe { asm!("mov {}, cr3", out(reg) value, options(nomem, nostack)) };
+    value
+}
+
+/// 写入 CR3（切换页表，同时刷新非全局 TLB 项）
+pub unsafe fn write_cr3(value: u64) {
+    asm!("mov cr3, {}", in(reg) value, options(nostack));
+}
+
+/// 读取 CR4
+pub fn read_cr4() -> u64 {
+    let value: u64;
+    unsafe { asm!("mov {}, cr4", out(reg) value, options(nomem, nostack)) };
+    value
+}
+
+/// 刷新一个虚拟地址的 TLB 项（含全局项）
+pub unsafe fn invlpg(addr: u64) {
+    asm!("invlpg [{}]", in(reg) addr, options(nostack));
+}
+
+/// 写回并使所有缓存失效（修改 PAT 时需要）
+pub unsafe fn wbinvd() {
+    asm!("wbinvd", options(nostack));
+}
+
+/// 读取时间戳计数器
+pub fn rdtsc() -> u64 {
+    unsafe { _rdtsc() }
+}
+
+/// 当前是否允许可屏蔽中断 (RFLAGS.IF)
+pub fn interrupts_enabled() -> bool {
+    let rflags: u64;
+    unsafe { asm!("pushfq", "pop {}", out(reg) rflags, options(nomem, preserves_flags)) };
+    rflags & (1 << 9) != 0
+}
+
+/// 关中断执行 `f`，之后恢复原来的中断标志
+///
+/// 与中断处理程序共用的锁必须在这里面获取，否则中断处理程序可能在锁被持有时自旋。
+pub fn without_interrupts<R>(f: impl FnOnce() -> R) -> R {
+    let enabled = interrupts_enabled();
+    if enabled {
+        unsafe { asm!("cli", options(nomem, nostack)) };
+    }
+    let result = f();
+    if enabled {
+        unsafe { asm!("sti", options(nomem, nostack)) };
+    }
+    result
+}
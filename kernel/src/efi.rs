@@ -0,0 +1,207 @@
+//! UEFI 运行时服务
+//!
+//! 引导程序调用 SetVirtualAddressMap 后，运行时区域映射在
+//! `物理地址 + UEFI_RUNTIME_OFFSET`，内核可以在自己的页表下调用运行时服务。
+//! 没有建立虚拟映射时所有调用返回 [`EfiError::Unavailable`]。
+//!
+//! 运行时服务不可重入，调用者负责串行化（目前内核只有一个执行流）。
+
+use boot_protocol::BootInfo;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+/// EFI_STATUS
+type Status = usize;
+
+const EFI_SUCCESS: Status = 0;
+const EFI_ERROR: Status = 1 << 63;
+const EFI_UNSUPPORTED: Status = EFI_ERROR | 3;
+const EFI_BUFFER_TOO_SMALL: Status = EFI_ERROR | 5;
+const EFI_NOT_FOUND: Status = EFI_ERROR | 14;
+
+/// 变量名（UTF-16，含结尾 null）的最大长度
+const VARIABLE_NAME_MAX: usize = 64;
+
+/// 运行时服务表的虚拟地址，0 表示不可用
+static RUNTIME_SERVICES: AtomicU64 = AtomicU64::new(0);
+
+/// 运行时服务调用失败原因
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EfiError {
+    /// 运行时服务没有映射到内核地址空间
+    Unavailable,
+    /// 固件不支持该调用
+    Unsupported,
+    /// 变量不存在
+    NotFound,
+    /// 缓冲区太小，附带需要的大小
+    BufferTooSmall(usize),
+    /// 变量名太长或含无法编码的字符
+    InvalidName,
+    /// 其他 EFI_STATUS
+    Other(usize),
+}
+
+impl EfiError {
+    fn from_status(status: Status, size: usize) -> Self {
+        match status {
+            EFI_UNSUPPORTED => Self::Unsupported,
+            EFI_NOT_FOUND => Self::NotFound,
+            EFI_BUFFER_TOO_SMALL => Self::BufferTooSmall(size),
+            _ => Self::Other(status),
+        }
+    }
+}
+
+/// EFI_GUID
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Guid {
+    pub data1: u32,
+    pub data2: u16,
+    pub data3: u16,
+    pub data4: [u8; 8],
+}
+
+/// EFI_GLOBAL_VARIABLE：BootOrder、BootCurrent 等标准变量的厂商 GUID
+pub const GLOBAL_VARIABLE: Guid = Guid {
+    data1: 0x8BE4_DF61,
+    data2: 0x93CA,
+    data3: 0x11D2,
+    data4: [0xAA, 0x0D, 0x00, 0xE0, 0x98, 0x03, 0x2B, 0x8C],
+};
+
+/// EFI_TIME
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Time {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    _pad1: u8,
+    pub nanosecond: u32,
+    /// 相对 UTC 的分钟偏移，2047 表示本地时间
+    pub time_zone: i16,
+    pub daylight: u8,
+    _pad2: u8,
+}
+
+/// ResetSystem 的复位类型（EfiResetWarm 与 EfiResetPlatformSpecific 未使用）
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResetType {
+    Cold = 0,
+    Shutdown = 2,
+}
+
+/// EFI_RUNTIME_SERVICES（只声明内核使用的函数，其余按位置占位）
+#[repr(C)]
+struct RuntimeServices {
+    _header: [u64; 3],
+    get_time: unsafe extern "efiapi" fn(time: *mut Time, capabilities: *mut u8) -> Status,
+    // SetTime, GetWakeupTime, SetWakeupTime, SetVirtualAddressMap, ConvertPointer
+    _reserved1: [usize; 5],
+    get_variable: unsafe extern "efiapi" fn(
+        name: *const u16,
+        vendor: *const Guid,
+        attributes: *mut u32,
+        data_size: *mut usize,
+        data: *mut u8,
+    ) -> Status,
+    // GetNextVariableName
+    _reserved2: usize,
+    set_variable: unsafe extern "efiapi" fn(
+        name: *const u16,
+        vendor: *const Guid,
+        attributes: u32,
+        data_size: usize,
+        data: *const u8,
+    ) -> Status,
+    // GetNextHighMonotonicCount
+    _reserved3: usize,
+    reset_system: unsafe extern "efiapi" fn(
+        reset_type: ResetType,
+        status: Status,
+        data_size: usize,
+        data: *const u8,
+    ) -> !,
+}
+
+/// 记录运行时服务表的虚拟地址
+pub fn init(info: &BootInfo) {
+    let table = info.uefi_runtime_services_virt().unwrap_or(0);
+    RUNTIME_SERVICES.store(table, Ordering::Release);
+}
+
+/// 运行时服务是否可用
+pub fn is_available() -> bool {
+    RUNTIME_SERVICES.load(Ordering::Acquire) != 0
+}
+
+fn runtime_services() -> Result<&'static RuntimeServices, EfiError> {
+    let table = RUNTIME_SERVICES.load(Ordering::Acquire);
+    if table == 0 {
+        return Err(EfiError::Unavailable);
+    }
+    Ok(unsafe { &*(table as *const RuntimeServices) })
+}
+
+/// 读取实时时钟
+pub fn get_time() -> Result<Time, EfiError> {
+    let rt = runtime_services()?;
+    let mut time = Time::default();
+    let status = unsafe { (rt.get_time)(&mut time, core::ptr::null_mut()) };
+    match status {
+        EFI_SUCCESS => Ok(time),
+        _ => Err(EfiError::from_status(status, 0)),
+    }
+}
+
+/// 读取变量到 `buf`，返回属性与数据长度
+pub fn get_variable(name: &str, vendor: &Guid, buf: &mut [u8]) -> Result<(u32, usize), EfiError> {
+    let rt = runtime_services()?;
+    let name = encode_name(name)?;
+    let mut attributes = 0;
+    let mut size = buf.len();
+    let status =
+        unsafe { (rt.get_variable)(name.as_ptr(), vendor, &mut attributes, &mut size, buf.as_mut_ptr()) };
+    match status {
+        EFI_SUCCESS => Ok((attributes, size)),
+        _ => Err(EfiError::from_status(status, size)),
+    }
+}
+
+/// 写入变量；`attributes` 为 EFI_VARIABLE_* 位，`data` 为空时删除变量
+#[allow(dead_code)] // 目前内核只读取变量
+pub fn set_variable(name: &str, vendor: &Guid, attributes: u32, data: &[u8]) -> Result<(), EfiError> {
+    let rt = runtime_services()?;
+    let name = encode_name(name)?;
+    let status =
+        unsafe { (rt.set_variable)(name.as_ptr(), vendor, attributes, data.len(), data.as_ptr()) };
+    match status {
+        EFI_SUCCESS => Ok(()),
+        _ => Err(EfiError::from_status(status, 0)),
+    }
+}
+
+/// 复位或关机；运行时服务不可用时返回
+pub fn reset_system(reset_type: ResetType) {
+    if let Ok(rt) = runtime_services() {
+        unsafe { (rt.reset_system)(reset_type, EFI_SUCCESS, 0, core::ptr::null()) }
+    }
+}
+
+/// 变量名编码为以 null 结尾的 UTF-16
+fn encode_name(name: &str) -> Result<[u16; VARIABLE_NAME_MAX], EfiError> {
+    let mut buf = [0u16; VARIABLE_NAME_MAX];
+    for (i, unit) in name.encode_utf16().enumerate() {
+        // 保留结尾 null
+        if i + 1 >= VARIABLE_NAME_MAX || unit == 0 {
+            return Err(EfiError::InvalidName);
+        }
+        buf[i] = unit;
+    }
+    Ok(buf)
+}
@@ -0,0 +1,28 @@
+//! 固件表的字节访问
+//!
+//! ACPI 与 SMBIOS 表都按物理地址交接、以小端序存放并用字节和校验，
+//! 两者共用这里的读取函数。
+
+use crate::paging;
+
+/// 直接映射中从 `phys` 开始的 `len` 字节
+pub(crate) fn phys_bytes(phys: u64, len: usize) -> &'static [u8] {
+    unsafe { core::slice::from_raw_parts(paging::phys_to_virt(phys) as *const u8, len) }
+}
+
+pub(crate) fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+pub(crate) fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+pub(crate) fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+/// 字节和为 0
+pub(crate) fn checksum(bytes: &[u8]) -> bool {
+    bytes.iter().fold(0u8, |sum, &b| sum.wrapping_add(b)) == 0
+}
@@ -0,0 +1,343 @@
+//! 物理页帧分配器
+//!
+//! 由 BootInfo 内存映射建立的位图分配器，每个 4 KiB 帧占一位（1 = 已占用）。
+//!
+//! - 初始时所有帧标记为占用，只释放 `Usable` 区域
+//! - 内核映像、BootInfo、帧缓冲区与位图本身随后重新标记为占用，
+//!   物理地址 0 所在的帧永不分配
+//! - 位图覆盖到最高的 `Usable` / `BootloaderReclaimable` 地址，交接数据
+//!   用完后可以通过 [`reclaim_bootloader_memory`] 把后者并入；内存映射本身
+//!   也在交接内存中，回收前先用 [`save_memory_map`] 复制到内核堆
+//!
+//! 2 MiB / 1 GiB 帧要求自然对齐，恰好对应位图中 8 / 4096 个对齐的 u64 字，
+//! 按整字检查即可；4 KiB 帧从上次分配的位置继续查找。
+
+use crate::sync::SpinLock;
+use alloc::vec::Vec;
+use boot_protocol::{BootInfo, MemoryRegion, MemoryRegionType, PAGE_SIZE};
+
+/// 基本帧大小
+pub const FRAME_SIZE: u64 = PAGE_SIZE;
+
+/// 每个位图字覆盖的帧数
+const BITS_PER_WORD: u64 = 64;
+
+/// 帧大小
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameSize {
+    Size4K,
+    Size2M,
+    Size1G,
+}
+
+impl FrameSize {
+    /// 字节数
+    pub const fn bytes(self) -> u64 {
+        match self {
+            Self::Size4K => 4 * 1024,
+            Self::Size2M => 2 * 1024 * 1024,
+            Self::Size1G => 1024 * 1024 * 1024,
+        }
+    }
+
+    /// 包含的 4 KiB 帧数
+    const fn frames(self) -> u64 {
+        self.bytes() / FRAME_SIZE
+    }
+
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Size4K => "4 KiB",
+            Self::Size2M => "2 MiB",
+            Self::Size1G => "1 GiB",
+        }
+    }
+}
+
+/// 分配器统计
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FrameStats {
+    /// 位图覆盖的帧数（从物理地址 0 起）
+    pub tracked_frames: u64,
+    /// 交给分配器管理的帧数
+    pub total_frames: u64,
+    /// 当前空闲帧数
+    pub free_frames: u64,
+    /// 当前已分配的 4 KiB 帧数
+    pub allocated_4k: u64,
+    /// 当前已分配的 2 MiB 帧数
+    pub allocated_2m: u64,
+    /// 当前已分配的 1 GiB 帧数
+    pub allocated_1g: u64,
+    /// 分配失败次数
+    pub failed: u64,
+    /// 位图占用的字节数
+    pub bitmap_bytes: u64,
+}
+
+/// 始终保持占用的物理范围数
+const MAX_EXCLUDED: usize = 3;
+
+struct FrameAllocator {
+    /// 位图（直接映射的虚拟地址）
+    bitmap: *mut u64,
+    /// 位图字数
+    words: usize,
+    /// 下一次 4 KiB 分配开始查找的字
+    next: usize,
+    /// 内核映像、BootInfo、帧缓冲区（物理起始, 字节数）
+    excluded: [(u64, u64); MAX_EXCLUDED],
+    stats: FrameStats,
+}
+
+// 位图只通过 FRAMES 锁访问
+unsafe impl Send for FrameAllocator {}
+
+static FRAMES: SpinLock<Option<FrameAllocator>> = SpinLock::new(None);
+/// 内存映射的内核副本，交接内存回收后仍然有效
+static MEMORY_MAP: SpinLock<Vec<MemoryRegion>> = SpinLock::new(Vec::new());
+
+/// 由内存映射建立分配器；返回 false 表示找不到放置位图的可用区域
+///
+/// 只能在启动早期调用一次。`boot_info_phys` 为 BootInfo 的物理地址。
+///
+/// # Safety
+///
+/// `info` 必须满足 [`BootInfo`] 的表访问约定。
+pub unsafe fn init(info: &BootInfo, boot_info_phys: u64) -> bool {
+    let regions = info.memory_map();
+    let tracked_end = regions
+        .iter()
+        .filter(|r| {
+            matches!(
+                r.region_type,
+                MemoryRegionType::Usable | MemoryRegionType::BootloaderReclaimable
+            )
+        })
+        .map(|r| r.phys_start + r.size())
+        .max()
+        .unwrap_or(0);
+    let words = (tracked_end / FRAME_SIZE).div_ceil(BITS_PER_WORD) as usize;
+    let bitmap_bytes = (words * 8) as u64;
+
+    // 位图放在第一个足够大的可用区域开头（跳过物理地址 0）
+    let Some(bitmap_phys) = regions
+        .iter()
+        .filter(|r| r.region_type == MemoryRegionType::Usable)
+        .map(|r| (r.phys_start.max(FRAME_SIZE), r.phys_start + r.size()))
+        .find(|&(start, end)| end.saturating_sub(start) >= bitmap_bytes)
+        .map(|(start, _)| start)
+    else {
+        return false;
+    };
+
+    let bitmap = info.phys_to_virt(bitmap_phys) as *mut u64;
+    unsafe { core::ptr::write_bytes(bitmap, 0xFF, words) };
+
+    let fb = &info.framebuffer;
+    let mut allocator = FrameAllocator {
+        bitmap,
+        words,
+        next: 0,
+        excluded: [
+            (info.kernel_phys_addr, info.kernel_size),
+            (boot_info_phys, info.size as u64),
+            (fb.address, fb.size),
+        ],
+        stats: FrameStats {
+            tracked_frames: words as u64 * BITS_PER_WORD,
+            bitmap_bytes,
+            ..FrameStats::default()
+        },
+    };
+
+    for region in regions.iter().filter(|r| r.region_type == MemoryRegionType::Usable) {
+        allocator.mark_range(region.phys_start, region.size(), false);
+    }
+    allocator.mark_range(0, FRAME_SIZE, true);
+    allocator.mark_range(bitmap_phys, bitmap_bytes, true);
+    allocator.mark_excluded();
+    allocator.stats.total_frames = allocator.stats.free_frames;
+
+    *FRAMES.lock() = Some(allocator);
+    true
+}
+
+/// 把内存映射复制到内核堆，供 [`reclaim_bootloader_memory`] 与 [`region_type`] 使用
+///
+/// 需要内核堆，在堆初始化之后、回收交接内存之前调用。
+pub fn save_memory_map(regions: &[MemoryRegion]) {
+    *MEMORY_MAP.lock() = regions.to_vec();
+}
+
+/// `phys` 所在内存映射区域的类型；不在任何区域中（或尚未 [`save_memory_map`]）时为 None
+pub fn region_type(phys: u64) -> Option<MemoryRegionType> {
+    MEMORY_MAP
+        .lock()
+        .iter()
+        .find(|r| r.phys_start <= phys && phys < r.phys_start + r.size())
+        .map(|r| r.region_type)
+}
+
+/// 把 [`save_memory_map`] 保存的 `BootloaderReclaimable` 区域并入空闲帧，
+/// 返回新增的帧数
+///
+/// # Safety
+///
+/// [`paging::init`](crate::paging::init) 必须已经成功（内核不再使用引导程序的页表），
+/// 调用者必须保证之后不再访问 BootInfo 引用的任何交接表与内核 ELF 副本；
+/// BootInfo 结构体本身与启动模块仍保持占用。
+pub unsafe fn reclaim_bootloader_memory() -> u64 {
+    let memory_map = MEMORY_MAP.lock();
+    let mut frames = FRAMES.lock();
+    let Some(allocator) = frames.as_mut() else {
+        return 0;
+    };
+    let before = allocator.stats.free_frames;
+    for region in memory_map
+        .iter()
+        .filter(|r| r.region_type == MemoryRegionType::BootloaderReclaimable)
+    {
+        allocator.mark_range(region.phys_start, region.size(), false);
+    }
+    allocator.mark_excluded();
+    let added = allocator.stats.free_frames - before;
+    allocator.stats.total_frames += added;
+    added
+}
+
+/// 分配一个帧，返回物理地址；内容未清零
+pub fn alloc(size: FrameSize) -> Option<u64> {
+    let mut frames = FRAMES.lock();
+    let allocator = frames.as_mut()?;
+    let addr = match size {
+        FrameSize::Size4K => allocator.alloc_4k(),
+        _ => allocator.alloc_aligned(size),
+    };
+    match addr {
+        Some(_) => *allocator.allocated(size) += 1,
+        None => allocator.stats.failed += 1,
+    }
+    addr
+}
+
+/// 释放 [`alloc`] 分配的帧
+///
+/// # Safety
+///
+/// `addr` 必须是以同样的 `size` 分配、且之后不再被访问的帧。
+pub unsafe fn free(addr: u64, size: FrameSize) {
+    let mut frames = FRAMES.lock();
+    let Some(allocator) = frames.as_mut() else {
+        return;
+    };
+    assert!(addr.is_multiple_of(size.bytes()), "frame free: misaligned address");
+    let first = addr / FRAME_SIZE;
+    assert!(
+        (first..first + size.frames()).all(|frame| allocator.is_used(frame)),
+        "frame free: frame is not allocated"
+    );
+    allocator.mark_range(addr, size.bytes(), false);
+    *allocator.allocated(size) -= 1;
+}
+
+/// 当前统计；分配器未初始化时全为 0
+pub fn stats() -> FrameStats {
+    FRAMES
+        .lock()
+        .as_ref()
+        .map(|allocator| allocator.stats)
+        .unwrap_or_default()
+}
+
+impl FrameAllocator {
+    fn word(&mut self, index: usize) -> &mut u64 {
+        unsafe { &mut *self.bitmap.add(index) }
+    }
+
+    fn is_used(&mut self, frame: u64) -> bool {
+        let word = (frame / BITS_PER_WORD) as usize;
+        word >= self.words || *self.word(word) & (1 << (frame % BITS_PER_WORD)) != 0
+    }
+
+    fn allocated(&mut self, size: FrameSize) -> &mut u64 {
+        match size {
+            FrameSize::Size4K => &mut self.stats.allocated_4k,
+            FrameSize::Size2M => &mut self.stats.allocated_2m,
+            FrameSize::Size1G => &mut self.stats.allocated_1g,
+        }
+    }
+
+    /// 标记 `[start, start + len)` 覆盖的帧为占用或空闲，同步更新空闲计数
+    ///
+    /// 标记为占用时包含部分覆盖的帧，标记为空闲时只包含完整的帧。
+    fn mark_range(&mut self, start: u64, len: u64, used: bool) {
+        let limit = self.words as u64 * BITS_PER_WORD;
+        let (mut frame, end) = if used {
+            (start / FRAME_SIZE, (start + len).div_ceil(FRAME_SIZE))
+        } else {
+            (start.div_ceil(FRAME_SIZE), (start + len) / FRAME_SIZE)
+        };
+        let end = end.min(limit);
+
+        while frame < end {
+            let bit = frame % BITS_PER_WORD;
+            let count = (BITS_PER_WORD - bit).min(end - frame);
+            let mask = if count == BITS_PER_WORD {
+                !0
+            } else {
+                ((1u64 << count) - 1) << bit
+            };
+            let word = self.word((frame / BITS_PER_WORD) as usize);
+            let changed = if used { !*word & mask } else { *word & mask };
+            if used {
+                *word |= mask;
+                self.stats.free_frames -= changed.count_ones() as u64;
+            } else {
+                *word &= !mask;
+                self.stats.free_frames += changed.count_ones() as u64;
+            }
+            frame += count;
+        }
+    }
+
+    /// 重新标记始终占用的范围
+    fn mark_excluded(&mut self) {
+        for (start, len) in self.excluded {
+            if len != 0 {
+                self.mark_range(start, len, true);
+            }
+        }
+    }
+
+    /// 从 `next` 开始查找第一个空闲位，到末尾后回绕
+    fn alloc_4k(&mut self) -> Option<u64> {
+        let words = self.words;
+        for index in (self.next..words).chain(0..self.next) {
+            let word = *self.word(index);
+            if word != !0 {
+                let frame = index as u64 * BITS_PER_WORD + (!word).trailing_zeros() as u64;
+                self.next = index;
+                let addr = frame * FRAME_SIZE;
+                self.mark_range(addr, FRAME_SIZE, true);
+                return Some(addr);
+            }
+        }
+        None
+    }
+
+    /// 查找自然对齐、整块空闲的 2 MiB / 1 GiB 帧
+    fn alloc_aligned(&mut self, size: FrameSize) -> Option<u64> {
+        let step = (size.frames() / BITS_PER_WORD) as usize;
+        let mut base = 0;
+        while base + step <= self.words {
+            if (base..base + step).all(|index| *self.word(index) == 0) {
+                let addr = base as u64 * BITS_PER_WORD * FRAME_SIZE;
+                self.mark_range(addr, size.bytes(), true);
+                return Some(addr);
+            }
+            base += step;
+        }
+        None
+    }
+}
@@ -0,0 +1,237 @@
+//! 帧缓冲区绘制
+//!
+//! 像素按引导程序交接的分量位掩码编码，RGB、BGR 与 Bitmask 格式统一处理；
+//! 颜色参数一律为 `0x00RRGGBB`。
+
+use crate::paging::{self, CacheMode};
+use boot_protocol::BootInfo;
+use core::cell::UnsafeCell;
+
+/// 简单的 5x7 字体（每个字符 5 列，每列低 7 位自上而下）
+const FONT: [[u8; 5]; 128] = {
+    let mut f = [[0u8; 5]; 128];
+    // 空格
+    f[b' ' as usize] = [0x00, 0x00, 0x00, 0x00, 0x00];
+    // 数字
+    f[b'0' as usize] = [0x3E, 0x51, 0x49, 0x45, 0x3E];
+    f[b'1' as usize] = [0x00, 0x42, 0x7F, 0x40, 0x00];
+    f[b'2' as usize] = [0x42, 0x61, 0x51, 0x49, 0x46];
+    f[b'3' as usize] = [0x21, 0x41, 0x45, 0x4B, 0x31];
+    f[b'4' as usize] = [0x18, 0x14, 0x12, 0x7F, 0x10];
+    f[b'5' as usize] = [0x27, 0x45, 0x45, 0x45, 0x39];
+    f[b'6' as usize] = [0x3C, 0x4A, 0x49, 0x49, 0x30];
+    f[b'7' as usize] = [0x01, 0x71, 0x09, 0x05, 0x03];
+    f[b'8' as usize] = [0x36, 0x49, 0x49, 0x49, 0x36];
+    f[b'9' as usize] = [0x06, 0x49, 0x49, 0x29, 0x1E];
+    // 大写字母
+    f[b'A' as usize] = [0x7E, 0x11, 0x11, 0x11, 0x7E];
+    f[b'B' as usize] = [0x7F, 0x49, 0x49, 0x49, 0x36];
+    f[b'C' as usize] = [0x3E, 0x41, 0x41, 0x41, 0x22];
+    f[b'D' as usize] = [0x7F, 0x41, 0x41, 0x22, 0x1C];
+    f[b'E' as usize] = [0x7F, 0x49, 0x49, 0x49, 0x41];
+    f[b'F' as usize] = [0x7F, 0x09, 0x09, 0x09, 0x01];
+    f[b'G' as usize] = [0x3E, 0x41, 0x49, 0x49, 0x7A];
+    f[b'H' as usize] = [0x7F, 0x08, 0x08, 0x08, 0x7F];
+    f[b'I' as usize] = [0x00, 0x41, 0x7F, 0x41, 0x00];
+    f[b'J' as usize] = [0x20, 0x40, 0x41, 0x3F, 0x01];
+    f[b'K' as usize] = [0x7F, 0x08, 0x14, 0x22, 0x41];
+    f[b'L' as usize] = [0x7F, 0x40, 0x40, 0x40, 0x40];
+    f[b'M' as usize] = [0x7F, 0x02, 0x0C, 0x02, 0x7F];
+    f[b'N' as usize] = [0x7F, 0x04, 0x08, 0x10, 0x7F];
+    f[b'O' as usize] = [0x3E, 0x41, 0x41, 0x41, 0x3E];
+    f[b'P' as usize] = [0x7F, 0x09, 0x09, 0x09, 0x06];
+    f[b'Q' as usize] = [0x3E, 0x41, 0x51, 0x21, 0x5E];
+    f[b'R' as usize] = [0x7F, 0x09, 0x19, 0x29, 0x46];
+    f[b'S' as usize] = [0x46, 0x49, 0x49, 0x49, 0x31];
+    f[b'T' as usize] = [0x01, 0x01, 0x7F, 0x01, 0x01];
+    f[b'U' as usize] = [0x3F, 0x40, 0x40, 0x40, 0x3F];
+    f[b'V' as usize] = [0x1F, 0x20, 0x40, 0x20, 0x1F];
+    f[b'W' as usize] = [0x3F, 0x40, 0x38, 0x40, 0x3F];
+    f[b'X' as usize] = [0x63, 0x14, 0x08, 0x14, 0x63];
+    f[b'Y' as usize] = [0x07, 0x08, 0x70, 0x08, 0x07];
+    f[b'Z' as usize] = [0x61, 0x51, 0x49, 0x45, 0x43];
+    // 小写字母
+    f[b'a' as usize] = [0x20, 0x54, 0x54, 0x54, 0x78];
+    f[b'b' as usize] = [0x7F, 0x48, 0x44, 0x44, 0x38];
+    f[b'c' as usize] = [0x38, 0x44, 0x44, 0x44, 0x20];
+    f[b'd' as usize] = [0x38, 0x44, 0x44, 0x48, 0x7F];
+    f[b'e' as usize] = [0x38, 0x54, 0x54, 0x54, 0x18];
+    f[b'f' as usize] = [0x08, 0x7E, 0x09, 0x01, 0x02];
+    f[b'g' as usize] = [0x0C, 0x52, 0x52, 0x52, 0x3E];
+    f[b'h' as usize] = [0x7F, 0x08, 0x04, 0x04, 0x78];
+    f[b'i' as usize] = [0x00, 0x44, 0x7D, 0x40, 0x00];
+    f[b'j' as usize] = [0x20, 0x40, 0x44, 0x3D, 0x00];
+    f[b'k' as usize] = [0x7F, 0x10, 0x28, 0x44, 0x00];
+    f[b'l' as usize] = [0x00, 0x41, 0x7F, 0x40, 0x00];
+    f[b'm' as usize] = [0x7C, 0x04, 0x18, 0x04, 0x78];
+    f[b'n' as usize] = [0x7C, 0x08, 0x04, 0x04, 0x78];
+    f[b'o' as usize] = [0x38, 0x44, 0x44, 0x44, 0x38];
+    f[b'p' as usize] = [0x7C, 0x14, 0x14, 0x14, 0x08];
+    f[b'q' as usize] = [0x08, 0x14, 0x14, 0x18, 0x7C];
+    f[b'r' as usize] = [0x7C, 0x08, 0x04, 0x04, 0x08];
+    f[b's' as usize] = [0x48, 0x54, 0x54, 0x54, 0x20];
+    f[b't' as usize] = [0x04, 0x3F, 0x44, 0x40, 0x20];
+    f[b'u' as usize] = [0x3C, 0x40, 0x40, 0x20, 0x7C];
+    f[b'v' as usize] = [0x1C, 0x20, 0x40, 0x20, 0x1C];
+    f[b'w' as usize] = [0x3C, 0x40, 0x30, 0x40, 0x3C];
+    f[b'x' as usize] = [0x44, 0x28, 0x10, 0x28, 0x44];
+    f[b'y' as usize] = [0x0C, 0x50, 0x50, 0x50, 0x3C];
+    f[b'z' as usize] = [0x44, 0x64, 0x54, 0x4C, 0x44];
+    // 符号
+    f[b'_' as usize] = [0x40, 0x40, 0x40, 0x40, 0x40];
+    f[b'-' as usize] = [0x08, 0x08, 0x08, 0x08, 0x08];
+    f[b'.' as usize] = [0x00, 0x60, 0x60, 0x00, 0x00];
+    f[b':' as usize] = [0x00, 0x36, 0x36, 0x00, 0x00];
+    f[b'/' as usize] = [0x20, 0x10, 0x08, 0x04, 0x02];
+    f[b'=' as usize] = [0x14, 0x14, 0x14, 0x14, 0x14];
+    f[b'[' as usize] = [0x00, 0x7F, 0x41, 0x41, 0x00];
+    f[b']' as usize] = [0x00, 0x41, 0x41, 0x7F, 0x00];
+    f[b'(' as usize] = [0x00, 0x1C, 0x22, 0x41, 0x00];
+    f[b')' as usize] = [0x00, 0x41, 0x22, 0x1C, 0x00];
+    f[b'x' as usize] = [0x44, 0x28, 0x10, 0x28, 0x44];
+    f[b'#' as usize] = [0x14, 0x7F, 0x14, 0x7F, 0x14];
+    f[b',' as usize] = [0x00, 0x50, 0x30, 0x00, 0x00];
+    f[b'!' as usize] = [0x00, 0x00, 0x5F, 0x00, 0x00];
+    f
+};
+
+
+/// 线性帧缓冲区
+pub struct Framebuffer {
+    base: *mut u8,
+    /// 物理地址
+    pub address: u64,
+    pub width: u32,
+    pub height: u32,
+    /// 每行像素数
+    stride: u32,
+    bytes_per_pixel: u32,
+    red_mask: u32,
+    green_mask: u32,
+    blue_mask: u32,
+}
+
+/// 内核使用的帧缓冲区，只在启动早期由 [`init`] 写入一次
+struct Screen(UnsafeCell<Option<Framebuffer>>);
+
+unsafe impl Sync for Screen {}
+
+static SCREEN: Screen = Screen(UnsafeCell::new(None));
+
+/// 映射帧缓冲区并保存，之后（包括异常处理程序中）通过 [`get`] 使用；
+/// 返回是否有可用的线性帧缓冲区
+///
+/// 只能在分页初始化之后、启动早期调用一次。
+pub fn init(info: &BootInfo) -> bool {
+    let fb = Framebuffer::new(info);
+    let available = fb.is_some();
+    unsafe { *SCREEN.0.get() = fb };
+    available
+}
+
+/// [`init`] 映射的帧缓冲区
+pub fn get() -> Option<&'static Framebuffer> {
+    unsafe { (*SCREEN.0.get()).as_ref() }
+}
+
+impl Framebuffer {
+    /// 映射引导程序交接的帧缓冲区（没有线性帧缓冲区时为 None）
+    ///
+    /// 优先以写合并 (WC) 映射到 MMIO 窗口（直接映射中的别名同时改为 WC），
+    /// 失败时退回直接映射。
+    fn new(info: &BootInfo) -> Option<Self> {
+        let fb = info.linear_framebuffer()?;
+        if !(1..=4).contains(&fb.bytes_per_pixel) {
+            return None;
+        }
+        let base = paging::map_mmio(fb.address, fb.size, CacheMode::WriteCombining)
+            .unwrap_or_else(|_| info.phys_to_virt(fb.address));
+        Some(Self {
+            base: base as *mut u8,
+            address: fb.address,
+            width: fb.width,
+            height: fb.height,
+            stride: fb.stride,
+            bytes_per_pixel: fb.bytes_per_pixel,
+            red_mask: fb.red_mask,
+            green_mask: fb.green_mask,
+            blue_mask: fb.blue_mask,
+        })
+    }
+
+    /// 把 `0x00RRGGBB` 编码为帧缓冲区的像素值
+    fn encode(&self, rgb: u32) -> u32 {
+        scale_channel((rgb >> 16) as u8, self.red_mask)
+            | scale_channel((rgb >> 8) as u8, self.green_mask)
+            | scale_channel(rgb as u8, self.blue_mask)
+    }
+
+    fn write_pixel(&self, x: u32, y: u32, pixel: u32) {
+        let offset = ((y * self.stride + x) * self.bytes_per_pixel) as usize;
+        unsafe {
+            let ptr = self.base.add(offset);
+            if self.bytes_per_pixel == 4 {
+                (ptr as *mut u32).write_volatile(pixel);
+            } else {
+                for (i, byte) in pixel.to_le_bytes()[..self.bytes_per_pixel as usize].iter().enumerate() {
+                    ptr.add(i).write_volatile(*byte);
+                }
+            }
+        }
+    }
+
+    /// 填充整个屏幕
+    pub fn clear(&self, color: u32) {
+        self.fill_rect(0, 0, self.width, self.height, color);
+    }
+
+    /// 填充矩形，超出屏幕的部分被裁剪
+    pub fn fill_rect(&self, x: u32, y: u32, w: u32, h: u32, color: u32) {
+        let pixel = self.encode(color);
+        for py in y..(y + h).min(self.height) {
+            for px in x..(x + w).min(self.width) {
+                self.write_pixel(px, py, pixel);
+            }
+        }
+    }
+
+    /// 绘制一个字符，`scale` 为放大倍数
+    pub fn draw_char(&self, x: u32, y: u32, c: char, color: u32, scale: u32) {
+        let idx = (c as usize).min(127);
+        let glyph = FONT[idx];
+
+        for (col, &bits) in glyph.iter().enumerate() {
+            for row in 0..7 {
+                if (bits >> row) & 1 != 0 {
+                    let px = x + (col as u32) * scale;
+                    let py = y + (row as u32) * scale;
+                    self.fill_rect(px, py, scale, scale, color);
+                }
+            }
+        }
+    }
+
+    /// 绘制字符串（字符宽 6 * scale 像素）
+    pub fn draw_string(&self, x: u32, y: u32, s: &str, color: u32, scale: u32) {
+        let mut cx = x;
+        for c in s.chars() {
+            self.draw_char(cx, y, c, color, scale);
+            cx += 6 * scale;
+        }
+    }
+}
+
+/// 8 位分量按掩码的位宽缩放并移到掩码位置
+fn scale_channel(value: u8, mask: u32) -> u32 {
+    if mask == 0 {
+        return 0;
+    }
+    let shift = mask.trailing_zeros();
+    let bits = (mask >> shift).count_ones();
+    let scaled = if bits >= 8 {
+        (value as u32) << (bits - 8)
+    } else {
+        (value as u32) >> (8 - bits)
+    };
+    (scaled << shift) & mask
+}
@@ -0,0 +1,199 @@
+//! 全局描述符表与任务状态段
+//!
+//! 内核在 `_start` 开头装载自己的 GDT，不再沿用固件留下的那一份：
+//!
+//! | 选择子 | 描述符 |
+//! |--------|--------|
+//! | 0x00   | 空描述符 |
+//! | 0x08   | 内核代码段（64 位，DPL 0） |
+//! | 0x10   | 内核数据段（DPL 0） |
+//! | 0x18   | 用户数据段（DPL 3） |
+//! | 0x20   | 用户代码段（64 位，DPL 3） |
+//! | 0x28   | TSS（16 字节系统描述符） |
+//!
+//! 用户数据段排在用户代码段之前，满足 `SYSRET` 对 `STAR` 的布局要求。
+//!
+//! TSS 的中断栈表 (IST) 为双重错误、NMI 与机器检查各提供一个独立的栈，
+//! 即使内核栈已经溢出或损坏，这些异常也能在已知完好的栈上运行。
+
+use core::arch::asm;
+use core::cell::UnsafeCell;
+use core::mem::size_of;
+
+/// 内核代码段选择子
+pub const KERNEL_CODE: u16 = 0x08;
+/// 内核数据段选择子
+pub const KERNEL_DATA: u16 = 0x10;
+/// TSS 选择子
+pub const TSS: u16 = 0x28;
+
+/// 双重错误使用的 IST 索引（IDT 门描述符中的 1..=7）
+pub const IST_DOUBLE_FAULT: u8 = 1;
+/// NMI 使用的 IST 索引
+pub const IST_NMI: u8 = 2;
+/// 机器检查使用的 IST 索引
+pub const IST_MACHINE_CHECK: u8 = 3;
+/// 使用的 IST 栈数
+const IST_STACKS: usize = 3;
+/// 每个 IST 栈的大小
+pub const IST_STACK_SIZE: usize = 16 * 1024;
+
+/// GDT 项数（TSS 描述符占两项）
+const GDT_ENTRIES: usize = 7;
+
+// 段描述符各位
+const DESC_ACCESSED: u64 = 1 << 40;
+const DESC_WRITABLE: u64 = 1 << 41;
+const DESC_EXECUTABLE: u64 = 1 << 43;
+const DESC_USER_SEGMENT: u64 = 1 << 44;
+const DESC_DPL3: u64 = 3 << 45;
+const DESC_PRESENT: u64 = 1 << 47;
+const DESC_LONG_MODE: u64 = 1 << 53;
+/// 64 位可用 TSS 的类型字段
+const DESC_TSS_AVAILABLE: u64 = 0x9 << 40;
+
+const KERNEL_CODE_DESC: u64 = DESC_PRESENT
+    | DESC_USER_SEGMENT
+    | DESC_EXECUTABLE
+    | DESC_WRITABLE
+    | DESC_ACCESSED
+    | DESC_LONG_MODE;
+const KERNEL_DATA_DESC: u64 = DESC_PRESENT | DESC_USER_SEGMENT | DESC_WRITABLE | DESC_ACCESSED;
+const USER_CODE_DESC: u64 = KERNEL_CODE_DESC | DESC_DPL3;
+const USER_DATA_DESC: u64 = KERNEL_DATA_DESC | DESC_DPL3;
+
+/// 64 位任务状态段
+#[repr(C, packed(4))]
+struct TaskStateSegment {
+    _reserved0: u32,
+    /// 从用户态进入 CPL 0..=2 时使用的栈
+    privilege_stacks: [u64; 3],
+    _reserved1: u64,
+    /// 中断栈表，`interrupt_stacks[n - 1]` 对应 IST 索引 n
+    interrupt_stacks: [u64; 7],
+    _reserved2: u64,
+    _reserved3: u16,
+    /// I/O 权限位图偏移；不小于段界限表示没有位图
+    iomap_base: u16,
+}
+
+const _: () = assert!(size_of::<TaskStateSegment>() == 104);
+
+#[repr(C, align(16))]
+struct Stack([u8; IST_STACK_SIZE]);
+
+/// GDT、TSS 与 IST 栈；只在启动早期由 [`init`] 写入一次，之后由 CPU 使用
+struct GdtStore {
+    gdt: UnsafeCell<[u64; GDT_ENTRIES]>,
+    tss: UnsafeCell<TaskStateSegment>,
+    stacks: UnsafeCell<[Stack; IST_STACKS]>,
+}
+
+unsafe impl Sync for GdtStore {}
+
+static STORE: GdtStore = GdtStore {
+    gdt: UnsafeCell::new([0; GDT_ENTRIES]),
+    tss: UnsafeCell::new(TaskStateSegment {
+        _reserved0: 0,
+        privilege_stacks: [0; 3],
+        _reserved1: 0,
+        interrupt_stacks: [0; 7],
+        _reserved2: 0,
+        _reserved3: 0,
+        iomap_base: size_of::<TaskStateSegment>() as u16,
+    }),
+    stacks: UnsafeCell::new([const { Stack([0; IST_STACK_SIZE]) }; IST_STACKS]),
+};
+
+/// `lgdt` 的操作数
+#[repr(C, packed)]
+struct DescriptorTablePointer {
+    limit: u16,
+    base: u64,
+}
+
+/// 建立并装载 GDT，重新加载所有段寄存器，再用 `ltr` 装载 TSS
+///
+/// # Safety
+///
+/// 只能在启动早期（单核、无中断）调用一次；调用之后，固件 GDT 中的选择子全部失效。
+pub unsafe fn init() {
+    let tss = &mut *STORE.tss.get();
+    let stacks = &*STORE.stacks.get();
+    // 栈向下增长，IST 项指向栈顶（TSS 是 packed 结构，整体赋值）
+    let mut interrupt_stacks = [0; 7];
+    for (slot, stack) in interrupt_stacks.iter_mut().zip(stacks) {
+        *slot = stack.0.as_ptr_range().end as u64;
+    }
+    tss.interrupt_stacks = interrupt_stacks;
+
+    let gdt = &mut *STORE.gdt.get();
+    gdt[1] = KERNEL_CODE_DESC;
+    gdt[2] = KERNEL_DATA_DESC;
+    gdt[3] = USER_DATA_DESC;
+    gdt[4] = USER_CODE_DESC;
+    let (low, high) = tss_descriptor(tss as *const TaskStateSegment as u64);
+    gdt[5] = low;
+    gdt[6] = high;
+
+    let pointer = DescriptorTablePointer {
+        limit: (size_of::<[u64; GDT_ENTRIES]>() - 1) as u16,
+        base: gdt.as_ptr() as u64,
+    };
+    asm!("lgdt [{}]", in(reg) &pointer, options(readonly, nostack, preserves_flags));
+
+    // 通过远返回重新加载 CS
+    asm!(
+        "push {sel}",
+        "lea {tmp}, [rip + 2f]",
+        "push {tmp}",
+        "retfq",
+        "2:",
+        sel = in(reg) KERNEL_CODE as u64,
+        tmp = lateout(reg) _,
+        options(preserves_flags),
+    );
+    asm!(
+        "mov ds, {data:x}",
+        "mov es, {data:x}",
+        "mov ss, {data:x}",
+        "mov fs, {null:x}",
+        "mov gs, {null:x}",
+        data = in(reg) KERNEL_DATA as u64,
+        null = in(reg) 0u64,
+        options(nostack, preserves_flags),
+    );
+    asm!("ltr {0:x}", in(reg) TSS, options(nostack, preserves_flags));
+}
+
+/// IST 索引 `index`（1..=7）对应栈的栈顶；未使用的索引返回 0
+pub fn ist_stack_top(index: u8) -> u64 {
+    let tss = unsafe { &*STORE.tss.get() };
+    let stacks = tss.interrupt_stacks;
+    match index {
+        1..=7 => stacks[index as usize - 1],
+        _ => 0,
+    }
+}
+
+/// 当前 CS 与 TR 中的选择子
+pub fn current_selectors() -> (u16, u16) {
+    let (cs, tr): (u16, u16);
+    unsafe {
+        asm!("mov {0:x}, cs", out(reg) cs, options(nomem, nostack, preserves_flags));
+        asm!("str {0:x}", out(reg) tr, options(nomem, nostack, preserves_flags));
+    }
+    (cs, tr)
+}
+
+/// 64 位 TSS 描述符（低 8 字节, 高 8 字节）
+fn tss_descriptor(base: u64) -> (u64, u64) {
+    let limit = (size_of::<TaskStateSegment>() - 1) as u64;
+    let low = DESC_PRESENT
+        | DESC_TSS_AVAILABLE
+        | (limit & 0xFFFF)
+        | ((base & 0xFF_FFFF) << 16)
+        | (((limit >> 16) & 0xF) << 48)
+        | (((base >> 24) & 0xFF) << 56);
+    (low, base >> 32)
+}
@@ -0,0 +1,285 @@
+//! 内核堆
+//!
+//! `#[global_allocator]`，位于内核虚拟地址 [`HEAP_BASE`, `HEAP_LIMIT`)，按需增长：
+//! 空闲页不够时从页帧分配器逐页取得物理帧并映射到堆顶，因此在分页初始化
+//! 之前所有分配都会失败。
+//!
+//! - 不超过 [`SLAB_MAX`] 的分配按 max(size, align) 向上取 2 的幂，走 slab 缓存：
+//!   每个尺寸一条空闲对象链表，链表空了就切分一整页；slab 页不归还
+//! - 更大的分配以页为单位从空闲页链表首次适配；链表按地址排序，
+//!   释放时与相邻区间合并
+//! - 分配失败时在串口输出请求大小与堆统计，然后返回空指针，由 alloc 的
+//!   默认处理程序 panic
+
+use crate::frame::{self, FrameSize};
+use crate::paging::{self, Attributes, PageSize};
+use crate::serial::{serial_write, serial_write_dec, serial_write_size};
+use crate::sync::SpinLock;
+use core::alloc::{GlobalAlloc, Layout};
+use core::ptr;
+
+/// 堆的虚拟地址范围（内核 MMIO 窗口之后，1 TiB）
+const HEAP_BASE: u64 = 0xFFFF_F000_0000_0000;
+const HEAP_LIMIT: u64 = 0xFFFF_F100_0000_0000;
+/// 堆页大小
+const PAGE: usize = 4096;
+/// 每次增长的最少页数
+const GROW_MIN_PAGES: usize = 16;
+
+/// 最小 slab 对象 (2^4 = 16 字节)
+const SLAB_MIN_SHIFT: u32 = 4;
+/// 最大 slab 对象
+const SLAB_MAX: usize = 2048;
+/// slab 尺寸数：16, 32, ..., 2048
+const SLAB_CLASSES: usize = (SLAB_MAX.trailing_zeros() - SLAB_MIN_SHIFT + 1) as usize;
+
+/// 堆统计
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HeapStats {
+    /// 已映射的堆大小（字节）
+    pub heap_size: u64,
+    /// 当前已分配的字节数（按请求大小）
+    pub in_use: u64,
+    /// `in_use` 的峰值
+    pub peak: u64,
+    /// 切分给 slab 缓存的字节数
+    pub slab_bytes: u64,
+    /// 大块分配当前占用的字节数（按页）
+    pub large_bytes: u64,
+    /// 累计分配次数
+    pub allocations: u64,
+    /// 累计释放次数
+    pub frees: u64,
+    /// 分配失败次数
+    pub failures: u64,
+}
+
+/// 空闲页区间（写在区间的第一页中）
+struct FreeRange {
+    pages: usize,
+    next: *mut FreeRange,
+}
+
+/// slab 空闲对象（写在对象本身中）
+struct FreeObject {
+    next: *mut FreeObject,
+}
+
+struct Heap {
+    /// 已映射部分的结束地址
+    top: u64,
+    /// 按地址排序的空闲页区间
+    ranges: *mut FreeRange,
+    /// 各尺寸的空闲对象链表
+    slabs: [*mut FreeObject; SLAB_CLASSES],
+    stats: HeapStats,
+}
+
+// 链表只通过 HEAP 锁访问
+unsafe impl Send for Heap {}
+
+/// 内核全局分配器
+pub struct KernelHeap(SpinLock<Heap>);
+
+#[global_allocator]
+static HEAP: KernelHeap = KernelHeap(SpinLock::new(Heap {
+    top: HEAP_BASE,
+    ranges: ptr::null_mut(),
+    slabs: [ptr::null_mut(); SLAB_CLASSES],
+    stats: HeapStats {
+        heap_size: 0,
+        in_use: 0,
+        peak: 0,
+        slab_bytes: 0,
+        large_bytes: 0,
+        allocations: 0,
+        frees: 0,
+        failures: 0,
+    },
+}));
+
+/// 当前堆统计
+pub fn stats() -> HeapStats {
+    HEAP.0.lock().stats
+}
+
+unsafe impl GlobalAlloc for KernelHeap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let mut heap = self.0.lock();
+        let ptr = match slab_class(layout) {
+            Some(class) => heap.alloc_object(class),
+            None => heap.alloc_pages(pages_for(layout), (layout.align() / PAGE).max(1)),
+        };
+        if ptr.is_null() {
+            heap.stats.failures += 1;
+            let stats = heap.stats;
+            drop(heap);
+            report_oom(layout, &stats);
+            return ptr;
+        }
+        heap.stats.allocations += 1;
+        heap.stats.in_use += layout.size() as u64;
+        heap.stats.peak = heap.stats.peak.max(heap.stats.in_use);
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let mut heap = self.0.lock();
+        match slab_class(layout) {
+            Some(class) => heap.free_object(class, ptr),
+            None => {
+                let pages = pages_for(layout);
+                heap.insert_range(ptr as u64, pages);
+                heap.stats.large_bytes -= (pages * PAGE) as u64;
+            }
+        }
+        heap.stats.frees += 1;
+        heap.stats.in_use -= layout.size() as u64;
+    }
+}
+
+impl Heap {
+    /// 从 slab 缓存取一个对象，缓存空时切分新页
+    unsafe fn alloc_object(&mut self, class: usize) -> *mut u8 {
+        if self.slabs[class].is_null() {
+            let page = self.alloc_pages(1, 1);
+            if page.is_null() {
+                return page;
+            }
+            self.stats.large_bytes -= PAGE as u64;
+            self.stats.slab_bytes += PAGE as u64;
+            let size = class_size(class);
+            for offset in (0..PAGE).step_by(size).rev() {
+                self.free_object(class, page.add(offset));
+            }
+        }
+        let object = self.slabs[class];
+        self.slabs[class] = (*object).next;
+        object as *mut u8
+    }
+
+    unsafe fn free_object(&mut self, class: usize, ptr: *mut u8) {
+        let object = ptr as *mut FreeObject;
+        (*object).next = self.slabs[class];
+        self.slabs[class] = object;
+    }
+
+    /// 分配 `pages` 页，起始地址按 `align` 页对齐；空闲区间不够时增长堆
+    unsafe fn alloc_pages(&mut self, pages: usize, align: usize) -> *mut u8 {
+        loop {
+            if let Some(addr) = self.take_range(pages, align) {
+                self.stats.large_bytes += (pages * PAGE) as u64;
+                return addr as *mut u8;
+            }
+            if !self.grow(pages + align - 1) {
+                return ptr::null_mut();
+            }
+        }
+    }
+
+    /// 首次适配：从空闲区间中切出对齐的 `pages` 页
+    unsafe fn take_range(&mut self, pages: usize, align: usize) -> Option<u64> {
+        let mut link: *mut *mut FreeRange = &mut self.ranges;
+        while !(*link).is_null() {
+            let range = *link;
+            let start = range as u64;
+            let available = (*range).pages;
+            let aligned = start.next_multiple_of((align * PAGE) as u64);
+            let lead = ((aligned - start) / PAGE as u64) as usize;
+            if lead + pages <= available {
+                *link = (*range).next;
+                let tail = available - lead - pages;
+                if lead > 0 {
+                    self.insert_range(start, lead);
+                }
+                if tail > 0 {
+                    self.insert_range(aligned + (pages * PAGE) as u64, tail);
+                }
+                return Some(aligned);
+            }
+            link = &mut (*range).next;
+        }
+        None
+    }
+
+    /// 按地址顺序插入空闲区间，并与前后相邻的区间合并
+    unsafe fn insert_range(&mut self, addr: u64, pages: usize) {
+        let mut prev: *mut FreeRange = ptr::null_mut();
+        let mut next = self.ranges;
+        while !next.is_null() && (next as u64) < addr {
+            prev = next;
+            next = (*next).next;
+        }
+
+        let range = addr as *mut FreeRange;
+        range.write(FreeRange { pages, next });
+        if !next.is_null() && addr + (pages * PAGE) as u64 == next as u64 {
+            (*range).pages += (*next).pages;
+            (*range).next = (*next).next;
+        }
+        if prev.is_null() {
+            self.ranges = range;
+        } else if prev as u64 + ((*prev).pages * PAGE) as u64 == addr {
+            (*prev).pages += (*range).pages;
+            (*prev).next = (*range).next;
+        } else {
+            (*prev).next = range;
+        }
+    }
+
+    /// 在堆顶映射至少 `pages` 页（最少 [`GROW_MIN_PAGES`]），返回是否增长了
+    unsafe fn grow(&mut self, pages: usize) -> bool {
+        let start = self.top;
+        let pages = pages.max(GROW_MIN_PAGES);
+        let mut mapped = 0;
+        while mapped < pages && self.top + PAGE as u64 <= HEAP_LIMIT {
+            let Some(phys) = frame::alloc(FrameSize::Size4K) else {
+                break;
+            };
+            let result = paging::with_kernel_space(|space| {
+                space.map(self.top, phys, PageSize::Size4K, Attributes::KERNEL_DATA)
+            });
+            if !matches!(result, Ok(Ok(()))) {
+                frame::free(phys, FrameSize::Size4K);
+                break;
+            }
+            self.top += PAGE as u64;
+            mapped += 1;
+        }
+        if mapped == 0 {
+            return false;
+        }
+        self.stats.heap_size += (mapped * PAGE) as u64;
+        self.insert_range(start, mapped);
+        true
+    }
+}
+
+/// slab 尺寸索引；超过 [`SLAB_MAX`] 的分配返回 None
+fn slab_class(layout: Layout) -> Option<usize> {
+    let size = layout.size().max(layout.align()).max(1 << SLAB_MIN_SHIFT);
+    (size <= SLAB_MAX).then(|| (size.next_power_of_two().trailing_zeros() - SLAB_MIN_SHIFT) as usize)
+}
+
+fn class_size(class: usize) -> usize {
+    1 << (class as u32 + SLAB_MIN_SHIFT)
+}
+
+fn pages_for(layout: Layout) -> usize {
+    layout.size().max(1).div_ceil(PAGE)
+}
+
+/// 分配失败时输出请求与堆状态
+fn report_oom(layout: Layout, stats: &HeapStats) {
+    serial_write("heap: out of memory allocating ");
+    serial_write_dec(layout.size() as u64);
+    serial_write(" bytes (align ");
+    serial_write_dec(layout.align() as u64);
+    serial_write("), heap ");
+    serial_write_size(stats.heap_size);
+    serial_write(", in use ");
+    serial_write_size(stats.in_use);
+    serial_write(", free frames ");
+    serial_write_dec(frame::stats().free_frames);
+    serial_write("\n");
+}
@@ -0,0 +1,547 @@
+//! 中断描述符表、CPU 异常处理与中断分发
+//!
+//! 256 个向量各有一段汇编入口：没有错误码的向量先压入 0，再压入向量号，
+//! 然后跳到公共入口保存全部通用寄存器，以 [`InterruptFrame`] 调用
+//! [`interrupt_handler`]。入口按 16 字节对齐排列，第 n 个入口位于
+//! `interrupt_stubs + 16 * n`。
+//!
+//! 双重错误、NMI 与机器检查使用 TSS 中的独立栈（见 [`crate::gdt`]）。
+//!
+//! 异常处理程序把向量、错误码、RIP、CR2（缺页时解码 P/W/U/I 位）与全部
+//! 寄存器输出到串口和帧缓冲区，然后进入 panic 流程（按 `panic=` 参数停机、
+//! 重启或关机）。`#BP` 只在串口报告，随后返回到下一条指令。
+//!
+//! 32 以上的向量调用 [`set_handler`] 注册的处理程序，之后向本地 APIC
+//! 发送 EOI；PIC 向量与 APIC 伪中断向量直接忽略。
+
+use crate::apic;
+use crate::cpu::{read_cr0, read_cr2, read_cr3, read_cr4};
+use crate::framebuffer;
+use crate::gdt;
+use crate::serial::{serial_write, serial_write_dec};
+use core::arch::{asm, global_asm};
+use core::cell::UnsafeCell;
+use core::mem::size_of;
+use core::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+
+/// 架构异常数
+pub const EXCEPTION_COUNT: usize = 32;
+const VECTOR_NMI: usize = 2;
+const VECTOR_BREAKPOINT: u64 = 3;
+const VECTOR_DOUBLE_FAULT: usize = 8;
+const VECTOR_PAGE_FAULT: usize = 14;
+const VECTOR_MACHINE_CHECK: usize = 18;
+
+/// 64 位中断门，DPL 0，存在
+const GATE_INTERRUPT: u8 = 0x8E;
+
+/// 中断处理程序；在关中断的状态下运行，返回后由分发代码发送 EOI
+pub type InterruptHandler = fn(&mut InterruptFrame);
+
+/// 各异常的助记符与名称
+const EXCEPTIONS: [(&str, &str); EXCEPTION_COUNT] = [
+    ("#DE", "Divide Error"),
+    ("#DB", "Debug"),
+    ("NMI", "Non-Maskable Interrupt"),
+    ("#BP", "Breakpoint"),
+    ("#OF", "Overflow"),
+    ("#BR", "Bound Range Exceeded"),
+    ("#UD", "Invalid Opcode"),
+    ("#NM", "Device Not Available"),
+    ("#DF", "Double Fault"),
+    ("---", "Coprocessor Segment Overrun"),
+    ("#TS", "Invalid TSS"),
+    ("#NP", "Segment Not Present"),
+    ("#SS", "Stack-Segment Fault"),
+    ("#GP", "General Protection Fault"),
+    ("#PF", "Page Fault"),
+    ("---", "Reserved"),
+    ("#MF", "x87 Floating-Point Error"),
+    ("#AC", "Alignment Check"),
+    ("#MC", "Machine Check"),
+    ("#XM", "SIMD Floating-Point Exception"),
+    ("#VE", "Virtualization Exception"),
+    ("#CP", "Control Protection Exception"),
+    ("---", "Reserved"),
+    ("---", "Reserved"),
+    ("---", "Reserved"),
+    ("---", "Reserved"),
+    ("---", "Reserved"),
+    ("---", "Reserved"),
+    ("#HV", "Hypervisor Injection Exception"),
+    ("#VC", "VMM Communication Exception"),
+    ("#SX", "Security Exception"),
+    ("---", "Reserved"),
+];
+
+/// 错误码为段选择子格式的向量（#TS、#NP、#SS、#GP）
+const SELECTOR_ERROR_VECTORS: [usize; 4] = [10, 11, 12, 13];
+
+/// 中断入口保存的现场，按栈上地址从低到高排列
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct InterruptFrame {
+    pub rax: u64,
+    pub rbx: u64,
+    pub rcx: u64,
+    pub rdx: u64,
+    pub rsi: u64,
+    pub rdi: u64,
+    pub rbp: u64,
+    pub r8: u64,
+    pub r9: u64,
+    pub r10: u64,
+    pub r11: u64,
+    pub r12: u64,
+    pub r13: u64,
+    pub r14: u64,
+    pub r15: u64,
+    pub vector: u64,
+    /// CPU 压入的错误码；没有错误码的向量为 0
+    pub error_code: u64,
+    pub rip: u64,
+    pub cs: u64,
+    pub rflags: u64,
+    pub rsp: u64,
+    pub ss: u64,
+}
+
+// 每个入口 16 字节对齐（最长 12 字节）。CPU 进入时把 RSP 对齐到 16 再压入 5 项，加上错误码、
+// 向量号与 15 个通用寄存器共 22 项，调用处理程序时栈仍是 16 字节对齐的。
+global_asm!(
+    ".p2align 4",
+    ".global interrupt_stubs",
+    "interrupt_stubs:",
+    ".set vector, 0",
+    ".rept 256",
+    ".p2align 4",
+    ".if (vector == 8) || ((vector >= 10) && (vector <= 14)) || (vector == 17) || (vector == 21) || (vector == 29) || (vector == 30)",
+    ".else",
+    "push 0",
+    ".endif",
+    "push vector",
+    "jmp interrupt_common",
+    ".set vector, vector + 1",
+    ".endr",
+    "",
+    "interrupt_common:",
+    "push r15",
+    "push r14",
+    "push r13",
+    "push r12",
+    "push r11",
+    "push r10",
+    "push r9",
+    "push r8",
+    "push rbp",
+    "push rdi",
+    "push rsi",
+    "push rdx",
+    "push rcx",
+    "push rbx",
+    "push rax",
+    "mov rdi, rsp",
+    "cld",
+    "call {handler}",
+    "pop rax",
+    "pop rbx",
+    "pop rcx",
+    "pop rdx",
+    "pop rsi",
+    "pop rdi",
+    "pop rbp",
+    "pop r8",
+    "pop r9",
+    "pop r10",
+    "pop r11",
+    "pop r12",
+    "pop r13",
+    "pop r14",
+    "pop r15",
+    // 丢弃向量号与错误码
+    "add rsp, 16",
+    "iretq",
+    handler = sym interrupt_handler,
+);
+
+unsafe extern "C" {
+    static interrupt_stubs: u8;
+}
+
+/// 门描述符
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct GateDescriptor {
+    offset_low: u16,
+    selector: u16,
+    /// 低 3 位为 IST 索引
+    ist: u8,
+    type_attr: u8,
+    offset_mid: u16,
+    offset_high: u32,
+    _reserved: u32,
+}
+
+const _: () = assert!(size_of::<GateDescriptor>() == 16);
+
+impl GateDescriptor {
+    const MISSING: Self = Self {
+        offset_low: 0,
+        selector: 0,
+        ist: 0,
+        type_attr: 0,
+        offset_mid: 0,
+        offset_high: 0,
+        _reserved: 0,
+    };
+
+    fn interrupt(handler: u64, ist: u8) -> Self {
+        Self {
+            offset_low: handler as u16,
+            selector: gdt::KERNEL_CODE,
+            ist,
+            type_attr: GATE_INTERRUPT,
+            offset_mid: (handler >> 16) as u16,
+            offset_high: (handler >> 32) as u32,
+            _reserved: 0,
+        }
+    }
+}
+
+/// IDT；只在启动早期由 [`init`] 写入一次，之后由 CPU 使用
+struct IdtStore(UnsafeCell<[GateDescriptor; 256]>);
+
+unsafe impl Sync for IdtStore {}
+
+static IDT: IdtStore = IdtStore(UnsafeCell::new([GateDescriptor::MISSING; 256]));
+
+/// 各向量注册的处理程序（函数指针，0 表示未注册）；异常向量不使用
+static HANDLERS: [AtomicUsize; 256] = [const { AtomicUsize::new(0) }; 256];
+
+/// 处理过的 `#BP` 次数
+static BREAKPOINTS: AtomicU64 = AtomicU64::new(0);
+
+/// 正在报告异常；报告过程中再次发生异常时只输出一行并停机
+static IN_EXCEPTION: AtomicBool = AtomicBool::new(false);
+
+/// `lidt` 的操作数
+#[repr(C, packed)]
+struct DescriptorTablePointer {
+    limit: u16,
+    base: u64,
+}
+
+/// 安装全部 256 个入口并装载 IDT
+///
+/// # Safety
+///
+/// 只能在 [`gdt::init`] 之后、启动早期（单核、无中断）调用一次。
+pub unsafe fn init() {
+    let idt = &mut *IDT.0.get();
+    let stubs = &raw const interrupt_stubs as u64;
+    for (vector, gate) in idt.iter_mut().enumerate() {
+        let ist = match vector {
+            VECTOR_DOUBLE_FAULT => gdt::IST_DOUBLE_FAULT,
+            VECTOR_NMI => gdt::IST_NMI,
+            VECTOR_MACHINE_CHECK => gdt::IST_MACHINE_CHECK,
+            _ => 0,
+        };
+        *gate = GateDescriptor::interrupt(stubs + 16 * vector as u64, ist);
+    }
+
+    let pointer = DescriptorTablePointer {
+        limit: (size_of::<[GateDescriptor; 256]>() - 1) as u16,
+        base: idt.as_ptr() as u64,
+    };
+    asm!("lidt [{}]", in(reg) &pointer, options(readonly, nostack, preserves_flags));
+}
+
+/// 为 `vector` 注册处理程序；异常向量或已注册的向量返回 false
+pub fn set_handler(vector: u8, handler: InterruptHandler) -> bool {
+    (vector as usize) >= EXCEPTION_COUNT
+        && HANDLERS[vector as usize]
+            .compare_exchange(0, handler as usize, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+}
+
+/// 异常的助记符与名称
+pub fn exception_name(vector: u8) -> (&'static str, &'static str) {
+    EXCEPTIONS
+        .get(vector as usize)
+        .copied()
+        .unwrap_or(("---", "Unknown"))
+}
+
+/// 触发一次 `#BP`，返回处理程序是否处理了它并返回到下一条指令
+pub fn breakpoint_self_test() -> bool {
+    let before = BREAKPOINTS.load(Ordering::Relaxed);
+    let marker: u64;
+    unsafe {
+        asm!("xor {0:e}, {0:e}", "int3", "mov {0:e}, 1", out(reg) marker, options(nostack));
+    }
+    marker == 1 && BREAKPOINTS.load(Ordering::Relaxed) == before + 1
+}
+
+/// 公共入口调用的 Rust 处理程序
+extern "C" fn interrupt_handler(frame: &mut InterruptFrame) {
+    let vector = frame.vector as usize;
+    if vector < EXCEPTION_COUNT {
+        exception_handler(frame);
+        return;
+    }
+    if apic::is_spurious(vector as u8) {
+        return;
+    }
+    match HANDLERS[vector].load(Ordering::Acquire) {
+        0 => {
+            serial_write("idt: unexpected interrupt on vector ");
+            serial_write_dec(frame.vector);
+            serial_write("\n");
+        }
+        handler => {
+            let handler: InterruptHandler = unsafe { core::mem::transmute(handler) };
+            handler(frame);
+        }
+    }
+    apic::eoi();
+}
+
+/// CPU 异常：报告现场，`#BP` 之外进入 panic 流程
+fn exception_handler(frame: &mut InterruptFrame) {
+    if IN_EXCEPTION.swap(true, Ordering::Acquire) {
+        serial_write("\n!!! EXCEPTION WHILE REPORTING AN EXCEPTION, HALTING !!!\n");
+        halt();
+    }
+
+    let fatal = frame.vector != VECTOR_BREAKPOINT;
+    report(frame, fatal);
+    IN_EXCEPTION.store(false, Ordering::Release);
+
+    if !fatal {
+        BREAKPOINTS.fetch_add(1, Ordering::Relaxed);
+        return;
+    }
+    panic!("unhandled CPU exception");
+}
+
+fn halt() -> ! {
+    loop {
+        unsafe { asm!("cli", "hlt", options(nomem, nostack)) };
+    }
+}
+
+// ============================================================================
+// 报告输出
+// ============================================================================
+
+/// 报告行的最大长度
+const LINE_MAX: usize = 96;
+/// 报告的行数
+const REPORT_LINES: u32 = 11;
+/// 帧缓冲区上报告的位置、行高与颜色
+const SCREEN_X: u32 = 16;
+const SCREEN_Y: u32 = 16;
+const SCREEN_LINE_HEIGHT: u32 = 10;
+const SCREEN_BG: u32 = 0x00600000;
+const SCREEN_FG: u32 = 0x00FFFFFF;
+
+/// 定长行缓冲区，逐行输出到串口，需要时同时画在帧缓冲区顶部
+struct Report {
+    line: [u8; LINE_MAX],
+    len: usize,
+    row: u32,
+    screen: Option<&'static framebuffer::Framebuffer>,
+}
+
+impl Report {
+    fn new(screen: bool) -> Self {
+        let screen = framebuffer::get().filter(|_| screen);
+        if let Some(fb) = screen {
+            let height = SCREEN_Y * 2 + REPORT_LINES * SCREEN_LINE_HEIGHT;
+            fb.fill_rect(0, 0, fb.width, height, SCREEN_BG);
+        }
+        Self {
+            line: [0; LINE_MAX],
+            len: 0,
+            row: 0,
+            screen,
+        }
+    }
+
+    fn push_byte(&mut self, b: u8) -> &mut Self {
+        if self.len < LINE_MAX {
+            self.line[self.len] = b;
+            self.len += 1;
+        }
+        self
+    }
+
+    fn push(&mut self, s: &str) -> &mut Self {
+        for &b in s.as_bytes() {
+            self.push_byte(b);
+        }
+        self
+    }
+
+    /// 16 位定宽十六进制
+    fn push_hex(&mut self, value: u64) -> &mut Self {
+        self.push_hex_digits(value, 16)
+    }
+
+    fn push_hex_digits(&mut self, value: u64, digits: u32) -> &mut Self {
+        const HEX: &[u8; 16] = b"0123456789ABCDEF";
+        for i in (0..digits).rev() {
+            self.push_byte(HEX[((value >> (i * 4)) & 0xF) as usize]);
+        }
+        self
+    }
+
+    fn push_dec(&mut self, value: u64) -> &mut Self {
+        let mut buf = [0u8; 20];
+        let mut i = buf.len();
+        let mut v = value;
+        loop {
+            i -= 1;
+            buf[i] = b'0' + (v % 10) as u8;
+            v /= 10;
+            if v == 0 {
+                break;
+            }
+        }
+        for &b in &buf[i..] {
+            self.push_byte(b);
+        }
+        self
+    }
+
+    /// 结束当前行：输出到串口，并画在帧缓冲区的下一行
+    fn end_line(&mut self) {
+        let text = core::str::from_utf8(&self.line[..self.len]).unwrap_or("?");
+        serial_write(text);
+        serial_write("\n");
+        if let Some(fb) = self.screen {
+            fb.draw_string(
+                SCREEN_X,
+                SCREEN_Y + self.row * SCREEN_LINE_HEIGHT,
+                text,
+                SCREEN_FG,
+                1,
+            );
+        }
+        self.row += 1;
+        self.len = 0;
+    }
+
+    /// 一行三个寄存器，如 `RAX=0000000000000000 RBX=...`
+    fn registers(&mut self, regs: [(&str, u64); 3]) {
+        for (i, (name, value)) in regs.into_iter().enumerate() {
+            if i != 0 {
+                self.push(" ");
+            }
+            self.push(name).push("=").push_hex(value);
+        }
+        self.end_line();
+    }
+}
+
+/// 输出异常报告；致命异常同时画在帧缓冲区上
+fn report(frame: &InterruptFrame, fatal: bool) {
+    let vector = frame.vector as usize;
+    let (mnemonic, name) = exception_name(frame.vector as u8);
+    let mut out = Report::new(fatal);
+
+    serial_write("\n");
+    out.push(if fatal {
+        "!!! CPU EXCEPTION "
+    } else {
+        "CPU EXCEPTION "
+    })
+    .push_dec(frame.vector)
+    .push(" ")
+    .push(mnemonic)
+    .push(" ")
+    .push(name)
+    .push(if fatal { " !!!" } else { "" });
+    out.end_line();
+
+    out.push("Error code: ")
+        .push_hex_digits(frame.error_code, 8);
+    if SELECTOR_ERROR_VECTORS.contains(&vector) && frame.error_code != 0 {
+        push_selector_error(&mut out, frame.error_code);
+    }
+    out.end_line();
+
+    out.push("RIP: ")
+        .push_hex(frame.rip)
+        .push("  CS: ")
+        .push_hex_digits(frame.cs, 4)
+        .push("  RFLAGS: ")
+        .push_hex_digits(frame.rflags, 8);
+    out.end_line();
+
+    let cr2 = read_cr2();
+    out.push("CR2: ").push_hex(cr2);
+    if vector == VECTOR_PAGE_FAULT {
+        push_page_fault_error(&mut out, frame.error_code);
+    }
+    out.end_line();
+
+    out.registers([("RAX", frame.rax), ("RBX", frame.rbx), ("RCX", frame.rcx)]);
+    out.registers([("RDX", frame.rdx), ("RSI", frame.rsi), ("RDI", frame.rdi)]);
+    out.registers([("RBP", frame.rbp), ("RSP", frame.rsp), ("R8 ", frame.r8)]);
+    out.registers([("R9 ", frame.r9), ("R10", frame.r10), ("R11", frame.r11)]);
+    out.registers([("R12", frame.r12), ("R13", frame.r13), ("R14", frame.r14)]);
+    out.registers([("R15", frame.r15), ("SS ", frame.ss), ("CR0", read_cr0())]);
+    out.registers([("CR2", cr2), ("CR3", read_cr3()), ("CR4", read_cr4())]);
+}
+
+/// 缺页错误码：`(not present, write, user, fetch)` 等
+fn push_page_fault_error(out: &mut Report, code: u64) {
+    out.push(if code & 1 != 0 {
+        " (protection violation"
+    } else {
+        " (not present"
+    });
+    out.push(if code & (1 << 1) != 0 {
+        ", write"
+    } else {
+        ", read"
+    });
+    out.push(if code & (1 << 2) != 0 {
+        ", user"
+    } else {
+        ", kernel"
+    });
+    if code & (1 << 3) != 0 {
+        out.push(", reserved bit");
+    }
+    if code & (1 << 4) != 0 {
+        out.push(", instruction fetch");
+    }
+    if code & (1 << 5) != 0 {
+        out.push(", protection key");
+    }
+    if code & (1 << 6) != 0 {
+        out.push(", shadow stack");
+    }
+    out.push(")");
+}
+
+/// 选择子错误码：`(GDT selector 0x0028, external)` 等
+fn push_selector_error(out: &mut Report, code: u64) {
+    let table = match (code >> 1) & 0b11 {
+        0 => "GDT",
+        2 => "LDT",
+        _ => "IDT",
+    };
+    out.push(" (").push(table).push(" ");
+    if table == "IDT" {
+        out.push("vector ").push_dec((code >> 3) & 0x1FFF);
+    } else {
+        out.push("selector ").push_hex_digits(code & 0xFFF8, 4);
+    }
+    if code & 1 != 0 {
+        out.push(", external");
+    }
+    out.push(")");
+}
@@ -6,344 +6,60 @@
 #![no_main]
 #![allow(unsafe_op_in_unsafe_fn)]
 
-use core::arch::asm;
-use core::panic::PanicInfo;
-
-// ============================================================================
-// 与引导程序共享的结构体定义
-// ============================================================================
-
-/// 帧缓冲区信息
-#[repr(C)]
-#[derive(Clone, Copy)]
-pub struct FramebufferInfo {
-    pub address: u64,
-    pub size: u64,
-    pub width: u32,
-    pub height: u32,
-    pub stride: u32,
-    pub bytes_per_pixel: u32,
-    pub pixel_format: u32,
-    pub _reserved: u32,
-}
-
-/// 内存区域描述符
-#[repr(C)]
-#[derive(Clone, Copy)]
-pub struct MemoryRegion {
-    pub phys_start: u64,
-    pub virt_start: u64,
-    pub page_count: u64,
-    pub region_type: u32,
-    pub attributes: u32,
-}
-
-/// 内存区域类型
-#[repr(u32)]
-#[derive(Clone, Copy, Debug, PartialEq)]
-pub enum MemoryRegionType {
-    Usable = 0,
-    Reserved = 1,
-    AcpiReclaimable = 2,
-    AcpiNvs = 3,
-    Mmio = 4,
-    BootloaderReclaimable = 5,
-    KernelAndModules = 6,
-    Framebuffer = 7,
-}
-
-/// 磁盘信息
-#[repr(C)]
-#[derive(Clone, Copy)]
-pub struct DiskInfo {
-    pub disk_type: u32,
-    pub removable: u32,
-    pub boot_device: u32,
-    pub read_only: u32,
-    pub block_size: u64,
-    pub total_blocks: u64,
-    pub total_size: u64,
-    pub media_id: u32,
-    pub _reserved: u32,
-}
-
-/// 主引导信息结构体
-#[repr(C)]
-#[derive(Clone, Copy)]
-pub struct BootInfo {
-    pub magic: u64,
-    pub version: u32,
-    pub size: u32,
-
-    pub framebuffer: FramebufferInfo,
-
-    pub memory_map_addr: u64,
-    pub memory_map_entries: u32,
-    pub memory_map_entry_size: u32,
-    pub total_memory: u64,
-    pub usable_memory: u64,
-
-    pub acpi_rsdp_addr: u64,
-    pub acpi_version: u32,
-    pub _acpi_reserved: u32,
-
-    pub smbios_addr: u64,
-    pub smbios_version: u32,
-    pub _smbios_reserved: u32,
+extern crate alloc;
 
-    pub disk_info_addr: u64,
-    pub disk_count: u32,
-    pub boot_disk_index: i32,
+mod acpi;
+mod aml;
+mod apic;
+mod cmdline;
+mod cpu;
+mod efi;
+mod firmware;
+mod frame;
+mod framebuffer;
+mod gdt;
+mod heap;
+mod idt;
+mod paging;
+mod pci;
+mod port;
+mod power;
+mod serial;
+mod smbios;
+mod sync;
+mod time;
 
-    pub uefi_runtime_services: u64,
-
-    pub kernel_phys_addr: u64,
-    pub kernel_size: u64,
-
-    pub cmdline_addr: u64,
-    pub cmdline_len: u32,
-    pub _cmdline_reserved: u32,
-}
-
-/// BootInfo 魔数
-const BOOTINFO_MAGIC: u64 = 0x4A414E5F4F530000;
-
-// ============================================================================
-// 串口驱动
-// ============================================================================
-
-const COM1: u16 = 0x3F8;
-
-unsafe fn outb(port: u16, value: u8) {
-    asm!("out dx, al", in("dx") port, in("al") value, options(nomem, nostack));
-}
-
-unsafe fn inb(port: u16) -> u8 {
-    let value: u8;
-    asm!("in al, dx", out("al") value, in("dx") port, options(nomem, nostack));
-    value
-}
-
-fn serial_init() {
-    unsafe {
-        outb(COM1 + 1, 0x00);  // 禁用中断
-        outb(COM1 + 3, 0x80);  // 启用 DLAB
-        outb(COM1 + 0, 0x03);  // 波特率 38400
-        outb(COM1 + 1, 0x00);
-        outb(COM1 + 3, 0x03);  // 8N1
-        outb(COM1 + 2, 0xC7);  // 启用 FIFO
-        outb(COM1 + 4, 0x0B);  // IRQ 启用, RTS/DSR 设置
-    }
-}
-
-fn serial_write_char(c: u8) {
-    unsafe {
-        while (inb(COM1 + 5) & 0x20) == 0 {}
-        outb(COM1, c);
-    }
-}
-
-fn serial_write(s: &str) {
-    for b in s.bytes() {
-        if b == b'\n' {
-            serial_write_char(b'\r');
-        }
-        serial_write_char(b);
-    }
-}
-
-fn serial_write_hex(val: u64) {
-    const HEX: &[u8] = b"0123456789ABCDEF";
-    serial_write("0x");
-    
-    if val == 0 {
-        serial_write_char(b'0');
-        return;
-    }
-    
-    let mut started = false;
-    for i in (0..16).rev() {
-        let digit = ((val >> (i * 4)) & 0xF) as usize;
-        if digit != 0 || started {
-            serial_write_char(HEX[digit]);
-            started = true;
-        }
-    }
-}
-
-fn serial_write_dec(val: u64) {
-    if val == 0 {
-        serial_write_char(b'0');
-        return;
-    }
-    
-    let mut buf = [0u8; 20];
-    let mut i = 0;
-    let mut v = val;
-    
-    while v > 0 {
-        buf[i] = b'0' + (v % 10) as u8;
-        v /= 10;
-        i += 1;
-    }
-    
-    while i > 0 {
-        i -= 1;
-        serial_write_char(buf[i]);
-    }
-}
-
-fn serial_write_size(bytes: u64) {
-    if bytes >= 1024 * 1024 * 1024 {
-        serial_write_dec(bytes / 1024 / 1024 / 1024);
-        serial_write(" GB");
-    } else if bytes >= 1024 * 1024 {
-        serial_write_dec(bytes / 1024 / 1024);
-        serial_write(" MB");
-    } else if bytes >= 1024 {
-        serial_write_dec(bytes / 1024);
-        serial_write(" KB");
-    } else {
-        serial_write_dec(bytes);
-        serial_write(" bytes");
-    }
-}
-
-// ============================================================================
-// 帧缓冲区绘制
-// ============================================================================
-
-fn fill_rect(fb: &FramebufferInfo, x: u32, y: u32, w: u32, h: u32, color: u32) {
-    let fb_ptr = fb.address as *mut u32;
-    for dy in 0..h {
-        for dx in 0..w {
-            let px = x + dx;
-            let py = y + dy;
-            if px < fb.width && py < fb.height {
-                unsafe {
-                    let offset = (py * fb.stride + px) as usize;
-                    *fb_ptr.add(offset) = color;
-                }
-            }
-        }
-    }
-}
-
-fn draw_char(fb: &FramebufferInfo, x: u32, y: u32, c: char, color: u32, scale: u32) {
-    // 简单的 5x7 字体
-    const FONT: [[u8; 5]; 128] = {
-        let mut f = [[0u8; 5]; 128];
-        // 空格
-        f[b' ' as usize] = [0x00, 0x00, 0x00, 0x00, 0x00];
-        // 数字
-        f[b'0' as usize] = [0x3E, 0x51, 0x49, 0x45, 0x3E];
-        f[b'1' as usize] = [0x00, 0x42, 0x7F, 0x40, 0x00];
-        f[b'2' as usize] = [0x42, 0x61, 0x51, 0x49, 0x46];
-        f[b'3' as usize] = [0x21, 0x41, 0x45, 0x4B, 0x31];
-        f[b'4' as usize] = [0x18, 0x14, 0x12, 0x7F, 0x10];
-        f[b'5' as usize] = [0x27, 0x45, 0x45, 0x45, 0x39];
-        f[b'6' as usize] = [0x3C, 0x4A, 0x49, 0x49, 0x30];
-        f[b'7' as usize] = [0x01, 0x71, 0x09, 0x05, 0x03];
-        f[b'8' as usize] = [0x36, 0x49, 0x49, 0x49, 0x36];
-        f[b'9' as usize] = [0x06, 0x49, 0x49, 0x29, 0x1E];
-        // 大写字母
-        f[b'A' as usize] = [0x7E, 0x11, 0x11, 0x11, 0x7E];
-        f[b'B' as usize] = [0x7F, 0x49, 0x49, 0x49, 0x36];
-        f[b'C' as usize] = [0x3E, 0x41, 0x41, 0x41, 0x22];
-        f[b'D' as usize] = [0x7F, 0x41, 0x41, 0x22, 0x1C];
-        f[b'E' as usize] = [0x7F, 0x49, 0x49, 0x49, 0x41];
-        f[b'F' as usize] = [0x7F, 0x09, 0x09, 0x09, 0x01];
-        f[b'G' as usize] = [0x3E, 0x41, 0x49, 0x49, 0x7A];
-        f[b'H' as usize] = [0x7F, 0x08, 0x08, 0x08, 0x7F];
-        f[b'I' as usize] = [0x00, 0x41, 0x7F, 0x41, 0x00];
-        f[b'J' as usize] = [0x20, 0x40, 0x41, 0x3F, 0x01];
-        f[b'K' as usize] = [0x7F, 0x08, 0x14, 0x22, 0x41];
-        f[b'L' as usize] = [0x7F, 0x40, 0x40, 0x40, 0x40];
-        f[b'M' as usize] = [0x7F, 0x02, 0x0C, 0x02, 0x7F];
-        f[b'N' as usize] = [0x7F, 0x04, 0x08, 0x10, 0x7F];
-        f[b'O' as usize] = [0x3E, 0x41, 0x41, 0x41, 0x3E];
-        f[b'P' as usize] = [0x7F, 0x09, 0x09, 0x09, 0x06];
-        f[b'Q' as usize] = [0x3E, 0x41, 0x51, 0x21, 0x5E];
-        f[b'R' as usize] = [0x7F, 0x09, 0x19, 0x29, 0x46];
-        f[b'S' as usize] = [0x46, 0x49, 0x49, 0x49, 0x31];
-        f[b'T' as usize] = [0x01, 0x01, 0x7F, 0x01, 0x01];
-        f[b'U' as usize] = [0x3F, 0x40, 0x40, 0x40, 0x3F];
-        f[b'V' as usize] = [0x1F, 0x20, 0x40, 0x20, 0x1F];
-        f[b'W' as usize] = [0x3F, 0x40, 0x38, 0x40, 0x3F];
-        f[b'X' as usize] = [0x63, 0x14, 0x08, 0x14, 0x63];
-        f[b'Y' as usize] = [0x07, 0x08, 0x70, 0x08, 0x07];
-        f[b'Z' as usize] = [0x61, 0x51, 0x49, 0x45, 0x43];
-        // 小写字母
-        f[b'a' as usize] = [0x20, 0x54, 0x54, 0x54, 0x78];
-        f[b'b' as usize] = [0x7F, 0x48, 0x44, 0x44, 0x38];
-        f[b'c' as usize] = [0x38, 0x44, 0x44, 0x44, 0x20];
-        f[b'd' as usize] = [0x38, 0x44, 0x44, 0x48, 0x7F];
-        f[b'e' as usize] = [0x38, 0x54, 0x54, 0x54, 0x18];
-        f[b'f' as usize] = [0x08, 0x7E, 0x09, 0x01, 0x02];
-        f[b'g' as usize] = [0x0C, 0x52, 0x52, 0x52, 0x3E];
-        f[b'h' as usize] = [0x7F, 0x08, 0x04, 0x04, 0x78];
-        f[b'i' as usize] = [0x00, 0x44, 0x7D, 0x40, 0x00];
-        f[b'j' as usize] = [0x20, 0x40, 0x44, 0x3D, 0x00];
-        f[b'k' as usize] = [0x7F, 0x10, 0x28, 0x44, 0x00];
-        f[b'l' as usize] = [0x00, 0x41, 0x7F, 0x40, 0x00];
-        f[b'm' as usize] = [0x7C, 0x04, 0x18, 0x04, 0x78];
-        f[b'n' as usize] = [0x7C, 0x08, 0x04, 0x04, 0x78];
-        f[b'o' as usize] = [0x38, 0x44, 0x44, 0x44, 0x38];
-        f[b'p' as usize] = [0x7C, 0x14, 0x14, 0x14, 0x08];
-        f[b'q' as usize] = [0x08, 0x14, 0x14, 0x18, 0x7C];
-        f[b'r' as usize] = [0x7C, 0x08, 0x04, 0x04, 0x08];
-        f[b's' as usize] = [0x48, 0x54, 0x54, 0x54, 0x20];
-        f[b't' as usize] = [0x04, 0x3F, 0x44, 0x40, 0x20];
-        f[b'u' as usize] = [0x3C, 0x40, 0x40, 0x20, 0x7C];
-        f[b'v' as usize] = [0x1C, 0x20, 0x40, 0x20, 0x1C];
-        f[b'w' as usize] = [0x3C, 0x40, 0x30, 0x40, 0x3C];
-        f[b'x' as usize] = [0x44, 0x28, 0x10, 0x28, 0x44];
-        f[b'y' as usize] = [0x0C, 0x50, 0x50, 0x50, 0x3C];
-        f[b'z' as usize] = [0x44, 0x64, 0x54, 0x4C, 0x44];
-        // 符号
-        f[b'_' as usize] = [0x40, 0x40, 0x40, 0x40, 0x40];
-        f[b'-' as usize] = [0x08, 0x08, 0x08, 0x08, 0x08];
-        f[b'.' as usize] = [0x00, 0x60, 0x60, 0x00, 0x00];
-        f[b':' as usize] = [0x00, 0x36, 0x36, 0x00, 0x00];
-        f[b'/' as usize] = [0x20, 0x10, 0x08, 0x04, 0x02];
-        f[b'=' as usize] = [0x14, 0x14, 0x14, 0x14, 0x14];
-        f[b'[' as usize] = [0x00, 0x7F, 0x41, 0x41, 0x00];
-        f[b']' as usize] = [0x00, 0x41, 0x41, 0x7F, 0x00];
-        f[b'(' as usize] = [0x00, 0x1C, 0x22, 0x41, 0x00];
-        f[b')' as usize] = [0x00, 0x41, 0x22, 0x1C, 0x00];
-        f[b'x' as usize] = [0x44, 0x28, 0x10, 0x28, 0x44];
-        f
-    };
-    
-    let idx = (c as usize).min(127);
-    let glyph = FONT[idx];
-    
-    for (col, &bits) in glyph.iter().enumerate() {
-        for row in 0..7 {
-            if (bits >> row) & 1 != 0 {
-                let px = x + (col as u32) * scale;
-                let py = y + (row as u32) * scale;
-                fill_rect(fb, px, py, scale, scale, color);
-            }
-        }
-    }
-}
-
-fn draw_string(fb: &FramebufferInfo, x: u32, y: u32, s: &str, color: u32, scale: u32) {
-    let mut cx = x;
-    for c in s.chars() {
-        draw_char(fb, cx, y, c, color, scale);
-        cx += 6 * scale;
-    }
-}
+use alloc::vec::Vec;
+use boot_protocol::{
+    BootInfo, BootInfoError, DiskInfo, DiskType, MemoryRegionType, PartitionInfo,
+    PartitionScheme, BOOTINFO_MAGIC, BOOTINFO_VERSION, BOOT_STAGE_COUNT, TRUNCATED_CMDLINE,
+    TRUNCATED_MEMORY_MAP, TRUNCATED_VIDEO_MODES, TRUNCATED_WARNINGS,
+};
+use core::arch::asm;
+use core::panic::PanicInfo;
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::time::Duration;
+use framebuffer::Framebuffer;
+use serial::{
+    serial_init, serial_write, serial_write_char, serial_write_dec, serial_write_hex,
+    serial_write_guid, serial_write_padded, serial_write_size,
+};
 
 // ============================================================================
 // 内核入口点
 // ============================================================================
 
+/// 内核入口，由引导程序跳转进入
+///
+/// # Safety
+///
+/// `boot_info_ptr` 必须指向引导程序填写的 `BootInfo`，且其引用的各个表在内核中可访问。
 #[unsafe(no_mangle)]
-#[unsafe(link_section = ".text.boot")]
 pub unsafe extern "C" fn _start(boot_info_ptr: *const BootInfo) -> ! {
+    // 先换上内核自己的 GDT、TSS 与 IDT，之后的异常都有可靠的栈并能报告现场
+    gdt::init();
+    idt::init();
+
     // 初始化串口
     serial_init();
 
@@ -359,18 +75,104 @@ pub unsafe extern "C" fn _start(boot_info_ptr: *const BootInfo) -> ! {
         halt();
     }
 
-    let info = &*boot_info_ptr;
+    // BootInfo 引用的交接数据只在 boot_report 中使用，返回后即可回收
+    let paging_ready = boot_report(&*boot_info_ptr);
+    if paging_ready {
+        let frames = frame::reclaim_bootloader_memory();
+        serial_write("Reclaimed ");
+        serial_write_size(frames * frame::FRAME_SIZE);
+        serial_write(" of bootloader memory\n\n");
+    }
 
-    if info.magic != BOOTINFO_MAGIC {
-        serial_write("FATAL: Invalid BootInfo magic number!\n");
-        serial_write("  Expected: ");
-        serial_write_hex(BOOTINFO_MAGIC);
-        serial_write("\n  Got:      ");
-        serial_write_hex(info.magic);
+    // ========== 图形测试 ==========
+    match framebuffer::get() {
+        Some(fb) => {
+            serial_write("Drawing to framebuffer...\n");
+            draw_status_screen(fb);
+            serial_write("Framebuffer updated!\n");
+        }
+        None => serial_write("No linear framebuffer, skipping graphics.\n"),
+    }
+    serial_write("\n");
+
+    // 串口输入回显，验证 IO-APIC 路由
+    match apic::route_isa_irq(serial::COM1_IRQ, apic::VECTOR_DEVICE_BASE, serial::serial_receive_interrupt) {
+        Ok(gsi) => {
+            serial::serial_enable_receive_interrupt();
+            serial_write("Serial input echo on IRQ ");
+            serial_write_dec(serial::COM1_IRQ as u64);
+            serial_write(" (GSI ");
+            serial_write_dec(gsi as u64);
+            serial_write(", vector ");
+            serial_write_hex(apic::VECTOR_DEVICE_BASE as u64);
+            serial_write(")\n");
+        }
+        Err(err) => {
+            serial_write("Serial IRQ routing failed: ");
+            serial_write(err.as_str());
+            serial_write("\n");
+        }
+    }
+    // shutdown=poweroff / shutdown=reboot：初始化完成后通过 ACPI 关机或重启
+    match cmdline::get("shutdown") {
+        Some("poweroff") => {
+            serial_write("Kernel initialization complete. Powering off.\n");
+            let err = power::power_off();
+            serial_write("Power off failed: ");
+            serial_write(err.as_str());
+            serial_write("\n");
+        }
+        Some("reboot") => {
+            serial_write("Kernel initialization complete. Rebooting.\n");
+            let err = power::reboot();
+            serial_write("Reboot failed: ");
+            serial_write(err.as_str());
+            serial_write("\n");
+        }
+        _ => {}
+    }
+    serial_write("Kernel initialization complete. Halting with interrupts enabled.\n");
+
+    halt();
+}
+
+/// 校验 BootInfo，初始化各子系统并输出启动报告，返回分页是否就绪
+///
+/// 需要保留的交接数据（命令行、内存映射）在这里复制到内核内存；返回后
+/// 调用者会回收引导程序内存，`info` 引用的各个表随之失效。
+///
+/// # Safety
+///
+/// 同 [`_start`]：`info` 引用的各个表必须在内核中可访问。
+unsafe fn boot_report(info: &BootInfo) -> bool {
+    if let Err(err) = info.validate() {
+        match err {
+            BootInfoError::BadMagic(magic) => {
+                serial_write("FATAL: Invalid BootInfo magic number!\n");
+                serial_write("  Expected: ");
+                serial_write_hex(BOOTINFO_MAGIC);
+                serial_write("\n  Got:      ");
+                serial_write_hex(magic);
+            }
+            BootInfoError::VersionMismatch(version) => {
+                serial_write("FATAL: BootInfo version mismatch!\n");
+                serial_write("  Expected: ");
+                serial_write_dec(BOOTINFO_VERSION as u64);
+                serial_write("\n  Got:      ");
+                serial_write_dec(version as u64);
+            }
+            BootInfoError::SizeMismatch => {
+                serial_write("FATAL: BootInfo layout mismatch (bootloader and kernel out of sync)!");
+            }
+        }
         serial_write("\n");
         halt();
     }
 
+    // 交接内存可能被回收，先把命令行复制到内核缓冲区
+    cmdline::init(info.cmdline_bytes());
+    efi::init(info);
+
     serial_write("BootInfo validated successfully.\n");
     serial_write("  Version: ");
     serial_write_dec(info.version as u64);
@@ -379,35 +181,118 @@ pub unsafe extern "C" fn _start(boot_info_ptr: *const BootInfo) -> ! {
     serial_write(" bytes\n");
     serial_write("\n");
 
-    // ========== 帧缓冲区信息 ==========
-    serial_write("=== FRAMEBUFFER ===\n");
-    serial_write("  Address:        ");
-    serial_write_hex(info.framebuffer.address);
+    // ========== 描述符表 ==========
+    serial_write("=== DESCRIPTOR TABLES ===\n");
+    let (cs, tr) = gdt::current_selectors();
+    serial_write("  CS / TR:        ");
+    serial_write_hex(cs as u64);
+    serial_write(" / ");
+    serial_write_hex(tr as u64);
     serial_write("\n");
-    serial_write("  Size:           ");
-    serial_write_size(info.framebuffer.size);
-    serial_write("\n");
-    serial_write("  Resolution:     ");
-    serial_write_dec(info.framebuffer.width as u64);
-    serial_write(" x ");
-    serial_write_dec(info.framebuffer.height as u64);
-    serial_write("\n");
-    serial_write("  Stride:         ");
-    serial_write_dec(info.framebuffer.stride as u64);
-    serial_write(" pixels/line\n");
-    serial_write("  Bytes/Pixel:    ");
-    serial_write_dec(info.framebuffer.bytes_per_pixel as u64);
-    serial_write("\n");
-    serial_write("  Pixel Format:   ");
-    match info.framebuffer.pixel_format {
-        0 => serial_write("RGB"),
-        1 => serial_write("BGR"),
-        2 => serial_write("Bitmask"),
-        3 => serial_write("BltOnly"),
-        _ => serial_write("Unknown"),
+    for (name, index) in [
+        ("Double fault", gdt::IST_DOUBLE_FAULT),
+        ("NMI", gdt::IST_NMI),
+        ("Machine check", gdt::IST_MACHINE_CHECK),
+    ] {
+        serial_write("  IST");
+        serial_write_dec(index as u64);
+        serial_write(" ");
+        serial_write_padded(name, 14);
+        serial_write(" ");
+        serial_write_hex(gdt::ist_stack_top(index));
+        serial_write(" (");
+        serial_write_size(gdt::IST_STACK_SIZE as u64);
+        serial_write(")\n");
     }
+    serial_write("  IDT:            ");
+    serial_write_dec(idt::EXCEPTION_COUNT as u64);
+    serial_write(" exception handlers, #BP self test ");
+    serial_write(if idt::breakpoint_self_test() { "ok" } else { "FAILED" });
     serial_write("\n\n");
 
+    // ========== 引导警告 ==========
+    serial_write("=== BOOT WARNINGS ===\n");
+    if info.warnings().is_empty() && info.truncated == 0 {
+        serial_write("  (none)\n");
+    }
+    for warning in info.warnings() {
+        serial_write("  [");
+        serial_write_dec(warning.stage.number() as u64);
+        serial_write("/");
+        serial_write_dec(BOOT_STAGE_COUNT as u64);
+        serial_write("] ");
+        serial_write(warning.stage.as_str());
+        serial_write(": status ");
+        serial_write_hex(warning.status);
+        serial_write("\n");
+    }
+    if info.truncated & TRUNCATED_WARNINGS != 0 {
+        serial_write("  ... (more warnings were dropped)\n");
+    }
+    for (flag, table) in [
+        (TRUNCATED_MEMORY_MAP, "memory map"),
+        (TRUNCATED_VIDEO_MODES, "video modes"),
+        (TRUNCATED_CMDLINE, "command line"),
+    ] {
+        if info.truncated & flag != 0 {
+            serial_write("  Truncated table: ");
+            serial_write(table);
+            serial_write("\n");
+        }
+    }
+    serial_write("\n");
+
+    // ========== 帧缓冲区信息 ==========
+    serial_write("=== FRAMEBUFFER ===\n");
+    match info.linear_framebuffer() {
+        Some(fb) => {
+            serial_write("  Address:        ");
+            serial_write_hex(fb.address);
+            serial_write("\n");
+            serial_write("  Size:           ");
+            serial_write_size(fb.size);
+            serial_write("\n");
+            serial_write("  Resolution:     ");
+            serial_write_dec(fb.width as u64);
+            serial_write(" x ");
+            serial_write_dec(fb.height as u64);
+            serial_write("\n");
+            serial_write("  Stride:         ");
+            serial_write_dec(fb.stride as u64);
+            serial_write(" pixels/line\n");
+            serial_write("  Bytes/Pixel:    ");
+            serial_write_dec(fb.bytes_per_pixel as u64);
+            serial_write("\n");
+            serial_write("  Pixel Format:   ");
+            serial_write(fb.pixel_format.as_str());
+            serial_write("\n");
+            serial_write("  Masks (R/G/B):  ");
+            serial_write_hex(fb.red_mask as u64);
+            serial_write(" / ");
+            serial_write_hex(fb.green_mask as u64);
+            serial_write(" / ");
+            serial_write_hex(fb.blue_mask as u64);
+            serial_write("\n");
+        }
+        None => serial_write("  (no linear framebuffer)\n"),
+    }
+    if !info.video_modes().is_empty() {
+        serial_write("  Video Modes:\n");
+        for (i, mode) in info.video_modes().iter().enumerate() {
+            serial_write("    ");
+            serial_write_dec(mode.width as u64);
+            serial_write("x");
+            serial_write_dec(mode.height as u64);
+            serial_write(" ");
+            serial_write(mode.pixel_format.as_str());
+            if i as i32 == info.video_mode_current {
+                serial_write(" [current]");
+            }
+            serial_write("\n");
+        }
+    }
+    serial_write("\n");
+
     // ========== 内存信息 ==========
     serial_write("=== MEMORY ===\n");
     serial_write("  Total Memory:   ");
@@ -431,11 +316,8 @@ pub unsafe extern "C" fn _start(boot_info_ptr: *const BootInfo) -> ! {
     serial_write("  #    Start Address     Pages       Size       Type\n");
     serial_write("  ---------------------------------------------------------\n");
     
-    let mem_regions = info.memory_map_addr as *const MemoryRegion;
-    let mut usable_regions = 0u32;
-    for i in 0..info.memory_map_entries.min(20) {  // 只打印前20个
-        let region = &*mem_regions.add(i as usize);
-        
+    for (i, region) in info.memory_map().iter().enumerate() {
+
         // 序号
         serial_write("  ");
         if i < 10 { serial_write(" "); }
@@ -458,7 +340,7 @@ pub unsafe extern "C" fn _start(boot_info_ptr: *const BootInfo) -> ! {
         serial_write(pad_str);
         
         // 大小
-        let size = pages * 4096;
+        let size = region.size();
         if size >= 1024 * 1024 {
             serial_write_dec(size / 1024 / 1024);
             serial_write(" MB     ");
@@ -471,66 +353,322 @@ pub unsafe extern "C" fn _start(boot_info_ptr: *const BootInfo) -> ! {
         }
         
         // 类型
-        match region.region_type {
-            0 => { serial_write("Usable"); usable_regions += 1; }
-            1 => serial_write("Reserved"),
-            2 => serial_write("ACPI Reclaimable"),
-            3 => serial_write("ACPI NVS"),
-            4 => serial_write("MMIO"),
-            5 => serial_write("Bootloader"),
-            6 => serial_write("Kernel"),
-            7 => serial_write("Framebuffer"),
-            _ => serial_write("Unknown"),
-        }
+        serial_write(region.region_type.as_str());
         serial_write("\n");
     }
     
-    if info.memory_map_entries > 20 {
-        serial_write("  ... (");
-        serial_write_dec((info.memory_map_entries - 20) as u64);
-        serial_write(" more entries)\n");
+    if info.truncated & TRUNCATED_MEMORY_MAP != 0 {
+        serial_write("  ... (truncated: regions beyond the table were dropped)\n");
     }
     serial_write("  ---------------------------------------------------------\n");
+    let usable_regions = info
+        .memory_map()
+        .iter()
+        .filter(|r| r.region_type == MemoryRegionType::Usable)
+        .count();
     serial_write("  Usable regions: ");
     serial_write_dec(usable_regions as u64);
     serial_write("\n\n");
 
+    // ========== 物理页帧分配器 ==========
+    serial_write("=== PHYSICAL FRAMES ===\n");
+    let boot_info_phys = info as *const BootInfo as u64 - info.physical_memory_offset;
+    if frame::init(info, boot_info_phys) {
+        // 各尺寸分配一次再释放，确认位图与统计一致
+        for size in [frame::FrameSize::Size4K, frame::FrameSize::Size2M, frame::FrameSize::Size1G] {
+            serial_write("  Alloc ");
+            serial_write_padded(size.as_str(), 8);
+            match frame::alloc(size) {
+                Some(addr) => {
+                    serial_write_hex(addr);
+                    frame::free(addr, size);
+                }
+                None => serial_write("unavailable"),
+            }
+            serial_write("\n");
+        }
+        let stats = frame::stats();
+        serial_write("  Managed:        ");
+        serial_write_size(stats.total_frames * frame::FRAME_SIZE);
+        serial_write(" (");
+        serial_write_dec(stats.total_frames);
+        serial_write(" frames)\n");
+        serial_write("  Free:           ");
+        serial_write_size(stats.free_frames * frame::FRAME_SIZE);
+        serial_write("\n");
+        serial_write("  Bitmap:         ");
+        serial_write_size(stats.bitmap_bytes);
+        serial_write(" for ");
+        serial_write_dec(stats.tracked_frames);
+        serial_write(" frames\n");
+    } else {
+        serial_write("  No usable region large enough for the frame bitmap\n");
+    }
+    serial_write("\n");
+
+    // ========== 虚拟内存 ==========
+    serial_write("=== VIRTUAL MEMORY ===\n");
+    let paging_ready = match paging::init(info) {
+        Ok(()) => {
+            serial_write("  Kernel PML4:    ");
+            serial_write_hex(paging::with_kernel_space(|space| space.root()).unwrap_or(0));
+            serial_write("\n  PAT:            ");
+            serial_write(if paging::pat_enabled() { "WB/WC/WT/UC" } else { "not supported" });
+            serial_write("\n  1 GiB pages:    ");
+            serial_write(if paging::supports_1g() { "yes" } else { "no" });
+            serial_write("\n  Kernel entry:   ");
+            let entry = _start as *const () as u64;
+            match paging::with_kernel_space(|space| space.translate(entry)) {
+                Ok(Some(t)) => {
+                    serial_write_hex(t.phys);
+                    serial_write(" (");
+                    serial_write(t.size.as_str());
+                    serial_write(" page, ");
+                    serial_write(t.attributes.cache.as_str());
+                    serial_write(")");
+                }
+                _ => serial_write("not mapped"),
+            }
+            serial_write("\n  Self test:      ");
+            match paging_self_test(info) {
+                Ok(()) => serial_write("ok"),
+                Err(err) => serial_write(err.as_str()),
+            }
+            serial_write("\n");
+            true
+        }
+        Err(err) => {
+            serial_write("  Paging init failed: ");
+            serial_write(err.as_str());
+            serial_write("\n");
+            false
+        }
+    };
+    serial_write("\n");
+
+    // 映射帧缓冲区，之后的绘制与异常报告都使用这份映射
+    framebuffer::init(info);
+
+    // ========== 内核堆 ==========
+    serial_write("=== KERNEL HEAP ===\n");
+    serial_write("  Self test:      ");
+    serial_write(if heap_self_test() { "ok" } else { "FAILED" });
+    serial_write("\n");
+    let stats = heap::stats();
+    serial_write("  Heap size:      ");
+    serial_write_size(stats.heap_size);
+    serial_write("\n  In use:         ");
+    serial_write_size(stats.in_use);
+    serial_write(" (peak ");
+    serial_write_size(stats.peak);
+    serial_write(")\n  Slab / large:   ");
+    serial_write_size(stats.slab_bytes);
+    serial_write(" / ");
+    serial_write_size(stats.large_bytes);
+    serial_write("\n  Allocs / frees: ");
+    serial_write_dec(stats.allocations);
+    serial_write(" / ");
+    serial_write_dec(stats.frees);
+    if stats.failures != 0 {
+        serial_write(" (");
+        serial_write_dec(stats.failures);
+        serial_write(" failed)");
+    }
+    serial_write("\n\n");
+
+    // 内存映射的内核副本：ACPI 操作区域据此选择缓存方式，回收引导程序内存之后也只能用它
+    frame::save_memory_map(info.memory_map());
+
     // ========== ACPI 信息 ==========
     serial_write("=== ACPI ===\n");
-    if info.acpi_rsdp_addr != 0 {
-        serial_write("  RSDP Address:   ");
-        serial_write_hex(info.acpi_rsdp_addr);
-        serial_write("\n");
-        serial_write("  ACPI Version:   ");
-        serial_write_dec(info.acpi_version as u64);
-        serial_write(".0\n");
-        
-        // 尝试读取 RSDP 签名
-        let rsdp = info.acpi_rsdp_addr as *const u8;
-        serial_write("  RSDP Signature: ");
-        for i in 0..8 {
-            let c = *rsdp.add(i);
-            if c >= 0x20 && c < 0x7F {
-                serial_write_char(c);
+    let acpi_status = acpi::init(info);
+    match acpi_status {
+        Ok(rsdp) => acpi::dump(&rsdp),
+        Err(err) => {
+            serial_write("  Not available: ");
+            serial_write(err.as_str());
+            serial_write("\n");
+        }
+    }
+    serial_write("\n");
+
+    // ========== 中断控制器 ==========
+    serial_write("=== INTERRUPT CONTROLLERS ===\n");
+    match apic::init() {
+        Ok(apic) => {
+            serial_write("  Legacy PIC:     ");
+            serial_write(if apic.madt.pcat_compat { "present, masked" } else { "not present" });
+            serial_write("\n  Local APIC:     ");
+            serial_write(apic.mode.as_str());
+            serial_write(" at ");
+            serial_write_hex(apic.local_apic_addr);
+            serial_write(", ID ");
+            serial_write_dec(apic.bsp_id as u64);
+            serial_write(", version ");
+            serial_write_hex(apic.version as u64);
+            serial_write(", ");
+            serial_write_dec(apic.lvt_entries as u64);
+            serial_write(" LVT entries\n  Processors:     ");
+            let usable = apic.madt.processors.iter().filter(|p| p.usable).count();
+            serial_write_dec(usable as u64);
+            serial_write(" usable of ");
+            serial_write_dec(apic.madt.processors.len() as u64);
+            serial_write("\n");
+            for io_apic in &apic.io_apics {
+                serial_write("  IO-APIC ");
+                serial_write_dec(io_apic.id as u64);
+                serial_write(":      ");
+                serial_write_hex(io_apic.address);
+                serial_write(", version ");
+                serial_write_hex(io_apic.version as u64);
+                serial_write(", GSI ");
+                serial_write_dec(io_apic.gsi_base as u64);
+                serial_write("-");
+                serial_write_dec((io_apic.gsi_base + io_apic.pins - 1) as u64);
+                serial_write("\n");
+            }
+            for iso in &apic.madt.overrides {
+                let (trigger, polarity) = apic::decode_mps_flags(iso.flags);
+                serial_write("  ISA IRQ ");
+                serial_write_dec(iso.source as u64);
+                serial_write(" -> GSI ");
+                serial_write_dec(iso.gsi as u64);
+                serial_write(match trigger {
+                    apic::TriggerMode::Edge => " edge",
+                    apic::TriggerMode::Level => " level",
+                });
+                serial_write(match polarity {
+                    apic::Polarity::ActiveHigh => " high\n",
+                    apic::Polarity::ActiveLow => " low\n",
+                });
+            }
+            for nmi in &apic.madt.nmis {
+                serial_write("  NMI:            LINT");
+                serial_write_dec(nmi.lint as u64);
+                serial_write(" of ");
+                if nmi.processor_uid == acpi::ALL_PROCESSORS {
+                    serial_write("all processors\n");
+                } else {
+                    serial_write("processor ");
+                    serial_write_dec(nmi.processor_uid as u64);
+                    serial_write("\n");
+                }
             }
         }
-        serial_write("\n");
+        Err(err) => {
+            serial_write("  APIC init failed: ");
+            serial_write(err.as_str());
+            serial_write("\n");
+        }
+    }
+    serial_write("\n");
+
+    // ========== 定时器 ==========
+    serial_write("=== TIMERS ===\n");
+    let time = time::init();
+    // 中断控制器与定时器就绪，从这里开始开中断运行
+    unsafe { asm!("sti", options(nomem, nostack)) };
+    serial_write("  Clock source:   ");
+    serial_write(time.clock.as_str());
+    if time.clock == time::ClockSource::Tsc && time.tsc_invariant {
+        serial_write(" (invariant)");
+    }
+    serial_write("\n  HPET:           ");
+    match time.hpet {
+        Some(hpet) => {
+            serial_write_hex(hpet.address);
+            serial_write(", ");
+            write_frequency(hpet.frequency());
+            serial_write(", ");
+            serial_write_dec(hpet.timers as u64);
+            serial_write(" comparators, ");
+            serial_write(if hpet.counter_64bit { "64-bit" } else { "32-bit" });
+            serial_write(", vendor ");
+            serial_write_hex(hpet.vendor_id as u64);
+        }
+        None => serial_write("not available"),
+    }
+    serial_write("\n  Calibrated by:  ");
+    serial_write(time.reference.as_str());
+    serial_write("\n  TSC:            ");
+    write_frequency(time.tsc_hz);
+    serial_write("\n  LAPIC timer:    ");
+    if time.lapic_timer_hz == 0 {
+        serial_write("not available\n");
     } else {
-        serial_write("  Not available\n");
+        write_frequency(time.lapic_timer_hz * apic::TIMER_DIVIDER);
+        serial_write(if time.tsc_deadline { ", TSC-deadline mode" } else { ", one-shot mode" });
+        let (slept, ticks) = timer_self_test();
+        serial_write("\n  Sleep 10 ms:    ");
+        serial_write_dec(slept.as_micros() as u64);
+        serial_write(" us\n  Periodic 1 ms:  ");
+        serial_write_dec(ticks);
+        serial_write(" ticks in 20 ms\n");
+    }
+    serial_write("\n");
+
+    // ========== ACPI 命名空间 ==========
+    // 在定时器之后加载，_INI 中的 Sleep/Stall 才有时钟可用
+    serial_write("=== ACPI NAMESPACE ===\n");
+    match aml::init() {
+        Ok(load) => {
+            serial_write("  AML:            ");
+            serial_write_dec(load.tables as u64);
+            serial_write(" definition blocks, ");
+            serial_write_size(load.bytes as u64);
+            serial_write(", ");
+            serial_write_dec(load.objects as u64);
+            serial_write(" objects (");
+            serial_write_dec(load.devices as u64);
+            serial_write(" devices, ");
+            serial_write_dec(load.methods as u64);
+            serial_write(" methods)\n  _INI:           ");
+            serial_write_dec(load.initialized as u64);
+            serial_write(" run");
+            if load.errors != 0 {
+                serial_write(", ");
+                serial_write_dec(load.errors as u64);
+                serial_write(" errors");
+            }
+            serial_write("\n  \\_S5:           ");
+            match aml::sleep_type(5) {
+                Ok((a, b)) => {
+                    serial_write("SLP_TYPa ");
+                    serial_write_dec(a as u64);
+                    serial_write(", SLP_TYPb ");
+                    serial_write_dec(b as u64);
+                }
+                Err(err) => serial_write(err.as_str()),
+            }
+            serial_write("\n");
+            write_acpi_devices();
+            write_pci_routes();
+        }
+        Err(err) => {
+            serial_write("  Not available: ");
+            serial_write(err.as_str());
+            serial_write("\n");
+        }
     }
     serial_write("\n");
 
+    // ========== PCI 设备 ==========
+    // 在 AML 切到 APIC 模式之后枚举，INTx 才能按 _PRT 解析
+    serial_write("=== PCI ===\n");
+    pci::register(&STORAGE_DRIVER);
+    let pci_scan = pci::init();
+    pci::dump(&pci_scan);
+    serial_write("\n");
+
     // ========== SMBIOS 信息 ==========
     serial_write("=== SMBIOS ===\n");
-    if info.smbios_addr != 0 {
-        serial_write("  Entry Point:    ");
-        serial_write_hex(info.smbios_addr);
-        serial_write("\n");
-        serial_write("  SMBIOS Version: ");
-        serial_write_dec(info.smbios_version as u64);
-        serial_write(".x\n");
-    } else {
-        serial_write("  Not available\n");
+    match smbios::init(info) {
+        Ok(entry) => smbios::dump(&entry),
+        Err(err) => {
+            serial_write("  Not available: ");
+            serial_write(err.as_str());
+            serial_write("\n");
+        }
     }
     serial_write("\n");
 
@@ -539,6 +677,9 @@ pub unsafe extern "C" fn _start(boot_info_ptr: *const BootInfo) -> ! {
     serial_write("  Disk Count:     ");
     serial_write_dec(info.disk_count as u64);
     serial_write("\n");
+    serial_write("  Partitions:     ");
+    serial_write_dec(info.partition_count as u64);
+    serial_write("\n");
     serial_write("  Boot Disk:      ");
     if info.boot_disk_index >= 0 {
         serial_write("#");
@@ -546,35 +687,65 @@ pub unsafe extern "C" fn _start(boot_info_ptr: *const BootInfo) -> ! {
     } else {
         serial_write("Unknown");
     }
-    serial_write("\n\n");
+    serial_write("\n");
+    let boot_device = &info.boot_device;
+    serial_write("  Boot Partition: ");
+    if boot_device.partition_number != 0 {
+        serial_write_dec(boot_device.partition_number as u64);
+        serial_write(" (");
+        serial_write(boot_device.scheme.as_str());
+        serial_write(", LBA ");
+        serial_write_dec(boot_device.partition_start);
+        serial_write(", ");
+        serial_write_dec(boot_device.partition_blocks);
+        serial_write(" blocks)");
+        if let Some(part) = info.boot_partition() {
+            serial_write(" #");
+            serial_write_dec(info.boot_device.partition_index as u64);
+            if part.name().next().is_some() {
+                serial_write(" '");
+                write_partition_name(part);
+                serial_write("'");
+            }
+        }
+    } else {
+        serial_write("None");
+    }
+    serial_write("\n");
+    if !boot_device.partition_guid.is_zero() {
+        serial_write("  Partition GUID: ");
+        serial_write_guid(&boot_device.partition_guid);
+        serial_write("\n");
+    }
+    if !boot_device.disk_guid.is_zero() {
+        serial_write("  Disk GUID:      ");
+        serial_write_guid(&boot_device.disk_guid);
+        serial_write("\n");
+    }
+    if boot_device.scheme == PartitionScheme::Mbr {
+        serial_write("  MBR Signature:  ");
+        serial_write_hex(boot_device.mbr_signature as u64);
+        serial_write("\n");
+    }
+    serial_write("\n");
 
-    if info.disk_count > 0 {
+    if !info.disks().is_empty() {
         serial_write("  Disk Details:\n");
-        serial_write("  -----------------------------------------------------\n");
-        serial_write("  #  Type      Removable  Size         Block Size\n");
-        serial_write("  -----------------------------------------------------\n");
+        serial_write("  ----------------------------------------------------------------------\n");
+        serial_write("  #  Type      Removable  Size         Block Size   Location\n");
+        serial_write("  ----------------------------------------------------------------------\n");
         
-        let disks = info.disk_info_addr as *const DiskInfo;
-        for i in 0..info.disk_count.min(16) {
-            let disk = &*disks.add(i as usize);
-            
+        for (i, disk) in info.disks().iter().enumerate() {
+
             serial_write("  ");
             serial_write_dec(i as u64);
             serial_write("  ");
             
             // 类型
-            match disk.disk_type {
-                0 => serial_write("Unknown   "),
-                1 => serial_write("HDD       "),
-                2 => serial_write("CD-ROM    "),
-                3 => serial_write("USB       "),
-                4 => serial_write("NVMe      "),
-                5 => serial_write("Floppy    "),
-                _ => serial_write("Other     "),
-            }
+            serial_write_padded(disk.disk_type.as_str(), 10);
             
             // 可移动
-            if disk.removable != 0 {
+            if disk.is_removable() {
                 serial_write("Yes        ");
             } else {
                 serial_write("No         ");
@@ -595,22 +766,68 @@ pub unsafe extern "C" fn _start(boot_info_ptr: *const BootInfo) -> ! {
             
             // 块大小
             serial_write_dec(disk.block_size);
-            serial_write(" bytes");
-            
-            if disk.boot_device != 0 {
+            serial_write(" bytes    ");
+
+            write_disk_location(disk);
+            if disk.is_boot_device() {
                 serial_write(" [BOOT]");
             }
             serial_write("\n");
+
+            if disk.scheme != PartitionScheme::None {
+                serial_write("       ");
+                serial_write(disk.scheme.as_str());
+                if disk.scheme == PartitionScheme::Gpt {
+                    serial_write(" ");
+                    serial_write_guid(&disk.disk_guid);
+                } else if disk.scheme == PartitionScheme::Mbr {
+                    serial_write(" signature ");
+                    serial_write_hex(disk.mbr_signature as u64);
+                }
+                serial_write("\n");
+            }
+            for (j, part) in info.disk_partitions(disk).iter().enumerate() {
+                let index = disk.first_partition as usize + j;
+                let is_boot = usize::try_from(boot_device.partition_index) == Ok(index);
+                write_partition(disk, part, is_boot);
+            }
         }
-        serial_write("  -----------------------------------------------------\n");
+        serial_write("  ----------------------------------------------------------------------\n");
     }
     serial_write("\n");
 
     // ========== UEFI 运行时服务 ==========
     serial_write("=== UEFI RUNTIME SERVICES ===\n");
-    serial_write("  Address:        ");
+    serial_write("  System Table:   ");
+    serial_write_hex(info.uefi_system_table);
+    serial_write("\n");
+    serial_write("  Runtime Table:  ");
     serial_write_hex(info.uefi_runtime_services);
-    serial_write("\n\n");
+    serial_write("\n");
+    if efi::is_available() {
+        serial_write("  Virtual Offset: ");
+        serial_write_hex(info.uefi_runtime_offset);
+        serial_write("\n");
+        serial_write("  Time:           ");
+        match efi::get_time() {
+            Ok(time) => write_efi_time(&time),
+            Err(_) => serial_write("(unavailable)"),
+        }
+        serial_write("\n");
+        serial_write("  BootCurrent:    ");
+        let mut boot_current = [0u8; 2];
+        match efi::get_variable("BootCurrent", &efi::GLOBAL_VARIABLE, &mut boot_current) {
+            Ok(_) => {
+                serial_write("Boot");
+                serial_write_hex(u16::from_le_bytes(boot_current) as u64);
+            }
+            Err(_) => serial_write("(not set)"),
+        }
+        serial_write("\n");
+    } else {
+        serial_write("  (not mapped; runtime services unavailable)\n");
+    }
+    serial_write("\n");
 
     // ========== 内核信息 ==========
     serial_write("=== KERNEL ===\n");
@@ -619,19 +836,101 @@ pub unsafe extern "C" fn _start(boot_info_ptr: *const BootInfo) -> ! {
     serial_write("\n");
     serial_write("  Size:           ");
     serial_write_size(info.kernel_size);
-    serial_write("\n\n");
+    serial_write("\n");
+    serial_write("  Entry Point:    ");
+    serial_write_hex(info.kernel_entry);
+    serial_write("\n");
+    serial_write("  ELF Image:      ");
+    serial_write_hex(info.kernel_elf_addr);
+    serial_write(" (");
+    serial_write_size(info.kernel_elf_size);
+    serial_write(")\n");
+    serial_write("  Segments:\n");
+    for segment in info.kernel_segments() {
+        serial_write("    ");
+        serial_write_hex(segment.virt_addr);
+        serial_write(" -> ");
+        serial_write_hex(segment.phys_addr);
+        serial_write("  ");
+        serial_write_size(segment.mem_size);
+        serial_write("  ");
+        serial_write(if segment.is_writable() { "RW" } else { "R-" });
+        serial_write(if segment.is_executable() { "X" } else { "-" });
+        serial_write("\n");
+    }
+    serial_write("\n");
+
+    // ========== 启动模块 ==========
+    serial_write("=== MODULES ===\n");
+    if info.module_count == 0 {
+        serial_write("  (none)\n");
+    }
+    for module in info.modules() {
+        serial_write("  ");
+        serial_write(module.name);
+        serial_write("\n    ");
+        serial_write_hex(module.phys_addr);
+        serial_write("  ");
+        serial_write_size(module.data.len() as u64);
+        if !module.cmdline.is_empty() {
+            serial_write("  \"");
+            serial_write(module.cmdline);
+            serial_write("\"");
+        }
+        serial_write("\n");
+    }
+    serial_write("\n");
+
+    // ========== 地址空间 ==========
+    serial_write("=== ADDRESS SPACE ===\n");
+    serial_write("  Kernel Base:    ");
+    serial_write_hex(info.kernel_virt_addr);
+    serial_write("\n");
+    serial_write("  Direct Map:     ");
+    serial_write_hex(info.physical_memory_offset);
+    serial_write(" (");
+    serial_write_size(info.physical_memory_mapped);
+    serial_write(")\n");
+    serial_write("  Page Table:     ");
+    serial_write_hex(info.page_table_root);
+    serial_write("\n");
+    serial_write("  Kernel Stack:   ");
+    serial_write_hex(info.kernel_stack_top);
+    serial_write(" (");
+    serial_write_size(info.kernel_stack_size);
+    serial_write(", guard page below)\n\n");
 
     // ========== 命令行 ==========
     serial_write("=== COMMAND LINE ===\n");
-    if info.cmdline_addr != 0 && info.cmdline_len > 0 {
+    if !cmdline::as_str().is_empty() {
         serial_write("  \"");
-        let cmdline = info.cmdline_addr as *const u8;
-        for i in 0..info.cmdline_len.min(256) {
-            let c = *cmdline.add(i as usize);
-            if c == 0 { break; }
-            serial_write_char(c);
-        }
+        serial_write(cmdline::as_str());
         serial_write("\"\n");
+        serial_write("  Console:        ");
+        match cmdline::console() {
+            cmdline::Console::Serial(index) => {
+                serial_write("ttyS");
+                serial_write_dec(index as u64);
+            }
+            cmdline::Console::Framebuffer => serial_write("tty0"),
+        }
+        serial_write("\n");
+        serial_write("  Log Level:      ");
+        let level = cmdline::loglevel();
+        serial_write_dec(level as u64);
+        serial_write(" (");
+        serial_write(level.as_str());
+        serial_write(")\n");
+        serial_write("  Parameters:\n");
+        for param in cmdline::params() {
+            serial_write("    ");
+            serial_write(param.key);
+            if let Some(value) = param.value {
+                serial_write(" = ");
+                serial_write(value);
+            }
+            serial_write("\n");
+        }
     } else {
         serial_write("  (none)\n");
     }
@@ -642,61 +941,415 @@ pub unsafe extern "C" fn _start(boot_info_ptr: *const BootInfo) -> ! {
     serial_write("================================================================\n");
     serial_write("\n");
 
-    // ========== 图形测试 ==========
-    serial_write("Drawing to framebuffer...\n");
-    
-    let fb = &info.framebuffer;
-    
+    paging_ready
+}
+
+/// 在帧缓冲区上绘制启动状态
+fn draw_status_screen(fb: &Framebuffer) {
     // 背景色 (深蓝色)
     let bg_color = 0x001a1a2e;
     // 填充背景
-    for y in 0..fb.height {
-        for x in 0..fb.width {
-            let offset = (y * fb.stride + x) as usize;
-            *((fb.address as *mut u32).add(offset)) = bg_color;
-        }
-    }
+    fb.clear(bg_color);
 
     // 标题
     let title_y = 50;
-    draw_string(fb, 50, title_y, "january_os", 0x00FFFFFF, 4);
+    fb.draw_string(50, title_y, "january_os", 0x00FFFFFF, 4);
     
     // 副标题
-    draw_string(fb, 50, title_y + 40, "Kernel loaded successfully", 0x0088FF88, 2);
+    fb.draw_string(50, title_y + 40, "Kernel loaded successfully", 0x0088FF88, 2);
     
     // 系统信息
     let info_y = title_y + 100;
     let info_color = 0x00AAAAAA;
     
-    draw_string(fb, 50, info_y, "System Information:", 0x00FFFF00, 2);
+    fb.draw_string(50, info_y, "System Information:", 0x00FFFF00, 2);
     
     // 分辨率
-    draw_string(fb, 50, info_y + 30, "Resolution:", info_color, 1);
+    fb.draw_string(50, info_y + 30, "Resolution:", info_color, 1);
     
     // 内存
-    draw_string(fb, 50, info_y + 50, "Memory:", info_color, 1);
+    fb.draw_string(50, info_y + 50, "Memory:", info_color, 1);
     
     // ACPI
-    draw_string(fb, 50, info_y + 70, "ACPI:", info_color, 1);
-    if info.acpi_rsdp_addr != 0 {
-        draw_string(fb, 150, info_y + 70, "Available", 0x0088FF88, 1);
+    fb.draw_string(50, info_y + 70, "ACPI:", info_color, 1);
+    if acpi::available() {
+        fb.draw_string(150, info_y + 70, "Available", 0x0088FF88, 1);
     } else {
-        draw_string(fb, 150, info_y + 70, "Not found", 0x00FF8888, 1);
+        fb.draw_string(150, info_y + 70, "Not found", 0x00FF8888, 1);
     }
     
     // 磁盘数量
-    draw_string(fb, 50, info_y + 90, "Disks:", info_color, 1);
+    fb.draw_string(50, info_y + 90, "Disks:", info_color, 1);
+    
+    // 机器型号 (SMBIOS)
+    fb.draw_string(50, info_y + 110, "Machine:", info_color, 1);
+    let product = smbios::system().and_then(|system| system.product);
+    fb.draw_string(150, info_y + 110, product.unwrap_or("Unknown"), info_color, 1);
     
     // 状态指示器
-    let status_y = fb.height - 50;
-    fill_rect(fb, 50, status_y, 20, 20, 0x0000FF00);  // 绿色方块
-    draw_string(fb, 80, status_y + 5, "Kernel running", 0x00FFFFFF, 1);
+    let status_y = fb.height.saturating_sub(50);
+    fb.fill_rect(50, status_y, 20, 20, 0x0000FF00);  // 绿色方块
+    fb.draw_string(80, status_y + 5, "Kernel running", 0x00FFFFFF, 1);
+}
+
+/// 磁盘控制器：目前只认领寄存器 BAR 是内存 BAR 的 AHCI / NVMe 控制器与 virtio 块设备，
+/// 磁盘访问仍然依赖引导程序交接的 DiskInfo
+static STORAGE_DRIVER: pci::Driver = pci::Driver {
+    name: "storage",
+    ids: &[
+        pci::DeviceId::class(0x01, 0x06).prog_if(0x01),
+        pci::DeviceId::class(0x01, 0x08).prog_if(0x02),
+        pci::DeviceId::device(0x1AF4, 0x1001),
+        pci::DeviceId::device(0x1AF4, 0x1042),
+    ],
+    probe: probe_storage,
+};
+
+fn probe_storage(device: &pci::Device) -> bool {
+    // AHCI 的 ABAR 是 BAR5，NVMe 的寄存器在 BAR0；virtio 的寄存器位置由能力描述
+    let registers = match (device.class, device.subclass) {
+        (0x01, 0x06) => 5,
+        (0x01, 0x08) => 0,
+        _ => return true,
+    };
+    device.bar(registers).is_some_and(|bar| bar.kind != pci::BarKind::Io)
+}
+
+/// 睡眠 10 ms 并测量实际时长，再用 1 ms 周期定时器计数 20 ms
+fn timer_self_test() -> (Duration, u64) {
+    static TICKS: AtomicU64 = AtomicU64::new(0);
+    fn tick() {
+        TICKS.fetch_add(1, Ordering::Relaxed);
+    }
+
+    let start = time::now();
+    time::sleep(Duration::from_millis(10));
+    let slept = time::now() - start;
+
+    let ticks = match time::periodic(Duration::from_millis(1), tick) {
+        Some(id) => {
+            time::sleep(Duration::from_millis(20));
+            time::cancel(id);
+            TICKS.load(Ordering::Relaxed)
+        }
+        None => 0,
+    };
+    (slept, ticks)
+}
+
+/// 输出命名空间中的设备与 PCI 根桥的资源窗口
+fn write_acpi_devices() {
+    let Ok(devices) = aml::devices() else {
+        return;
+    };
+    serial_write("  Devices:\n");
+    for device in &devices {
+        serial_write("    ");
+        serial_write_padded(&device.path, 24);
+        serial_write(" ");
+        serial_write_padded(device.hid.as_deref().unwrap_or("-"), 10);
+        if let Some(address) = device.address {
+            serial_write(" _ADR ");
+            serial_write_hex(address);
+        }
+        serial_write(" _STA ");
+        serial_write_hex(device.status);
+        serial_write("\n");
+    }
+    for (path, resources) in aml::root_bridges().unwrap_or_default() {
+        serial_write("  ");
+        serial_write(&path);
+        serial_write(" windows:\n");
+        for resource in resources {
+            serial_write("    ");
+            match resource {
+                aml::Resource::Range {
+                    kind,
+                    min,
+                    max,
+                    translation,
+                    length,
+                } => {
+                    serial_write(kind.as_str());
+                    serial_write(" ");
+                    serial_write_hex(min);
+                    serial_write("-");
+                    serial_write_hex(max);
+                    serial_write(" (");
+                    serial_write_size(length);
+                    serial_write(")");
+                    if translation != 0 {
+                        serial_write(" translation ");
+                        serial_write_hex(translation);
+                    }
+                }
+                aml::Resource::Io { base, length } => {
+                    serial_write("io ");
+                    serial_write_hex(base as u64);
+                    serial_write(" + ");
+                    serial_write_dec(length as u64);
+                }
+                aml::Resource::Memory { base, length } => {
+                    serial_write("mem ");
+                    serial_write_hex(base);
+                    serial_write(" (");
+                    serial_write_size(length);
+                    serial_write(")");
+                }
+                aml::Resource::Interrupt { numbers, .. } => {
+                    serial_write("irq");
+                    for number in numbers {
+                        serial_write(" ");
+                        serial_write_dec(number as u64);
+                    }
+                }
+            }
+            serial_write("\n");
+        }
+    }
+}
 
-    serial_write("Framebuffer updated!\n");
+/// 输出 [`aml::init`] 解析的根桥 `_PRT`；日志级别低于 debug 时只输出摘要
+fn write_pci_routes() {
+    if let Err(err) = aml::apic_mode() {
+        serial_write("  \\_PIC failed:    ");
+        serial_write(err.as_str());
+        serial_write("\n");
+    }
+    serial_write("  PCI routing:    ");
+    let aml::PciRouting { routes, errors } = match aml::pci_routes() {
+        Ok(routing) => routing,
+        Err(err) => {
+            serial_write(err.as_str());
+            serial_write("\n");
+            return;
+        }
+    };
+    serial_write_dec(routes.len() as u64);
+    serial_write(" _PRT entries, GSIs");
+    let mut gsis: Vec<u32> = routes.iter().map(|route| route.gsi).collect();
+    gsis.sort_unstable();
+    gsis.dedup();
+    for gsi in gsis {
+        serial_write(" ");
+        serial_write_dec(gsi as u64);
+    }
+    if errors != 0 {
+        serial_write(", ");
+        serial_write_dec(errors as u64);
+        serial_write(" skipped");
+    }
     serial_write("\n");
-    serial_write("Kernel initialization complete. Halting.\n");
+    if cmdline::loglevel() < cmdline::LogLevel::Debug {
+        return;
+    }
+    for route in &routes {
+        serial_write("    ");
+        serial_write_hex(route.segment as u64);
+        serial_write(":");
+        serial_write_hex(route.bus as u64);
+        serial_write(":");
+        serial_write_hex(route.device as u64);
+        serial_write(" INT");
+        serial_write_char(b'A' + route.pin);
+        serial_write(" -> GSI ");
+        serial_write_dec(route.gsi as u64);
+        serial_write(match route.trigger {
+            apic::TriggerMode::Edge => " edge",
+            apic::TriggerMode::Level => " level",
+        });
+        serial_write(match route.polarity {
+            apic::Polarity::ActiveHigh => " high",
+            apic::Polarity::ActiveLow => " low",
+        });
+        if let Some(link) = &route.link {
+            serial_write(" via ");
+            serial_write(link);
+        }
+        serial_write("\n");
+    }
+}
 
-    halt();
+/// 输出频率，如 `2995.2 MHz`
+fn write_frequency(hz: u64) {
+    serial_write_dec(hz / 1_000_000);
+    serial_write(".");
+    serial_write_dec(hz / 100_000 % 10);
+    serial_write(" MHz");
+}
+
+/// 在新地址空间中映射一页并写入，再修改权限、重映射、解除映射，
+/// 检查每一步的翻译结果
+fn paging_self_test(info: &BootInfo) -> Result<(), paging::MapError> {
+    use paging::{AddressSpace, Attributes, MapError, PageSize};
+    const TEST_VIRT: u64 = 0x4000_0000;
+    const MAGIC: u64 = 0x4A41_4E5F_4F53_5654;
+
+    let first = frame::alloc(frame::FrameSize::Size4K).ok_or(MapError::OutOfMemory)?;
+    let Some(second) = frame::alloc(frame::FrameSize::Size4K) else {
+        unsafe { frame::free(first, frame::FrameSize::Size4K) };
+        return Err(MapError::OutOfMemory);
+    };
+
+    let result = (|| {
+        let mut space = AddressSpace::new()?;
+        space.map(TEST_VIRT, first, PageSize::Size4K, Attributes::USER_DATA)?;
+        // 切换到新地址空间写入，再切回内核地址空间
+        unsafe {
+            space.activate();
+            (TEST_VIRT as *mut u64).write_volatile(MAGIC);
+            paging::with_kernel_space(|kernel| kernel.activate())?;
+        }
+        let written = unsafe { (info.phys_to_virt(first) as *const u64).read_volatile() };
+
+        space.protect(TEST_VIRT, Attributes::USER_DATA.read_only())?;
+        let protected = space.translate(TEST_VIRT).is_some_and(|t| !t.attributes.writable);
+        space.remap(TEST_VIRT, second)?;
+        let remapped = space.translate(TEST_VIRT + 8).is_some_and(|t| t.phys == second + 8);
+        space.unmap(TEST_VIRT)?;
+        let unmapped = space.translate(TEST_VIRT).is_none();
+
+        match written == MAGIC && protected && remapped && unmapped {
+            true => Ok(()),
+            false => Err(MapError::NotMapped),
+        }
+    })();
+
+    unsafe {
+        frame::free(first, frame::FrameSize::Size4K);
+        frame::free(second, frame::FrameSize::Size4K);
+    }
+    result
+}
+
+/// 用 Vec、Box 与 String 分别走 slab、大块与 realloc 路径，检查内容
+fn heap_self_test() -> bool {
+    use alloc::boxed::Box;
+    use alloc::string::String;
+    use alloc::vec::Vec;
+
+    let small: Vec<Box<u64>> = (0..256).map(Box::new).collect();
+    let large = alloc::vec![0x5Au8; 64 * 1024].into_boxed_slice();
+    let mut text = String::new();
+    for word in cmdline::as_str().split_whitespace().chain(["january_os"]) {
+        text.push_str(word);
+        text.push(' ');
+    }
+
+    small.iter().enumerate().all(|(i, value)| **value == i as u64)
+        && large.iter().all(|&byte| byte == 0x5A)
+        && text.ends_with("january_os ")
+}
+
+/// 输出磁盘表下的一行分区信息，如
+/// `p1  LBA 2048  512 MB  C12A7328-F81F-11D2-BA4B-00A0C93EC93B 'EFI System'`
+fn write_partition(disk: &DiskInfo, part: &PartitionInfo, is_boot: bool) {
+    serial_write("       p");
+    serial_write_dec(part.number as u64);
+    serial_write("  LBA ");
+    serial_write_dec(part.start_lba);
+    serial_write("  ");
+    serial_write_size(part.block_count * disk.block_size);
+    serial_write("  ");
+    match part.scheme {
+        PartitionScheme::Gpt => serial_write_guid(&part.type_guid),
+        _ => {
+            serial_write("type ");
+            serial_write_hex(part.mbr_type as u64);
+        }
+    }
+    if part.name().next().is_some() {
+        serial_write(" '");
+        write_partition_name(part);
+        serial_write("'");
+    }
+    if part.is_bootable() {
+        serial_write(" [ACTIVE]");
+    }
+    if is_boot {
+        serial_write(" [BOOT]");
+    }
+    serial_write("\n");
+}
+
+/// 输出分区名（非 ASCII 字符显示为 `?`）
+fn write_partition_name(part: &PartitionInfo) {
+    for c in part.name() {
+        serial_write_char(if c.is_ascii() && !c.is_ascii_control() { c as u8 } else { b'?' });
+    }
+}
+
+/// 输出磁盘控制器的 PCI 位置与端口，如 `PCI 0000:00:1f.2 port 0`
+fn write_disk_location(disk: &DiskInfo) {
+    fn hex2(value: u8) {
+        const HEX: &[u8; 16] = b"0123456789abcdef";
+        serial_write_char(HEX[(value >> 4) as usize]);
+        serial_write_char(HEX[(value & 0xF) as usize]);
+    }
+
+    if let Some((segment, bus, device, function)) = disk.pci_location() {
+        serial_write("PCI ");
+        hex2((segment >> 8) as u8);
+        hex2(segment as u8);
+        serial_write(":");
+        hex2(bus);
+        serial_write(":");
+        hex2(device);
+        serial_write(".");
+        serial_write_dec(function as u64);
+        // 枚举到的控制器类型与绑定的驱动
+        let address = pci::Address::new(segment, bus, device, function);
+        if let Some(controller) = pci::devices().into_iter().find(|d| d.address == address) {
+            serial_write(" (");
+            serial_write(controller.class_name());
+            if let Some(driver) = controller.driver {
+                serial_write(", ");
+                serial_write(driver);
+            }
+            serial_write(")");
+        }
+    }
+    match disk.disk_type {
+        DiskType::NVMe => {
+            serial_write(" nsid ");
+            serial_write_dec(disk.namespace_id as u64);
+        }
+        DiskType::Sata | DiskType::Scsi | DiskType::CdRom | DiskType::HardDisk => {
+            serial_write(" port ");
+            serial_write_dec(disk.port as u64);
+            if disk.lun != 0 {
+                serial_write(" lun ");
+                serial_write_dec(disk.lun as u64);
+            }
+        }
+        DiskType::Usb | DiskType::Sd | DiskType::Emmc => {
+            serial_write(" port ");
+            serial_write_dec(disk.port as u64);
+        }
+        _ => {}
+    }
+}
+
+/// 输出 `YYYY-MM-DD hh:mm:ss`
+fn write_efi_time(time: &efi::Time) {
+    fn two_digits(value: u8) {
+        if value < 10 {
+            serial_write_char(b'0');
+        }
+        serial_write_dec(value as u64);
+    }
+
+    serial_write_dec(time.year as u64);
+    serial_write("-");
+    two_digits(time.month);
+    serial_write("-");
+    two_digits(time.day);
+    serial_write(" ");
+    two_digits(time.hour);
+    serial_write(":");
+    two_digits(time.minute);
+    serial_write(":");
+    two_digits(time.second);
 }
 
 fn halt() -> ! {
@@ -717,5 +1370,16 @@ fn panic(info: &PanicInfo) -> ! {
         serial_write_dec(location.line() as u64);
         serial_write("\n");
     }
+    if let Some(message) = info.message().as_str() {
+        serial_write("Message: ");
+        serial_write(message);
+        serial_write("\n");
+    }
+    // panic=reboot / panic=poweroff：通过 UEFI 运行时服务重启或关机
+    match cmdline::get("panic") {
+        Some("reboot") => efi::reset_system(efi::ResetType::Cold),
+        Some("poweroff") => efi::reset_system(efi::ResetType::Shutdown),
+        _ => {}
+    }
     halt();
 }
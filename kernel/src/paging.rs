@@ -0,0 +1,699 @@
+//! 内核页表管理
+//!
+//! 把引导程序页表的高半部分复制为内核地址空间，在其上提供映射、解除映射、
+//! 重映射、修改权限与地址翻译：
+//!
+//! - 页表页从物理页帧分配器取得，通过直接映射访问；引导程序的页表页不再使用，
+//!   可以随交接内存一起回收
+//! - 初始化时补齐 PML4 高半部分（256..512）的全部表项，之后新建的地址空间
+//!   复制这些表项即可共享内核映射；低半部分属于各地址空间自己
+//! - 缓存属性通过 PAT 选择：PAT1 改为 WC，PAT5 保留 WT，PAT3 为 UC
+//!   （与引导程序给 MMIO 使用的 PWT|PCD 组合一致）
+//! - 设备内存（帧缓冲区、APIC 等）通过 [`map_mmio`] 映射到内核 MMIO 窗口；
+//!   窗口中的映射是永久的，同一物理范围再次请求时复用
+//!
+//! 映射不会拆分已有的大页：在大页范围内映射或修改 4 KiB 页返回
+//! [`MapError::HugePageConflict`]。
+
+use crate::cpu::{invlpg, rdmsr, read_cr3, wbinvd, wrmsr, write_cr3};
+use crate::frame::{self, FrameSize};
+use crate::sync::SpinLock;
+use alloc::vec::Vec;
+use boot_protocol::BootInfo;
+use core::arch::x86_64::__cpuid;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+/// 页表项：存在
+const PRESENT: u64 = 1 << 0;
+/// 页表项：可写
+const WRITABLE: u64 = 1 << 1;
+/// 页表项：用户态可访问
+const USER: u64 = 1 << 2;
+/// 页表项：写穿透（PAT 索引位 0）
+const WRITE_THROUGH: u64 = 1 << 3;
+/// 页表项：禁用缓存（PAT 索引位 1）
+const CACHE_DISABLE: u64 = 1 << 4;
+/// 页表项：大页 (PDPT 中为 1 GiB，PD 中为 2 MiB)；4 KiB 页表项中同一位是 PAT
+const HUGE_PAGE: u64 = 1 << 7;
+/// 4 KiB 页表项的 PAT 位（PAT 索引位 2）
+const PAT_4K: u64 = 1 << 7;
+/// 大页表项的 PAT 位
+const PAT_HUGE: u64 = 1 << 12;
+/// 页表项：全局页
+const GLOBAL: u64 = 1 << 8;
+/// 页表项：不可执行
+const NO_EXECUTE: u64 = 1 << 63;
+/// 4 KiB 页表项中的物理地址掩码
+const ADDR_MASK: u64 = 0x000F_FFFF_FFFF_F000;
+
+/// EFER MSR
+const IA32_EFER: u32 = 0xC000_0080;
+/// EFER.NXE 位
+const EFER_NXE: u64 = 1 << 11;
+/// IA32_PAT MSR
+const IA32_PAT: u32 = 0x277;
+/// PAT 内存类型编码
+const PAT_UC: u64 = 0x00;
+const PAT_WC: u64 = 0x01;
+const PAT_WT: u64 = 0x04;
+const PAT_WB: u64 = 0x06;
+const PAT_UC_MINUS: u64 = 0x07;
+/// PAT0..PAT7 = WB, WC, UC-, UC, WB, WT, UC-, UC
+const PAT_VALUE: u64 = PAT_WB
+    | PAT_WC << 8
+    | PAT_UC_MINUS << 16
+    | PAT_UC << 24
+    | PAT_WB << 32
+    | PAT_WT << 40
+    | PAT_UC_MINUS << 48
+    | PAT_UC << 56;
+
+/// 内核 MMIO 窗口
+const MMIO_BASE: u64 = 0xFFFF_E000_0000_0000;
+const MMIO_END: u64 = 0xFFFF_F000_0000_0000;
+
+/// 高半部分在 PML4 中的起始索引
+const KERNEL_PML4_START: usize = 256;
+
+/// 直接映射偏移
+static PHYS_OFFSET: AtomicU64 = AtomicU64::new(0);
+/// 内核地址空间的 PML4 物理地址
+static KERNEL_ROOT: AtomicU64 = AtomicU64::new(0);
+/// 引导程序已设置 EFER.NXE，可以使用 NX 位
+static NX: AtomicBool = AtomicBool::new(false);
+/// CPU 支持 1 GiB 页
+static HUGE_1G: AtomicBool = AtomicBool::new(false);
+/// 已按 [`PAT_VALUE`] 设置 PAT
+static PAT: AtomicBool = AtomicBool::new(false);
+/// MMIO 窗口中下一个可用地址
+static MMIO_NEXT: AtomicU64 = AtomicU64::new(MMIO_BASE);
+/// MMIO 窗口中已建立的映射
+static MMIO_MAPPINGS: SpinLock<Vec<MmioMapping>> = SpinLock::new(Vec::new());
+/// 内核地址空间（高半部分的修改都通过它进行）
+static KERNEL_SPACE: SpinLock<Option<AddressSpace>> = SpinLock::new(None);
+
+/// 页大小
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PageSize {
+    Size4K,
+    Size2M,
+    Size1G,
+}
+
+impl PageSize {
+    /// 字节数
+    pub const fn bytes(self) -> u64 {
+        match self {
+            Self::Size4K => 0x1000,
+            Self::Size2M => 0x20_0000,
+            Self::Size1G => 0x4000_0000,
+        }
+    }
+
+    /// 叶子表项所在的层级（0 = PT，1 = PD，2 = PDPT）
+    const fn level(self) -> usize {
+        match self {
+            Self::Size4K => 0,
+            Self::Size2M => 1,
+            Self::Size1G => 2,
+        }
+    }
+
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Size4K => "4 KiB",
+            Self::Size2M => "2 MiB",
+            Self::Size1G => "1 GiB",
+        }
+    }
+}
+
+/// 缓存属性
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CacheMode {
+    WriteBack,
+    WriteThrough,
+    WriteCombining,
+    Uncached,
+}
+
+impl CacheMode {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::WriteBack => "WB",
+            Self::WriteThrough => "WT",
+            Self::WriteCombining => "WC",
+            Self::Uncached => "UC",
+        }
+    }
+}
+
+/// 页属性
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Attributes {
+    pub writable: bool,
+    pub executable: bool,
+    pub user: bool,
+    pub cache: CacheMode,
+}
+
+impl Attributes {
+    /// 内核数据：可写、不可执行
+    pub const KERNEL_DATA: Self = Self {
+        writable: true,
+        executable: false,
+        user: false,
+        cache: CacheMode::WriteBack,
+    };
+
+    /// 用户数据：可写、不可执行、用户态可访问
+    pub const USER_DATA: Self = Self {
+        writable: true,
+        executable: false,
+        user: true,
+        cache: CacheMode::WriteBack,
+    };
+
+    /// 设备内存：可写、不可执行
+    pub const fn mmio(cache: CacheMode) -> Self {
+        Self {
+            writable: true,
+            executable: false,
+            user: false,
+            cache,
+        }
+    }
+
+    /// 同样的属性，只读
+    pub const fn read_only(self) -> Self {
+        Self {
+            writable: false,
+            ..self
+        }
+    }
+
+    /// 编码为叶子表项的标志位
+    fn encode(self, size: PageSize, virt: u64) -> u64 {
+        let mut flags = PRESENT;
+        if self.writable {
+            flags |= WRITABLE;
+        }
+        if self.user {
+            flags |= USER;
+        } else if is_kernel_address(virt) {
+            flags |= GLOBAL;
+        }
+        if !self.executable && NX.load(Ordering::Relaxed) {
+            flags |= NO_EXECUTE;
+        }
+        let pat = if size == PageSize::Size4K { PAT_4K } else { PAT_HUGE };
+        flags |= match (self.cache, PAT.load(Ordering::Relaxed)) {
+            (CacheMode::WriteBack, _) => 0,
+            (CacheMode::WriteCombining, true) => WRITE_THROUGH,
+            (CacheMode::WriteThrough, true) => pat | WRITE_THROUGH,
+            // 没有 PAT 时 PWT 即为 WT，WC 退化为 UC
+            (CacheMode::WriteThrough, false) => WRITE_THROUGH,
+            (CacheMode::Uncached | CacheMode::WriteCombining, _) => WRITE_THROUGH | CACHE_DISABLE,
+        };
+        if size != PageSize::Size4K {
+            flags |= HUGE_PAGE;
+        }
+        flags
+    }
+
+    /// 从叶子表项解码
+    fn decode(entry: u64, size: PageSize) -> Self {
+        let pat = if size == PageSize::Size4K { PAT_4K } else { PAT_HUGE };
+        let cache = match (
+            entry & pat != 0,
+            entry & CACHE_DISABLE != 0,
+            entry & WRITE_THROUGH != 0,
+        ) {
+            (_, true, _) => CacheMode::Uncached,
+            (_, false, false) => CacheMode::WriteBack,
+            (false, false, true) if PAT.load(Ordering::Relaxed) => CacheMode::WriteCombining,
+            (_, false, true) => CacheMode::WriteThrough,
+        };
+        Self {
+            writable: entry & WRITABLE != 0,
+            executable: entry & NO_EXECUTE == 0,
+            user: entry & USER != 0,
+            cache,
+        }
+    }
+}
+
+/// 页表操作失败原因
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MapError {
+    /// 分配页表或 MMIO 虚拟地址失败
+    OutOfMemory,
+    /// 目标地址已有映射
+    AlreadyMapped,
+    /// 目标地址没有映射
+    NotMapped,
+    /// 目标地址位于已有的大页中（或页表中已有更小的页）
+    HugePageConflict,
+    /// 地址没有按页大小对齐，或不是规范地址
+    Misaligned,
+    /// CPU 不支持该页大小
+    Unsupported,
+    /// 分页尚未初始化
+    Uninitialized,
+    /// 与同一物理页已有映射的缓存类型冲突
+    CacheConflict,
+}
+
+impl MapError {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::OutOfMemory => "out of memory",
+            Self::AlreadyMapped => "already mapped",
+            Self::NotMapped => "not mapped",
+            Self::HugePageConflict => "huge page conflict",
+            Self::Misaligned => "misaligned address",
+            Self::Unsupported => "unsupported page size",
+            Self::Uninitialized => "paging not initialized",
+            Self::CacheConflict => "cache mode conflict",
+        }
+    }
+}
+
+/// 地址翻译结果
+#[derive(Clone, Copy, Debug)]
+pub struct Translation {
+    /// 物理地址（含页内偏移）
+    pub phys: u64,
+    /// 所在页的大小
+    pub size: PageSize,
+    pub attributes: Attributes,
+}
+
+/// 初始化分页：记录直接映射偏移，设置 PAT，复制引导程序页表的高半部分
+/// 并切换到副本，补齐内核 PML4 表项
+///
+/// 必须在页帧分配器初始化之后、只调用一次。失败时仍使用引导程序的页表。
+pub fn init(info: &BootInfo) -> Result<(), MapError> {
+    PHYS_OFFSET.store(info.physical_memory_offset, Ordering::Relaxed);
+
+    NX.store(unsafe { rdmsr(IA32_EFER) } & EFER_NXE != 0, Ordering::Relaxed);
+    if __cpuid(0x8000_0000).eax >= 0x8000_0001 {
+        HUGE_1G.store(__cpuid(0x8000_0001).edx & (1 << 26) != 0, Ordering::Relaxed);
+    }
+    if __cpuid(1).edx & (1 << 16) != 0 {
+        unsafe {
+            wbinvd();
+            wrmsr(IA32_PAT, PAT_VALUE);
+            wbinvd();
+            write_cr3(read_cr3());
+        }
+        PAT.store(true, Ordering::Relaxed);
+    }
+
+    let boot_root = read_cr3() & ADDR_MASK;
+    let root = alloc_table()?;
+    let copied = (|| {
+        for index in KERNEL_PML4_START..512 {
+            let entry = table(boot_root)[index];
+            table(root)[index] = match entry & PRESENT {
+                0 => alloc_table()? | PRESENT | WRITABLE,
+                _ => copy_tables(entry & ADDR_MASK, 2)? | (entry & !ADDR_MASK),
+            };
+        }
+        Ok(())
+    })();
+    if let Err(err) = copied {
+        free_tables(root, 3);
+        return Err(err);
+    }
+    // 低半部分只有引导程序跳板的恒等映射，内核不再需要
+    unsafe { write_cr3(root) };
+    KERNEL_ROOT.store(root, Ordering::Relaxed);
+    *KERNEL_SPACE.lock() = Some(AddressSpace { root, owned: false });
+    Ok(())
+}
+
+/// 物理地址在直接映射中的虚拟地址
+pub fn phys_to_virt(phys: u64) -> u64 {
+    phys + PHYS_OFFSET.load(Ordering::Relaxed)
+}
+
+/// CPU 是否支持 1 GiB 页
+pub fn supports_1g() -> bool {
+    HUGE_1G.load(Ordering::Relaxed)
+}
+
+/// 是否启用了 PAT（WC 可用）
+pub fn pat_enabled() -> bool {
+    PAT.load(Ordering::Relaxed)
+}
+
+/// 在内核地址空间中操作（高半部分映射所有地址空间共享）
+pub fn with_kernel_space<R>(f: impl FnOnce(&mut AddressSpace) -> R) -> Result<R, MapError> {
+    let mut space = KERNEL_SPACE.lock();
+    let space = space.as_mut().ok_or(MapError::Uninitialized)?;
+    Ok(f(space))
+}
+
+/// MMIO 窗口中的一段映射（页对齐）
+#[derive(Clone, Copy)]
+struct MmioMapping {
+    phys: u64,
+    len: u64,
+    virt: u64,
+    cache: CacheMode,
+}
+
+/// 把一段设备内存映射到内核 MMIO 窗口，返回对应 `phys` 的虚拟地址
+///
+/// 虚拟地址与物理地址在 2 MiB 内的偏移相同，足够大且对齐的部分使用 2 MiB 页。
+/// 映射是永久的，没有对应的解除映射：请求落在已有映射内且缓存类型相同时复用它，
+/// 与已有映射重叠但缓存类型不同时返回 [`MapError::CacheConflict`]。
+///
+/// 直接映射中同一物理页的别名会改为相同的缓存类型，避免一个物理页有两种内存类型；
+/// 需要修改的别名页越出请求范围（大页还覆盖了其他内存）时返回
+/// [`MapError::HugePageConflict`]，此时不做任何修改。
+pub fn map_mmio(phys: u64, len: u64, cache: CacheMode) -> Result<u64, MapError> {
+    let page = PageSize::Size4K.bytes();
+    let large = PageSize::Size2M.bytes();
+    let phys_start = phys & !(page - 1);
+    let len = (phys + len).next_multiple_of(page) - phys_start;
+    let phys_end = phys_start + len;
+
+    let mut mappings = MMIO_MAPPINGS.lock();
+    for mapping in mappings.iter() {
+        if mapping.phys < phys_end && phys_start < mapping.phys + mapping.len {
+            if mapping.cache != cache {
+                return Err(MapError::CacheConflict);
+            }
+            if mapping.phys <= phys_start && phys_end <= mapping.phys + mapping.len {
+                return Ok(mapping.virt + (phys - mapping.phys));
+            }
+        }
+    }
+
+    // 页表锁内不能分配堆内存（堆扩展也要修改页表）
+    mappings.reserve(1);
+    with_kernel_space(|space| {
+        space.set_direct_map_cache(phys_start, phys_end, cache)?;
+        let mut virt = 0;
+        MMIO_NEXT
+            .try_update(Ordering::Relaxed, Ordering::Relaxed, |next| {
+                virt = next.next_multiple_of(large) + phys_start % large;
+                let end = (virt + len).next_multiple_of(page);
+                (end <= MMIO_END).then_some(end)
+            })
+            .map_err(|_| MapError::OutOfMemory)?;
+        space.map_range(virt, phys_start, len, Attributes::mmio(cache))?;
+        mappings.push(MmioMapping {
+            phys: phys_start,
+            len,
+            virt,
+            cache,
+        });
+        Ok(virt + (phys - phys_start))
+    })?
+}
+
+/// 一个地址空间（一棵 4 级页表）
+pub struct AddressSpace {
+    /// PML4 物理地址
+    root: u64,
+    /// 是否由本对象分配（内核地址空间不是）
+    owned: bool,
+}
+
+impl AddressSpace {
+    /// 新建地址空间，共享内核的高半部分映射
+    pub fn new() -> Result<Self, MapError> {
+        let kernel_root = KERNEL_ROOT.load(Ordering::Relaxed);
+        if kernel_root == 0 {
+            return Err(MapError::Uninitialized);
+        }
+        let root = alloc_table()?;
+        table(root)[KERNEL_PML4_START..].copy_from_slice(&table(kernel_root)[KERNEL_PML4_START..]);
+        Ok(Self { root, owned: true })
+    }
+
+    /// PML4 物理地址（CR3 的值）
+    pub fn root(&self) -> u64 {
+        self.root
+    }
+
+    /// 是否为当前 CR3
+    pub fn is_active(&self) -> bool {
+        read_cr3() & ADDR_MASK == self.root
+    }
+
+    /// 切换到本地址空间
+    ///
+    /// # Safety
+    ///
+    /// 调用者当前执行的代码、栈与之后访问的数据必须在本地址空间中有映射
+    /// （内核高半部分总是共享的）。
+    pub unsafe fn activate(&self) {
+        write_cr3(self.root);
+    }
+
+    /// 映射一页
+    pub fn map(&mut self, virt: u64, phys: u64, size: PageSize, attributes: Attributes) -> Result<(), MapError> {
+        check_aligned(virt, phys, size)?;
+        if size == PageSize::Size1G && !supports_1g() {
+            return Err(MapError::Unsupported);
+        }
+        let entry = self.walk_create(virt, size, attributes.user)?;
+        if *entry & PRESENT != 0 {
+            return Err(if size != PageSize::Size4K && *entry & HUGE_PAGE == 0 {
+                MapError::HugePageConflict
+            } else {
+                MapError::AlreadyMapped
+            });
+        }
+        *entry = (phys & ADDR_MASK) | attributes.encode(size, virt);
+        Ok(())
+    }
+
+    /// 映射一段连续范围，尽量使用大页；`virt`、`phys`、`len` 须按 4 KiB 对齐
+    ///
+    /// 中途失败时已映射的部分保持映射。
+    pub fn map_range(&mut self, virt: u64, phys: u64, len: u64, attributes: Attributes) -> Result<(), MapError> {
+        let mut offset = 0;
+        while offset < len {
+            let (v, p, remaining) = (virt + offset, phys + offset, len - offset);
+            let size = [PageSize::Size1G, PageSize::Size2M, PageSize::Size4K]
+                .into_iter()
+                .find(|size| {
+                    let bytes = size.bytes();
+                    (*size != PageSize::Size1G || supports_1g())
+                        && v % bytes == p % bytes
+                        && v.is_multiple_of(bytes)
+                        && remaining >= bytes
+                })
+                .unwrap_or(PageSize::Size4K);
+            self.map(v, p, size, attributes)?;
+            offset += size.bytes();
+        }
+        Ok(())
+    }
+
+    /// 解除 `virt` 所在页的映射，返回原来的物理页与页大小（物理页不释放）
+    pub fn unmap(&mut self, virt: u64) -> Result<(u64, PageSize), MapError> {
+        let (entry, size) = self.leaf(virt).ok_or(MapError::NotMapped)?;
+        let phys = *entry & leaf_addr_mask(size);
+        *entry = 0;
+        self.flush(virt);
+        Ok((phys, size))
+    }
+
+    /// 把 `virt` 所在页改为指向 `phys`，保留属性
+    pub fn remap(&mut self, virt: u64, phys: u64) -> Result<(), MapError> {
+        let (entry, size) = self.leaf(virt).ok_or(MapError::NotMapped)?;
+        if !phys.is_multiple_of(size.bytes()) {
+            return Err(MapError::Misaligned);
+        }
+        let mask = leaf_addr_mask(size);
+        *entry = (*entry & !mask) | (phys & mask);
+        self.flush(virt);
+        Ok(())
+    }
+
+    /// 修改 `virt` 所在页的属性，返回页大小
+    pub fn protect(&mut self, virt: u64, attributes: Attributes) -> Result<PageSize, MapError> {
+        let (entry, size) = self.leaf(virt).ok_or(MapError::NotMapped)?;
+        let page = virt & !(size.bytes() - 1);
+        *entry = (*entry & leaf_addr_mask(size)) | attributes.encode(size, page);
+        self.flush(virt);
+        Ok(size)
+    }
+
+    /// 把直接映射中 `[start, end)` 的别名改为 `cache`
+    ///
+    /// 需要修改的页须整个落在范围内，先全部检查再修改；直接映射没有覆盖的部分跳过。
+    fn set_direct_map_cache(&mut self, start: u64, end: u64, cache: CacheMode) -> Result<(), MapError> {
+        for apply in [false, true] {
+            let mut phys = start;
+            while phys < end {
+                let Some(t) = self.translate(phys_to_virt(phys)) else {
+                    phys += PageSize::Size4K.bytes();
+                    continue;
+                };
+                let page_start = t.phys & !(t.size.bytes() - 1);
+                let page_end = page_start + t.size.bytes();
+                if t.attributes.cache != cache {
+                    if page_start < start || page_end > end {
+                        return Err(MapError::HugePageConflict);
+                    }
+                    if apply {
+                        self.protect(phys_to_virt(page_start), Attributes { cache, ..t.attributes })?;
+                    }
+                }
+                phys = page_end;
+            }
+        }
+        Ok(())
+    }
+
+    /// 翻译虚拟地址
+    pub fn translate(&self, virt: u64) -> Option<Translation> {
+        let (entry, size) = self.leaf(virt)?;
+        let entry = *entry;
+        Some(Translation {
+            phys: (entry & leaf_addr_mask(size)) | (virt & (size.bytes() - 1)),
+            size,
+            attributes: Attributes::decode(entry, size),
+        })
+    }
+
+    /// 找到 `virt` 的叶子表项（存在的大页或 4 KiB 页）
+    #[allow(clippy::mut_from_ref)] // 页表通过直接映射访问，不属于 self
+    fn leaf(&self, virt: u64) -> Option<(&mut u64, PageSize)> {
+        if !is_canonical(virt) {
+            return None;
+        }
+        let mut table_phys = self.root;
+        for level in (0..4).rev() {
+            let entry = &mut table(table_phys)[index(virt, level)];
+            if *entry & PRESENT == 0 {
+                return None;
+            }
+            match level {
+                0 => return Some((entry, PageSize::Size4K)),
+                1 if *entry & HUGE_PAGE != 0 => return Some((entry, PageSize::Size2M)),
+                2 if *entry & HUGE_PAGE != 0 => return Some((entry, PageSize::Size1G)),
+                _ => table_phys = *entry & ADDR_MASK,
+            }
+        }
+        None
+    }
+
+    /// 找到（必要时创建中间页表）`size` 对应层级的表项
+    fn walk_create(&mut self, virt: u64, size: PageSize, user: bool) -> Result<&mut u64, MapError> {
+        let mut table_phys = self.root;
+        for level in (size.level() + 1..4).rev() {
+            let entry = &mut table(table_phys)[index(virt, level)];
+            if *entry & PRESENT == 0 {
+                // 中间级页表给出最宽松的权限，由叶子表项决定实际权限
+                *entry = alloc_table()? | PRESENT | WRITABLE;
+            } else if *entry & HUGE_PAGE != 0 {
+                return Err(MapError::HugePageConflict);
+            }
+            if user {
+                *entry |= USER;
+            }
+            table_phys = *entry & ADDR_MASK;
+        }
+        Ok(&mut table(table_phys)[index(virt, size.level())])
+    }
+
+    /// 刷新 TLB（高半部分所有地址空间共享，总是刷新）
+    fn flush(&self, virt: u64) {
+        if self.is_active() || is_kernel_address(virt) {
+            unsafe { invlpg(virt) };
+        }
+    }
+}
+
+impl Drop for AddressSpace {
+    /// 释放低半部分的页表页与 PML4（映射的物理页由各自的所有者释放）
+    fn drop(&mut self) {
+        if !self.owned {
+            return;
+        }
+        for &entry in &table(self.root)[..KERNEL_PML4_START] {
+            if entry & PRESENT != 0 {
+                free_tables(entry & ADDR_MASK, 2);
+            }
+        }
+        unsafe { frame::free(self.root, FrameSize::Size4K) };
+    }
+}
+
+/// 递归释放 `level` 级页表（2 = PDPT）及其下级页表
+fn free_tables(table_phys: u64, level: usize) {
+    if level > 0 {
+        for &entry in table(table_phys).iter() {
+            if entry & PRESENT != 0 && entry & HUGE_PAGE == 0 {
+                free_tables(entry & ADDR_MASK, level - 1);
+            }
+        }
+    }
+    unsafe { frame::free(table_phys, FrameSize::Size4K) };
+}
+
+/// 复制一页页表及其下级页表（大页与 4 KiB 表项原样保留），返回副本的物理地址
+fn copy_tables(table_phys: u64, level: usize) -> Result<u64, MapError> {
+    let copy = alloc_table()?;
+    for index in 0..512 {
+        let entry = table(table_phys)[index];
+        if level == 0 || entry & PRESENT == 0 || entry & HUGE_PAGE != 0 {
+            table(copy)[index] = entry;
+            continue;
+        }
+        match copy_tables(entry & ADDR_MASK, level - 1) {
+            Ok(child) => table(copy)[index] = child | (entry & !ADDR_MASK),
+            Err(err) => {
+                free_tables(copy, level);
+                return Err(err);
+            }
+        }
+    }
+    Ok(copy)
+}
+
+/// 通过直接映射访问一页页表
+fn table(phys: u64) -> &'static mut [u64; 512] {
+    unsafe { &mut *((phys + PHYS_OFFSET.load(Ordering::Relaxed)) as *mut [u64; 512]) }
+}
+
+/// 分配一页清零的页表，返回物理地址
+fn alloc_table() -> Result<u64, MapError> {
+    let phys = frame::alloc(FrameSize::Size4K).ok_or(MapError::OutOfMemory)?;
+    table(phys).fill(0);
+    Ok(phys)
+}
+
+/// 大页表项中物理地址的掩码（去掉 PAT 位）
+fn leaf_addr_mask(size: PageSize) -> u64 {
+    ADDR_MASK & !(size.bytes() - 1)
+}
+
+fn check_aligned(virt: u64, phys: u64, size: PageSize) -> Result<(), MapError> {
+    let mask = size.bytes() - 1;
+    if virt & mask != 0 || phys & mask != 0 || !is_canonical(virt) {
+        return Err(MapError::Misaligned);
+    }
+    Ok(())
+}
+
+fn index(virt: u64, level: usize) -> usize {
+    ((virt >> (12 + 9 * level)) & 0x1FF) as usize
+}
+
+fn is_canonical(virt: u64) -> bool {
+    let high = virt >> 47;
+    high == 0 || high == 0x1_FFFF
+}
+
+fn is_kernel_address(virt: u64) -> bool {
+    virt >> 47 == 0x1_FFFF
+}
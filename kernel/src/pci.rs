@@ -0,0 +1,1136 @@
+//! PCI / PCIe
+//!
+//! 配置空间访问：ACPI MCFG 描述的总线通过 ECAM 内存映射访问（每个功能 4 KiB，
+//! 包括扩展配置空间），其余情况退回传统的 0xCF8/0xCFC 端口，只能访问段 0
+//! 每个功能的前 256 字节。
+//!
+//! [`init`] 从每个主桥的根总线开始递归扫描 PCI-PCI 桥下的次级总线，记录每个
+//! 功能的 ID、BAR（写全 1 测出大小）、能力链表（MSI、MSI-X、PCIe 等）与
+//! 扩展能力，并按 ACPI `_PRT` 解析 INTx 路由（桥后的设备按槽位旋转引脚）。
+//! 总线号沿用固件的分配，不重新编号。
+//!
+//! 驱动通过 [`register`] 注册，按厂商/设备 ID 或类别匹配；每个功能最多绑定
+//! 一个驱动，先注册的优先。
+
+use crate::acpi;
+use crate::aml::{self, RangeKind, Resource};
+use crate::cpu::without_interrupts;
+use crate::framebuffer;
+use crate::paging::{self, CacheMode};
+use crate::port::{inb, inl, inw, outb, outl, outw};
+use crate::serial::{
+    serial_write, serial_write_char, serial_write_dec, serial_write_hex, serial_write_size,
+};
+use crate::sync::SpinLock;
+use alloc::vec::Vec;
+
+const CONFIG_ADDRESS: u16 = 0xCF8;
+const CONFIG_DATA: u16 = 0xCFC;
+
+// 配置空间头
+const VENDOR_ID: u16 = 0x00;
+const DEVICE_ID: u16 = 0x02;
+const COMMAND: u16 = 0x04;
+const STATUS: u16 = 0x06;
+const REVISION: u16 = 0x08;
+const HEADER_TYPE: u16 = 0x0E;
+const BAR0: u16 = 0x10;
+const PRIMARY_BUS: u16 = 0x18;
+const SECONDARY_BUS: u16 = 0x19;
+const SUBORDINATE_BUS: u16 = 0x1A;
+const SUBSYSTEM_VENDOR_ID: u16 = 0x2C;
+const SUBSYSTEM_ID: u16 = 0x2E;
+const CAPABILITIES: u16 = 0x34;
+const CARDBUS_CAPABILITIES: u16 = 0x14;
+const INTERRUPT_PIN: u16 = 0x3D;
+/// 扩展能力从这里开始（只有 ECAM 能访问）
+const EXTENDED_CAPABILITIES: u16 = 0x100;
+
+const COMMAND_IO: u16 = 1 << 0;
+const COMMAND_MEMORY: u16 = 1 << 1;
+const STATUS_CAPABILITIES: u16 = 1 << 4;
+const HEADER_MULTIFUNCTION: u8 = 0x80;
+
+const HEADER_DEVICE: u8 = 0;
+const HEADER_BRIDGE: u8 = 1;
+const HEADER_CARDBUS: u8 = 2;
+
+const CAP_POWER_MANAGEMENT: u8 = 0x01;
+const CAP_MSI: u8 = 0x05;
+const CAP_EXPRESS: u8 = 0x10;
+const CAP_MSIX: u8 = 0x11;
+
+/// 桥的最大嵌套深度，防止固件把总线号配成环
+const MAX_BRIDGE_DEPTH: usize = 32;
+/// 能力链表最多走的项数，防止链表成环
+const MAX_CAPABILITIES: usize = 64;
+
+/// 映射好的 ECAM 区域
+static ECAM: SpinLock<Vec<EcamRegion>> = SpinLock::new(Vec::new());
+/// [`init`] 枚举到的所有功能
+static DEVICES: SpinLock<Vec<Device>> = SpinLock::new(Vec::new());
+/// 已注册的驱动，按注册顺序
+static DRIVERS: SpinLock<Vec<&'static Driver>> = SpinLock::new(Vec::new());
+
+/// 一个 PCI 功能的位置
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Address {
+    pub segment: u16,
+    pub bus: u8,
+    pub device: u8,
+    pub function: u8,
+}
+
+impl Address {
+    pub const fn new(segment: u16, bus: u8, device: u8, function: u8) -> Self {
+        Self {
+            segment,
+            bus,
+            device,
+            function,
+        }
+    }
+}
+
+// ============================================================================
+// 配置空间访问
+// ============================================================================
+
+struct EcamRegion {
+    segment: u16,
+    start_bus: u8,
+    end_bus: u8,
+    /// `start_bus` 在 MMIO 窗口中的虚拟地址
+    base: u64,
+}
+
+/// ECAM 中 `address` 的配置空间虚拟地址
+fn ecam_address(address: Address) -> Option<u64> {
+    ECAM.lock()
+        .iter()
+        .find(|region| {
+            region.segment == address.segment
+                && (region.start_bus..=region.end_bus).contains(&address.bus)
+        })
+        .map(|region| {
+            region.base
+                + (((address.bus - region.start_bus) as u64) << 20
+                    | ((address.device & 0x1F) as u64) << 15
+                    | ((address.function & 0x07) as u64) << 12)
+        })
+}
+
+/// 选中 `offset` 所在的双字
+fn select(address: Address, offset: u16) {
+    let value = 1 << 31
+        | (address.bus as u32) << 16
+        | ((address.device & 0x1F) as u32) << 11
+        | ((address.function & 0x07) as u32) << 8
+        | (offset & 0xFC) as u32;
+    unsafe { outl(CONFIG_ADDRESS, value) };
+}
+
+/// 读配置空间，`width` 为 8/16/32/64 位；访问不到的位置读出全 1
+pub fn config_read(address: Address, offset: u16, width: usize) -> u64 {
+    if width == 64 {
+        let low = config_read(address, offset, 32);
+        let high = config_read(address, offset + 4, 32);
+        return low | high << 32;
+    }
+    if let Some(base) = ecam_address(address) {
+        let ptr = base + (offset & 0xFFF) as u64;
+        return unsafe {
+            match width {
+                8 => (ptr as *const u8).read_volatile() as u64,
+                16 => (ptr as *const u16).read_volatile() as u64,
+                _ => (ptr as *const u32).read_volatile() as u64,
+            }
+        };
+    }
+    if address.segment != 0 || offset >= 0x100 {
+        return (1 << width) - 1;
+    }
+    select(address, offset);
+    let port = CONFIG_DATA + (offset & 3);
+    unsafe {
+        match width {
+            8 => inb(port) as u64,
+            16 => inw(port) as u64,
+            _ => inl(CONFIG_DATA) as u64,
+        }
+    }
+}
+
+/// 写配置空间，`width` 为 8/16/32/64 位；访问不到的位置忽略
+pub fn config_write(address: Address, offset: u16, width: usize, value: u64) {
+    if width == 64 {
+        config_write(address, offset, 32, value & 0xFFFF_FFFF);
+        config_write(address, offset + 4, 32, value >> 32);
+        return;
+    }
+    if let Some(base) = ecam_address(address) {
+        let ptr = base + (offset & 0xFFF) as u64;
+        unsafe {
+            match width {
+                8 => (ptr as *mut u8).write_volatile(value as u8),
+                16 => (ptr as *mut u16).write_volatile(value as u16),
+                _ => (ptr as *mut u32).write_volatile(value as u32),
+            }
+        }
+        return;
+    }
+    if address.segment != 0 || offset >= 0x100 {
+        return;
+    }
+    select(address, offset);
+    let port = CONFIG_DATA + (offset & 3);
+    unsafe {
+        match width {
+            8 => outb(port, value as u8),
+            16 => outw(port, value as u16),
+            _ => outl(CONFIG_DATA, value as u32),
+        }
+    }
+}
+
+fn read8(address: Address, offset: u16) -> u8 {
+    config_read(address, offset, 8) as u8
+}
+
+fn read16(address: Address, offset: u16) -> u16 {
+    config_read(address, offset, 16) as u16
+}
+
+fn read32(address: Address, offset: u16) -> u32 {
+    config_read(address, offset, 32) as u32
+}
+
+fn write16(address: Address, offset: u16, value: u16) {
+    config_write(address, offset, 16, value as u64);
+}
+
+fn write32(address: Address, offset: u16, value: u32) {
+    config_write(address, offset, 32, value as u64);
+}
+
+// ============================================================================
+// 设备
+// ============================================================================
+
+/// BAR 类型
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BarKind {
+    Io,
+    Memory32,
+    Memory64,
+}
+
+/// 一个已实现的 BAR
+#[derive(Clone, Copy, Debug)]
+pub struct Bar {
+    /// BAR 编号（64 位 BAR 占用两个编号，记在低的那个）
+    pub index: u8,
+    pub kind: BarKind,
+    pub address: u64,
+    pub size: u64,
+    pub prefetchable: bool,
+}
+
+/// MSI 能力
+#[derive(Clone, Copy, Debug)]
+pub struct Msi {
+    pub offset: u8,
+    pub enabled: bool,
+    /// 消息地址为 64 位
+    pub is_64bit: bool,
+    /// 支持按向量屏蔽
+    pub per_vector_mask: bool,
+    /// 最多可请求的向量数
+    pub max_vectors: u8,
+}
+
+/// MSI-X 能力
+#[derive(Clone, Copy, Debug)]
+pub struct MsiX {
+    pub offset: u8,
+    pub enabled: bool,
+    /// 向量表项数
+    pub table_size: u16,
+    /// 向量表与挂起位数组所在的 BAR 及偏移
+    pub table_bar: u8,
+    pub table_offset: u32,
+    pub pba_bar: u8,
+    pub pba_offset: u32,
+}
+
+/// PCI Express 能力
+#[derive(Clone, Copy, Debug)]
+pub struct Express {
+    pub offset: u8,
+    pub version: u8,
+    pub port_type: u8,
+    /// 链路能力中的最高速度（编码）与宽度
+    pub max_link_speed: u8,
+    pub max_link_width: u8,
+    /// 当前协商出的速度与宽度（没有链路时为 0）
+    pub link_speed: u8,
+    pub link_width: u8,
+}
+
+impl Express {
+    pub fn port_type_name(&self) -> &'static str {
+        match self.port_type {
+            0x0 => "Endpoint",
+            0x1 => "Legacy Endpoint",
+            0x4 => "Root Port",
+            0x5 => "Upstream Port",
+            0x6 => "Downstream Port",
+            0x7 => "PCIe-to-PCI Bridge",
+            0x8 => "PCI-to-PCIe Bridge",
+            0x9 => "Root Complex Integrated Endpoint",
+            0xA => "Root Complex Event Collector",
+            _ => "Unknown",
+        }
+    }
+}
+
+/// 能力链表中的一项
+#[derive(Clone, Copy, Debug)]
+pub enum Capability {
+    PowerManagement { offset: u8 },
+    Msi(Msi),
+    MsiX(MsiX),
+    Express(Express),
+    Other { id: u8, offset: u8 },
+}
+
+/// PCI-PCI 桥的总线号
+#[derive(Clone, Copy, Debug)]
+pub struct Bridge {
+    pub primary: u8,
+    pub secondary: u8,
+    pub subordinate: u8,
+}
+
+/// 一个 PCI 功能
+#[derive(Clone, Debug)]
+pub struct Device {
+    pub address: Address,
+    pub vendor_id: u16,
+    pub device_id: u16,
+    pub class: u8,
+    pub subclass: u8,
+    pub prog_if: u8,
+    pub revision: u8,
+    /// 子系统厂商与 ID（只有普通设备头有）
+    pub subsystem: Option<(u16, u16)>,
+    /// 1..=4 对应 INTA#..INTD#，0 表示不使用 INTx
+    pub interrupt_pin: u8,
+    /// INTx 经旋转后落到的根总线 `_PRT` 路由
+    pub route: Option<aml::PciRoute>,
+    pub bars: Vec<Bar>,
+    pub capabilities: Vec<Capability>,
+    /// 扩展能力的 (ID, 偏移)
+    pub extended_capabilities: Vec<(u16, u16)>,
+    pub bridge: Option<Bridge>,
+    /// 绑定的驱动名
+    pub driver: Option<&'static str>,
+}
+
+impl Device {
+    /// 编号为 `index` 的 BAR
+    pub fn bar(&self, index: u8) -> Option<&Bar> {
+        self.bars.iter().find(|bar| bar.index == index)
+    }
+
+    /// 类别名称，认识的子类用子类名称
+    pub fn class_name(&self) -> &'static str {
+        match (self.class, self.subclass, self.prog_if) {
+            (0x01, 0x00, _) => "SCSI controller",
+            (0x01, 0x01, _) => "IDE controller",
+            (0x01, 0x06, 0x01) => "AHCI controller",
+            (0x01, 0x06, _) => "SATA controller",
+            (0x01, 0x07, _) => "SAS controller",
+            (0x01, 0x08, 0x02) => "NVMe controller",
+            (0x02, 0x00, _) => "Ethernet controller",
+            (0x03, 0x00, _) => "VGA controller",
+            (0x04, 0x01, _) => "Audio device",
+            (0x04, 0x03, _) => "HD Audio controller",
+            (0x06, 0x00, _) => "Host bridge",
+            (0x06, 0x01, _) => "ISA bridge",
+            (0x06, 0x04, _) => "PCI bridge",
+            (0x07, 0x00, _) => "Serial controller",
+            (0x08, 0x05, _) => "SD host controller",
+            (0x0C, 0x03, 0x00) => "UHCI controller",
+            (0x0C, 0x03, 0x10) => "OHCI controller",
+            (0x0C, 0x03, 0x20) => "EHCI controller",
+            (0x0C, 0x03, 0x30) => "xHCI controller",
+            (0x0C, 0x05, _) => "SMBus controller",
+            (0x00, _, _) => "Unclassified device",
+            (0x01, _, _) => "Mass storage controller",
+            (0x02, _, _) => "Network controller",
+            (0x03, _, _) => "Display controller",
+            (0x04, _, _) => "Multimedia controller",
+            (0x05, _, _) => "Memory controller",
+            (0x06, _, _) => "Bridge",
+            (0x07, _, _) => "Communication controller",
+            (0x08, _, _) => "System peripheral",
+            (0x09, _, _) => "Input device controller",
+            (0x0A, _, _) => "Docking station",
+            (0x0B, _, _) => "Processor",
+            (0x0C, _, _) => "Serial bus controller",
+            (0x0D, _, _) => "Wireless controller",
+            (0x0E, _, _) => "Intelligent controller",
+            (0x0F, _, _) => "Satellite controller",
+            (0x10, _, _) => "Encryption controller",
+            (0x11, _, _) => "Signal processing controller",
+            (0x12, _, _) => "Processing accelerator",
+            (0x13, _, _) => "Non-essential instrumentation",
+            (0x40, _, _) => "Coprocessor",
+            _ => "Unassigned class",
+        }
+    }
+}
+
+/// 读一个功能的配置空间头、BAR 与能力
+fn probe(address: Address) -> Device {
+    let class = read32(address, REVISION);
+    let header_type = read8(address, HEADER_TYPE) & !HEADER_MULTIFUNCTION;
+    let bridge = (header_type == HEADER_BRIDGE).then(|| Bridge {
+        primary: read8(address, PRIMARY_BUS),
+        secondary: read8(address, SECONDARY_BUS),
+        subordinate: read8(address, SUBORDINATE_BUS),
+    });
+    let bar_count = match header_type {
+        HEADER_DEVICE => 6,
+        HEADER_BRIDGE => 2,
+        _ => 0,
+    };
+    // 主桥与 ISA/LPC 桥关闭解码可能切断 CPU 对内存或传统 I/O 的访问，正在显示
+    // 帧缓冲区的显卡关闭解码会让写合并映射失效，都不测它们的 BAR
+    let keep_decode = matches!(class >> 16, 0x0600 | 0x0601)
+        || (class >> 24 == 0x03 && owns_framebuffer(address, bar_count));
+    let mut device = Device {
+        address,
+        vendor_id: read16(address, VENDOR_ID),
+        device_id: read16(address, DEVICE_ID),
+        class: (class >> 24) as u8,
+        subclass: (class >> 16) as u8,
+        prog_if: (class >> 8) as u8,
+        revision: class as u8,
+        subsystem: (header_type == HEADER_DEVICE).then(|| {
+            (
+                read16(address, SUBSYSTEM_VENDOR_ID),
+                read16(address, SUBSYSTEM_ID),
+            )
+        }),
+        interrupt_pin: match header_type {
+            HEADER_DEVICE | HEADER_BRIDGE => read8(address, INTERRUPT_PIN).min(4),
+            _ => 0,
+        },
+        route: None,
+        bars: if keep_decode {
+            Vec::new()
+        } else {
+            size_bars(address, bar_count)
+        },
+        capabilities: Vec::new(),
+        extended_capabilities: Vec::new(),
+        bridge,
+        driver: None,
+    };
+    if read16(address, STATUS) & STATUS_CAPABILITIES != 0 {
+        let pointer = if header_type == HEADER_CARDBUS {
+            CARDBUS_CAPABILITIES
+        } else {
+            CAPABILITIES
+        };
+        device.capabilities = capabilities(address, read8(address, pointer));
+    }
+    device.extended_capabilities = extended_capabilities(address);
+    device
+}
+
+/// 显示控制器的某个内存 BAR 是否可能包含当前的帧缓冲区
+///
+/// 只读 BAR 不测大小：内存 BAR 按自身大小对齐，基址的最低置位限定了 BAR 最大的范围。
+fn owns_framebuffer(address: Address, count: u16) -> bool {
+    let Some(fb) = framebuffer::get() else {
+        return false;
+    };
+    let mut index = 0;
+    while index < count {
+        let low = read32(address, BAR0 + index * 4);
+        if low & 1 != 0 {
+            index += 1;
+            continue;
+        }
+        let is_64bit = (low >> 1) & 0x3 == 0x2 && index + 1 < count;
+        let high = if is_64bit {
+            read32(address, BAR0 + index * 4 + 4)
+        } else {
+            0
+        };
+        let base = (high as u64) << 32 | (low & !0xF) as u64;
+        if base != 0 && base <= fb.address && fb.address - base < 1 << base.trailing_zeros() {
+            return true;
+        }
+        index += if is_64bit { 2 } else { 1 };
+    }
+    false
+}
+
+/// 向每个 BAR 写全 1 读回掩码得出大小；测量期间关闭 I/O 与内存解码
+///
+/// 解码关闭时中断处理程序不能访问这个设备，所以整个过程关中断进行。
+fn size_bars(address: Address, count: u16) -> Vec<Bar> {
+    // 没有 BAR（CardBus 桥）时不动解码位
+    if count == 0 {
+        return Vec::new();
+    }
+    without_interrupts(|| size_bars_with_decode_off(address, count))
+}
+
+fn size_bars_with_decode_off(address: Address, count: u16) -> Vec<Bar> {
+    let command = read16(address, COMMAND);
+    write16(address, COMMAND, command & !(COMMAND_IO | COMMAND_MEMORY));
+    let mut bars = Vec::new();
+    let mut index = 0;
+    while index < count {
+        let offset = BAR0 + index * 4;
+        let low = read32(address, offset);
+        write32(address, offset, u32::MAX);
+        let mask_low = read32(address, offset);
+        write32(address, offset, low);
+        if low & 1 != 0 {
+            // I/O BAR：高 16 位不可写时是 16 位端口
+            let mask = match mask_low & !0x3 {
+                0 => 0,
+                mask if mask & 0xFFFF_0000 == 0 => mask | 0xFFFF_0000,
+                mask => mask,
+            };
+            if mask != 0 {
+                bars.push(Bar {
+                    index: index as u8,
+                    kind: BarKind::Io,
+                    address: (low & !0x3) as u64,
+                    size: (!mask).wrapping_add(1) as u64,
+                    prefetchable: false,
+                });
+            }
+            index += 1;
+            continue;
+        }
+        let is_64bit = (low >> 1) & 0x3 == 0x2 && index + 1 < count;
+        let (high, mask_high) = if is_64bit {
+            let high = read32(address, offset + 4);
+            write32(address, offset + 4, u32::MAX);
+            let mask_high = read32(address, offset + 4);
+            write32(address, offset + 4, high);
+            (high, mask_high)
+        } else {
+            (0, u32::MAX)
+        };
+        let mask = (mask_high as u64) << 32 | (mask_low & !0xF) as u64;
+        if mask_low & !0xF != 0 || (is_64bit && mask_high != 0) {
+            bars.push(Bar {
+                index: index as u8,
+                kind: if is_64bit {
+                    BarKind::Memory64
+                } else {
+                    BarKind::Memory32
+                },
+                address: (high as u64) << 32 | (low & !0xF) as u64,
+                size: (!mask).wrapping_add(1),
+                prefetchable: low & 0x8 != 0,
+            });
+        }
+        index += if is_64bit { 2 } else { 1 };
+    }
+    write16(address, COMMAND, command);
+    bars
+}
+
+/// 走标准能力链表
+fn capabilities(address: Address, first: u8) -> Vec<Capability> {
+    let mut list = Vec::new();
+    let mut offset = first & 0xFC;
+    while offset >= 0x40 && list.len() < MAX_CAPABILITIES {
+        let header = read16(address, offset as u16);
+        let id = header as u8;
+        let base = offset as u16;
+        list.push(match id {
+            CAP_POWER_MANAGEMENT => Capability::PowerManagement { offset },
+            CAP_MSI => {
+                let control = read16(address, base + 2);
+                Capability::Msi(Msi {
+                    offset,
+                    enabled: control & 1 != 0,
+                    is_64bit: control & (1 << 7) != 0,
+                    per_vector_mask: control & (1 << 8) != 0,
+                    max_vectors: 1 << ((control >> 1) & 0x7).min(5),
+                })
+            }
+            CAP_MSIX => {
+                let control = read16(address, base + 2);
+                let table = read32(address, base + 4);
+                let pba = read32(address, base + 8);
+                Capability::MsiX(MsiX {
+                    offset,
+                    enabled: control & (1 << 15) != 0,
+                    table_size: (control & 0x7FF) + 1,
+                    table_bar: (table & 0x7) as u8,
+                    table_offset: table & !0x7,
+                    pba_bar: (pba & 0x7) as u8,
+                    pba_offset: pba & !0x7,
+                })
+            }
+            CAP_EXPRESS => {
+                let capabilities = read16(address, base + 2);
+                let link_capabilities = read32(address, base + 0x0C);
+                let link_status = read16(address, base + 0x12);
+                Capability::Express(Express {
+                    offset,
+                    version: (capabilities & 0xF) as u8,
+                    port_type: ((capabilities >> 4) & 0xF) as u8,
+                    max_link_speed: (link_capabilities & 0xF) as u8,
+                    max_link_width: ((link_capabilities >> 4) & 0x3F) as u8,
+                    link_speed: (link_status & 0xF) as u8,
+                    link_width: ((link_status >> 4) & 0x3F) as u8,
+                })
+            }
+            id => Capability::Other { id, offset },
+        });
+        offset = (header >> 8) as u8 & 0xFC;
+    }
+    list
+}
+
+/// 走扩展能力链表；传统端口访问不到时读出全 1，返回空表
+fn extended_capabilities(address: Address) -> Vec<(u16, u16)> {
+    let mut list = Vec::new();
+    let mut offset = EXTENDED_CAPABILITIES;
+    while offset >= EXTENDED_CAPABILITIES && list.len() < MAX_CAPABILITIES {
+        let header = read32(address, offset);
+        if header == 0 || header == u32::MAX {
+            break;
+        }
+        list.push((header as u16, offset));
+        offset = (header >> 20) as u16 & 0xFFC;
+    }
+    list
+}
+
+// ============================================================================
+// 枚举
+// ============================================================================
+
+/// 扫描时记录的上游位置：桥后的设备按根总线上的槽位与累计的引脚偏移查 `_PRT`
+#[derive(Clone, Copy)]
+struct Upstream {
+    root_bus: u8,
+    /// 根总线上的槽位与引脚偏移；设备就在根总线上时为 `None`
+    slot: Option<(u8, u8)>,
+}
+
+struct Scanner {
+    routes: Vec<aml::PciRoute>,
+    devices: Vec<Device>,
+    /// 已扫描的 (段, 总线)
+    buses: Vec<(u16, u8)>,
+}
+
+impl Scanner {
+    fn scan_bus(&mut self, segment: u16, bus: u8, upstream: Upstream, depth: usize) {
+        if depth > MAX_BRIDGE_DEPTH || self.buses.contains(&(segment, bus)) {
+            return;
+        }
+        self.buses.push((segment, bus));
+        for device in 0..32 {
+            let first = Address::new(segment, bus, device, 0);
+            if read16(first, VENDOR_ID) == u16::MAX {
+                continue;
+            }
+            let functions = if read8(first, HEADER_TYPE) & HEADER_MULTIFUNCTION != 0 {
+                8
+            } else {
+                1
+            };
+            for function in 0..functions {
+                let address = Address::new(segment, bus, device, function);
+                if read16(address, VENDOR_ID) == u16::MAX {
+                    continue;
+                }
+                let mut found = probe(address);
+                found.route = self.route(&found, upstream);
+                let bridge = found.bridge;
+                self.devices.push(found);
+                if let Some(bridge) = bridge
+                    && bridge.secondary > bus
+                {
+                    let slot = match upstream.slot {
+                        None => (device, 0),
+                        Some((slot, offset)) => (slot, (offset + device) % 4),
+                    };
+                    let child = Upstream {
+                        root_bus: upstream.root_bus,
+                        slot: Some(slot),
+                    };
+                    self.scan_bus(segment, bridge.secondary, child, depth + 1);
+                }
+            }
+        }
+    }
+
+    /// 逐级旋转引脚直到根总线，再查 `_PRT`
+    fn route(&self, device: &Device, upstream: Upstream) -> Option<aml::PciRoute> {
+        let pin = device.interrupt_pin.checked_sub(1)?;
+        let (slot, pin) = match upstream.slot {
+            None => (device.address.device, pin),
+            Some((slot, offset)) => (slot, (pin + device.address.device + offset) % 4),
+        };
+        self.routes
+            .iter()
+            .find(|route| {
+                route.segment == device.address.segment
+                    && route.bus == upstream.root_bus
+                    && route.device == slot
+                    && route.pin == pin
+            })
+            .cloned()
+    }
+}
+
+/// 枚举结果统计
+#[derive(Clone, Copy, Debug)]
+pub struct ScanInfo {
+    /// 映射的 ECAM 区域数，0 表示只用传统端口
+    pub ecam_regions: usize,
+    pub buses: usize,
+    pub devices: usize,
+    /// 绑定了驱动的功能数
+    pub bound: usize,
+}
+
+/// 映射 MCFG 中的 ECAM 区域并返回各段的起始总线
+fn map_ecam() -> Vec<(u16, u8)> {
+    let Some(mcfg) = acpi::mcfg() else {
+        return Vec::new();
+    };
+    let mut regions = ECAM.lock();
+    let mut roots = Vec::new();
+    for entry in &mcfg.entries {
+        let start = entry.base + ((entry.start_bus as u64) << 20);
+        let Ok(base) = paging::map_mmio(start, entry.size(), CacheMode::Uncached) else {
+            continue;
+        };
+        regions.push(EcamRegion {
+            segment: entry.segment,
+            start_bus: entry.start_bus,
+            end_bus: entry.end_bus,
+            base,
+        });
+        roots.push((entry.segment, entry.start_bus));
+    }
+    roots
+}
+
+/// 建立配置空间访问并枚举所有总线；需要在 [`aml::init`] 之后调用，才能得到
+/// APIC 模式下的 INTx 路由
+pub fn init() -> ScanInfo {
+    let mut roots = map_ecam();
+    let ecam_regions = roots.len();
+    if roots.is_empty() {
+        roots.push((0, 0));
+    }
+    // 多个主桥时其余根总线只能从 ACPI 根桥的总线号资源得知；不知道它们属于哪个段，
+    // 只在只有段 0 时采用
+    if roots.iter().all(|&(segment, _)| segment == 0)
+        && let Ok(bridges) = aml::root_bridges()
+    {
+        for (_, resources) in &bridges {
+            for resource in resources {
+                if let Resource::Range {
+                    kind: RangeKind::BusNumber,
+                    min,
+                    ..
+                } = resource
+                    && !roots.contains(&(0, *min as u8))
+                {
+                    roots.push((0, *min as u8));
+                }
+            }
+        }
+    }
+
+    let mut scanner = Scanner {
+        routes: aml::pci_routes()
+            .map(|routing| routing.routes)
+            .unwrap_or_default(),
+        devices: Vec::new(),
+        buses: Vec::new(),
+    };
+    for &(segment, bus) in &roots {
+        let upstream = Upstream {
+            root_bus: bus,
+            slot: None,
+        };
+        scanner.scan_bus(segment, bus, upstream, 0);
+    }
+
+    let drivers = DRIVERS.lock().clone();
+    let mut bound = 0;
+    for device in &mut scanner.devices {
+        device.driver = drivers
+            .iter()
+            .find(|driver| driver.matches(device) && (driver.probe)(device))
+            .map(|driver| driver.name);
+        bound += device.driver.is_some() as usize;
+    }
+    let info = ScanInfo {
+        ecam_regions,
+        buses: scanner.buses.len(),
+        devices: scanner.devices.len(),
+        bound,
+    };
+    *DEVICES.lock() = scanner.devices;
+    info
+}
+
+/// 枚举到的所有功能
+pub fn devices() -> Vec<Device> {
+    DEVICES.lock().clone()
+}
+
+// ============================================================================
+// 驱动
+// ============================================================================
+
+/// 驱动匹配的 ID；`None` 的字段匹配任意值
+#[derive(Clone, Copy, Debug)]
+pub struct DeviceId {
+    vendor: Option<u16>,
+    device: Option<u16>,
+    class: Option<u8>,
+    subclass: Option<u8>,
+    prog_if: Option<u8>,
+}
+
+impl DeviceId {
+    /// 按厂商与设备 ID 匹配
+    pub const fn device(vendor: u16, device: u16) -> Self {
+        Self {
+            vendor: Some(vendor),
+            device: Some(device),
+            class: None,
+            subclass: None,
+            prog_if: None,
+        }
+    }
+
+    /// 按类别与子类匹配
+    pub const fn class(class: u8, subclass: u8) -> Self {
+        Self {
+            vendor: None,
+            device: None,
+            class: Some(class),
+            subclass: Some(subclass),
+            prog_if: None,
+        }
+    }
+
+    /// 再要求编程接口相同
+    pub const fn prog_if(self, prog_if: u8) -> Self {
+        Self {
+            prog_if: Some(prog_if),
+            ..self
+        }
+    }
+
+    fn matches(&self, device: &Device) -> bool {
+        fn field<T: PartialEq>(want: Option<T>, value: T) -> bool {
+            want.is_none_or(|want| want == value)
+        }
+        field(self.vendor, device.vendor_id)
+            && field(self.device, device.device_id)
+            && field(self.class, device.class)
+            && field(self.subclass, device.subclass)
+            && field(self.prog_if, device.prog_if)
+    }
+}
+
+/// 一个 PCI 驱动
+pub struct Driver {
+    pub name: &'static str,
+    pub ids: &'static [DeviceId],
+    /// ID 匹配后调用，返回 `true` 表示接管这个功能
+    pub probe: fn(&Device) -> bool,
+}
+
+impl Driver {
+    fn matches(&self, device: &Device) -> bool {
+        self.ids.iter().any(|id| id.matches(device))
+    }
+}
+
+/// 注册驱动，并对已枚举、尚未绑定的功能立即探测；返回这次绑定的功能数
+pub fn register(driver: &'static Driver) -> usize {
+    DRIVERS.lock().push(driver);
+    // 探测时不持锁，驱动可以再查询设备列表
+    let candidates: Vec<(usize, Device)> = DEVICES
+        .lock()
+        .iter()
+        .enumerate()
+        .filter(|(_, device)| device.driver.is_none() && driver.matches(device))
+        .map(|(index, device)| (index, device.clone()))
+        .collect();
+    let mut bound = 0;
+    for (index, device) in candidates {
+        if (driver.probe)(&device) {
+            DEVICES.lock()[index].driver = Some(driver.name);
+            bound += 1;
+        }
+    }
+    bound
+}
+
+// ============================================================================
+// 串口输出
+// ============================================================================
+
+/// 输出访问方式、统计以及每个功能的 ID、BAR、中断与能力
+pub fn dump(info: &ScanInfo) {
+    serial_write("  Config access:  ");
+    if info.ecam_regions > 0 {
+        serial_write("ECAM (");
+        serial_write_dec(info.ecam_regions as u64);
+        serial_write(" regions)\n");
+    } else {
+        serial_write("I/O ports 0xCF8/0xCFC\n");
+    }
+    serial_write("  Devices:        ");
+    serial_write_dec(info.devices as u64);
+    serial_write(" functions on ");
+    serial_write_dec(info.buses as u64);
+    serial_write(" buses, ");
+    serial_write_dec(info.bound as u64);
+    serial_write(" bound to drivers\n");
+
+    for device in DEVICES.lock().iter() {
+        serial_write("    ");
+        write_address(device.address);
+        serial_write(" ");
+        write_hex_digits(device.vendor_id as u64, 4);
+        serial_write(":");
+        write_hex_digits(device.device_id as u64, 4);
+        serial_write(" [");
+        write_hex_digits(device.class as u64, 2);
+        write_hex_digits(device.subclass as u64, 2);
+        write_hex_digits(device.prog_if as u64, 2);
+        serial_write("] rev ");
+        write_hex_digits(device.revision as u64, 2);
+        serial_write(" ");
+        serial_write(device.class_name());
+        if let Some(driver) = device.driver {
+            serial_write(" <");
+            serial_write(driver);
+            serial_write(">");
+        }
+        serial_write("\n");
+
+        if let Some((vendor, id)) = device.subsystem.filter(|&(vendor, _)| vendor != 0) {
+            serial_write("      subsystem ");
+            write_hex_digits(vendor as u64, 4);
+            serial_write(":");
+            write_hex_digits(id as u64, 4);
+            serial_write("\n");
+        }
+        if let Some(bridge) = device.bridge {
+            serial_write("      bus ");
+            serial_write_dec(bridge.primary as u64);
+            serial_write(" -> ");
+            serial_write_dec(bridge.secondary as u64);
+            serial_write("-");
+            serial_write_dec(bridge.subordinate as u64);
+            serial_write("\n");
+        }
+        for bar in &device.bars {
+            serial_write("      BAR");
+            serial_write_dec(bar.index as u64);
+            serial_write(match bar.kind {
+                BarKind::Io => " io    ",
+                BarKind::Memory32 => " mem32 ",
+                BarKind::Memory64 => " mem64 ",
+            });
+            serial_write_hex(bar.address);
+            serial_write(" (");
+            serial_write_size(bar.size);
+            serial_write(")");
+            if bar.prefetchable {
+                serial_write(" prefetchable");
+            }
+            serial_write("\n");
+        }
+        if device.interrupt_pin != 0 {
+            serial_write("      INT");
+            serial_write_char(b'A' + device.interrupt_pin - 1);
+            match &device.route {
+                Some(route) => {
+                    serial_write(" -> GSI ");
+                    serial_write_dec(route.gsi as u64);
+                    if let Some(link) = &route.link {
+                        serial_write(" via ");
+                        serial_write(link);
+                    }
+                }
+                None => serial_write(" not routed"),
+            }
+            serial_write("\n");
+        }
+        for capability in &device.capabilities {
+            serial_write("      ");
+            write_capability(capability);
+            serial_write("\n");
+        }
+        if !device.extended_capabilities.is_empty() {
+            serial_write("      extended:");
+            for &(id, offset) in &device.extended_capabilities {
+                serial_write(" ");
+                serial_write(extended_capability_name(id));
+                serial_write("@");
+                write_hex_digits(offset as u64, 3);
+            }
+            serial_write("\n");
+        }
+    }
+}
+
+fn write_capability(capability: &Capability) {
+    match capability {
+        Capability::PowerManagement { offset } => {
+            write_capability_offset(*offset);
+            serial_write("Power Management");
+        }
+        Capability::Msi(msi) => {
+            write_capability_offset(msi.offset);
+            serial_write("MSI: ");
+            serial_write_dec(msi.max_vectors as u64);
+            serial_write(" vectors");
+            if msi.is_64bit {
+                serial_write(", 64-bit");
+            }
+            if msi.per_vector_mask {
+                serial_write(", maskable");
+            }
+            if msi.enabled {
+                serial_write(", enabled");
+            }
+        }
+        Capability::MsiX(msix) => {
+            write_capability_offset(msix.offset);
+            serial_write("MSI-X: ");
+            serial_write_dec(msix.table_size as u64);
+            serial_write(" vectors, table BAR");
+            serial_write_dec(msix.table_bar as u64);
+            serial_write("+");
+            serial_write_hex(msix.table_offset as u64);
+            serial_write(", PBA BAR");
+            serial_write_dec(msix.pba_bar as u64);
+            serial_write("+");
+            serial_write_hex(msix.pba_offset as u64);
+            if msix.enabled {
+                serial_write(", enabled");
+            }
+        }
+        Capability::Express(express) => {
+            write_capability_offset(express.offset);
+            serial_write("PCIe v");
+            serial_write_dec(express.version as u64);
+            serial_write(" ");
+            serial_write(express.port_type_name());
+            if express.link_width != 0 {
+                serial_write(", link ");
+                write_link(express.link_speed, express.link_width);
+                serial_write(" (max ");
+                write_link(express.max_link_speed, express.max_link_width);
+                serial_write(")");
+            }
+        }
+        Capability::Other { id, offset } => {
+            write_capability_offset(*offset);
+            match id {
+                0x03 => serial_write("VPD"),
+                0x09 => serial_write("Vendor Specific"),
+                0x0D => serial_write("Bridge Subsystem ID"),
+                0x12 => serial_write("SATA"),
+                0x13 => serial_write("Advanced Features"),
+                _ => {
+                    serial_write("Capability ");
+                    write_hex_digits(*id as u64, 2);
+                }
+            }
+        }
+    }
+}
+
+fn write_capability_offset(offset: u8) {
+    serial_write("[");
+    write_hex_digits(offset as u64, 2);
+    serial_write("] ");
+}
+
+/// 输出 `8 GT/s x4` 形式的链路速度与宽度
+fn write_link(speed: u8, width: u8) {
+    serial_write(match speed {
+        1 => "2.5",
+        2 => "5",
+        3 => "8",
+        4 => "16",
+        5 => "32",
+        6 => "64",
+        _ => "?",
+    });
+    serial_write(" GT/s x");
+    serial_write_dec(width as u64);
+}
+
+fn extended_capability_name(id: u16) -> &'static str {
+    match id {
+        0x0001 => "AER",
+        0x0002 | 0x0009 => "VC",
+        0x0003 => "DSN",
+        0x000B => "VSEC",
+        0x000D => "ACS",
+        0x000E => "ARI",
+        0x0010 => "SR-IOV",
+        0x0015 => "ResizableBAR",
+        0x0017 => "TPH",
+        0x0018 => "LTR",
+        0x0019 => "SecondaryPCIe",
+        0x001E => "L1SS",
+        0x001F => "PTM",
+        0x0023 => "DVSEC",
+        0x0025 => "DLF",
+        0x0026 => "PL16",
+        _ => "?",
+    }
+}
+
+/// 输出 `0000:00:1f.2` 形式的位置
+fn write_address(address: Address) {
+    write_hex_digits(address.segment as u64, 4);
+    serial_write(":");
+    write_hex_digits(address.bus as u64, 2);
+    serial_write(":");
+    write_hex_digits(address.device as u64, 2);
+    serial_write(".");
+    serial_write_dec(address.function as u64);
+}
+
+/// 输出固定位数的小写十六进制（不带 `0x`）
+fn write_hex_digits(value: u64, digits: u32) {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+    for i in (0..digits).rev() {
+        serial_write_char(HEX[(value >> (i * 4)) as usize & 0xF]);
+    }
+}
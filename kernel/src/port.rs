@@ -0,0 +1,39 @@
+//! x86 I/O 端口访问
+
+use core::arch::asm;
+
+/// 向端口写一个字节
+pub unsafe fn outb(port: u16, value: u8) {
+    asm!("out dx, al", in("dx") port, in("al") value, options(nomem, nostack));
+}
+
+/// 从端口读一个字节
+pub unsafe fn inb(port: u16) -> u8 {
+    let value: u8;
+    asm!("in al, dx", out("al") value, in("dx") port, options(nomem, nostack));
+    value
+}
+
+/// 向端口写一个字
+pub unsafe fn outw(port: u16, value: u16) {
+    asm!("out dx, ax", in("dx") port, in("ax") value, options(nomem, nostack));
+}
+
+/// 从端口读一个字
+pub unsafe fn inw(port: u16) -> u16 {
+    let value: u16;
+    asm!("in ax, dx", out("ax") value, in("dx") port, options(nomem, nostack));
+    value
+}
+
+/// 向端口写一个双字
+pub unsafe fn outl(port: u16, value: u32) {
+    asm!("out dx, eax", in("dx") port, in("eax") value, options(nomem, nostack));
+}
+
+/// 从端口读一个双字
+pub unsafe fn inl(port: u16) -> u32 {
+    let value: u32;
+    asm!("in eax, dx", out("eax") value, in("dx") port, options(nomem, nostack));
+    value
+}
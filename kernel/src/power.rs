@@ -0,0 +1,144 @@
+//! 电源管理：ACPI 关机与重启
+//!
+//! - 关机：求值 `\_S5` 得到 SLP_TYP，调用 `\_PTS(5)`，必要时经 SMI 命令端口
+//!   切换到 ACPI 模式，再把 SLP_TYP | SLP_EN 写入 PM1a/PM1b 控制寄存器
+//!   （硬件精简 ACPI 写睡眠控制寄存器）
+//! - 重启：写 FADT 复位寄存器；不支持或无效时依次尝试 8042 键盘控制器与
+//!   0xCF9 复位控制寄存器
+//!
+//! 两者成功时都不返回；失败返回时中断标志保持调用前的状态。
+
+use crate::acpi::{self, FADT_HW_REDUCED, Fadt};
+use crate::aml::{self, AmlError};
+use crate::cpu::without_interrupts;
+use crate::port::outb;
+use crate::time;
+use core::time::Duration;
+
+/// PM1 控制寄存器：SCI_EN（ACPI 模式）
+const PM1_SCI_EN: u64 = 1 << 0;
+/// PM1 控制寄存器：SLP_TYP 字段
+const PM1_SLP_TYP_SHIFT: u64 = 10;
+const PM1_SLP_TYP_MASK: u64 = 0b111 << PM1_SLP_TYP_SHIFT;
+/// PM1 控制寄存器：SLP_EN
+const PM1_SLP_EN: u64 = 1 << 13;
+/// 睡眠控制寄存器：SLP_TYP 在位 2-4，SLP_EN 在位 5
+const SLEEP_CONTROL_SLP_TYP_SHIFT: u64 = 2;
+const SLEEP_CONTROL_SLP_EN: u64 = 1 << 5;
+
+/// 8042 命令端口与“脉冲复位线”命令
+const KBC_COMMAND: u16 = 0x64;
+const KBC_RESET: u8 = 0xFE;
+/// 复位控制寄存器：先写 SYS_RST，再加上 RST_CPU 触发硬复位
+const RESET_CONTROL: u16 = 0xCF9;
+const RESET_CONTROL_SYS_RST: u8 = 0x02;
+const RESET_CONTROL_RST_CPU: u8 = 0x04;
+
+/// 等待硬件生效的时间
+const SETTLE: Duration = Duration::from_millis(500);
+
+/// 关机或重启失败原因
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PowerError {
+    /// 没有 FADT
+    NoAcpi,
+    /// 无法得到 `\_S5`
+    SleepType(AmlError),
+    /// 没有 PM1a 控制寄存器或睡眠控制寄存器
+    NoControlRegister,
+    /// 写入后机器仍在运行
+    StillRunning,
+}
+
+impl PowerError {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::NoAcpi => "no FADT",
+            Self::SleepType(err) => err.as_str(),
+            Self::NoControlRegister => "no PM1 control register",
+            Self::StillRunning => "hardware did not respond",
+        }
+    }
+}
+
+/// 进入 S5（软关机）；只有失败时返回
+pub fn power_off() -> PowerError {
+    let Some(fadt) = acpi::fadt() else {
+        return PowerError::NoAcpi;
+    };
+    let (slp_typ_a, slp_typ_b) = match aml::sleep_type(5) {
+        Ok(types) => types,
+        Err(err) => return PowerError::SleepType(err),
+    };
+    let hw_reduced = fadt.flags & FADT_HW_REDUCED != 0;
+    let control = if hw_reduced {
+        fadt.sleep_control
+    } else {
+        fadt.pm1a_control
+    };
+    let Some(control) = control else {
+        return PowerError::NoControlRegister;
+    };
+    // _PTS 失败不妨碍关机
+    let _ = aml::prepare_to_sleep(5);
+
+    // 关中断写睡眠寄存器；没有断电时恢复原来的中断标志
+    without_interrupts(|| {
+        if hw_reduced {
+            control.write(
+                (slp_typ_a as u64 & 0b111) << SLEEP_CONTROL_SLP_TYP_SHIFT | SLEEP_CONTROL_SLP_EN,
+            );
+        } else {
+            enable_acpi_mode(&fadt);
+            // 规范要求 PM1a 与 PM1b 分别写入各自的 SLP_TYP
+            for (register, slp_typ) in [(Some(control), slp_typ_a), (fadt.pm1b_control, slp_typ_b)]
+            {
+                if let Some(register) = register {
+                    let value = register.read().unwrap_or(0) & !(PM1_SLP_TYP_MASK | PM1_SLP_EN);
+                    register.write(value | (slp_typ as u64 & 0b111) << PM1_SLP_TYP_SHIFT | PM1_SLP_EN);
+                }
+            }
+        }
+        time::busy_wait(SETTLE);
+    });
+    PowerError::StillRunning
+}
+
+/// 固件仍处于传统模式时经 SMI 命令端口切换到 ACPI 模式并等待 SCI_EN
+fn enable_acpi_mode(fadt: &Fadt) {
+    let Some(pm1a) = fadt.pm1a_control else {
+        return;
+    };
+    if fadt.smi_command == 0 || fadt.acpi_enable == 0 || pm1a.read().unwrap_or(0) & PM1_SCI_EN != 0
+    {
+        return;
+    }
+    unsafe { outb(fadt.smi_command as u16, fadt.acpi_enable) };
+    for _ in 0..100 {
+        if pm1a.read().unwrap_or(0) & PM1_SCI_EN != 0 {
+            break;
+        }
+        time::busy_wait(Duration::from_millis(10));
+    }
+}
+
+/// 重启；只有所有方法都失败时返回
+pub fn reboot() -> PowerError {
+    // 关中断尝试各种复位方法；都失败时恢复原来的中断标志
+    without_interrupts(|| {
+        if let Some(fadt) = acpi::fadt()
+            && let Some(register) = fadt.reset_register.filter(|_| fadt.reset_supported())
+        {
+            register.write(fadt.reset_value as u64);
+            time::busy_wait(SETTLE);
+        }
+        unsafe {
+            outb(KBC_COMMAND, KBC_RESET);
+            time::busy_wait(SETTLE);
+            outb(RESET_CONTROL, RESET_CONTROL_SYS_RST);
+            outb(RESET_CONTROL, RESET_CONTROL_SYS_RST | RESET_CONTROL_RST_CPU);
+        }
+        time::busy_wait(SETTLE);
+    });
+    PowerError::StillRunning
+}
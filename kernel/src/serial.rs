@@ -0,0 +1,129 @@
+//! COM1 串口驱动
+//!
+//! 内核最早可用的输出设备，所有诊断信息都经由这里输出。
+
+use crate::idt::InterruptFrame;
+use crate::port::{inb, outb};
+use boot_protocol::Guid;
+
+/// COM1 端口基址
+const COM1: u16 = 0x3F8;
+/// COM1 的 ISA IRQ
+pub const COM1_IRQ: u8 = 4;
+
+pub fn serial_init() {
+    unsafe {
+        outb(COM1 + 1, 0x00);  // 禁用中断
+        outb(COM1 + 3, 0x80);  // 启用 DLAB
+        outb(COM1, 0x03);      // 波特率 38400
+        outb(COM1 + 1, 0x00);
+        outb(COM1 + 3, 0x03);  // 8N1
+        outb(COM1 + 2, 0xC7);  // 启用 FIFO
+        outb(COM1 + 4, 0x0B);  // IRQ 启用, RTS/DSR 设置
+    }
+}
+
+/// 打开 COM1 的接收中断；中断线需要先通过 `apic::route_isa_irq` 路由
+pub fn serial_enable_receive_interrupt() {
+    unsafe { outb(COM1 + 1, 0x01) };
+}
+
+/// COM1 接收中断：读出 FIFO 中的全部字节并回显（回车回显为换行）
+pub fn serial_receive_interrupt(_frame: &mut InterruptFrame) {
+    unsafe {
+        while inb(COM1 + 5) & 0x01 != 0 {
+            match inb(COM1) {
+                b'\r' => serial_write("\n"),
+                c => serial_write_char(c),
+            }
+        }
+    }
+}
+
+pub fn serial_write_char(c: u8) {
+    unsafe {
+        while (inb(COM1 + 5) & 0x20) == 0 {}
+        outb(COM1, c);
+    }
+}
+
+pub fn serial_write(s: &str) {
+    for b in s.bytes() {
+        if b == b'\n' {
+            serial_write_char(b'\r');
+        }
+        serial_write_char(b);
+    }
+}
+
+pub fn serial_write_hex(val: u64) {
+    const HEX: &[u8] = b"0123456789ABCDEF";
+    serial_write("0x");
+    
+    if val == 0 {
+        serial_write_char(b'0');
+        return;
+    }
+    
+    let mut started = false;
+    for i in (0..16).rev() {
+        let digit = ((val >> (i * 4)) & 0xF) as usize;
+        if digit != 0 || started {
+            serial_write_char(HEX[digit]);
+            started = true;
+        }
+    }
+}
+
+pub fn serial_write_dec(val: u64) {
+    if val == 0 {
+        serial_write_char(b'0');
+        return;
+    }
+    
+    let mut buf = [0u8; 20];
+    let mut i = 0;
+    let mut v = val;
+    
+    while v > 0 {
+        buf[i] = b'0' + (v % 10) as u8;
+        v /= 10;
+        i += 1;
+    }
+    
+    while i > 0 {
+        i -= 1;
+        serial_write_char(buf[i]);
+    }
+}
+
+/// 输出字符串并用空格补齐到指定宽度
+pub fn serial_write_padded(s: &str, width: usize) {
+    serial_write(s);
+    for _ in s.len()..width {
+        serial_write_char(b' ');
+    }
+}
+
+pub fn serial_write_size(bytes: u64) {
+    if bytes >= 1024 * 1024 * 1024 {
+        serial_write_dec(bytes / 1024 / 1024 / 1024);
+        serial_write(" GB");
+    } else if bytes >= 1024 * 1024 {
+        serial_write_dec(bytes / 1024 / 1024);
+        serial_write(" MB");
+    } else if bytes >= 1024 {
+        serial_write_dec(bytes / 1024);
+        serial_write(" KB");
+    } else {
+        serial_write_dec(bytes);
+        serial_write(" bytes");
+    }
+}
+
+/// 输出 `xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx` 形式的 GUID
+pub fn serial_write_guid(guid: &Guid) {
+    for c in guid.to_ascii() {
+        serial_write_char(c);
+    }
+}
@@ -0,0 +1,1028 @@
+//! SMBIOS 结构表
+//!
+//! 校验引导程序交接的 SMBIOS 入口点（2.x 的 `_SM_`/`_DMI_` 与 3.x 的 `_SM3_`），
+//! 遍历结构表，并把常用的结构解码为类型化记录：
+//!
+//! | 类型 | 解码结果 |
+//! |------|----------|
+//! | 0 | [`BiosInfo`]：厂商、版本、发布日期、ROM 大小与特性 |
+//! | 1 | [`SystemInfo`]：制造商、产品、序列号、UUID |
+//! | 2 | [`Baseboard`] |
+//! | 3 | [`Chassis`]：类型与各种状态 |
+//! | 4 | [`Processor`]：插槽、型号、频率、核心与线程数 |
+//! | 17 | [`MemoryDevice`]：插槽、容量、类型与速度 |
+//!
+//! 其他类型可以通过 [`structures`] 按原始字节访问。结构表通过直接映射
+//! 访问，必须在分页初始化之后使用。
+
+use crate::firmware::{checksum, phys_bytes, read_u16, read_u32, read_u64};
+use crate::serial::{
+    serial_write, serial_write_dec, serial_write_guid, serial_write_hex, serial_write_size,
+};
+use alloc::vec::Vec;
+use boot_protocol::{BootInfo, Guid};
+use core::sync::atomic::{AtomicU16, AtomicU32, AtomicU64, Ordering};
+
+/// 结构表物理地址，0 表示没有 SMBIOS
+static TABLE: AtomicU64 = AtomicU64::new(0);
+/// 结构表长度（3.x 为最大长度）
+static TABLE_LENGTH: AtomicU32 = AtomicU32::new(0);
+/// 结构个数（3.x 的入口点不记录，为 `u32::MAX`）
+static STRUCTURE_COUNT: AtomicU32 = AtomicU32::new(0);
+/// 规范版本，`major << 8 | minor`
+static VERSION: AtomicU16 = AtomicU16::new(0);
+
+/// 2.1 入口点最小长度
+const ENTRY_V2_LEN: usize = 0x1F;
+/// 3.0 入口点最小长度
+const ENTRY_V3_LEN: usize = 0x18;
+/// 结构头长度
+const HEADER_LEN: usize = 4;
+/// 表结束结构
+const END_OF_TABLE: u8 = 127;
+
+pub const TYPE_BIOS: u8 = 0;
+pub const TYPE_SYSTEM: u8 = 1;
+pub const TYPE_BASEBOARD: u8 = 2;
+pub const TYPE_CHASSIS: u8 = 3;
+pub const TYPE_PROCESSOR: u8 = 4;
+pub const TYPE_MEMORY_DEVICE: u8 = 17;
+
+/// 初始化失败原因
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SmbiosError {
+    /// 引导程序没有找到入口点
+    NotPresent,
+    /// 入口点签名不是 `_SM_` / `_SM3_`
+    BadSignature,
+    /// 入口点校验和错误
+    BadChecksum,
+    /// 结构表地址或长度无效
+    BadTable,
+}
+
+impl SmbiosError {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::NotPresent => "no entry point",
+            Self::BadSignature => "bad entry point signature",
+            Self::BadChecksum => "bad entry point checksum",
+            Self::BadTable => "bad structure table",
+        }
+    }
+}
+
+/// 解析后的入口点
+#[derive(Clone, Copy, Debug)]
+pub struct EntryPoint {
+    /// 物理地址
+    pub address: u64,
+    /// 64 位（3.x）入口点
+    pub v3: bool,
+    pub major: u8,
+    pub minor: u8,
+    /// 文档修订号（只有 3.x 入口点提供）
+    pub docrev: u8,
+    /// 结构表物理地址
+    pub table: u64,
+    /// 结构表长度（3.x 为最大长度）
+    pub table_length: u32,
+    /// 结构个数（只有 2.x 入口点提供）
+    pub structure_count: Option<u16>,
+}
+
+/// 校验入口点并记下结构表位置；只在分页初始化之后调用一次
+pub fn init(info: &BootInfo) -> Result<EntryPoint, SmbiosError> {
+    let (address, _) = info.smbios().ok_or(SmbiosError::NotPresent)?;
+    let head = phys_bytes(address, 5);
+    let entry = if head == b"_SM3_" {
+        let bytes = phys_bytes(address, ENTRY_V3_LEN);
+        let length = (bytes[6] as usize).max(ENTRY_V3_LEN);
+        if !checksum(phys_bytes(address, length)) {
+            return Err(SmbiosError::BadChecksum);
+        }
+        EntryPoint {
+            address,
+            v3: true,
+            major: bytes[7],
+            minor: bytes[8],
+            docrev: bytes[9],
+            table: read_u64(bytes, 0x10),
+            table_length: read_u32(bytes, 0x0C),
+            structure_count: None,
+        }
+    } else if &head[..4] == b"_SM_" {
+        let bytes = phys_bytes(address, ENTRY_V2_LEN);
+        // 2.1 的入口点长度字段有的固件写成 0x1E
+        let length = (bytes[5] as usize).max(0x1E);
+        if !checksum(phys_bytes(address, length)) || !checksum(&bytes[0x10..0x1F]) {
+            return Err(SmbiosError::BadChecksum);
+        }
+        if &bytes[0x10..0x15] != b"_DMI_" {
+            return Err(SmbiosError::BadSignature);
+        }
+        let (major, minor) = match (bytes[6], bytes[7]) {
+            // 部分固件把 2.33 / 2.51 写成了次版本号 33 / 51
+            (2, 33) => (2, 3),
+            (2, 51) => (2, 5),
+            version => version,
+        };
+        EntryPoint {
+            address,
+            v3: false,
+            major,
+            minor,
+            docrev: 0,
+            table: read_u32(bytes, 0x18) as u64,
+            table_length: read_u16(bytes, 0x16) as u32,
+            structure_count: Some(read_u16(bytes, 0x1C)),
+        }
+    } else {
+        return Err(SmbiosError::BadSignature);
+    };
+    if entry.table == 0 || (entry.table_length as usize) < HEADER_LEN {
+        return Err(SmbiosError::BadTable);
+    }
+
+    TABLE.store(entry.table, Ordering::Relaxed);
+    TABLE_LENGTH.store(entry.table_length, Ordering::Relaxed);
+    STRUCTURE_COUNT.store(
+        entry.structure_count.map_or(u32::MAX, |count| count as u32),
+        Ordering::Relaxed,
+    );
+    VERSION.store(
+        (entry.major as u16) << 8 | entry.minor as u16,
+        Ordering::Relaxed,
+    );
+    Ok(entry)
+}
+
+/// 规范版本不低于 `major.minor`
+fn at_least(major: u8, minor: u8) -> bool {
+    VERSION.load(Ordering::Relaxed) >= (major as u16) << 8 | minor as u16
+}
+
+// ============================================================================
+// 结构表
+// ============================================================================
+
+/// 一个结构：格式化区与其后的字符串集
+#[derive(Clone, Copy, Debug)]
+pub struct Structure {
+    pub kind: u8,
+    pub handle: u16,
+    /// 格式化区（含 4 字节结构头）
+    pub data: &'static [u8],
+    /// 字符串集，以 NUL 分隔，不含结尾的双 NUL
+    strings: &'static [u8],
+}
+
+impl Structure {
+    /// 字符串集中第 `index` 个字符串（从 1 开始，0 表示没有）；去掉尾部空格，
+    /// 空串或不是 UTF-8 时返回 `None`
+    pub fn string(&self, index: u8) -> Option<&'static str> {
+        let index = (index as usize).checked_sub(1)?;
+        let bytes = self.strings.split(|&b| b == 0).nth(index)?;
+        let s = core::str::from_utf8(bytes).ok()?.trim_end();
+        (!s.is_empty()).then_some(s)
+    }
+
+    /// 格式化区中偏移处的字节；老版本的结构较短，超出格式化区时返回 `None`
+    fn byte(&self, offset: usize) -> Option<u8> {
+        self.data.get(offset).copied()
+    }
+
+    fn word(&self, offset: usize) -> Option<u16> {
+        (offset + 2 <= self.data.len()).then(|| read_u16(self.data, offset))
+    }
+
+    fn dword(&self, offset: usize) -> Option<u32> {
+        (offset + 4 <= self.data.len()).then(|| read_u32(self.data, offset))
+    }
+
+    fn qword(&self, offset: usize) -> Option<u64> {
+        (offset + 8 <= self.data.len()).then(|| read_u64(self.data, offset))
+    }
+
+    /// 格式化区中偏移处的字节作为字符串编号
+    fn string_at(&self, offset: usize) -> Option<&'static str> {
+        self.string(self.byte(offset)?)
+    }
+}
+
+/// 结构表迭代器
+struct Structures {
+    bytes: &'static [u8],
+    pos: usize,
+    remaining: u32,
+}
+
+impl Iterator for Structures {
+    type Item = Structure;
+
+    fn next(&mut self) -> Option<Structure> {
+        if self.remaining == 0 || self.pos + HEADER_LEN > self.bytes.len() {
+            return None;
+        }
+        let start = self.pos;
+        let kind = self.bytes[start];
+        let length = self.bytes[start + 1] as usize;
+        if kind == END_OF_TABLE || length < HEADER_LEN || start + length > self.bytes.len() {
+            return None;
+        }
+        // 字符串集以双 NUL 结束；没有字符串时格式化区之后直接是双 NUL
+        let strings = start + length;
+        let end = self.bytes[strings..]
+            .windows(2)
+            .position(|pair| pair == [0, 0])
+            .map(|offset| strings + offset)?;
+        self.pos = end + 2;
+        self.remaining -= 1;
+        Some(Structure {
+            kind,
+            handle: read_u16(self.bytes, start + 2),
+            data: &self.bytes[start..strings],
+            strings: &self.bytes[strings..end],
+        })
+    }
+}
+
+/// 结构表中的所有结构，到表结束结构为止
+pub fn structures() -> impl Iterator<Item = Structure> {
+    let table = TABLE.load(Ordering::Relaxed);
+    let bytes = match table {
+        0 => &[][..],
+        _ => phys_bytes(table, TABLE_LENGTH.load(Ordering::Relaxed) as usize),
+    };
+    Structures {
+        bytes,
+        pos: 0,
+        remaining: STRUCTURE_COUNT.load(Ordering::Relaxed),
+    }
+}
+
+/// 指定类型的所有结构
+pub fn find(kind: u8) -> impl Iterator<Item = Structure> {
+    structures().filter(move |s| s.kind == kind)
+}
+
+// ============================================================================
+// BIOS 信息（类型 0）
+// ============================================================================
+
+/// 特性：支持 PCI
+const BIOS_PCI: u64 = 1 << 7;
+/// 特性：BIOS 可升级（Flash）
+const BIOS_UPGRADEABLE: u64 = 1 << 11;
+/// 扩展特性字节 2：UEFI
+const BIOS_EXT_UEFI: u8 = 1 << 3;
+/// 扩展特性字节 2：运行在虚拟机中
+const BIOS_EXT_VIRTUAL_MACHINE: u8 = 1 << 4;
+
+#[derive(Clone, Copy, Debug)]
+pub struct BiosInfo {
+    pub vendor: Option<&'static str>,
+    pub version: Option<&'static str>,
+    pub release_date: Option<&'static str>,
+    /// 运行时映像的起始段（0 表示 UEFI 系统不适用）
+    pub starting_segment: u16,
+    /// ROM 大小（字节）
+    pub rom_size: u64,
+    pub characteristics: u64,
+    /// 扩展特性字节（2.4+）
+    pub characteristics_ext: [u8; 2],
+    /// 系统 BIOS 主次版本（2.4+）
+    pub release: Option<(u8, u8)>,
+    /// 嵌入式控制器固件主次版本（2.4+）
+    pub ec_release: Option<(u8, u8)>,
+}
+
+impl BiosInfo {
+    pub fn is_uefi(&self) -> bool {
+        self.characteristics_ext[1] & BIOS_EXT_UEFI != 0
+    }
+
+    pub fn is_virtual_machine(&self) -> bool {
+        self.characteristics_ext[1] & BIOS_EXT_VIRTUAL_MACHINE != 0
+    }
+}
+
+pub fn bios() -> Option<BiosInfo> {
+    let s = find(TYPE_BIOS).next()?;
+    let rom_size = match s.byte(0x09)? {
+        // 3.1+：扩展 ROM 大小，位 15:14 为单位（MB / GB）
+        0xFF => s.word(0x18).map_or(0, |ext| {
+            let size = (ext & 0x3FFF) as u64;
+            match ext >> 14 {
+                0 => size << 20,
+                1 => size << 30,
+                _ => 0,
+            }
+        }),
+        blocks => (blocks as u64 + 1) * 64 * 1024,
+    };
+    // 0xFF 表示不支持
+    let release = |offset: usize| {
+        let major = s.byte(offset)?;
+        let minor = s.byte(offset + 1)?;
+        (major != 0xFF || minor != 0xFF).then_some((major, minor))
+    };
+    Some(BiosInfo {
+        vendor: s.string_at(0x04),
+        version: s.string_at(0x05),
+        release_date: s.string_at(0x08),
+        starting_segment: s.word(0x06)?,
+        rom_size,
+        characteristics: s.qword(0x0A).unwrap_or(0),
+        characteristics_ext: [s.byte(0x12).unwrap_or(0), s.byte(0x13).unwrap_or(0)],
+        release: release(0x14),
+        ec_release: release(0x16),
+    })
+}
+
+// ============================================================================
+// 系统信息（类型 1）
+// ============================================================================
+
+#[derive(Clone, Copy, Debug)]
+pub struct SystemInfo {
+    pub manufacturer: Option<&'static str>,
+    pub product: Option<&'static str>,
+    pub version: Option<&'static str>,
+    pub serial: Option<&'static str>,
+    /// 全 0（未设置）或全 1（不存在）时为 `None`；字节序与 [`Guid`] 相同
+    pub uuid: Option<Guid>,
+    pub wake_up_type: u8,
+    pub sku: Option<&'static str>,
+    pub family: Option<&'static str>,
+}
+
+impl SystemInfo {
+    pub fn wake_up_type_name(&self) -> &'static str {
+        match self.wake_up_type {
+            1 => "Other",
+            2 => "Unknown",
+            3 => "APM Timer",
+            4 => "Modem Ring",
+            5 => "LAN Remote",
+            6 => "Power Switch",
+            7 => "PCI PME#",
+            8 => "AC Power Restored",
+            _ => "Reserved",
+        }
+    }
+}
+
+pub fn system() -> Option<SystemInfo> {
+    let s = find(TYPE_SYSTEM).next()?;
+    let uuid = s
+        .data
+        .get(0x08..0x18)
+        .map(|bytes| <[u8; 16]>::try_from(bytes).unwrap())
+        .filter(|bytes| bytes.iter().any(|&b| b != 0) && bytes.iter().any(|&b| b != 0xFF))
+        .map(|mut bytes| {
+            // 2.6 之前前三段按网络字节序存放
+            if !at_least(2, 6) {
+                bytes[0..4].reverse();
+                bytes[4..6].reverse();
+                bytes[6..8].reverse();
+            }
+            Guid(bytes)
+        });
+    Some(SystemInfo {
+        manufacturer: s.string_at(0x04),
+        product: s.string_at(0x05),
+        version: s.string_at(0x06),
+        serial: s.string_at(0x07),
+        uuid,
+        wake_up_type: s.byte(0x18).unwrap_or(2),
+        sku: s.string_at(0x19),
+        family: s.string_at(0x1A),
+    })
+}
+
+// ============================================================================
+// 主板（类型 2）
+// ============================================================================
+
+#[derive(Clone, Copy, Debug)]
+pub struct Baseboard {
+    pub manufacturer: Option<&'static str>,
+    pub product: Option<&'static str>,
+    pub version: Option<&'static str>,
+    pub serial: Option<&'static str>,
+    pub asset_tag: Option<&'static str>,
+    /// 在机箱中的位置
+    pub location: Option<&'static str>,
+    pub board_type: u8,
+}
+
+impl Baseboard {
+    pub fn board_type_name(&self) -> &'static str {
+        match self.board_type {
+            2 => "Other",
+            3 => "Server Blade",
+            4 => "Connectivity Switch",
+            5 => "System Management Module",
+            6 => "Processor Module",
+            7 => "I/O Module",
+            8 => "Memory Module",
+            9 => "Daughter Board",
+            0x0A => "Motherboard",
+            0x0B => "Processor/Memory Module",
+            0x0C => "Processor/IO Module",
+            0x0D => "Interconnect Board",
+            _ => "Unknown",
+        }
+    }
+}
+
+pub fn baseboards() -> Vec<Baseboard> {
+    find(TYPE_BASEBOARD)
+        .map(|s| Baseboard {
+            manufacturer: s.string_at(0x04),
+            product: s.string_at(0x05),
+            version: s.string_at(0x06),
+            serial: s.string_at(0x07),
+            asset_tag: s.string_at(0x08),
+            location: s.string_at(0x0A),
+            board_type: s.byte(0x0D).unwrap_or(1),
+        })
+        .collect()
+}
+
+// ============================================================================
+// 机箱（类型 3）
+// ============================================================================
+
+#[derive(Clone, Copy, Debug)]
+pub struct Chassis {
+    pub manufacturer: Option<&'static str>,
+    pub chassis_type: u8,
+    /// 有机箱锁
+    pub lock: bool,
+    pub version: Option<&'static str>,
+    pub serial: Option<&'static str>,
+    pub asset_tag: Option<&'static str>,
+    /// 上次启动时的机箱状态（2.1+）
+    pub boot_up_state: Option<u8>,
+    pub power_supply_state: Option<u8>,
+    pub thermal_state: Option<u8>,
+}
+
+impl Chassis {
+    pub fn chassis_type_name(&self) -> &'static str {
+        const NAMES: [&str; 36] = [
+            "Other",
+            "Unknown",
+            "Desktop",
+            "Low Profile Desktop",
+            "Pizza Box",
+            "Mini Tower",
+            "Tower",
+            "Portable",
+            "Laptop",
+            "Notebook",
+            "Hand Held",
+            "Docking Station",
+            "All in One",
+            "Sub Notebook",
+            "Space-saving",
+            "Lunch Box",
+            "Main Server Chassis",
+            "Expansion Chassis",
+            "SubChassis",
+            "Bus Expansion Chassis",
+            "Peripheral Chassis",
+            "RAID Chassis",
+            "Rack Mount Chassis",
+            "Sealed-case PC",
+            "Multi-system Chassis",
+            "Compact PCI",
+            "Advanced TCA",
+            "Blade",
+            "Blade Enclosure",
+            "Tablet",
+            "Convertible",
+            "Detachable",
+            "IoT Gateway",
+            "Embedded PC",
+            "Mini PC",
+            "Stick PC",
+        ];
+        (self.chassis_type as usize)
+            .checked_sub(1)
+            .and_then(|i| NAMES.get(i))
+            .copied()
+            .unwrap_or("Unknown")
+    }
+}
+
+/// 机箱状态字段（启动、电源、温度）的名称
+pub fn chassis_state_name(state: u8) -> &'static str {
+    match state {
+        1 => "Other",
+        3 => "Safe",
+        4 => "Warning",
+        5 => "Critical",
+        6 => "Non-recoverable",
+        _ => "Unknown",
+    }
+}
+
+pub fn chassis() -> Vec<Chassis> {
+    find(TYPE_CHASSIS)
+        .map(|s| {
+            let kind = s.byte(0x05).unwrap_or(2);
+            Chassis {
+                manufacturer: s.string_at(0x04),
+                chassis_type: kind & 0x7F,
+                lock: kind & 0x80 != 0,
+                version: s.string_at(0x06),
+                serial: s.string_at(0x07),
+                asset_tag: s.string_at(0x08),
+                boot_up_state: s.byte(0x09),
+                power_supply_state: s.byte(0x0A),
+                thermal_state: s.byte(0x0B),
+            }
+        })
+        .collect()
+}
+
+// ============================================================================
+// 处理器（类型 4）
+// ============================================================================
+
+/// 状态字节：插槽上装有处理器
+const CPU_SOCKET_POPULATED: u8 = 1 << 6;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Processor {
+    /// 插槽标识
+    pub socket: Option<&'static str>,
+    pub processor_type: u8,
+    /// 处理器族（0xFE 时取自 2.6+ 的处理器族 2）
+    pub family: u16,
+    pub manufacturer: Option<&'static str>,
+    /// 处理器 ID（x86 上是 CPUID 1 的 EAX 与 EDX）
+    pub id: u64,
+    pub version: Option<&'static str>,
+    /// 外部时钟、最高与当前频率（MHz，0 表示未知）
+    pub external_clock: u16,
+    pub max_speed: u16,
+    pub current_speed: u16,
+    pub populated: bool,
+    /// 状态字节的位 2:0
+    pub status: u8,
+    /// 核心数、启用的核心数、线程数（2.5+）
+    pub cores: Option<u16>,
+    pub cores_enabled: Option<u16>,
+    pub threads: Option<u16>,
+}
+
+impl Processor {
+    pub fn processor_type_name(&self) -> &'static str {
+        match self.processor_type {
+            1 => "Other",
+            3 => "Central Processor",
+            4 => "Math Processor",
+            5 => "DSP Processor",
+            6 => "Video Processor",
+            _ => "Unknown",
+        }
+    }
+
+    pub fn status_name(&self) -> &'static str {
+        match self.status {
+            1 => "Enabled",
+            2 => "Disabled by user",
+            3 => "Disabled by BIOS (POST error)",
+            4 => "Idle",
+            7 => "Other",
+            _ => "Unknown",
+        }
+    }
+}
+
+pub fn processors() -> Vec<Processor> {
+    find(TYPE_PROCESSOR)
+        .map(|s| {
+            let status = s.byte(0x18).unwrap_or(0);
+            // 0xFF 表示真实值在 3.0+ 的 16 位字段中
+            let count = |offset: usize, extended: usize| match s.byte(offset)? {
+                0 => None,
+                0xFF => s.word(extended).filter(|&n| n != 0),
+                n => Some(n as u16),
+            };
+            let family = match s.byte(0x06).unwrap_or(2) {
+                0xFE => s.word(0x28).unwrap_or(2),
+                family => family as u16,
+            };
+            Processor {
+                socket: s.string_at(0x04),
+                processor_type: s.byte(0x05).unwrap_or(2),
+                family,
+                manufacturer: s.string_at(0x07),
+                id: s.qword(0x08).unwrap_or(0),
+                version: s.string_at(0x10),
+                external_clock: s.word(0x12).unwrap_or(0),
+                max_speed: s.word(0x14).unwrap_or(0),
+                current_speed: s.word(0x16).unwrap_or(0),
+                populated: status & CPU_SOCKET_POPULATED != 0,
+                status: status & 0x07,
+                cores: count(0x23, 0x2A),
+                cores_enabled: count(0x24, 0x2C),
+                threads: count(0x25, 0x2E),
+            }
+        })
+        .collect()
+}
+
+// ============================================================================
+// 内存设备（类型 17）
+// ============================================================================
+
+/// 内存设备容量
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MemorySize {
+    /// 插槽为空
+    Empty,
+    Unknown,
+    Bytes(u64),
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct MemoryDevice {
+    pub handle: u16,
+    /// 插槽标识，如 `DIMM 0`
+    pub locator: Option<&'static str>,
+    pub bank_locator: Option<&'static str>,
+    pub size: MemorySize,
+    pub form_factor: u8,
+    pub memory_type: u8,
+    /// 总位宽与数据位宽（0xFFFF 表示未知）
+    pub total_width: u16,
+    pub data_width: u16,
+    /// 最高与配置速度（MT/s，2.3+ / 2.7+，未知时为 `None`）
+    pub speed: Option<u32>,
+    pub configured_speed: Option<u32>,
+    pub manufacturer: Option<&'static str>,
+    pub serial: Option<&'static str>,
+    pub part_number: Option<&'static str>,
+}
+
+impl MemoryDevice {
+    pub fn memory_type_name(&self) -> &'static str {
+        match self.memory_type {
+            0x01 => "Other",
+            0x03 => "DRAM",
+            0x04 => "EDRAM",
+            0x05 => "VRAM",
+            0x06 => "SRAM",
+            0x07 => "RAM",
+            0x08 => "ROM",
+            0x09 => "Flash",
+            0x0A => "EEPROM",
+            0x0B => "FEPROM",
+            0x0C => "EPROM",
+            0x0D => "CDRAM",
+            0x0E => "3DRAM",
+            0x0F => "SDRAM",
+            0x10 => "SGRAM",
+            0x11 => "RDRAM",
+            0x12 => "DDR",
+            0x13 => "DDR2",
+            0x14 => "DDR2 FB-DIMM",
+            0x18 => "DDR3",
+            0x19 => "FBD2",
+            0x1A => "DDR4",
+            0x1B => "LPDDR",
+            0x1C => "LPDDR2",
+            0x1D => "LPDDR3",
+            0x1E => "LPDDR4",
+            0x1F => "Logical non-volatile",
+            0x20 => "HBM",
+            0x21 => "HBM2",
+            0x22 => "DDR5",
+            0x23 => "LPDDR5",
+            0x24 => "HBM3",
+            _ => "Unknown",
+        }
+    }
+
+    pub fn form_factor_name(&self) -> &'static str {
+        match self.form_factor {
+            0x01 => "Other",
+            0x03 => "SIMM",
+            0x04 => "SIP",
+            0x05 => "Chip",
+            0x06 => "DIP",
+            0x07 => "ZIP",
+            0x08 => "Proprietary Card",
+            0x09 => "DIMM",
+            0x0A => "TSOP",
+            0x0B => "Row of chips",
+            0x0C => "RIMM",
+            0x0D => "SODIMM",
+            0x0E => "SRIMM",
+            0x0F => "FB-DIMM",
+            0x10 => "Die",
+            0x11 => "CAMM",
+            _ => "Unknown",
+        }
+    }
+}
+
+pub fn memory_devices() -> Vec<MemoryDevice> {
+    find(TYPE_MEMORY_DEVICE)
+        .map(|s| {
+            let size = match s.word(0x0C).unwrap_or(0xFFFF) {
+                0 => MemorySize::Empty,
+                0xFFFF => MemorySize::Unknown,
+                // 2.7+：真实容量在扩展容量字段（MB）
+                0x7FFF => s.dword(0x1C).map_or(MemorySize::Unknown, |mb| {
+                    MemorySize::Bytes(((mb & 0x7FFF_FFFF) as u64) << 20)
+                }),
+                // 位 15 置位时单位为 KB，否则为 MB
+                size if size & 0x8000 != 0 => MemorySize::Bytes(((size & 0x7FFF) as u64) << 10),
+                size => MemorySize::Bytes((size as u64) << 20),
+            };
+            // 0xFFFF 表示真实值在 3.3+ 的 32 位扩展速度字段
+            let speed = |offset: usize, extended: usize| match s.word(offset)? {
+                0 => None,
+                0xFFFF => s.dword(extended).map(|speed| speed & 0x7FFF_FFFF),
+                speed => Some(speed as u32),
+            };
+            MemoryDevice {
+                handle: s.handle,
+                locator: s.string_at(0x10),
+                bank_locator: s.string_at(0x11),
+                size,
+                form_factor: s.byte(0x0E).unwrap_or(2),
+                memory_type: s.byte(0x12).unwrap_or(2),
+                total_width: s.word(0x08).unwrap_or(0xFFFF),
+                data_width: s.word(0x0A).unwrap_or(0xFFFF),
+                speed: speed(0x15, 0x54),
+                configured_speed: speed(0x20, 0x58),
+                manufacturer: s.string_at(0x17),
+                serial: s.string_at(0x18),
+                part_number: s.string_at(0x1A),
+            }
+        })
+        .collect()
+}
+
+// ============================================================================
+// 串口输出
+// ============================================================================
+
+/// 输出入口点、结构统计以及各个已解码结构的内容
+pub fn dump(entry: &EntryPoint) {
+    serial_write("  Entry Point:    ");
+    serial_write_hex(entry.address);
+    serial_write(if entry.v3 {
+        " (_SM3_), SMBIOS "
+    } else {
+        " (_SM_), SMBIOS "
+    });
+    serial_write_dec(entry.major as u64);
+    serial_write(".");
+    serial_write_dec(entry.minor as u64);
+    if entry.v3 {
+        serial_write(".");
+        serial_write_dec(entry.docrev as u64);
+    }
+    serial_write("\n  Table:          ");
+    serial_write_hex(entry.table);
+    serial_write(", ");
+    serial_write_dec(entry.table_length as u64);
+    serial_write(if entry.v3 { " bytes max, " } else { " bytes, " });
+    serial_write_dec(structures().count() as u64);
+    serial_write(" structures");
+    if let Some(count) = entry.structure_count {
+        serial_write(" (entry point: ");
+        serial_write_dec(count as u64);
+        serial_write(")");
+    }
+    serial_write("\n");
+
+    if let Some(bios) = bios() {
+        serial_write("  BIOS:           ");
+        write_str(bios.vendor);
+        serial_write(" ");
+        write_str(bios.version);
+        serial_write(" (");
+        write_str(bios.release_date);
+        serial_write(")\n    ROM ");
+        serial_write_size(bios.rom_size);
+        if bios.starting_segment != 0 {
+            serial_write(" at ");
+            serial_write_hex((bios.starting_segment as u64) << 4);
+        }
+        if let Some((major, minor)) = bios.release {
+            serial_write(", release ");
+            write_release(major, minor);
+        }
+        if let Some((major, minor)) = bios.ec_release {
+            serial_write(", EC ");
+            write_release(major, minor);
+        }
+        serial_write(", characteristics ");
+        serial_write_hex(bios.characteristics);
+        for (flag, name) in [(BIOS_PCI, " [PCI]"), (BIOS_UPGRADEABLE, " [flash]")] {
+            if bios.characteristics & flag != 0 {
+                serial_write(name);
+            }
+        }
+        if bios.is_uefi() {
+            serial_write(" [UEFI]");
+        }
+        if bios.is_virtual_machine() {
+            serial_write(" [VM]");
+        }
+        serial_write("\n");
+    }
+
+    if let Some(system) = system() {
+        serial_write("  System:         ");
+        write_str(system.manufacturer);
+        serial_write(" ");
+        write_str(system.product);
+        serial_write(" ");
+        write_str(system.version);
+        serial_write("\n    Serial ");
+        write_str(system.serial);
+        serial_write(", SKU ");
+        write_str(system.sku);
+        serial_write(", family ");
+        write_str(system.family);
+        serial_write("\n    UUID ");
+        match &system.uuid {
+            Some(uuid) => serial_write_guid(uuid),
+            None => serial_write("-"),
+        }
+        serial_write(", wake-up ");
+        serial_write(system.wake_up_type_name());
+        serial_write("\n");
+    }
+
+    for board in baseboards() {
+        serial_write("  Baseboard:      ");
+        write_str(board.manufacturer);
+        serial_write(" ");
+        write_str(board.product);
+        serial_write(" ");
+        write_str(board.version);
+        serial_write(" (");
+        serial_write(board.board_type_name());
+        serial_write(")\n    Serial ");
+        write_str(board.serial);
+        serial_write(", asset tag ");
+        write_str(board.asset_tag);
+        serial_write(", location ");
+        write_str(board.location);
+        serial_write("\n");
+    }
+
+    for chassis in chassis() {
+        serial_write("  Chassis:        ");
+        write_str(chassis.manufacturer);
+        serial_write(" ");
+        serial_write(chassis.chassis_type_name());
+        if chassis.lock {
+            serial_write(" [lock]");
+        }
+        serial_write(", version ");
+        write_str(chassis.version);
+        serial_write("\n    Serial ");
+        write_str(chassis.serial);
+        serial_write(", asset tag ");
+        write_str(chassis.asset_tag);
+        for (name, state) in [
+            (", boot-up ", chassis.boot_up_state),
+            (", power supply ", chassis.power_supply_state),
+            (", thermal ", chassis.thermal_state),
+        ] {
+            if let Some(state) = state {
+                serial_write(name);
+                serial_write(chassis_state_name(state));
+            }
+        }
+        serial_write("\n");
+    }
+
+    for cpu in processors() {
+        serial_write("  Processor:      ");
+        write_str(cpu.socket);
+        serial_write(": ");
+        if !cpu.populated {
+            serial_write("empty\n");
+            continue;
+        }
+        write_str(cpu.version);
+        serial_write(" (");
+        write_str(cpu.manufacturer);
+        serial_write(")\n    ");
+        serial_write(cpu.processor_type_name());
+        serial_write(", family ");
+        serial_write_hex(cpu.family as u64);
+        serial_write(", ID ");
+        serial_write_hex(cpu.id);
+        serial_write(", ");
+        serial_write(cpu.status_name());
+        serial_write("\n    ");
+        serial_write_dec(cpu.current_speed as u64);
+        serial_write(" / ");
+        serial_write_dec(cpu.max_speed as u64);
+        serial_write(" MHz, bus ");
+        serial_write_dec(cpu.external_clock as u64);
+        serial_write(" MHz");
+        for (name, count) in [
+            (", cores ", cpu.cores),
+            (", enabled ", cpu.cores_enabled),
+            (", threads ", cpu.threads),
+        ] {
+            if let Some(count) = count {
+                serial_write(name);
+                serial_write_dec(count as u64);
+            }
+        }
+        serial_write("\n");
+    }
+
+    let devices = memory_devices();
+    let mut total = 0;
+    for device in &devices {
+        serial_write("  Memory device:  ");
+        serial_write_hex(device.handle as u64);
+        serial_write(" ");
+        write_str(device.locator);
+        serial_write(" / ");
+        write_str(device.bank_locator);
+        serial_write(": ");
+        match device.size {
+            MemorySize::Empty => {
+                serial_write("empty\n");
+                continue;
+            }
+            MemorySize::Unknown => serial_write("unknown size"),
+            MemorySize::Bytes(bytes) => {
+                total += bytes;
+                serial_write_size(bytes);
+            }
+        }
+        serial_write(" ");
+        serial_write(device.memory_type_name());
+        serial_write(" ");
+        serial_write(device.form_factor_name());
+        if device.data_width != 0xFFFF && device.total_width != 0xFFFF {
+            serial_write(", ");
+            serial_write_dec(device.data_width as u64);
+            serial_write("/");
+            serial_write_dec(device.total_width as u64);
+            serial_write(" bits");
+        }
+        if let Some(speed) = device.speed {
+            serial_write(", ");
+            serial_write_dec(speed as u64);
+            if let Some(configured) = device.configured_speed {
+                serial_write(" (running ");
+                serial_write_dec(configured as u64);
+                serial_write(")");
+            }
+            serial_write(" MT/s");
+        }
+        serial_write("\n    ");
+        write_str(device.manufacturer);
+        serial_write(" ");
+        write_str(device.part_number);
+        serial_write(", serial ");
+        write_str(device.serial);
+        serial_write("\n");
+    }
+    if !devices.is_empty() {
+        serial_write("  Installed:      ");
+        serial_write_size(total);
+        serial_write(" in ");
+        serial_write_dec(
+            devices
+                .iter()
+                .filter(|d| d.size != MemorySize::Empty)
+                .count() as u64,
+        );
+        serial_write(" of ");
+        serial_write_dec(devices.len() as u64);
+        serial_write(" slots\n");
+    }
+}
+
+/// 输出可选字符串，没有时输出 `-`
+fn write_str(s: Option<&str>) {
+    serial_write(s.unwrap_or("-"));
+}
+
+fn write_release(major: u8, minor: u8) {
+    serial_write_dec(major as u64);
+    serial_write(".");
+    serial_write_dec(minor as u64);
+}
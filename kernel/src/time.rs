@@ -0,0 +1,458 @@
+//! 时间子系统
+//!
+//! - 时钟源：CPU 声明不变 TSC 或没有 64 位 HPET 时使用 TSC，否则使用 HPET
+//!   主计数器；单调时钟 [`now`] 从 [`init`] 起计
+//! - 校准：以 HPET（没有时用 PIT 通道 2）为基准测量 10 ms，得到 TSC 与
+//!   本地 APIC 定时器的频率
+//! - 定时器：[`one_shot`] / [`periodic`] 注册的回调在本地 APIC 定时器中断中
+//!   运行，硬件只为最早的到期时间编程。CPUID 声明 TSC-deadline 时使用该模式，
+//!   但时钟源为 HPET 时除外：到期时间按 TSC 换算，与 HPET 时钟会逐渐偏离，
+//!   此时退回本地 APIC 单次计数
+//! - [`sleep`] 开中断时用 `hlt` 等待定时器，否则退化为 [`busy_wait`]
+//!
+//! 定时器回调在关中断的中断上下文中运行，不能分配内存或长时间阻塞。
+
+use crate::acpi;
+use crate::apic::{self, TimerMode};
+use crate::cpu::{interrupts_enabled, rdtsc, without_interrupts};
+use crate::idt::{self, InterruptFrame};
+use crate::paging::{self, CacheMode};
+use crate::port::{inb, outb};
+use crate::sync::SpinLock;
+use alloc::vec::Vec;
+use core::arch::asm;
+use core::arch::x86_64::__cpuid;
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::time::Duration;
+
+const NS_PER_SEC: u64 = 1_000_000_000;
+/// 1 ns = 10^6 fs
+const FS_PER_NS: u64 = 1_000_000;
+/// 校准时长
+const CALIBRATION_NS: u64 = 10_000_000;
+
+// HPET 寄存器
+const HPET_CAPABILITIES: u64 = 0x000;
+const HPET_CONFIG: u64 = 0x010;
+const HPET_COUNTER: u64 = 0x0F0;
+const HPET_CONFIG_ENABLE: u64 = 1 << 0;
+const HPET_CAP_64BIT: u64 = 1 << 13;
+
+// PIT 通道 2（门控与输出经由端口 0x61）
+const PIT_HZ: u64 = 1_193_182;
+const PIT_CHANNEL2: u16 = 0x42;
+const PIT_COMMAND: u16 = 0x43;
+const PIT_GATE_PORT: u16 = 0x61;
+const PIT_GATE: u8 = 1 << 0;
+const PIT_SPEAKER: u8 = 1 << 1;
+const PIT_OUT2: u8 = 1 << 5;
+
+/// 单调时钟的来源
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClockSource {
+    Tsc,
+    Hpet,
+}
+
+impl ClockSource {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Tsc => "TSC",
+            Self::Hpet => "HPET",
+        }
+    }
+}
+
+/// 校准基准
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reference {
+    Hpet,
+    Pit,
+}
+
+impl Reference {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Hpet => "HPET",
+            Self::Pit => "PIT",
+        }
+    }
+}
+
+/// HPET 事件定时器块
+#[derive(Clone, Copy, Debug)]
+pub struct Hpet {
+    /// 寄存器物理地址
+    pub address: u64,
+    /// 寄存器虚拟地址
+    base: u64,
+    /// 主计数器周期（飞秒）
+    pub period_fs: u64,
+    /// 比较器个数
+    pub timers: u32,
+    pub counter_64bit: bool,
+    pub vendor_id: u16,
+}
+
+impl Hpet {
+    /// 映射 ACPI HPET 表描述的寄存器块并启动主计数器
+    fn probe() -> Option<Self> {
+        let table = acpi::hpet()?;
+        let base = paging::map_mmio(table.address, 0x400, CacheMode::Uncached).ok()?;
+        let mut hpet = Self {
+            address: table.address,
+            base,
+            period_fs: 0,
+            timers: 0,
+            counter_64bit: false,
+            vendor_id: 0,
+        };
+        let capabilities = hpet.read(HPET_CAPABILITIES);
+        hpet.period_fs = capabilities >> 32;
+        // 规范要求周期不超过 100 ns
+        if hpet.period_fs == 0 || hpet.period_fs > 100 * FS_PER_NS {
+            return None;
+        }
+        hpet.timers = ((capabilities >> 8) & 0x1F) as u32 + 1;
+        hpet.counter_64bit = capabilities & HPET_CAP_64BIT != 0;
+        hpet.vendor_id = (capabilities >> 16) as u16;
+        hpet.write(HPET_CONFIG, hpet.read(HPET_CONFIG) | HPET_CONFIG_ENABLE);
+        Some(hpet)
+    }
+
+    fn read(&self, register: u64) -> u64 {
+        unsafe { ((self.base + register) as *const u64).read_volatile() }
+    }
+
+    fn write(&self, register: u64, value: u64) {
+        unsafe { ((self.base + register) as *mut u64).write_volatile(value) }
+    }
+
+    /// 主计数器
+    fn counter(&self) -> u64 {
+        self.read(HPET_COUNTER)
+    }
+
+    /// 主计数器两次读数之间的计数（32 位计数器按 32 位回绕）
+    fn elapsed(&self, start: u64) -> u64 {
+        let elapsed = self.counter().wrapping_sub(start);
+        if self.counter_64bit {
+            elapsed
+        } else {
+            elapsed & 0xFFFF_FFFF
+        }
+    }
+
+    /// 计数频率 (Hz)
+    pub fn frequency(&self) -> u64 {
+        NS_PER_SEC * FS_PER_NS / self.period_fs
+    }
+}
+
+/// 初始化结果
+#[derive(Clone, Copy, Debug)]
+pub struct TimeInfo {
+    pub clock: ClockSource,
+    pub reference: Reference,
+    pub hpet: Option<Hpet>,
+    pub tsc_hz: u64,
+    /// TSC 不随频率与 C 状态变化
+    pub tsc_invariant: bool,
+    /// 本地 APIC 定时器分频后的频率；0 表示没有可用的本地 APIC 定时器
+    pub lapic_timer_hz: u64,
+    /// 定时器使用 TSC-deadline 模式（只在时钟源为 TSC 时）
+    pub tsc_deadline: bool,
+}
+
+/// 时钟状态；只在启动早期由 [`init`] 写入一次，之后只读（中断处理程序也会读）
+struct ClockStore(UnsafeCell<Option<Clock>>);
+
+unsafe impl Sync for ClockStore {}
+
+static CLOCK: ClockStore = ClockStore(UnsafeCell::new(None));
+
+#[derive(Clone, Copy)]
+struct Clock {
+    info: TimeInfo,
+    /// [`init`] 时的 TSC 与 HPET 读数（单调时钟零点）
+    tsc_base: u64,
+    hpet_base: u64,
+}
+
+fn clock() -> Option<&'static Clock> {
+    unsafe { (*CLOCK.0.get()).as_ref() }
+}
+
+/// 定时器回调
+pub type TimerCallback = fn();
+
+/// 定时器标识，用于 [`cancel`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimerId(u64);
+
+#[derive(Clone, Copy)]
+struct TimerEntry {
+    id: u64,
+    /// 到期时间（单调时钟纳秒）
+    deadline: u64,
+    /// 周期；0 表示单次
+    period: u64,
+    callback: TimerCallback,
+}
+
+/// 已注册的定时器；非中断上下文必须在 [`without_interrupts`] 中加锁
+static TIMERS: SpinLock<Vec<TimerEntry>> = SpinLock::new(Vec::new());
+static NEXT_TIMER_ID: AtomicU64 = AtomicU64::new(1);
+
+/// 发现 HPET、校准 TSC 与本地 APIC 定时器，并安装定时器中断
+///
+/// 必须在 [`apic::init`] 之后、开中断之前调用一次；本地 APIC 不可用时
+/// 只提供时钟与忙等待。
+pub fn init() -> TimeInfo {
+    let hpet = Hpet::probe();
+    let lapic = apic::timer_configure(TimerMode::OneShot, None);
+
+    let max_extended = __cpuid(0x8000_0000).eax;
+    let tsc_invariant = max_extended >= 0x8000_0007 && __cpuid(0x8000_0007).edx & (1 << 8) != 0;
+
+    let (reference, tsc_hz, lapic_timer_hz) = calibrate(hpet.as_ref(), lapic);
+    let clock = match hpet {
+        Some(hpet) if hpet.counter_64bit && !tsc_invariant => ClockSource::Hpet,
+        _ => ClockSource::Tsc,
+    };
+    let tsc_deadline = lapic && clock == ClockSource::Tsc && __cpuid(1).ecx & (1 << 24) != 0;
+    let info = TimeInfo {
+        clock,
+        reference,
+        hpet,
+        tsc_hz,
+        tsc_invariant,
+        lapic_timer_hz,
+        tsc_deadline,
+    };
+    unsafe {
+        *CLOCK.0.get() = Some(Clock {
+            info,
+            tsc_base: rdtsc(),
+            hpet_base: hpet.map_or(0, |hpet| hpet.counter()),
+        });
+    }
+
+    if lapic_timer_hz != 0 && idt::set_handler(apic::VECTOR_TIMER, timer_interrupt) {
+        let mode = if tsc_deadline {
+            TimerMode::TscDeadline
+        } else {
+            TimerMode::OneShot
+        };
+        apic::timer_configure(mode, Some(apic::VECTOR_TIMER));
+    }
+    info
+}
+
+/// 以 HPET 或 PIT 为基准测量 TSC 与本地 APIC 定时器的频率
+fn calibrate(hpet: Option<&Hpet>, lapic: bool) -> (Reference, u64, u64) {
+    if lapic {
+        apic::timer_arm_count(u32::MAX);
+    }
+    let tsc_start = rdtsc();
+    let (reference, elapsed_ns) = match hpet {
+        Some(hpet) => {
+            let start = hpet.counter();
+            let ticks = CALIBRATION_NS * FS_PER_NS / hpet.period_fs;
+            while hpet.elapsed(start) < ticks {
+                core::hint::spin_loop();
+            }
+            (Reference::Hpet, ticks * hpet.period_fs / FS_PER_NS)
+        }
+        None => (Reference::Pit, pit_wait(CALIBRATION_NS)),
+    };
+    let tsc_ticks = rdtsc() - tsc_start;
+    let lapic_ticks = if lapic {
+        let remaining = apic::timer_current_count();
+        apic::timer_arm_count(0);
+        (u32::MAX - remaining) as u64
+    } else {
+        0
+    };
+    (
+        reference,
+        scale(tsc_ticks, NS_PER_SEC, elapsed_ns),
+        scale(lapic_ticks, NS_PER_SEC, elapsed_ns),
+    )
+}
+
+/// 用 PIT 通道 2 单次计数等待约 `ns` 纳秒（不超过 54 ms），返回实际时长
+fn pit_wait(ns: u64) -> u64 {
+    let count = scale(ns, PIT_HZ, NS_PER_SEC).clamp(1, 0xFFFF);
+    unsafe {
+        // 打开通道 2 门控、关闭扬声器；模式 0，先低后高字节
+        let gate = inb(PIT_GATE_PORT) & !(PIT_SPEAKER | PIT_GATE);
+        outb(PIT_GATE_PORT, gate);
+        outb(PIT_COMMAND, 0b1011_0000);
+        outb(PIT_CHANNEL2, count as u8);
+        outb(PIT_CHANNEL2, (count >> 8) as u8);
+        outb(PIT_GATE_PORT, gate | PIT_GATE);
+        while inb(PIT_GATE_PORT) & PIT_OUT2 == 0 {
+            core::hint::spin_loop();
+        }
+        outb(PIT_GATE_PORT, gate);
+    }
+    scale(count, NS_PER_SEC, PIT_HZ)
+}
+
+/// `value * numerator / denominator`，中间结果用 128 位避免溢出
+fn scale(value: u64, numerator: u64, denominator: u64) -> u64 {
+    if denominator == 0 {
+        return 0;
+    }
+    (value as u128 * numerator as u128 / denominator as u128).min(u64::MAX as u128) as u64
+}
+
+fn duration_ns(duration: Duration) -> u64 {
+    duration.as_nanos().min(u64::MAX as u128) as u64
+}
+
+/// 自 [`init`] 起的单调时间；初始化之前为 0
+pub fn now() -> Duration {
+    Duration::from_nanos(now_ns())
+}
+
+fn now_ns() -> u64 {
+    let Some(clock) = clock() else {
+        return 0;
+    };
+    match (clock.info.clock, clock.info.hpet) {
+        (ClockSource::Hpet, Some(hpet)) => {
+            scale(hpet.elapsed(clock.hpet_base), hpet.period_fs, FS_PER_NS)
+        }
+        _ => scale(rdtsc() - clock.tsc_base, NS_PER_SEC, clock.info.tsc_hz),
+    }
+}
+
+/// 忙等待 `duration`；时间子系统未初始化时立即返回
+pub fn busy_wait(duration: Duration) {
+    if clock().is_none() {
+        return;
+    }
+    let deadline = now_ns().saturating_add(duration_ns(duration));
+    while now_ns() < deadline {
+        core::hint::spin_loop();
+    }
+}
+
+/// 睡眠 `duration`：开中断且有定时器时用 `hlt` 等待，否则忙等待
+pub fn sleep(duration: Duration) {
+    fn wake() {}
+
+    let deadline = now_ns().saturating_add(duration_ns(duration));
+    if !interrupts_enabled() || one_shot(duration, wake).is_none() {
+        busy_wait(duration);
+        return;
+    }
+    loop {
+        // 关中断检查，再用 sti; hlt 原子地开中断并等待，避免错过唤醒
+        unsafe { asm!("cli", options(nomem, nostack)) };
+        if now_ns() >= deadline {
+            unsafe { asm!("sti", options(nomem, nostack)) };
+            break;
+        }
+        unsafe { asm!("sti", "hlt", options(nomem, nostack)) };
+    }
+}
+
+/// 注册单次定时器，`delay` 之后调用一次 `callback`；没有定时器硬件时返回 None
+pub fn one_shot(delay: Duration, callback: TimerCallback) -> Option<TimerId> {
+    add_timer(duration_ns(delay), 0, callback)
+}
+
+/// 注册周期定时器，每隔 `period` 调用一次 `callback`；周期为 0 或没有定时器硬件时返回 None
+pub fn periodic(period: Duration, callback: TimerCallback) -> Option<TimerId> {
+    match duration_ns(period) {
+        0 => None,
+        period => add_timer(period, period, callback),
+    }
+}
+
+/// 取消定时器，返回它是否还在等待
+pub fn cancel(id: TimerId) -> bool {
+    without_interrupts(|| {
+        let mut timers = TIMERS.lock();
+        let Some(index) = timers.iter().position(|timer| timer.id == id.0) else {
+            return false;
+        };
+        timers.swap_remove(index);
+        program_next(&timers);
+        true
+    })
+}
+
+fn add_timer(delay: u64, period: u64, callback: TimerCallback) -> Option<TimerId> {
+    if clock()?.info.lapic_timer_hz == 0 {
+        return None;
+    }
+    let id = NEXT_TIMER_ID.fetch_add(1, Ordering::Relaxed);
+    let entry = TimerEntry {
+        id,
+        deadline: now_ns().saturating_add(delay),
+        period,
+        callback,
+    };
+    without_interrupts(|| {
+        let mut timers = TIMERS.lock();
+        timers.push(entry);
+        program_next(&timers);
+    });
+    Some(TimerId(id))
+}
+
+/// 按最早的到期时间设置本地 APIC 定时器；没有定时器时停止
+fn program_next(timers: &[TimerEntry]) {
+    let Some(clock) = clock() else {
+        return;
+    };
+    let next = timers.iter().map(|timer| timer.deadline).min();
+    if clock.info.tsc_deadline {
+        let tsc = next.map_or(0, |deadline| {
+            clock.tsc_base + scale(deadline, clock.info.tsc_hz, NS_PER_SEC)
+        });
+        apic::timer_arm_deadline(tsc);
+    } else {
+        // 计数器只有 32 位，太远的到期时间分几次到达
+        let count = next.map_or(0, |deadline| {
+            let delta = deadline.saturating_sub(now_ns());
+            scale(delta, clock.info.lapic_timer_hz, NS_PER_SEC).clamp(1, u32::MAX as u64)
+        });
+        apic::timer_arm_count(count as u32);
+    }
+}
+
+/// 本地 APIC 定时器中断：依次运行所有到期的回调，再设置下一次到期时间
+fn timer_interrupt(_frame: &mut InterruptFrame) {
+    loop {
+        let now = now_ns();
+        let callback = {
+            let mut timers = TIMERS.lock();
+            let Some(index) = timers
+                .iter()
+                .enumerate()
+                .filter(|(_, timer)| timer.deadline <= now)
+                .min_by_key(|(_, timer)| timer.deadline)
+                .map(|(index, _)| index)
+            else {
+                break;
+            };
+            let timer = timers[index];
+            if timer.period == 0 {
+                timers.swap_remove(index);
+            } else {
+                // 错过多个周期时不补发，从现在起重新计
+                let next = timer.deadline + timer.period;
+                timers[index].deadline = if next > now { next } else { now + timer.period };
+            }
+            timer.callback
+        };
+        callback();
+    }
+    program_next(&TIMERS.lock());
+}